@@ -1,10 +1,10 @@
-use anyhow::{Context, Result};
+use crate::error::{Result, RsmapError};
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::Path;
 
 use crate::cache::Cache;
-use crate::model::{CrateInfo, Module};
+use crate::model::{CrateInfo, Item, Module};
 
 /// Storage for annotations (module and item descriptions).
 ///
@@ -36,33 +36,58 @@ impl AnnotationStore {
     /// Load annotations from the output directory
     pub fn load(output_dir: &Path) -> Result<Self> {
         let path = output_dir.join("annotations.toml");
-        let content = std::fs::read_to_string(&path)
-            .with_context(|| format!("Cannot read {}", path.display()))?;
-        let store: AnnotationStore =
-            toml::from_str(&content).context("Failed to parse annotations.toml")?;
-        Ok(store)
+        let content = std::fs::read_to_string(&path).map_err(|e| RsmapError::io(&path, e))?;
+        toml::from_str(&content).map_err(|e| RsmapError::annotations(Some(&path), e))
     }
 
     /// Save annotations to the output directory
     pub fn save(&self, output_dir: &Path) -> Result<()> {
         let path = output_dir.join("annotations.toml");
-        let content = toml::to_string_pretty(self).context("Failed to serialize annotations")?;
+        let content = toml::to_string_pretty(self)
+            .map_err(|e| RsmapError::annotations(Some(&path), e))?;
 
         let header = "# Auto-generated by rsmap.\n\
                       # Fill in 'note' fields manually or via LLM.\n\
                       # Entries with stale=true have changed since last annotation.\n\n";
 
         std::fs::write(&path, format!("{}{}", header, content))
-            .with_context(|| format!("Cannot write {}", path.display()))?;
+            .map_err(|e| RsmapError::io(&path, e))?;
         Ok(())
     }
 }
 
+/// Module descriptions kept by hand in an optional `descriptions.toml` in the
+/// project root, mapping module paths to one-liners:
+///
+/// ```toml
+/// "crate::engine" = "Expression evaluation"
+/// ```
+///
+/// They sit between doc comments and annotations in priority, and have no
+/// staleness tracking.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct DescriptionOverrides {
+    pub modules: BTreeMap<String, String>,
+}
+
+impl DescriptionOverrides {
+    /// Load descriptions.toml from the project root; empty if there is none
+    pub fn load(project_root: &Path) -> Result<Self> {
+        let path = project_root.join("descriptions.toml");
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path).map_err(|e| RsmapError::io(&path, e))?;
+        toml::from_str(&content).map_err(|e| RsmapError::annotations(Some(&path), e))
+    }
+}
+
 /// Update annotations based on current crate data and cache comparison.
 ///
 /// - New items get empty notes
 /// - Changed items (hash differs between old and new cache) get stale=true
-/// - Removed items get removed=true
+/// - Removed items get removed=true; removed entries with no note are dropped
 pub fn update_annotations(
     existing: &AnnotationStore,
     crates: &[CrateInfo],
@@ -84,7 +109,7 @@ pub fn update_annotations(
     }
 
     // Update module annotations
-    for (path, _) in &current_modules {
+    for path in current_modules.keys() {
         let is_stale = old_cache
             .map(|old| old.module_hash_changed(new_cache, path))
             .unwrap_or(false);
@@ -110,7 +135,7 @@ pub fn update_annotations(
     }
 
     // Update item annotations
-    for (path, _) in &current_items {
+    for path in current_items.keys() {
         let is_stale = old_cache
             .map(|old| old.item_hash_changed(new_cache, path))
             .unwrap_or(false);
@@ -135,18 +160,30 @@ pub fn update_annotations(
         }
     }
 
-    // Mark removed items
+    // Mark removed items: anything no longer in the tree, plus items the
+    // cache diff reports as deleted since the last run
     for (path, entry) in updated.modules.iter_mut() {
         if !current_modules.contains_key(path) {
             entry.removed = true;
         }
     }
+    let deleted: BTreeSet<String> = old_cache
+        .map(|old| Cache::removed_items(old, new_cache).into_iter().collect())
+        .unwrap_or_default();
     for (path, entry) in updated.items.iter_mut() {
-        if !current_items.contains_key(path) {
+        if !current_items.contains_key(path) || deleted.contains(path) {
             entry.removed = true;
         }
     }
 
+    // Orphaned entries without a note carry nothing worth keeping
+    updated
+        .modules
+        .retain(|_, entry| !(entry.removed && entry.note.is_empty()));
+    updated
+        .items
+        .retain(|_, entry| !(entry.removed && entry.note.is_empty()));
+
     updated
 }
 
@@ -212,10 +249,130 @@ pub fn export_for_annotation(annotations: &AnnotationStore) -> String {
     out
 }
 
+/// Why an entry is listed by `annotate export --json`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportReason {
+    /// Never annotated
+    New,
+    /// Changed since its note was written
+    Stale,
+}
+
+/// A module or item that needs a note, with what the model knows about it
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportEntry {
+    pub path: String,
+    /// `module`, or the item kind; `None` if the path is no longer in the model
+    pub kind: Option<String>,
+    pub signature: Option<String>,
+    pub existing_doc: Option<String>,
+    pub reason: ExportReason,
+}
+
+/// Export unannotated or stale items as a JSON array of [`ExportEntry`],
+/// taking kinds, signatures and docs from `crates`
+pub fn export_for_annotation_json(annotations: &AnnotationStore, crates: &[CrateInfo]) -> String {
+    let mut modules = BTreeMap::new();
+    let mut items = BTreeMap::new();
+    for crate_info in crates {
+        collect_exported(&crate_info.root_module, &mut modules, &mut items);
+    }
+
+    let pending = |entry: &AnnotationEntry| {
+        if entry.removed {
+            None
+        } else if entry.stale {
+            Some(ExportReason::Stale)
+        } else if entry.note.is_empty() {
+            Some(ExportReason::New)
+        } else {
+            None
+        }
+    };
+
+    let mut entries = Vec::new();
+    for (path, entry) in &annotations.modules {
+        if let Some(reason) = pending(entry) {
+            let module = modules.get(path.as_str());
+            entries.push(ExportEntry {
+                path: path.clone(),
+                kind: module.map(|_| "module".to_string()),
+                signature: None,
+                existing_doc: module.and_then(|m| m.doc_comment.clone()),
+                reason,
+            });
+        }
+    }
+    for (path, entry) in &annotations.items {
+        if let Some(reason) = pending(entry) {
+            let item = items.get(path);
+            entries.push(ExportEntry {
+                path: path.clone(),
+                kind: item.map(|i| i.kind.to_string()),
+                signature: item.map(|i| i.signature.clone()),
+                existing_doc: item.and_then(|i| i.doc_comment.clone()),
+                reason,
+            });
+        }
+    }
+
+    serde_json::to_string_pretty(&entries).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Index modules and items by the paths annotations.toml uses for them
+fn collect_exported<'a>(
+    module: &'a Module,
+    modules: &mut BTreeMap<&'a str, &'a Module>,
+    items: &mut BTreeMap<String, &'a Item>,
+) {
+    modules.insert(&module.path, module);
+    for item in &module.items {
+        items.insert(format!("{}::{}", module.path, item.name), item);
+    }
+    for sub in &module.submodules {
+        collect_exported(sub, modules, items);
+    }
+}
+
+/// Format of a file given to `annotate import`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    Toml,
+    Json,
+}
+
+impl ImportFormat {
+    /// Detect the format from a `.json` or `.toml` extension, falling back to
+    /// the content: JSON if it starts with `{`, which TOML can't
+    pub fn detect(path: &Path, content: &str) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => ImportFormat::Json,
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => ImportFormat::Toml,
+            _ if content.trim_start().starts_with('{') => ImportFormat::Json,
+            _ => ImportFormat::Toml,
+        }
+    }
+}
+
 /// Import annotations from a TOML string (typically LLM-generated)
 pub fn import_annotations(store: &mut AnnotationStore, import_content: &str) -> Result<()> {
-    let imported: AnnotationStore =
-        toml::from_str(import_content).context("Failed to parse import TOML")?;
+    import_annotations_as(store, import_content, ImportFormat::Toml)
+}
+
+/// Import annotations in the given format. JSON has the same shape as the
+/// TOML, e.g. `{"items": {"crate::init": {"note": "..."}}}`.
+pub fn import_annotations_as(
+    store: &mut AnnotationStore,
+    import_content: &str,
+    format: ImportFormat,
+) -> Result<()> {
+    let imported: AnnotationStore = match format {
+        ImportFormat::Toml => toml::from_str(import_content)
+            .map_err(|e| RsmapError::annotations(None, format!("invalid TOML: {}", e)))?,
+        ImportFormat::Json => serde_json::from_str(import_content)
+            .map_err(|e| RsmapError::annotations(None, format!("invalid JSON: {}", e)))?,
+    };
 
     // Merge imported modules
     for (path, entry) in imported.modules {
@@ -247,34 +404,43 @@ mod tests {
     use std::path::PathBuf;
 
     fn sample_crate() -> CrateInfo {
-        CrateInfo {
-            name: "test".to_string(),
-            kind: CrateKind::Lib,
-            edition: "2021".to_string(),
-            version: "0.1.0".to_string(),
-            external_deps: vec![],
-            root_module: Module {
-                path: "crate".to_string(),
-                file_path: PathBuf::from("src/lib.rs"),
+        CrateInfo::for_test(
+            "test",
+            Module {
                 file_hash: "abc123".to_string(),
-                doc_comment: None,
-                visibility: Visibility::Pub,
-                items: vec![Item {
+                ..Module::for_test("crate", "src/lib.rs").with_items(vec![Item {
                     name: "init".to_string(),
                     kind: ItemKind::Function,
                     visibility: Visibility::Pub,
                     signature: "pub fn init();".to_string(),
                     doc_comment: None,
+                    derives: vec![],
+                    attributes: vec![],
+                    deprecated: None,
+                    generics: vec![],
+                    where_clause: None,
+                    supertraits: Vec::new(),
+                    abi: None,
+                    members: vec![],
+                    params: vec![],
+                    return_type: None,
+                    return_trait: None,
+                    is_async: false,
+                    is_unsafe: false,
+                    is_const: false,
+                    repr: None,
+                    cfg: None,
+                    doc_hidden: false,
+                    reexport_of: None,
                     file_path: PathBuf::from("src/lib.rs"),
                     line_start: 1,
                     line_end: 5,
+                    byte_start: 0,
+                    byte_end: 0,
                     content_hash: "hash1".to_string(),
-                }],
-                submodules: vec![],
-                use_statements: vec![],
-                is_inline: false,
+                }])
             },
-        }
+        )
     }
 
     #[test]
@@ -290,6 +456,48 @@ mod tests {
         assert!(updated.items["crate::init"].note.is_empty());
     }
 
+    #[test]
+    fn test_export_for_annotation_json() {
+        let crates = vec![sample_crate()];
+        let mut store = update_annotations(
+            &AnnotationStore::default(),
+            &crates,
+            None,
+            &Cache::from_crates(&crates),
+        );
+        store.modules.get_mut("crate").unwrap().note = "Root".to_string();
+        store.items.insert(
+            "crate::gone".to_string(),
+            AnnotationEntry {
+                note: "Old".to_string(),
+                stale: true,
+                removed: false,
+            },
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&export_for_annotation_json(&store, &crates)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {
+                    "path": "crate::gone",
+                    "kind": null,
+                    "signature": null,
+                    "existing_doc": null,
+                    "reason": "stale"
+                },
+                {
+                    "path": "crate::init",
+                    "kind": "function",
+                    "signature": "pub fn init();",
+                    "existing_doc": null,
+                    "reason": "new"
+                }
+            ])
+        );
+    }
+
     #[test]
     fn test_update_annotations_stale() {
         let mut existing = AnnotationStore::default();
@@ -319,6 +527,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_update_annotations_removed() {
+        let mut crates = vec![sample_crate()];
+        let old_cache = Cache::from_crates(&crates);
+
+        let mut existing = AnnotationStore::default();
+        existing.items.insert(
+            "crate::init".to_string(),
+            AnnotationEntry {
+                note: "Initializes the system".to_string(),
+                stale: false,
+                removed: false,
+            },
+        );
+        existing
+            .items
+            .insert("crate::gone".to_string(), AnnotationEntry::default());
+
+        // Delete the item between the two snapshots
+        crates[0].root_module.items.clear();
+        let new_cache = Cache::from_crates(&crates);
+        assert_eq!(
+            Cache::removed_items(&old_cache, &new_cache),
+            vec!["crate::init".to_string()]
+        );
+
+        let updated = update_annotations(&existing, &crates, Some(&old_cache), &new_cache);
+        assert!(updated.items["crate::init"].removed);
+        assert_eq!(updated.items["crate::init"].note, "Initializes the system");
+        // Orphaned entries with no note are pruned
+        assert!(!updated.items.contains_key("crate::gone"));
+    }
+
     #[test]
     fn test_import_annotations() {
         let mut store = AnnotationStore::default();
@@ -341,6 +582,13 @@ note = "Initializes the application"
             store.items["crate::init"].note,
             "Initializes the application"
         );
+
+        let json = r#"{"items": {"crate::init": {"note": "Sets things up", "hash": "x"}}}"#;
+        assert_eq!(ImportFormat::detect(Path::new("notes.txt"), json), ImportFormat::Json);
+        assert_eq!(ImportFormat::detect(Path::new("notes.toml"), json), ImportFormat::Toml);
+        assert_eq!(ImportFormat::detect(Path::new("notes"), import), ImportFormat::Toml);
+        import_annotations_as(&mut store, json, ImportFormat::Json).unwrap();
+        assert_eq!(store.items["crate::init"].note, "Sets things up");
     }
 
     #[test]
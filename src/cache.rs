@@ -1,22 +1,30 @@
-use anyhow::{Context, Result};
+use crate::error::{Result, RsmapError};
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
-use std::path::Path;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 
 use crate::model::{CrateInfo, Module};
 
+/// Format version of cache.json and the module cache. Bump whenever `Cache`
+/// or the serialized model changes so older caches are rebuilt, not misread.
+pub const CACHE_VERSION: u32 = 27;
+
 /// Cache of all hashes for incremental rebuilds and staleness detection.
 ///
 /// This is the single source of truth for change detection. LLM-facing files
 /// (annotations.toml, api-surface.md, etc.) never contain hashes.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Cache {
+    /// Format version; caches without one predate versioning
+    #[serde(default)]
+    pub version: u32,
     /// Per-file hashes (for incremental parse skipping)
     pub files: BTreeMap<String, CacheFileEntry>,
     /// Per-module hashes (file hash of the module's source)
     #[serde(default)]
     pub modules: BTreeMap<String, String>,
-    /// Per-item content hashes (hash of the item's source lines)
+    /// Per-item content hashes (hash of the item's tokens)
     #[serde(default)]
     pub items: BTreeMap<String, String>,
 }
@@ -27,13 +35,32 @@ pub struct CacheFileEntry {
     pub last_indexed: String,
 }
 
+impl Default for Cache {
+    fn default() -> Self {
+        Cache {
+            version: CACHE_VERSION,
+            files: BTreeMap::new(),
+            modules: BTreeMap::new(),
+            items: BTreeMap::new(),
+        }
+    }
+}
+
 impl Cache {
-    /// Load cache from the output directory
+    /// Load cache from the output directory.
+    ///
+    /// Fails if the cache was written by a different cache format version.
     pub fn load(output_dir: &Path) -> Result<Self> {
         let path = output_dir.join("cache.json");
-        let content = std::fs::read_to_string(&path)
-            .with_context(|| format!("Cannot read {}", path.display()))?;
-        let cache: Cache = serde_json::from_str(&content).context("Failed to parse cache.json")?;
+        let content = std::fs::read_to_string(&path).map_err(|e| RsmapError::io(&path, e))?;
+        let cache: Cache =
+            serde_json::from_str(&content).map_err(|e| RsmapError::cache(&path, e))?;
+        if cache.version != CACHE_VERSION {
+            return Err(RsmapError::cache(
+                &path,
+                format!("format version {}, expected {}", cache.version, CACHE_VERSION),
+            ));
+        }
         Ok(cache)
     }
 
@@ -41,9 +68,8 @@ impl Cache {
     pub fn save(&self, output_dir: &Path) -> Result<()> {
         let path = output_dir.join("cache.json");
         let content =
-            serde_json::to_string_pretty(self).context("Failed to serialize cache")?;
-        std::fs::write(&path, content)
-            .with_context(|| format!("Cannot write {}", path.display()))?;
+            serde_json::to_string_pretty(self).map_err(|e| RsmapError::cache(&path, e))?;
+        std::fs::write(&path, content).map_err(|e| RsmapError::io(&path, e))?;
         Ok(())
     }
 
@@ -84,6 +110,88 @@ impl Cache {
             _ => false,
         }
     }
+
+    /// Item paths present in `old` but gone from `new`, i.e. deleted from source
+    pub fn removed_items(old: &Cache, new: &Cache) -> Vec<String> {
+        old.items
+            .keys()
+            .filter(|path| !new.items.contains_key(*path))
+            .cloned()
+            .collect()
+    }
+}
+
+/// On-disk store of parsed `Module` trees (modules/<key>.json), used to skip
+/// re-parsing files whose hash hasn't changed since the last run.
+#[derive(Debug)]
+pub struct ModuleCache {
+    dir: PathBuf,
+    /// Keys loaded or stored during this run; everything else is pruned
+    used: RefCell<BTreeSet<String>>,
+}
+
+impl ModuleCache {
+    pub fn new(output_dir: &Path) -> Self {
+        ModuleCache {
+            dir: output_dir.join("modules"),
+            used: RefCell::new(BTreeSet::new()),
+        }
+    }
+
+    /// Cache key for a file: its path, content hash, the options that affect
+    /// parsing and the cache format version
+    pub fn key(relative_path: &Path, file_hash: &str, options: &str) -> String {
+        let input = format!(
+            "{}\n{}\n{}\n{}",
+            relative_path.display(),
+            file_hash,
+            options,
+            CACHE_VERSION
+        );
+        blake3::hash(input.as_bytes()).to_hex().to_string()
+    }
+
+    /// Load a previously stored module, if present and readable
+    pub fn load(&self, key: &str) -> Option<Module> {
+        let content = std::fs::read_to_string(self.dir.join(format!("{}.json", key))).ok()?;
+        let module = serde_json::from_str(&content).ok()?;
+        self.used.borrow_mut().insert(key.to_string());
+        Some(module)
+    }
+
+    /// Store a parsed module under the given key
+    pub fn store(&self, key: &str, module: &Module) -> Result<()> {
+        std::fs::create_dir_all(&self.dir).map_err(|e| RsmapError::io(&self.dir, e))?;
+        let path = self.dir.join(format!("{}.json", key));
+        let content = serde_json::to_string(module).map_err(|e| RsmapError::cache(&path, e))?;
+        std::fs::write(&path, content).map_err(|e| RsmapError::io(&path, e))?;
+        self.used.borrow_mut().insert(key.to_string());
+        Ok(())
+    }
+
+    /// Remove cached modules that were not used during this run
+    pub fn prune(&self) -> Result<usize> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(0),
+        };
+
+        let used = self.used.borrow();
+        let mut removed = 0;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_unused = path.extension().is_some_and(|e| e == "json")
+                && path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .is_some_and(|stem| !used.contains(stem));
+            if is_unused {
+                std::fs::remove_file(&path).map_err(|e| RsmapError::io(&path, e))?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
 }
 
 fn collect_hashes(module: &Module, cache: &mut Cache, now: &str) {
@@ -139,6 +247,26 @@ mod tests {
         assert_eq!(loaded.items["crate::init"], "def456");
     }
 
+    #[test]
+    fn test_cache_version_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        Cache::default().save(dir.path()).unwrap();
+        assert_eq!(Cache::load(dir.path()).unwrap().version, CACHE_VERSION);
+
+        let stale = Cache {
+            version: CACHE_VERSION + 1,
+            ..Cache::default()
+        };
+        stale.save(dir.path()).unwrap();
+        let err = Cache::load(dir.path()).unwrap_err();
+        assert!(matches!(err, RsmapError::Cache { .. }));
+        assert!(err.to_string().contains("format version"));
+
+        // Caches written before versioning have no version field at all
+        std::fs::write(dir.path().join("cache.json"), r#"{"files": {}}"#).unwrap();
+        assert!(Cache::load(dir.path()).is_err());
+    }
+
     #[test]
     fn test_is_file_unchanged() {
         let mut cache = Cache::default();
@@ -155,6 +283,35 @@ mod tests {
         assert!(!cache.is_file_unchanged("src/main.rs", "abc123"));
     }
 
+    #[test]
+    fn test_module_cache_roundtrip_and_prune() {
+        let dir = tempfile::tempdir().unwrap();
+        let module = Module {
+            file_hash: "abc123".to_string(),
+            doc_comment: Some("Root".to_string()),
+            use_statements: vec!["crate::model::Item".to_string()],
+            ..Module::for_test("crate", "src/lib.rs")
+        };
+
+        let key = ModuleCache::key(Path::new("src/lib.rs"), "abc123", "false");
+        assert_ne!(key, ModuleCache::key(Path::new("src/lib.rs"), "abc123", "true"));
+
+        let writer = ModuleCache::new(dir.path());
+        writer.store(&key, &module).unwrap();
+        writer.store("stale", &module).unwrap();
+
+        let reader = ModuleCache::new(dir.path());
+        let loaded = reader.load(&key).unwrap();
+        assert_eq!(loaded.doc_comment.as_deref(), Some("Root"));
+        assert_eq!(loaded.use_statements, module.use_statements);
+        assert!(reader.load("missing").is_none());
+
+        // Only the entry used by `reader` survives
+        assert_eq!(reader.prune().unwrap(), 1);
+        assert!(reader.load(&key).is_some());
+        assert!(reader.load("stale").is_none());
+    }
+
     #[test]
     fn test_staleness_detection() {
         let mut old_cache = Cache::default();
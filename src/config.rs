@@ -0,0 +1,159 @@
+//! `rsmap.toml`: project-level defaults for `rsmap generate`.
+//!
+//! Every key mirrors a `generate` flag of the same name (with `-` written as
+//! `_`). Flags given on the command line take precedence over the file.
+
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Result, RsmapError};
+use crate::layer1::{Layout, VisibilityFilter};
+
+/// Name of the config file looked up in the project root
+pub const CONFIG_FILE: &str = "rsmap.toml";
+
+/// Which relationships files (and whether index.yaml or index.ndjson) to write
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// relationships.md only
+    #[default]
+    Md,
+    /// relationships.json only
+    Json,
+    /// relationships.md plus index.yaml
+    Yaml,
+    /// relationships.md, relationships.json and index.yaml
+    Both,
+    /// relationships.md plus index.ndjson, one index entry per line
+    Ndjson,
+}
+
+impl OutputFormat {
+    pub fn relationships_md(self) -> bool {
+        self != OutputFormat::Json
+    }
+
+    pub fn relationships_json(self) -> bool {
+        matches!(self, OutputFormat::Json | OutputFormat::Both)
+    }
+
+    pub fn index_yaml(self) -> bool {
+        matches!(self, OutputFormat::Yaml | OutputFormat::Both)
+    }
+
+    pub fn index_ndjson(self) -> bool {
+        self == OutputFormat::Ndjson
+    }
+}
+
+/// Settings read from `rsmap.toml`; unset keys fall back to the CLI defaults
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Output directory, relative to the project path
+    pub output: Option<PathBuf>,
+    pub exclude: Option<Vec<String>>,
+    pub format: Option<OutputFormat>,
+    pub include_tests: Option<bool>,
+    pub layout: Option<Layout>,
+    pub visibility: Option<VisibilityFilter>,
+    pub max_signature_width: Option<usize>,
+    pub compact_impls: Option<bool>,
+    pub call_graph: Option<bool>,
+    pub mermaid: Option<bool>,
+    pub hotspot_threshold: Option<usize>,
+    pub hotspot_top: Option<usize>,
+    pub scan_todos: Option<bool>,
+    pub no_dev_deps: Option<bool>,
+    pub overview_max_depth: Option<usize>,
+    pub absolute_paths: Option<bool>,
+    pub respect_gitignore: Option<bool>,
+    pub strict: Option<bool>,
+    pub html: Option<bool>,
+    pub split_by_crate: Option<bool>,
+    pub combined: Option<bool>,
+    pub legacy_index: Option<bool>,
+    pub index_docs: Option<bool>,
+    pub prefix: Option<String>,
+}
+
+impl Config {
+    /// Parse a config file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| RsmapError::io(path, e))?;
+        toml::from_str(&content).map_err(|e| RsmapError::Config {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })
+    }
+
+    /// Load `explicit` if given, else `<project_path>/rsmap.toml` if it exists,
+    /// else the empty config
+    pub fn find(project_path: &Path, explicit: Option<&Path>) -> Result<Self> {
+        if let Some(path) = explicit {
+            return Self::load(path);
+        }
+        let path = project_path.join(CONFIG_FILE);
+        if path.is_file() {
+            Self::load(&path)
+        } else {
+            Ok(Config::default())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config: Config = toml::from_str(
+            r#"
+output = "docs/index"
+exclude = ["src/generated/**"]
+format = "both"
+include_tests = true
+visibility = "pub-crate"
+overview_max_depth = 2
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.output, Some(PathBuf::from("docs/index")));
+        assert_eq!(config.exclude, Some(vec!["src/generated/**".to_string()]));
+        assert_eq!(config.format, Some(OutputFormat::Both));
+        assert_eq!(config.include_tests, Some(true));
+        assert_eq!(config.visibility, Some(VisibilityFilter::PubCrate));
+        assert_eq!(config.overview_max_depth, Some(2));
+        assert_eq!(config.layout, None);
+        assert_eq!(config.strict, None);
+    }
+
+    #[test]
+    fn test_config_rejects_unknown_keys() {
+        assert!(toml::from_str::<Config>("outptu = \"x\"").is_err());
+        assert!(toml::from_str::<Config>("format = \"xml\"").is_err());
+    }
+
+    #[test]
+    fn test_find_config() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(Config::find(dir.path(), None).unwrap(), Config::default());
+
+        std::fs::write(dir.path().join(CONFIG_FILE), "strict = true\n").unwrap();
+        assert_eq!(Config::find(dir.path(), None).unwrap().strict, Some(true));
+
+        let other = dir.path().join("other.toml");
+        std::fs::write(&other, "html = true\n").unwrap();
+        let config = Config::find(dir.path(), Some(&other)).unwrap();
+        assert_eq!(config.html, Some(true));
+        assert_eq!(config.strict, None);
+
+        let missing = Config::find(dir.path(), Some(&dir.path().join("missing.toml")));
+        assert!(matches!(missing, Err(RsmapError::Io { .. })));
+        std::fs::write(&other, "html = 1\n").unwrap();
+        let invalid = Config::find(dir.path(), Some(&other));
+        assert!(matches!(invalid, Err(RsmapError::Config { path, .. }) if path == other));
+    }
+}
@@ -0,0 +1,214 @@
+//! Documentation coverage of the public API.
+
+use serde::Serialize;
+
+use crate::model::{CrateInfo, Item, ItemKind, Module, Visibility};
+
+/// How many `pub` items have doc comments, per crate and module
+#[derive(Debug, Default, Serialize)]
+pub struct CoverageReport {
+    pub documented: usize,
+    pub total: usize,
+    pub percent: f64,
+    pub crates: Vec<CrateCoverage>,
+    /// Public items without a doc comment, in module order
+    pub missing: Vec<MissingDoc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CrateCoverage {
+    pub name: String,
+    pub documented: usize,
+    pub total: usize,
+    pub percent: f64,
+    /// Modules with at least one public item
+    pub modules: Vec<ModuleCoverage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModuleCoverage {
+    pub path: String,
+    pub documented: usize,
+    pub total: usize,
+    pub percent: f64,
+}
+
+/// A public item with no doc comment
+#[derive(Debug, Serialize)]
+pub struct MissingDoc {
+    pub path: String,
+    pub kind: String,
+    pub file: String,
+    pub line: usize,
+}
+
+/// Items that count towards coverage: `pub`, and not a re-export or impl block
+fn is_public_api(item: &Item) -> bool {
+    item.visibility == Visibility::Pub
+        && !matches!(item.kind, ItemKind::Use | ItemKind::Impl { .. })
+}
+
+fn percent(documented: usize, total: usize) -> f64 {
+    if total == 0 {
+        100.0
+    } else {
+        documented as f64 * 100.0 / total as f64
+    }
+}
+
+/// Count documented public items across every crate and module
+pub fn compute_coverage(crates: &[CrateInfo]) -> CoverageReport {
+    let mut report = CoverageReport::default();
+
+    for crate_info in crates {
+        let public: Vec<&Item> = crate_info
+            .root_module
+            .all_items()
+            .into_iter()
+            .filter(|i| is_public_api(i))
+            .collect();
+        let documented = public.iter().filter(|i| i.doc_comment.is_some()).count();
+
+        let mut modules = Vec::new();
+        for module in crate_info.root_module.all_modules() {
+            if let Some(coverage) = module_coverage(module, &mut report.missing) {
+                modules.push(coverage);
+            }
+        }
+
+        report.documented += documented;
+        report.total += public.len();
+        report.crates.push(CrateCoverage {
+            name: crate_info.name.clone(),
+            documented,
+            total: public.len(),
+            percent: percent(documented, public.len()),
+            modules,
+        });
+    }
+
+    report.percent = percent(report.documented, report.total);
+    report
+}
+
+fn module_coverage(module: &Module, missing: &mut Vec<MissingDoc>) -> Option<ModuleCoverage> {
+    let public: Vec<&Item> = module.items.iter().filter(|i| is_public_api(i)).collect();
+    if public.is_empty() {
+        return None;
+    }
+
+    let mut documented = 0;
+    for item in &public {
+        if item.doc_comment.is_some() {
+            documented += 1;
+        } else {
+            missing.push(MissingDoc {
+                path: format!("{}::{}", module.path, item.name),
+                kind: item.kind.to_string(),
+                file: item.file_path.display().to_string(),
+                line: item.line_start,
+            });
+        }
+    }
+
+    Some(ModuleCoverage {
+        path: module.path.clone(),
+        documented,
+        total: public.len(),
+        percent: percent(documented, public.len()),
+    })
+}
+
+/// Human-readable summary: per-crate and per-module tables, then the
+/// undocumented items and a one-line total
+pub fn format_coverage(report: &CoverageReport) -> String {
+    let mut out = String::new();
+
+    for krate in &report.crates {
+        out.push_str(&format!(
+            "# Crate: {} ({}/{}, {:.0}%)\n\n",
+            krate.name, krate.documented, krate.total, krate.percent
+        ));
+        let width = krate.modules.iter().map(|m| m.path.len()).max().unwrap_or(0);
+        for module in &krate.modules {
+            out.push_str(&format!(
+                "{:<width$}  {}/{}  {:.0}%\n",
+                module.path,
+                module.documented,
+                module.total,
+                module.percent,
+                width = width
+            ));
+        }
+        out.push('\n');
+    }
+
+    if !report.missing.is_empty() {
+        out.push_str(&format!("## Undocumented ({})\n\n", report.missing.len()));
+        for item in &report.missing {
+            out.push_str(&format!(
+                "{}  [{}] {}:{}\n",
+                item.path, item.kind, item.file, item.line
+            ));
+        }
+        out.push('\n');
+    }
+
+    out.push_str(&format!(
+        "{:.0}% of public items documented; {} missing\n",
+        report.percent,
+        report.missing.len()
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::*;
+
+    #[test]
+    fn test_compute_coverage() {
+        let engine = Module::for_test("crate::engine", "src/engine.rs")
+            .with_source("/// Runs it\npub fn run() {}\nfn helper() {}\n");
+        let internal =
+            Module::for_test("crate::internal", "src/internal.rs").with_source("fn hidden() {}\n");
+        let root = Module::for_test("crate", "src/lib.rs")
+            .with_source(
+                "/// Settings\npub struct Config;\nimpl Config {}\npub fn init() {}\n\
+                 pub use engine::run;\npub const LIMIT: u32 = 3;\n",
+            )
+            .with_submodules(vec![engine, internal]);
+        let crates = vec![CrateInfo::for_test("test", root)];
+
+        let report = compute_coverage(&crates);
+        // Config, init, LIMIT and engine::run; the impl, re-export and private fns don't count
+        assert_eq!((report.documented, report.total), (2, 4));
+        assert_eq!(report.percent, 50.0);
+
+        let modules = &report.crates[0].modules;
+        assert_eq!(modules.len(), 2);
+        assert_eq!((modules[0].path.as_str(), modules[0].total), ("crate", 3));
+        assert_eq!(modules[1].percent, 100.0);
+
+        let missing: Vec<&str> = report.missing.iter().map(|m| m.path.as_str()).collect();
+        assert_eq!(missing, vec!["crate::init", "crate::LIMIT"]);
+        assert_eq!(report.missing[0].line, 4);
+
+        let text = format_coverage(&report);
+        assert!(text.starts_with("# Crate: test (2/4, 50%)\n\ncrate          1/3  33%\n"));
+        assert!(text.contains("## Undocumented (2)\n\ncrate::init  [function] src/lib.rs:4\n"));
+        assert!(text.ends_with("50% of public items documented; 2 missing\n"));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["missing"][1]["kind"], "const");
+        assert_eq!(json["crates"][0]["modules"][1]["path"], "crate::engine");
+    }
+
+    #[test]
+    fn test_coverage_without_public_items() {
+        let report = compute_coverage(&[]);
+        assert_eq!(report.total, 0);
+        assert_eq!(format_coverage(&report), "100% of public items documented; 0 missing\n");
+    }
+}
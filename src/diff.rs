@@ -0,0 +1,305 @@
+//! Compare two index.json snapshots.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::layer3::{self, IndexEntry};
+
+/// Everything that changed between two indexes, each list in path order
+#[derive(Debug, Default, Serialize)]
+pub struct IndexDiff {
+    pub added: Vec<DiffItem>,
+    pub removed: Vec<DiffItem>,
+    pub moved: Vec<MovedItem>,
+    pub kind_changes: Vec<Change>,
+    pub visibility_changes: Vec<Change>,
+}
+
+/// An item present in only one of the indexes
+#[derive(Debug, Serialize)]
+pub struct DiffItem {
+    pub path: String,
+    pub kind: String,
+    pub visibility: String,
+    /// Removing a `pub` item breaks callers
+    pub breaking: bool,
+}
+
+/// An item whose file or line range shifted
+#[derive(Debug, Serialize)]
+pub struct MovedItem {
+    pub path: String,
+    pub old_location: String,
+    pub new_location: String,
+}
+
+/// A kind or visibility that differs between the indexes
+#[derive(Debug, Serialize)]
+pub struct Change {
+    pub path: String,
+    pub old: String,
+    pub new: String,
+    pub breaking: bool,
+}
+
+impl IndexDiff {
+    /// Number of breaking changes (removed pub items, pub items that changed
+    /// kind, and items that lost or gained `pub`)
+    pub fn breaking_count(&self) -> usize {
+        self.removed.iter().filter(|i| i.breaking).count()
+            + self.kind_changes.iter().filter(|c| c.breaking).count()
+            + self.visibility_changes.iter().filter(|c| c.breaking).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.moved.is_empty()
+            && self.kind_changes.is_empty()
+            && self.visibility_changes.is_empty()
+    }
+}
+
+/// Load an index.json file, or the index.json inside an index directory
+pub fn load_index(path: &Path) -> Result<BTreeMap<String, IndexEntry>> {
+    let path = if path.is_dir() {
+        path.join("index.json")
+    } else {
+        path.to_path_buf()
+    };
+    layer3::read_index(&path)
+}
+
+/// Keyed comparison of two indexes by fully-qualified path
+pub fn diff_indexes(
+    old: &BTreeMap<String, IndexEntry>,
+    new: &BTreeMap<String, IndexEntry>,
+) -> IndexDiff {
+    let mut diff = IndexDiff::default();
+
+    for (path, entry) in old {
+        let Some(new_entry) = new.get(path) else {
+            diff.removed.push(DiffItem {
+                path: path.clone(),
+                kind: entry.kind.clone(),
+                visibility: entry.visibility.clone(),
+                breaking: entry.visibility == "pub",
+            });
+            continue;
+        };
+
+        if entry.kind != new_entry.kind {
+            diff.kind_changes.push(Change {
+                path: path.clone(),
+                old: entry.kind.clone(),
+                new: new_entry.kind.clone(),
+                breaking: entry.visibility == "pub",
+            });
+        }
+        if entry.visibility != new_entry.visibility {
+            // Gaining or losing `pub` changes the public API either way
+            diff.visibility_changes.push(Change {
+                path: path.clone(),
+                old: entry.visibility.clone(),
+                new: new_entry.visibility.clone(),
+                breaking: entry.visibility == "pub" || new_entry.visibility == "pub",
+            });
+        }
+
+        let old_location = location(entry);
+        let new_location = location(new_entry);
+        if old_location != new_location {
+            diff.moved.push(MovedItem {
+                path: path.clone(),
+                old_location,
+                new_location,
+            });
+        }
+    }
+
+    for (path, entry) in new {
+        if !old.contains_key(path) {
+            diff.added.push(DiffItem {
+                path: path.clone(),
+                kind: entry.kind.clone(),
+                visibility: entry.visibility.clone(),
+                breaking: false,
+            });
+        }
+    }
+
+    diff
+}
+
+fn location(entry: &IndexEntry) -> String {
+    format!("{}:{}-{}", entry.file, entry.line_start, entry.line_end)
+}
+
+/// Human-readable report, one section per kind of change
+pub fn format_diff(diff: &IndexDiff) -> String {
+    let mut out = String::new();
+    if diff.is_empty() {
+        out.push_str("No changes.\n");
+        return out;
+    }
+
+    write_items(&mut out, "Added", '+', &diff.added);
+    write_items(&mut out, "Removed", '-', &diff.removed);
+    write_changes(&mut out, "Visibility Changes", &diff.visibility_changes);
+    write_changes(&mut out, "Kind Changes", &diff.kind_changes);
+
+    if !diff.moved.is_empty() {
+        out.push_str(&format!("## Moved ({})\n\n", diff.moved.len()));
+        for item in &diff.moved {
+            out.push_str(&format!(
+                "~ {}  {} -> {}\n",
+                item.path, item.old_location, item.new_location
+            ));
+        }
+        out.push('\n');
+    }
+
+    out.push_str(&format!(
+        "{} added, {} removed, {} moved, {} changed ({} breaking)\n",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.moved.len(),
+        diff.kind_changes.len() + diff.visibility_changes.len(),
+        diff.breaking_count()
+    ));
+    out
+}
+
+fn write_items(out: &mut String, title: &str, marker: char, items: &[DiffItem]) {
+    if items.is_empty() {
+        return;
+    }
+    out.push_str(&format!("## {} ({})\n\n", title, items.len()));
+    for item in items {
+        out.push_str(&format!(
+            "{} {}  [{}] {}{}\n",
+            marker,
+            item.path,
+            item.kind,
+            item.visibility,
+            breaking_tag(item.breaking)
+        ));
+    }
+    out.push('\n');
+}
+
+fn write_changes(out: &mut String, title: &str, changes: &[Change]) {
+    if changes.is_empty() {
+        return;
+    }
+    out.push_str(&format!("## {} ({})\n\n", title, changes.len()));
+    for change in changes {
+        out.push_str(&format!(
+            "! {}  {} -> {}{}\n",
+            change.path,
+            change.old,
+            change.new,
+            breaking_tag(change.breaking)
+        ));
+    }
+    out.push('\n');
+}
+
+fn breaking_tag(breaking: bool) -> &'static str {
+    if breaking {
+        "  BREAKING"
+    } else {
+        ""
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(kind: &str, visibility: &str, line_start: usize) -> IndexEntry {
+        IndexEntry {
+            file: "src/lib.rs".to_string(),
+            line_start,
+            line_end: line_start + 2,
+            byte_start: None,
+            byte_end: None,
+            kind: kind.to_string(),
+            visibility: visibility.to_string(),
+            struct_kind: None,
+            field_count: None,
+            generics: vec![],
+            where_clause: None,
+            abi: None,
+            deprecated: false,
+            params: vec![],
+            return_type: None,
+            return_trait: None,
+            is_async: false,
+            is_unsafe: false,
+            is_const: false,
+            has_default: false,
+            is_method: false,
+            cfg: None,
+            doc_hidden: false,
+            reexport_of: None,
+            implementors: vec![],
+            implements: None,
+            doc_summary: None,
+        }
+    }
+
+    fn index(entries: &[(&str, IndexEntry)]) -> BTreeMap<String, IndexEntry> {
+        entries
+            .iter()
+            .map(|(path, entry)| (path.to_string(), entry.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_indexes() {
+        let old = index(&[
+            ("crate::Config", entry("struct", "pub", 1)),
+            ("crate::init", entry("function", "pub", 5)),
+            ("crate::helper", entry("function", "private", 9)),
+            ("crate::Mode", entry("struct", "pub", 12)),
+            ("crate::reset", entry("function", "pub", 15)),
+        ]);
+        let new = index(&[
+            ("crate::Config", entry("struct", "pub", 3)),
+            ("crate::helper", entry("function", "pub", 9)),
+            ("crate::Mode", entry("enum", "pub", 12)),
+            ("crate::reset", entry("function", "pub(crate)", 15)),
+            ("crate::shutdown", entry("function", "pub", 20)),
+        ]);
+
+        let diff = diff_indexes(&old, &new);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].path, "crate::shutdown");
+        assert_eq!(diff.removed.len(), 1);
+        assert!(diff.removed[0].breaking);
+        assert_eq!(diff.moved.len(), 1);
+        assert_eq!(diff.moved[0].old_location, "src/lib.rs:1-3");
+        assert_eq!(diff.moved[0].new_location, "src/lib.rs:3-5");
+        assert_eq!(diff.kind_changes[0].new, "enum");
+        assert_eq!(diff.visibility_changes.len(), 2);
+        assert_eq!(diff.breaking_count(), 4);
+
+        let report = format_diff(&diff);
+        assert!(report.contains("+ crate::shutdown  [function] pub\n"));
+        assert!(report.contains("- crate::init  [function] pub  BREAKING\n"));
+        assert!(report.contains("! crate::reset  pub -> pub(crate)  BREAKING\n"));
+        assert!(report.contains("~ crate::Config  src/lib.rs:1-3 -> src/lib.rs:3-5\n"));
+        assert!(report.ends_with("1 added, 1 removed, 1 moved, 3 changed (4 breaking)\n"));
+    }
+
+    #[test]
+    fn test_diff_identical() {
+        let old = index(&[("crate::Config", entry("struct", "pub", 1))]);
+        let diff = diff_indexes(&old, &old.clone());
+        assert!(diff.is_empty());
+        assert_eq!(format_diff(&diff), "No changes.\n");
+    }
+}
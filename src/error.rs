@@ -0,0 +1,170 @@
+//! Errors from the indexing pipeline (metadata, parsing, resolution and cache)
+//! and from the tools working on its output (index.json, annotations, config).
+//!
+//! Library callers can match on [`RsmapError`] to tell the failure modes
+//! apart; the CLI wraps them in `anyhow` with extra context.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// What went wrong while indexing a project
+#[derive(Debug)]
+pub enum RsmapError {
+    /// `cargo metadata` failed, e.g. the path isn't a Cargo project
+    Metadata(cargo_metadata::Error),
+    /// A file or directory couldn't be read or written
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// A source file isn't valid Rust; `line` and `column` are 1-based
+    Parse {
+        file: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// cache.json or a cached module couldn't be read back or written
+    Cache { path: PathBuf, message: String },
+    /// An `--exclude`, `--filter-module` or `--include-body` glob is invalid
+    Pattern {
+        /// The flag the pattern was given to, e.g. `--exclude`
+        flag: &'static str,
+        pattern: String,
+        source: globset::Error,
+    },
+    /// index.json (`path`, when read from a file) isn't a readable index
+    Index {
+        path: Option<PathBuf>,
+        message: String,
+    },
+    /// annotations.toml, descriptions.toml or imported annotations (no
+    /// `path`) couldn't be parsed or written
+    Annotations {
+        path: Option<PathBuf>,
+        message: String,
+    },
+    /// An rsmap.toml config file is invalid
+    Config { path: PathBuf, message: String },
+    /// A `search --regex` pattern is invalid
+    Regex {
+        pattern: String,
+        source: regex::Error,
+    },
+    /// The `--sqlite` database couldn't be written
+    #[cfg(feature = "sqlite")]
+    Sqlite {
+        path: PathBuf,
+        source: rusqlite::Error,
+    },
+}
+
+/// Result of the fallible pipeline functions
+pub type Result<T, E = RsmapError> = std::result::Result<T, E>;
+
+impl RsmapError {
+    /// An I/O error on `path`
+    pub fn io(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        RsmapError::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    /// A cache problem with the file at `path`
+    pub fn cache(path: impl Into<PathBuf>, message: impl fmt::Display) -> Self {
+        RsmapError::Cache {
+            path: path.into(),
+            message: message.to_string(),
+        }
+    }
+
+    /// An unreadable index, read from `path` if given
+    pub fn index(path: Option<&Path>, message: impl fmt::Display) -> Self {
+        RsmapError::Index {
+            path: path.map(Path::to_path_buf),
+            message: message.to_string(),
+        }
+    }
+
+    /// An annotations problem, with the file at `path` if given
+    pub fn annotations(path: Option<&Path>, message: impl fmt::Display) -> Self {
+        RsmapError::Annotations {
+            path: path.map(Path::to_path_buf),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// ` <path>`, or nothing for an error that isn't about a file
+fn with_path(path: &Option<PathBuf>) -> String {
+    path.as_ref().map(|p| format!(" {}", p.display())).unwrap_or_default()
+}
+
+impl fmt::Display for RsmapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RsmapError::Metadata(_) => {
+                write!(f, "Failed to run cargo metadata. Is this a valid Cargo project?")
+            }
+            RsmapError::Io { path, .. } => write!(f, "Cannot access {}", path.display()),
+            RsmapError::Parse {
+                file,
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "Failed to parse {}:{}:{}: {}",
+                file.display(),
+                line,
+                column,
+                message
+            ),
+            RsmapError::Cache { path, message } => {
+                write!(f, "Bad cache file {}: {}", path.display(), message)
+            }
+            RsmapError::Pattern { flag, pattern, .. } => {
+                write!(f, "Invalid {} pattern: {}", flag, pattern)
+            }
+            RsmapError::Index { path, message } => {
+                write!(f, "Bad index{}: {}", with_path(path), message)
+            }
+            RsmapError::Annotations { path, message } => {
+                write!(f, "Bad annotations{}: {}", with_path(path), message)
+            }
+            RsmapError::Config { path, message } => {
+                write!(f, "Invalid config file {}: {}", path.display(), message)
+            }
+            RsmapError::Regex { pattern, .. } => write!(f, "Invalid regex: {}", pattern),
+            #[cfg(feature = "sqlite")]
+            RsmapError::Sqlite { path, .. } => {
+                write!(f, "Cannot write SQLite database {}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for RsmapError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RsmapError::Metadata(e) => Some(e),
+            RsmapError::Io { source, .. } => Some(source),
+            RsmapError::Pattern { source, .. } => Some(source),
+            RsmapError::Regex { source, .. } => Some(source),
+            #[cfg(feature = "sqlite")]
+            RsmapError::Sqlite { source, .. } => Some(source),
+            RsmapError::Parse { .. }
+            | RsmapError::Cache { .. }
+            | RsmapError::Index { .. }
+            | RsmapError::Annotations { .. }
+            | RsmapError::Config { .. } => None,
+        }
+    }
+}
+
+impl From<cargo_metadata::Error> for RsmapError {
+    fn from(e: cargo_metadata::Error) -> Self {
+        RsmapError::Metadata(e)
+    }
+}
@@ -0,0 +1,303 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::annotations::{AnnotationStore, DescriptionOverrides};
+use crate::layer0::get_module_description;
+use crate::model::{CrateInfo, Item, Module};
+use crate::output::{html_code_block, html_escape};
+
+const STYLE: &str = "\
+body { font-family: system-ui, sans-serif; margin: 0; display: flex; }
+nav { width: 22rem; height: 100vh; overflow: auto; position: sticky; top: 0; \
+padding: 1rem; box-sizing: border-box; border-right: 1px solid #ddd; background: #fafafa; }
+nav ul { list-style: none; padding-left: 1rem; margin: 0; }
+nav > ul { padding-left: 0; }
+main { flex: 1; padding: 1rem 2rem; min-width: 0; }
+a { color: #0550ae; text-decoration: none; }
+a:hover { text-decoration: underline; }
+.meta { color: #666; font-size: 0.85em; }
+details { border: 1px solid #ddd; border-radius: 4px; margin: 0.5rem 0; padding: 0.25rem 0.75rem; }
+summary { cursor: pointer; font-weight: 600; }
+.item { margin: 1rem 0; }
+.doc { white-space: pre-wrap; margin: 0.25rem 0; }
+.note { color: #6a4c00; }
+.deprecated { color: #b00020; }
+pre { background: #f6f8fa; padding: 0.75rem; overflow-x: auto; border-radius: 4px; }
+";
+
+/// Generate a single self-contained HTML page (index.html)
+///
+/// The module tree (as in overview.md) links to a collapsible section per
+/// module listing its items (as in api-surface.md), each with its own anchor.
+pub fn generate_html(
+    crates: &[CrateInfo],
+    annotations: &AnnotationStore,
+    descriptions: &DescriptionOverrides,
+) -> String {
+    let mut out = String::new();
+    let mut anchors = Anchors::default();
+    out.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str("<title>rsmap index</title>\n");
+    out.push_str(&format!("<style>\n{}</style>\n", STYLE));
+    out.push_str("</head>\n<body>\n");
+
+    out.push_str("<nav>\n");
+    for crate_info in crates {
+        out.push_str(&format!(
+            "<h2>{} <span class=\"meta\">({})</span></h2>\n<ul>\n",
+            html_escape(&crate_info.name),
+            crate_info.kind
+        ));
+        write_tree_entry(
+            &mut out,
+            &mut anchors,
+            &crate_info.name,
+            &crate_info.root_module,
+            annotations,
+            descriptions,
+        );
+        out.push_str("</ul>\n");
+    }
+    out.push_str("</nav>\n");
+
+    out.push_str("<main>\n");
+    for crate_info in crates {
+        out.push_str(&format!(
+            "<h1>Crate: {} ({})</h1>\n<p class=\"meta\">Edition {} &middot; Version {}</p>\n",
+            html_escape(&crate_info.name),
+            crate_info.kind,
+            html_escape(&crate_info.edition),
+            html_escape(&crate_info.version)
+        ));
+        if !crate_info.external_deps.is_empty() {
+            out.push_str(&format!(
+                "<p class=\"meta\">External deps: {}</p>\n",
+                html_escape(&crate_info.external_deps.join(", "))
+            ));
+        }
+        for module in crate_info.root_module.all_modules() {
+            write_module_section(&mut out, &mut anchors, &crate_info.name, module, annotations);
+        }
+    }
+    out.push_str("</main>\n</body>\n</html>\n");
+
+    out
+}
+
+/// Anchor ids for module and item paths, qualified by crate so workspaces
+/// don't collide, and unique across the page
+#[derive(Default)]
+struct Anchors {
+    used: BTreeSet<String>,
+    modules: BTreeMap<String, String>,
+}
+
+impl Anchors {
+    /// The id of a module's section; the tree links to it by the same id
+    fn module(&mut self, crate_name: &str, path: &str) -> String {
+        let key = format!("{}/{}", crate_name, path);
+        if let Some(id) = self.modules.get(&key) {
+            return id.clone();
+        }
+        let id = self.fresh(&key);
+        self.modules.insert(key, id.clone());
+        id
+    }
+
+    /// A new id for an item; impl blocks for the same type share a path
+    fn item(&mut self, crate_name: &str, path: &str) -> String {
+        self.fresh(&format!("{}/{}", crate_name, path))
+    }
+
+    /// `key` as a slug, with `-2`, `-3`, ... appended if that's already taken
+    fn fresh(&mut self, key: &str) -> String {
+        let slug = anchor_slug(key);
+        let mut id = slug.clone();
+        let mut n = 2;
+        while !self.used.insert(id.clone()) {
+            id = format!("{}-{}", slug, n);
+            n += 1;
+        }
+        id
+    }
+}
+
+/// `path` with whitespace and `<>&"'` turned into single dashes, e.g.
+/// `crate::impl From<u16> for Expr` -> `crate::impl-From-u16-for-Expr`
+fn anchor_slug(path: &str) -> String {
+    let mut slug = String::with_capacity(path.len());
+    for c in path.chars() {
+        if c.is_whitespace() || matches!(c, '<' | '>' | '&' | '"' | '\'') {
+            if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        } else {
+            slug.push(c);
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+fn write_tree_entry(
+    out: &mut String,
+    anchors: &mut Anchors,
+    crate_name: &str,
+    module: &Module,
+    annotations: &AnnotationStore,
+    descriptions: &DescriptionOverrides,
+) {
+    let short_name = module.path.rsplit("::").next().unwrap_or(&module.path);
+    out.push_str(&format!(
+        "<li><a href=\"#{}\">{}</a>",
+        anchors.module(crate_name, &module.path),
+        html_escape(short_name)
+    ));
+    let description = get_module_description(module, annotations, descriptions);
+    if !description.is_empty() {
+        out.push_str(&format!(" <span class=\"meta\">— {}</span>", html_escape(&description)));
+    }
+    if !module.submodules.is_empty() {
+        out.push_str("\n<ul>\n");
+        for sub in &module.submodules {
+            write_tree_entry(out, anchors, crate_name, sub, annotations, descriptions);
+        }
+        out.push_str("</ul>\n");
+    }
+    out.push_str("</li>\n");
+}
+
+fn write_module_section(
+    out: &mut String,
+    anchors: &mut Anchors,
+    crate_name: &str,
+    module: &Module,
+    annotations: &AnnotationStore,
+) {
+    out.push_str(&format!(
+        "<details id=\"{}\">\n<summary>{} <span class=\"meta\">{} &middot; {} items</span>\
+         </summary>\n",
+        anchors.module(crate_name, &module.path),
+        html_escape(&module.path),
+        html_escape(&module.file_path.display().to_string()),
+        module.items.len()
+    ));
+
+    let mut items: Vec<&Item> = module.items.iter().collect();
+    items.sort_by_key(|i| i.line_start);
+    for item in items {
+        write_item(out, anchors, crate_name, item, annotations, &module.path);
+    }
+
+    out.push_str("</details>\n");
+}
+
+fn write_item(
+    out: &mut String,
+    anchors: &mut Anchors,
+    crate_name: &str,
+    item: &Item,
+    annotations: &AnnotationStore,
+    module_path: &str,
+) {
+    let full_path = item.full_path(module_path);
+    let id = anchors.item(crate_name, &full_path);
+    out.push_str(&format!(
+        "<div class=\"item\" id=\"{}\">\n<a href=\"#{}\">{}</a> <span class=\"meta\">[{}] \
+         line {}</span>\n",
+        id,
+        id,
+        html_escape(full_path.rsplit("::").next().unwrap_or(&full_path)),
+        html_escape(&item.kind.to_string()),
+        item.line_start
+    ));
+
+    if let Some(ref doc) = item.doc_comment {
+        out.push_str(&format!("<p class=\"doc\">{}</p>\n", html_escape(doc)));
+    }
+
+    let item_path = format!("{}::{}", module_path, item.name);
+    if let Some(entry) = annotations.items.get(&item_path) {
+        if !entry.note.is_empty() {
+            out.push_str(&format!(
+                "<p class=\"note\">NOTE: {}</p>\n",
+                html_escape(&entry.note)
+            ));
+        }
+    }
+
+    if let Some(ref deprecated) = item.deprecated {
+        let mut text = "DEPRECATED".to_string();
+        if let Some(ref since) = deprecated.since {
+            text.push_str(&format!(" (since {})", since));
+        }
+        if let Some(ref note) = deprecated.note {
+            text.push_str(&format!(": {}", note));
+        }
+        out.push_str(&format!("<p class=\"deprecated\">{}</p>\n", html_escape(&text)));
+    }
+
+    out.push_str(&html_code_block(&item.signature, "rust"));
+    out.push_str("\n</div>\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_generate_html() {
+        let items = crate::parse::parse_file(
+            &PathBuf::from("src/lib.rs"),
+            "/// Picks <one>\npub fn pick<T>(xs: &[T]) -> &T { &xs[0] }\n",
+        )
+        .unwrap();
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            Module {
+                doc_comment: Some("The root".to_string()),
+                loc: 2,
+                ..Module::for_test("crate", "src/lib.rs").with_items(items)
+            },
+        )];
+
+        let html =
+            generate_html(&crates, &AnnotationStore::default(), &DescriptionOverrides::default());
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<style>"));
+        assert!(!html.contains("<link"));
+        assert!(html.contains("<a href=\"#test/crate\">crate</a>"));
+        assert!(html.contains("— The root"));
+        assert!(html.contains("<details id=\"test/crate\">"));
+        assert!(html.contains("<div class=\"item\" id=\"test/crate::pick\">"));
+        assert!(html.contains("<p class=\"doc\">Picks &lt;one&gt;</p>"));
+        assert!(html.contains("<code class=\"language-rust\">pub fn pick&lt;T&gt;"));
+    }
+
+    #[test]
+    fn test_impl_anchors() {
+        let items = crate::parse::parse_file(
+            &PathBuf::from("src/lib.rs"),
+            "pub struct Wrapper<T>(T);\n\
+             impl<T> From<T> for Wrapper<T> { fn from(t: T) -> Self { Wrapper(t) } }\n\
+             impl<T> Wrapper<T> { pub fn get(&self) -> &T { &self.0 } }\n\
+             impl<T> Wrapper<T> { pub fn take(self) -> T { self.0 } }\n",
+        )
+        .unwrap();
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            Module {
+                loc: 4,
+                ..Module::for_test("crate", "src/lib.rs").with_items(items)
+            },
+        )];
+
+        let html =
+            generate_html(&crates, &AnnotationStore::default(), &DescriptionOverrides::default());
+        let id = "test/crate::impl-From-T-for-Wrapper-T";
+        assert!(html.contains(&format!("id=\"{}\">\n<a href=\"#{}\">", id, id)));
+        // Both `impl Wrapper<T>` blocks get an anchor of their own
+        assert!(html.contains("id=\"test/crate::impl-Wrapper-T\""));
+        assert!(html.contains("id=\"test/crate::impl-Wrapper-T-2\""));
+    }
+}
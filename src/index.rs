@@ -0,0 +1,121 @@
+//! In-memory lookups over the parsed crates, for tools that answer many
+//! queries (an LSP server, a chat plugin) without going through index.json.
+//!
+//! Item paths are the keys index.json uses, e.g. `crate::engine::evaluate` or
+//! `crate::engine::eval::impl Evaluable for Expr`.
+
+use std::collections::BTreeMap;
+
+use crate::layer3;
+use crate::model::{CrateInfo, Item, Module};
+
+/// The parsed crates with lookup maps built once, up front
+#[derive(Debug, Clone)]
+pub struct Index {
+    crates: Vec<CrateInfo>,
+    /// Where each module sits: its crate, then its submodule index at each level
+    module_routes: Vec<(usize, Vec<usize>)>,
+    /// Module path -> position in `module_routes`
+    modules: BTreeMap<String, usize>,
+    /// Item path -> position of its module in `module_routes`, and of the item in it
+    items: BTreeMap<String, (usize, usize)>,
+    /// Trait path -> paths of the types implementing it
+    implementors: BTreeMap<String, Vec<String>>,
+}
+
+impl Index {
+    /// Index `crates`. As in index.json, when two items (or modules) share a
+    /// path, e.g. in a lib and a bin target, the later one wins.
+    pub fn new(crates: Vec<CrateInfo>) -> Self {
+        let mut module_routes = Vec::new();
+        for (crate_index, crate_info) in crates.iter().enumerate() {
+            let root = &crate_info.root_module;
+            collect_routes(root, crate_index, &mut Vec::new(), &mut module_routes);
+        }
+
+        let mut index = Index {
+            implementors: layer3::trait_implementors(&crates),
+            crates,
+            module_routes,
+            modules: BTreeMap::new(),
+            items: BTreeMap::new(),
+        };
+        for id in 0..index.module_routes.len() {
+            let module = index.module_at(id);
+            let path = module.path.clone();
+            let item_paths: Vec<String> =
+                module.items.iter().map(|item| item.full_path(&module.path)).collect();
+            index.modules.insert(path, id);
+            for (i, item_path) in item_paths.into_iter().enumerate() {
+                index.items.insert(item_path, (id, i));
+            }
+        }
+        index
+    }
+
+    /// The indexed crates
+    pub fn crates(&self) -> &[CrateInfo] {
+        &self.crates
+    }
+
+    /// The item at `path`, e.g. `crate::engine::eval::Expr`
+    pub fn get(&self, path: &str) -> Option<&Item> {
+        let &(module, item) = self.items.get(path)?;
+        self.module_at(module).items.get(item)
+    }
+
+    /// The module at `path`, e.g. `crate::engine`
+    pub fn module(&self, path: &str) -> Option<&Module> {
+        self.modules.get(path).map(|&id| self.module_at(id))
+    }
+
+    /// The items declared directly in the module at `path` (not in its
+    /// submodules), or `None` if there is no such module
+    pub fn items_in_module(&self, path: &str) -> Option<&[Item]> {
+        self.module(path).map(|module| module.items.as_slice())
+    }
+
+    /// Paths of the types with an impl of `trait_path`, sorted; blanket impls
+    /// aren't listed. Traits defined in the crates go by their full path
+    /// (`crate::engine::eval::Evaluable`), others as written
+    /// (`std::fmt::Display`, `From<String>`).
+    pub fn implementors_of(&self, trait_path: &str) -> &[String] {
+        self.implementors.get(trait_path).map_or(&[], Vec::as_slice)
+    }
+
+    /// Every item for which `predicate(path, item)` holds, in path order
+    pub fn search<F>(&self, predicate: F) -> Vec<(&str, &Item)>
+    where
+        F: Fn(&str, &Item) -> bool,
+    {
+        self.items
+            .iter()
+            .filter_map(|(path, &(module, item))| {
+                let item = self.module_at(module).items.get(item)?;
+                predicate(path, item).then_some((path.as_str(), item))
+            })
+            .collect()
+    }
+
+    fn module_at(&self, id: usize) -> &Module {
+        let (crate_index, ref route) = self.module_routes[id];
+        route.iter().fold(&self.crates[crate_index].root_module, |module, &sub| {
+            &module.submodules[sub]
+        })
+    }
+}
+
+/// Record the route to `module` and each module below it
+fn collect_routes(
+    module: &Module,
+    crate_index: usize,
+    route: &mut Vec<usize>,
+    out: &mut Vec<(usize, Vec<usize>)>,
+) {
+    out.push((crate_index, route.clone()));
+    for (i, sub) in module.submodules.iter().enumerate() {
+        route.push(i);
+        collect_routes(sub, crate_index, route, out);
+        route.pop();
+    }
+}
@@ -1,20 +1,53 @@
-use crate::annotations::AnnotationStore;
-use crate::model::{CrateInfo, Module};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::annotations::{AnnotationStore, DescriptionOverrides};
+use crate::layer1::VisibilityFilter;
+use crate::model::{CrateInfo, ItemKind, Module, Visibility};
 use crate::output;
 
 /// Generate Layer 0: Overview (overview.md)
 ///
-/// Contains crate info, module tree with descriptions, and token estimates.
-pub fn generate_overview(crates: &[CrateInfo], annotations: &AnnotationStore) -> String {
+/// Contains crate info, a count of the public API (see [`public_api_stats`]),
+/// module tree with descriptions (see [`get_module_description`]), and token
+/// estimates.
+/// With `max_depth`, modules below that depth are collapsed into a count on
+/// their ancestor's line. Workspaces of several crates start with the union of
+/// their external deps. Under the `pub` visibility view, modules unreachable
+/// from outside the crate are left out of the tree and the estimates.
+pub fn generate_overview(
+    crates: &[CrateInfo],
+    annotations: &AnnotationStore,
+    descriptions: &DescriptionOverrides,
+    max_depth: Option<usize>,
+    visibility: VisibilityFilter,
+) -> String {
     let mut out = String::new();
 
+    let all: Vec<&CrateInfo> = crates.iter().collect();
+    if crate_count(&all) > 1 {
+        out.push_str("# Workspace Dependencies\n");
+        write_workspace_deps(&mut out, &all);
+        out.push('\n');
+    }
+
     for crate_info in crates {
+        let root = visibility.module_tree(&crate_info.root_module);
         out.push_str(&format!(
             "# Crate: {} ({})\n",
             crate_info.name, crate_info.kind
         ));
         out.push_str(&format!("Edition: {}\n", crate_info.edition));
         out.push_str(&format!("Version: {}\n", crate_info.version));
+        if !crate_info.crate_attributes.is_empty() {
+            out.push_str(&format!(
+                "Crate attributes: {}\n",
+                crate_info.crate_attributes.join(" ")
+            ));
+        }
+        out.push_str(&format!(
+            "Estimated tokens: ~{}\n",
+            crate_token_estimate(&root)
+        ));
 
         if !crate_info.external_deps.is_empty() {
             out.push_str(&format!(
@@ -22,9 +55,24 @@ pub fn generate_overview(crates: &[CrateInfo], annotations: &AnnotationStore) ->
                 crate_info.external_deps.join(", ")
             ));
         }
+        if !crate_info.dev_deps.is_empty() {
+            out.push_str(&format!("Dev deps: {}\n", crate_info.dev_deps.join(", ")));
+        }
+        if !crate_info.build_deps.is_empty() {
+            out.push_str(&format!("Build deps: {}\n", crate_info.build_deps.join(", ")));
+        }
+
+        let stats = public_api_stats(&crate_info.root_module);
+        out.push_str("\n## Public API\n");
+        out.push_str("| Structs | Enums | Traits | Functions | Total items |\n");
+        out.push_str("|---------|-------|--------|-----------|-------------|\n");
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n",
+            stats.structs, stats.enums, stats.traits, stats.functions, stats.total
+        ));
 
         out.push_str("\n## Module Tree\n");
-        write_module_tree(&mut out, &crate_info.root_module, 0, annotations);
+        write_module_tree(&mut out, &root, 0, max_depth, annotations, descriptions);
 
         out.push('\n');
     }
@@ -32,45 +80,180 @@ pub fn generate_overview(crates: &[CrateInfo], annotations: &AnnotationStore) ->
     out
 }
 
+/// Top-level overview.md for `--split-by-crate`: one link per crate to the
+/// layers in its subdirectory, named `overview_file` and `api_surface_file`
+pub fn generate_crate_index(
+    crates: &[(&CrateInfo, &str)],
+    overview_file: &str,
+    api_surface_file: &str,
+) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("# Workspace ({} crate(s))\n\n", crates.len()));
+    for (crate_info, dir) in crates {
+        out.push_str(&format!(
+            "- [{}]({}/{}) ({}) — v{}, ~{} tokens — [API surface]({}/{})\n",
+            crate_info.name,
+            dir,
+            overview_file,
+            crate_info.kind,
+            crate_info.version,
+            crate_token_estimate(&crate_info.root_module),
+            dir,
+            api_surface_file
+        ));
+    }
+
+    let all: Vec<&CrateInfo> = crates.iter().map(|(crate_info, _)| *crate_info).collect();
+    out.push_str("\n## Workspace Dependencies\n");
+    write_workspace_deps(&mut out, &all);
+    out
+}
+
+/// Counts of a crate's `pub` items by kind
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ApiStats {
+    pub structs: usize,
+    pub enums: usize,
+    pub traits: usize,
+    /// Free functions; methods belong to their impl
+    pub functions: usize,
+    /// Every counted item, of any kind but re-exports (which name items
+    /// counted where they're defined, or outside the crate)
+    pub total: usize,
+}
+
+/// Tally the `pub` items reachable from outside the crate: those in `root`
+/// and in the `pub` modules below it, as in the `pub` view of api-surface.md.
+/// Test modules and `#[doc(hidden)]` items are left out.
+pub fn public_api_stats(root: &Module) -> ApiStats {
+    let mut stats = ApiStats::default();
+    let root = VisibilityFilter::Pub.module_tree(root);
+    for module in root.all_modules().into_iter().filter(|m| !m.is_test) {
+        let public = module
+            .items
+            .iter()
+            .filter(|i| i.visibility == Visibility::Pub && !i.doc_hidden);
+        for item in public {
+            match item.kind {
+                ItemKind::Struct { .. } => stats.structs += 1,
+                ItemKind::Enum => stats.enums += 1,
+                ItemKind::Trait { .. } => stats.traits += 1,
+                ItemKind::Function => stats.functions += 1,
+                ItemKind::Use => continue,
+                _ => {}
+            }
+            stats.total += 1;
+        }
+    }
+    stats
+}
+
+/// Crates counted by name, so a package's lib and bin of the same name are one
+fn crate_count(crates: &[&CrateInfo]) -> usize {
+    crates.iter().map(|c| &c.name).collect::<BTreeSet<_>>().len()
+}
+
+/// List every external dep of `crates` with the number of crates using it
+fn write_workspace_deps(out: &mut String, crates: &[&CrateInfo]) {
+    let mut users: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for crate_info in crates {
+        for dep in &crate_info.external_deps {
+            users.entry(dep).or_default().insert(&crate_info.name);
+        }
+    }
+
+    if users.is_empty() {
+        out.push_str("(none)\n");
+    }
+    for (dep, names) in users {
+        out.push_str(&format!("- {} — used by {} crate(s)\n", dep, names.len()));
+    }
+}
+
 fn write_module_tree(
     out: &mut String,
     module: &Module,
     depth: usize,
+    max_depth: Option<usize>,
     annotations: &AnnotationStore,
+    descriptions: &DescriptionOverrides,
 ) {
-    let description = get_module_description(module, annotations);
+    let description = get_module_description(module, annotations, descriptions);
     let entry = output::tree_entry(&module.path, &description, depth);
     out.push_str(&entry);
+    out.push_str(&format!(
+        " ({} LoC, {} items, ~{} tokens)",
+        module.loc,
+        module.items.len(),
+        module_token_estimate(module)
+    ));
+    if module.error.is_some() {
+        out.push_str(" [parse error]");
+    }
+
+    if max_depth.is_some_and(|max| depth >= max) && !module.submodules.is_empty() {
+        // all_modules includes the module itself
+        let collapsed = module.all_modules().len() - 1;
+        out.push_str(&format!(" (… {} submodules collapsed)\n", collapsed));
+        return;
+    }
     out.push('\n');
 
     for sub in &module.submodules {
-        write_module_tree(out, sub, depth + 1, annotations);
+        write_module_tree(out, sub, depth + 1, max_depth, annotations, descriptions);
+    }
+}
+
+/// Estimate the tokens a module contributes to api-surface.md (its own items only)
+fn module_token_estimate(module: &Module) -> usize {
+    let mut text = String::new();
+    for item in &module.items {
+        if let Some(ref doc) = item.doc_comment {
+            text.push_str(doc);
+            text.push('\n');
+        }
+        text.push_str(&item.signature);
+        text.push('\n');
     }
+    output::estimate_tokens(&text)
+}
+
+/// Estimate the tokens for a whole crate (all modules)
+fn crate_token_estimate(root: &Module) -> usize {
+    root.all_modules()
+        .iter()
+        .map(|m| module_token_estimate(m))
+        .sum()
 }
 
 /// Get module description from various sources (priority order):
 /// 1. Inner doc comment (//!)
-/// 2. Annotation
-/// 3. Empty placeholder
-fn get_module_description(module: &Module, annotations: &AnnotationStore) -> String {
+/// 2. descriptions.toml
+/// 3. Annotation
+/// 4. Empty placeholder
+pub(crate) fn get_module_description(
+    module: &Module,
+    annotations: &AnnotationStore,
+    descriptions: &DescriptionOverrides,
+) -> String {
     // Priority 1: Inner doc comment
-    if let Some(ref doc) = module.doc_comment {
-        // Take only the first line/sentence
-        let first_line = doc.lines().next().unwrap_or("");
-        let trimmed = first_line.trim();
-        if !trimmed.is_empty() {
-            return trimmed.to_string();
-        }
+    if let Some(line) = module.doc_comment.as_deref().and_then(output::first_doc_line) {
+        return line.to_string();
     }
 
-    // Priority 2: Annotation
+    // Priority 2: descriptions.toml
+    if let Some(description) = descriptions.modules.get(&module.path) {
+        return description.clone();
+    }
+
+    // Priority 3: Annotation
     if let Some(entry) = annotations.modules.get(&module.path) {
         if !entry.note.is_empty() {
             return entry.note.clone();
         }
     }
 
-    // Priority 3: Empty
+    // Priority 4: Empty
     String::new()
 }
 
@@ -82,58 +265,166 @@ mod tests {
 
     fn sample_crate() -> CrateInfo {
         CrateInfo {
-            name: "test_crate".to_string(),
-            kind: CrateKind::Lib,
-            edition: "2021".to_string(),
-            version: "0.1.0".to_string(),
             external_deps: vec!["serde".to_string(), "tokio".to_string()],
-            root_module: Module {
-                path: "crate".to_string(),
-                file_path: PathBuf::from("src/lib.rs"),
-                file_hash: "abc123".to_string(),
-                doc_comment: Some("Main library crate".to_string()),
-                visibility: Visibility::Pub,
-                items: vec![],
-                submodules: vec![
-                    Module {
-                        path: "crate::config".to_string(),
-                        file_path: PathBuf::from("src/config.rs"),
-                        file_hash: "def456".to_string(),
-                        doc_comment: Some("Configuration module".to_string()),
-                        visibility: Visibility::Pub,
-                        items: vec![],
-                        submodules: vec![],
-                        use_statements: vec![],
-                        is_inline: false,
-                    },
-                    Module {
-                        path: "crate::engine".to_string(),
-                        file_path: PathBuf::from("src/engine/mod.rs"),
-                        file_hash: "ghi789".to_string(),
-                        doc_comment: None,
-                        visibility: Visibility::Pub,
-                        items: vec![],
-                        submodules: vec![],
-                        use_statements: vec![],
-                        is_inline: false,
-                    },
-                ],
-                use_statements: vec![],
-                is_inline: false,
-            },
+            dev_deps: vec!["pretty_assertions".to_string()],
+            ..CrateInfo::for_test(
+                "test_crate",
+                Module {
+                    doc_comment: Some("Main library crate".to_string()),
+                    ..Module::for_test("crate", "src/lib.rs").with_submodules(vec![
+                        Module {
+                            doc_comment: Some("Configuration module".to_string()),
+                            ..Module::for_test("crate::config", "src/config.rs")
+                        },
+                        Module::for_test("crate::engine", "src/engine/mod.rs"),
+                    ])
+                },
+            )
         }
     }
 
     #[test]
     fn test_generate_overview() {
-        let crates = vec![sample_crate()];
+        let mut crates = vec![sample_crate()];
+        crates[0].crate_attributes =
+            vec!["#![no_std]".to_string(), "#![feature(never_type)]".to_string()];
         let annotations = AnnotationStore::default();
-        let output = generate_overview(&crates, &annotations);
+        let descriptions = DescriptionOverrides::default();
+        let output =
+            generate_overview(&crates, &annotations, &descriptions, None, VisibilityFilter::All);
 
         assert!(output.contains("# Crate: test_crate (lib)"));
+        // A lone crate's deps aren't repeated in a workspace summary
+        assert!(!output.contains("Workspace Dependencies"));
         assert!(output.contains("Edition: 2021"));
-        assert!(output.contains("serde, tokio"));
+        assert!(output.contains("Crate attributes: #![no_std] #![feature(never_type)]\n"));
+        assert!(output.contains("External deps: serde, tokio\n"));
+        assert!(output.contains("Dev deps: pretty_assertions\n"));
+        assert!(!output.contains("Build deps:"));
         assert!(output.contains("- crate — Main library crate"));
         assert!(output.contains("  - config — Configuration module"));
+        assert!(output.contains("Estimated tokens: ~0"));
+        assert!(output.contains("  - engine (0 LoC, 0 items, ~0 tokens)"));
+    }
+
+    #[test]
+    fn test_description_priority() {
+        let krate = sample_crate();
+        let mut annotations = AnnotationStore::default();
+        for path in ["crate::config", "crate::engine"] {
+            annotations.modules.insert(
+                path.to_string(),
+                crate::annotations::AnnotationEntry {
+                    note: format!("annotated {}", path),
+                    ..Default::default()
+                },
+            );
+        }
+        let mut descriptions = DescriptionOverrides::default();
+        for path in ["crate", "crate::engine"] {
+            descriptions.modules.insert(path.to_string(), format!("described {}", path));
+        }
+
+        // Doc comments win, then descriptions.toml, then annotations
+        let output =
+            generate_overview(&[krate], &annotations, &descriptions, None, VisibilityFilter::All);
+        assert!(output.contains("- crate — Main library crate ("));
+        assert!(output.contains("  - config — Configuration module ("));
+        assert!(output.contains("  - engine — described crate::engine ("));
+    }
+
+    #[test]
+    fn test_token_estimates() {
+        let mut krate = sample_crate();
+        krate.root_module.submodules[0].items.push(Item {
+            name: "load".to_string(),
+            kind: ItemKind::Function,
+            visibility: Visibility::Pub,
+            signature: "pub fn load() -> Config;".to_string(), // 24 chars
+            doc_comment: Some("Load it".to_string()),      // 7 chars
+            derives: vec![],
+            attributes: vec![],
+            deprecated: None,
+            generics: vec![],
+            where_clause: None,
+            supertraits: Vec::new(),
+            abi: None,
+            members: vec![],
+            params: vec![],
+            return_type: None,
+            return_trait: None,
+            is_async: false,
+            is_unsafe: false,
+            is_const: false,
+            repr: None,
+            cfg: None,
+            doc_hidden: false,
+            reexport_of: None,
+            file_path: PathBuf::from("src/config.rs"),
+            line_start: 1,
+            line_end: 3,
+            byte_start: 0,
+            byte_end: 0,
+            content_hash: "h".to_string(),
+        });
+        let annotations = AnnotationStore::default();
+        let descriptions = DescriptionOverrides::default();
+        let output =
+            generate_overview(&[krate], &annotations, &descriptions, None, VisibilityFilter::All);
+
+        // 24 + 7 + 2 newlines = 33 chars -> 9 tokens
+        assert!(output.contains("Estimated tokens: ~9"));
+        assert!(output.contains("  - config — Configuration module (0 LoC, 1 items, ~9 tokens)"));
+        assert!(output.contains("- crate — Main library crate (0 LoC, 0 items, ~0 tokens)"));
+    }
+
+    #[test]
+    fn test_generate_crate_index() {
+        let krate = sample_crate();
+        let output = generate_crate_index(
+            &[(&krate, "test_crate")],
+            "overview.md",
+            "api-surface.md",
+        );
+        assert!(output.starts_with("# Workspace (1 crate(s))\n\n"));
+        assert!(output.contains(
+            "- [test_crate](test_crate/overview.md) (lib) — v0.1.0, ~0 tokens — \
+             [API surface](test_crate/api-surface.md)\n"
+        ));
+        assert!(output.ends_with(
+            "\n## Workspace Dependencies\n\
+             - serde — used by 1 crate(s)\n\
+             - tokio — used by 1 crate(s)\n"
+        ));
+    }
+
+    #[test]
+    fn test_public_api_stats() {
+        let parse = |file: &str, source: &str| {
+            crate::parse::parse_file(&PathBuf::from(file), source).unwrap()
+        };
+        let mut krate = sample_crate();
+        let root = &mut krate.root_module;
+        root.items = parse(
+            "src/lib.rs",
+            "pub struct Config;\npub use engine::Engine;\n\
+             #[doc(hidden)]\npub fn internal() {}\npub fn run() {}\n",
+        );
+        root.submodules[0].items =
+            parse("src/config.rs", "pub enum Mode { A }\npub trait Load {}\n");
+        // A private module's pub items aren't reachable from outside the crate
+        root.submodules[1].visibility = Visibility::Private;
+        root.submodules[1].items = parse("src/engine/mod.rs", "pub struct Engine;\n");
+
+        assert_eq!(
+            public_api_stats(&krate.root_module),
+            ApiStats {
+                structs: 1,
+                enums: 1,
+                traits: 1,
+                functions: 1,
+                total: 4,
+            }
+        );
     }
 }
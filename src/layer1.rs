@@ -1,70 +1,462 @@
+use globset::GlobSet;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+
 use crate::annotations::AnnotationStore;
-use crate::model::{CrateInfo, Item, ItemKind, Module};
+use crate::error::Result;
+use crate::model::{CrateInfo, Item, ItemKind, MemberKind, Module, ProcMacroKind, Visibility};
+use crate::output::{self, SourceFiles};
+use crate::parse;
+use crate::resolve;
+
+/// How items are ordered within each module of api-surface.md
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Layout {
+    /// Bucket items into Types/Traits/Functions/etc. sections
+    #[default]
+    Grouped,
+    /// Emit items in source order, with their kind shown inline
+    Source,
+}
+
+fn retain_pub_modules(module: &mut Module) {
+    module.submodules.retain(|sub| sub.visibility == Visibility::Pub);
+    for sub in &mut module.submodules {
+        retain_pub_modules(sub);
+    }
+}
+
+/// Default `--max-signature-width`, matching rustfmt's line width
+pub const DEFAULT_SIGNATURE_WIDTH: usize = 100;
+
+/// Which items api-surface.md lists, by visibility
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum VisibilityFilter {
+    /// Only `pub` items (the external API)
+    Pub,
+    /// `pub` and `pub(crate)` items
+    PubCrate,
+    /// Every item, private ones included
+    #[default]
+    All,
+}
+
+impl VisibilityFilter {
+    fn allows(self, visibility: &Visibility) -> bool {
+        match self {
+            VisibilityFilter::Pub => *visibility == Visibility::Pub,
+            VisibilityFilter::PubCrate => {
+                matches!(visibility, Visibility::Pub | Visibility::PubCrate)
+            }
+            VisibilityFilter::All => true,
+        }
+    }
+
+    /// The module tree as this view lists it. The `pub` view drops modules
+    /// that aren't `pub` along with everything below them, since a `pub` item
+    /// inside a private module isn't reachable from outside the crate.
+    pub fn module_tree(self, root: &Module) -> Cow<'_, Module> {
+        if self != VisibilityFilter::Pub {
+            return Cow::Borrowed(root);
+        }
+        let mut root = root.clone();
+        retain_pub_modules(&mut root);
+        Cow::Owned(root)
+    }
+
+    /// The item as it should be listed, or `None` if it's filtered out.
+    ///
+    /// `#[doc(hidden)]` items are left out of the `pub` view. Inherent impls
+    /// keep only the members that pass (and are dropped if none do); trait
+    /// impls are kept whole since their items share the trait's visibility,
+    /// unless their self type is declared in `module_items` and filtered out.
+    fn apply(self, item: &Item, module_items: &[Item]) -> Option<Item> {
+        if self == VisibilityFilter::All {
+            return Some(item.clone());
+        }
+        if self == VisibilityFilter::Pub && item.doc_hidden {
+            return None;
+        }
+        match &item.kind {
+            ItemKind::Impl {
+                self_ty,
+                trait_name: Some(_),
+                ..
+            } => {
+                let self_name = self_ty.split('<').next().unwrap_or(self_ty).trim();
+                let hidden_self = module_items.iter().any(|declared| {
+                    declared.name == self_name
+                        && matches!(
+                            declared.kind,
+                            ItemKind::Struct { .. } | ItemKind::Enum | ItemKind::TypeAlias
+                        )
+                        && !self.allows(&declared.visibility)
+                });
+                (!hidden_self).then(|| item.clone())
+            }
+            ItemKind::Impl {
+                trait_name: None, ..
+            } => {
+                let members: Vec<_> = item
+                    .members
+                    .iter()
+                    .filter(|m| self.allows(&m.visibility))
+                    .cloned()
+                    .collect();
+                if members.is_empty() {
+                    return None;
+                }
+                let header = item
+                    .signature
+                    .split_once(" {\n")
+                    .map_or(item.signature.as_str(), |(header, _)| header);
+                let body: Vec<String> = members
+                    .iter()
+                    .map(|m| {
+                        let docs = match (&m.kind, &m.doc_comment) {
+                            (MemberKind::Method, Some(doc)) => {
+                                parse::doc_comment_lines(doc, "    ")
+                            }
+                            _ => String::new(),
+                        };
+                        format!("{}    {}", docs, m.signature)
+                    })
+                    .collect();
+                Some(Item {
+                    signature: format!("{} {{\n{}\n}}", header, body.join("\n")),
+                    members,
+                    ..item.clone()
+                })
+            }
+            _ if self.allows(&item.visibility) => Some(item.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Items whose full source api-surface.md shows in place of their signature,
+/// picked by `--include-body` globs over item paths, e.g. `crate::init` or
+/// `engine::*`. Globs match with or without the leading `crate::`.
+#[derive(Debug, Default)]
+pub struct BodyFilter {
+    globs: GlobSet,
+    /// Item file paths are relative to this directory
+    project_root: PathBuf,
+    sources: RefCell<SourceFiles>,
+}
+
+impl BodyFilter {
+    pub fn new(project_root: &Path, patterns: &[String]) -> Result<Self> {
+        Ok(BodyFilter {
+            globs: resolve::build_globs("--include-body", patterns)?,
+            project_root: project_root.to_path_buf(),
+            sources: RefCell::default(),
+        })
+    }
+
+    fn matches(&self, item_path: &str) -> bool {
+        let relative = item_path.strip_prefix("crate::").unwrap_or(item_path);
+        self.globs.is_match(relative) || self.globs.is_match(item_path)
+    }
+
+    /// The item with its signature swapped for its source lines in a fenced
+    /// block, if it matches. The source carries the doc comment, so that is
+    /// dropped to avoid printing it twice. Unreadable files keep the signature.
+    fn apply(&self, module_path: &str, item: Item) -> Item {
+        if self.globs.is_empty() || !self.matches(&format!("{}::{}", module_path, item.name)) {
+            return item;
+        }
+        let file = self.project_root.join(&item.file_path);
+        let source = self.sources.borrow_mut().lines(&file, item.line_start, item.line_end);
+        match source {
+            Some(source) => Item {
+                signature: output::code_block(&source, "rust"),
+                doc_comment: None,
+                ..item
+            },
+            None => item,
+        }
+    }
+}
 
 /// Generate Layer 1: API Surface (api-surface.md)
 ///
-/// All items (pub AND private), grouped by module, signatures only.
-pub fn generate_api_surface(crates: &[CrateInfo], annotations: &AnnotationStore) -> String {
+/// All items (pub AND private, unless narrowed by `visibility`), grouped by
+/// module, signatures only (full source for items picked by `bodies`). The
+/// `pub` view also leaves out private modules and everything inside them. Fn
+/// signatures longer than `max_signature_width` (0 for no limit) get one
+/// parameter per line. With `compact_impls`, impl blocks show only their header
+/// and method names. Test modules (when indexed) are listed separately after
+/// the crate's main modules.
+pub fn generate_api_surface(
+    crates: &[CrateInfo],
+    annotations: &AnnotationStore,
+    layout: Layout,
+    visibility: VisibilityFilter,
+    bodies: &BodyFilter,
+    max_signature_width: usize,
+    compact_impls: bool,
+) -> String {
     let mut out = String::new();
+    let style = SignatureStyle {
+        max_width: max_signature_width,
+        compact_impls,
+    };
 
     for crate_info in crates {
         out.push_str(&format!(
             "# Crate: {} ({})\n\n",
             crate_info.name, crate_info.kind
         ));
-        write_module_surface(&mut out, &crate_info.root_module, annotations);
+        let root = visibility.module_tree(&crate_info.root_module);
+        write_module_surface(
+            &mut out,
+            &root,
+            annotations,
+            layout,
+            visibility,
+            bodies,
+            style,
+        );
+
+        let mut test_modules = Vec::new();
+        collect_test_roots(&root, &mut test_modules);
+        if !test_modules.is_empty() {
+            out.push_str("# Test Modules (#[cfg(test)])\n\n");
+            for module in test_modules {
+                write_module_surface(
+                    &mut out,
+                    module,
+                    annotations,
+                    layout,
+                    visibility,
+                    bodies,
+                    style,
+                );
+            }
+        }
     }
 
     out
 }
 
-fn write_module_surface(out: &mut String, module: &Module, annotations: &AnnotationStore) {
-    // Module header
-    out.push_str(&format!("# {}\n", module.path));
-    out.push_str(&format!(
-        "<!-- file: {} -->\n\n",
-        module.file_path.display(),
-    ));
+/// How api-surface.md renders signatures
+#[derive(Debug, Clone, Copy)]
+struct SignatureStyle {
+    /// Wrap fn signatures longer than this (0: never)
+    max_width: usize,
+    compact_impls: bool,
+}
+
+impl SignatureStyle {
+    fn render(self, item: &Item) -> String {
+        if self.compact_impls && matches!(item.kind, ItemKind::Impl { .. }) {
+            compact_impl_signature(item)
+        } else {
+            parse::wrap_signature(&item.signature, self.max_width)
+        }
+    }
+}
+
+/// An impl block as its header and method names, e.g.
+/// `impl Evaluable for Expr { eval }`
+fn compact_impl_signature(item: &Item) -> String {
+    let header = item
+        .signature
+        .split_once(" {\n")
+        .map_or(item.signature.as_str(), |(header, _)| header);
+    let methods: Vec<&str> = item
+        .members
+        .iter()
+        .filter(|m| m.kind == MemberKind::Method)
+        .map(|m| m.name.as_str())
+        .collect();
+    if methods.is_empty() {
+        format!("{} {{}}", header)
+    } else {
+        format!("{} {{ {} }}", header, methods.join(", "))
+    }
+}
+
+/// Fold each impl block into the first earlier one with the same header and
+/// cfg, so `impl Foo` written three times reads as one block with every
+/// method in source order
+fn merge_impl_blocks(items: Vec<Item>) -> Vec<Item> {
+    let mut merged: Vec<Item> = Vec::with_capacity(items.len());
+    for item in items {
+        let earlier = if matches!(item.kind, ItemKind::Impl { .. }) {
+            merged.iter_mut().find(|m| {
+                matches!(m.kind, ItemKind::Impl { .. })
+                    && m.cfg == item.cfg
+                    && split_impl_signature(&m.signature).0
+                        == split_impl_signature(&item.signature).0
+            })
+        } else {
+            None
+        };
+        let Some(first) = earlier else {
+            merged.push(item);
+            continue;
+        };
+        let (header, body) = split_impl_signature(&first.signature);
+        let (_, more) = split_impl_signature(&item.signature);
+        let body = match (body.is_empty(), more.is_empty()) {
+            (_, true) => body.to_string(),
+            (true, false) => more.to_string(),
+            (false, false) => format!("{}\n{}", body, more),
+        };
+        first.signature = format!("{} {{\n{}\n}}", header, body);
+        first.members.extend(item.members);
+        first.doc_comment = match (first.doc_comment.take(), item.doc_comment) {
+            (Some(a), Some(b)) => Some(format!("{}\n\n{}", a, b)),
+            (a, b) => a.or(b),
+        };
+    }
+    merged
+}
+
+/// An impl signature's header and body, without the braces around the body
+fn split_impl_signature(signature: &str) -> (&str, &str) {
+    match signature.split_once(" {\n") {
+        Some((header, rest)) => (header, rest.strip_suffix("\n}").unwrap_or(rest)),
+        None => (signature, ""),
+    }
+}
+
+/// Collect the outermost test modules (their descendants are written with them)
+fn collect_test_roots<'a>(module: &'a Module, out: &mut Vec<&'a Module>) {
+    for sub in &module.submodules {
+        if sub.is_test {
+            out.push(sub);
+        } else {
+            collect_test_roots(sub, out);
+        }
+    }
+}
 
+fn write_module_surface(
+    out: &mut String,
+    module: &Module,
+    annotations: &AnnotationStore,
+    layout: Layout,
+    visibility: VisibilityFilter,
+    bodies: &BodyFilter,
+    style: SignatureStyle,
+) {
+    let mut items: Vec<Item> =
+        module.items.iter().filter_map(|item| visibility.apply(item, &module.items)).collect();
+    if layout == Layout::Grouped {
+        items = merge_impl_blocks(items);
+    }
+    let items: Vec<Item> = items
+        .into_iter()
+        .map(|item| Item {
+            signature: style.render(&item),
+            ..item
+        })
+        .map(|item| bodies.apply(&module.path, item))
+        .collect();
+
+    // A filtered view leaves out modules with nothing left to show
+    if visibility == VisibilityFilter::All || !items.is_empty() {
+        // Module header
+        out.push_str(&format!("# {}\n", module.path));
+        out.push_str(&format!(
+            "<!-- file: {} -->\n\n",
+            module.file_path.display(),
+        ));
+        if let Some(ref error) = module.error {
+            out.push_str(&format!("<!-- {} -->\n\n", error));
+        }
+
+        match layout {
+            Layout::Grouped => write_grouped_items(out, &items, &module.path, annotations),
+            Layout::Source => write_source_order_items(out, &items, &module.path, annotations),
+        }
+
+        out.push_str("---\n\n");
+    }
+
+    // Recurse into submodules (test modules are written in their own section)
+    for sub in module.submodules.iter().filter(|s| s.is_test == module.is_test) {
+        write_module_surface(
+            out,
+            sub,
+            annotations,
+            layout,
+            visibility,
+            bodies,
+            style,
+        );
+    }
+}
+
+/// Write items in the order they appear in the source file
+fn write_source_order_items(
+    out: &mut String,
+    items: &[Item],
+    module_path: &str,
+    annotations: &AnnotationStore,
+) {
+    let mut items: Vec<&Item> = items.iter().collect();
+    // Stable sort keeps parse order for items sharing a line
+    items.sort_by_key(|i| i.line_start);
+
+    for item in items {
+        out.push_str(&format!("// [{}]\n", item.kind));
+        write_item(out, item, annotations, module_path);
+    }
+}
+
+/// Write items bucketed by kind (Types, Traits, Functions, ...)
+fn write_grouped_items(
+    out: &mut String,
+    items: &[Item],
+    module_path: &str,
+    annotations: &AnnotationStore,
+) {
     // Group items by kind
-    let types: Vec<&Item> = module
-        .items
+    let types: Vec<&Item> = items
         .iter()
-        .filter(|i| matches!(i.kind, ItemKind::Struct | ItemKind::Enum | ItemKind::TypeAlias))
+        .filter(|i| {
+            matches!(i.kind, ItemKind::Struct { .. } | ItemKind::Enum | ItemKind::TypeAlias)
+        })
         .collect();
 
-    let traits: Vec<&Item> = module
-        .items
+    let traits: Vec<&Item> = items
         .iter()
-        .filter(|i| matches!(i.kind, ItemKind::Trait))
+        .filter(|i| matches!(i.kind, ItemKind::Trait { .. }))
         .collect();
 
-    let functions: Vec<&Item> = module
-        .items
+    let functions: Vec<&Item> = items
         .iter()
         .filter(|i| matches!(i.kind, ItemKind::Function))
         .collect();
 
-    let impls: Vec<&Item> = module
-        .items
+    let impls: Vec<&Item> = items
         .iter()
         .filter(|i| matches!(i.kind, ItemKind::Impl { .. }))
         .collect();
 
-    let consts: Vec<&Item> = module
-        .items
+    let consts: Vec<&Item> = items
         .iter()
         .filter(|i| matches!(i.kind, ItemKind::Const | ItemKind::Static))
         .collect();
 
-    let macros: Vec<&Item> = module
-        .items
+    let macros: Vec<&Item> = items
         .iter()
         .filter(|i| matches!(i.kind, ItemKind::Macro))
         .collect();
 
-    let uses: Vec<&Item> = module
-        .items
+    let proc_macros: Vec<&Item> = items
+        .iter()
+        .filter(|i| matches!(i.kind, ItemKind::ProcMacro { .. }))
+        .collect();
+
+    let uses: Vec<&Item> = items
         .iter()
         .filter(|i| matches!(i.kind, ItemKind::Use))
         .collect();
@@ -72,66 +464,72 @@ fn write_module_surface(out: &mut String, module: &Module, annotations: &Annotat
     if !types.is_empty() {
         out.push_str("## Types\n\n");
         for item in &types {
-            write_item(out, item, annotations, &module.path);
+            write_item(out, item, annotations, module_path);
         }
-        out.push('\n');
     }
 
     if !traits.is_empty() {
         out.push_str("## Traits\n\n");
         for item in &traits {
-            write_item(out, item, annotations, &module.path);
+            write_item(out, item, annotations, module_path);
         }
-        out.push('\n');
     }
 
     if !functions.is_empty() {
         out.push_str("## Functions\n\n");
         for item in &functions {
-            write_item(out, item, annotations, &module.path);
+            write_item(out, item, annotations, module_path);
         }
-        out.push('\n');
     }
 
-    if !impls.is_empty() {
-        for item in &impls {
-            // Use the impl block's name as section header
-            out.push_str(&format!("## {}\n\n", format_impl_header(&item.kind)));
+    // Impl blocks for the same type (and trait) share one section, named
+    // after them; blocks that still differ (in generics or cfg) follow one
+    // another in source order
+    let mut impl_sections: Vec<(String, Vec<&Item>)> = Vec::new();
+    for item in &impls {
+        let header = format_impl_header(&item.kind);
+        match impl_sections.iter_mut().find(|(h, _)| *h == header) {
+            Some((_, blocks)) => blocks.push(item),
+            None => impl_sections.push((header, vec![item])),
+        }
+    }
+    for (header, blocks) in impl_sections {
+        out.push_str(&format!("## {}\n\n", header));
+        for item in blocks {
             // The signature contains the full impl with methods
-            write_item(out, item, annotations, &module.path);
-            out.push('\n');
+            write_item(out, item, annotations, module_path);
         }
     }
 
     if !consts.is_empty() {
         out.push_str("## Constants\n\n");
         for item in &consts {
-            write_item(out, item, annotations, &module.path);
+            write_item(out, item, annotations, module_path);
         }
-        out.push('\n');
     }
 
     if !macros.is_empty() {
         out.push_str("## Macros\n\n");
         for item in &macros {
-            write_item(out, item, annotations, &module.path);
+            write_item(out, item, annotations, module_path);
+        }
+    }
+
+    if !proc_macros.is_empty() {
+        out.push_str("## Proc Macros\n\n");
+        for item in &proc_macros {
+            if let ItemKind::ProcMacro { kind } = &item.kind {
+                out.push_str(&format!("// usage: {}\n", proc_macro_usage(kind, &item.name)));
+            }
+            write_item(out, item, annotations, module_path);
         }
-        out.push('\n');
     }
 
     if !uses.is_empty() {
         out.push_str("## Re-exports\n\n");
         for item in &uses {
-            write_item(out, item, annotations, &module.path);
+            write_item(out, item, annotations, module_path);
         }
-        out.push('\n');
-    }
-
-    out.push_str("---\n\n");
-
-    // Recurse into submodules
-    for sub in &module.submodules {
-        write_module_surface(out, sub, annotations);
     }
 }
 
@@ -151,19 +549,57 @@ fn write_item(out: &mut String, item: &Item, annotations: &AnnotationStore, modu
         }
     }
 
+    if let Some(ref deprecated) = item.deprecated {
+        out.push_str("// DEPRECATED");
+        if let Some(ref since) = deprecated.since {
+            out.push_str(&format!(" (since {})", since));
+        }
+        if let Some(ref note) = deprecated.note {
+            out.push_str(&format!(": {}", note));
+        }
+        out.push('\n');
+    }
+
+    if let Some(ref cfg) = item.cfg {
+        out.push_str(&format!("// cfg: {}\n", cfg));
+    }
+
+    if item.doc_hidden {
+        out.push_str("// doc(hidden)\n");
+    }
+
+    if let ItemKind::Trait {
+        object_safe: Some(object_safe),
+    } = item.kind
+    {
+        let guess = if object_safe { "yes" } else { "no" };
+        out.push_str(&format!("// object-safe: {}\n", guess));
+    }
+
     out.push_str(&item.signature);
     out.push_str("\n\n");
 }
 
+/// How a crate using the proc macro invokes it
+fn proc_macro_usage(kind: &ProcMacroKind, fn_name: &str) -> String {
+    match kind {
+        ProcMacroKind::Derive(name) => format!("#[derive({})]", name),
+        ProcMacroKind::Attribute => format!("#[{}]", fn_name),
+        ProcMacroKind::FunctionLike => format!("{}!(...)", fn_name),
+    }
+}
+
 fn format_impl_header(kind: &ItemKind) -> String {
     match kind {
         ItemKind::Impl {
             self_ty,
             trait_name: Some(trait_name),
+            ..
         } => format!("Impl {} for {}", trait_name, self_ty),
         ItemKind::Impl {
             self_ty,
             trait_name: None,
+            ..
         } => format!("Impl {}", self_ty),
         _ => "Impl".to_string(),
     }
@@ -177,55 +613,373 @@ mod tests {
 
     #[test]
     fn test_generate_api_surface() {
-        let crates = vec![CrateInfo {
-            name: "test".to_string(),
-            kind: CrateKind::Lib,
-            edition: "2021".to_string(),
-            version: "0.1.0".to_string(),
-            external_deps: vec![],
-            root_module: Module {
-                path: "crate".to_string(),
-                file_path: PathBuf::from("src/lib.rs"),
-                file_hash: "abc12345".to_string(),
-                doc_comment: None,
-                visibility: Visibility::Pub,
-                items: vec![
-                    Item {
-                        name: "Config".to_string(),
-                        kind: ItemKind::Struct,
-                        visibility: Visibility::Pub,
-                        signature: "pub struct Config {\n    pub name: String,\n}".to_string(),
-                        doc_comment: Some("Configuration struct".to_string()),
-                        file_path: PathBuf::from("src/lib.rs"),
-                        line_start: 1,
-                        line_end: 3,
-                        content_hash: "hash1".to_string(),
-                    },
-                    Item {
-                        name: "init".to_string(),
-                        kind: ItemKind::Function,
-                        visibility: Visibility::Pub,
-                        signature: "pub fn init() -> Config;".to_string(),
-                        doc_comment: None,
-                        file_path: PathBuf::from("src/lib.rs"),
-                        line_start: 5,
-                        line_end: 10,
-                        content_hash: "hash2".to_string(),
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            Module::for_test("crate", "src/lib.rs").with_items(vec![
+                Item {
+                    name: "Config".to_string(),
+                    kind: ItemKind::Struct {
+                        struct_kind: StructKind::Named,
+                        field_count: 1,
                     },
-                ],
-                submodules: vec![],
-                use_statements: vec![],
-                is_inline: false,
-            },
-        }];
+                    visibility: Visibility::Pub,
+                    signature: "pub struct Config {\n    pub name: String,\n}".to_string(),
+                    doc_comment: Some("Configuration struct".to_string()),
+                    derives: vec![],
+                    attributes: vec![],
+                    deprecated: None,
+                    generics: vec![],
+                    where_clause: None,
+                    supertraits: Vec::new(),
+                    abi: None,
+                    members: vec![],
+                    params: vec![],
+                    return_type: None,
+                    return_trait: None,
+                    is_async: false,
+                    is_unsafe: false,
+                    is_const: false,
+                    repr: None,
+                    cfg: None,
+                    doc_hidden: false,
+                    reexport_of: None,
+                    file_path: PathBuf::from("src/lib.rs"),
+                    line_start: 1,
+                    line_end: 3,
+                    byte_start: 0,
+                    byte_end: 0,
+                    content_hash: "hash1".to_string(),
+                },
+                Item {
+                    name: "init".to_string(),
+                    kind: ItemKind::Function,
+                    visibility: Visibility::Pub,
+                    signature: "pub fn init() -> Config;".to_string(),
+                    doc_comment: None,
+                    derives: vec![],
+                    attributes: vec![],
+                    deprecated: Some(DeprecationInfo {
+                        since: Some("0.2".to_string()),
+                        note: Some("use Config::load".to_string()),
+                    }),
+                    generics: vec![],
+                    where_clause: None,
+                    supertraits: Vec::new(),
+                    abi: None,
+                    members: vec![],
+                    params: vec![],
+                    return_type: None,
+                    return_trait: None,
+                    is_async: false,
+                    is_unsafe: false,
+                    is_const: false,
+                    repr: None,
+                    cfg: Some("unix".to_string()),
+                    doc_hidden: false,
+                    reexport_of: None,
+                    file_path: PathBuf::from("src/lib.rs"),
+                    line_start: 5,
+                    line_end: 10,
+                    byte_start: 0,
+                    byte_end: 0,
+                    content_hash: "hash2".to_string(),
+                },
+            ]),
+        )];
 
         let annotations = AnnotationStore::default();
-        let output = generate_api_surface(&crates, &annotations);
+        let output = generate_api_surface(
+            &crates,
+            &annotations,
+            Layout::Grouped,
+            VisibilityFilter::All,
+            &BodyFilter::default(),
+            0,
+            false,
+        );
 
         assert!(output.contains("## Types"));
         assert!(output.contains("pub struct Config"));
         assert!(output.contains("## Functions"));
-        assert!(output.contains("pub fn init() -> Config;"));
+        assert!(output.contains(
+            "// DEPRECATED (since 0.2): use Config::load\n// cfg: unix\npub fn init() -> Config;"
+        ));
         assert!(output.contains("/// Configuration struct"));
+        assert!(!output.contains("# Test Modules"));
+    }
+
+    #[test]
+    fn test_proc_macros_section() {
+        let items = crate::parse::parse_file(
+            &PathBuf::from("src/lib.rs"),
+            "#[proc_macro_derive(Builder)]\n\
+             pub fn derive_builder(input: TokenStream) -> TokenStream { input }\n\
+             #[proc_macro_attribute]\n\
+             pub fn route(a: TokenStream, i: TokenStream) -> TokenStream { i }\n\
+             #[proc_macro]\n\
+             pub fn sql(input: TokenStream) -> TokenStream { input }\n",
+        )
+        .unwrap();
+        let crates = vec![CrateInfo {
+            kind: CrateKind::ProcMacro,
+            ..CrateInfo::for_test(
+                "macros",
+                Module::for_test("crate", "src/lib.rs").with_items(items),
+            )
+        }];
+        let output = generate_api_surface(
+            &crates,
+            &AnnotationStore::default(),
+            Layout::Grouped,
+            VisibilityFilter::All,
+            &BodyFilter::default(),
+            0,
+            false,
+        );
+
+        assert!(!output.contains("## Functions"));
+        assert!(output.contains(
+            "## Proc Macros\n\n// usage: #[derive(Builder)]\n\
+             pub fn derive_builder(input: TokenStream) -> TokenStream;\n"
+        ));
+        assert!(output.contains("// usage: #[route]\npub fn route("));
+        assert!(output.contains("// usage: sql!(...)\npub fn sql("));
+    }
+
+    #[test]
+    fn test_source_layout() {
+        let items = crate::parse::parse_file(
+            &PathBuf::from("src/lib.rs"),
+            "pub fn first() {}\npub struct Second;\nimpl Second {}\npub fn third() {}\n",
+        )
+        .unwrap();
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            Module::for_test("crate", "src/lib.rs").with_items(items),
+        )];
+
+        let output = generate_api_surface(
+            &crates,
+            &AnnotationStore::default(),
+            Layout::Source,
+            VisibilityFilter::All,
+            &BodyFilter::default(),
+            0,
+            false,
+        );
+        assert!(!output.contains("## Functions"));
+        let first = output.find("// [function]\npub fn first();").unwrap();
+        let second = output.find("// [struct]\npub struct Second;").unwrap();
+        let imp = output.find("// [impl Second]").unwrap();
+        let third = output.find("pub fn third();").unwrap();
+        assert!(first < second && second < imp && imp < third);
+    }
+
+    #[test]
+    fn test_merged_impl_sections() {
+        let source = "pub struct Foo;\nimpl Foo {\n    pub fn new() -> Self { Foo }\n}\n\
+                      impl Clone for Foo {\n    fn clone(&self) -> Self { Foo }\n}\n\
+                      impl Foo {\n    pub fn reset(&mut self) {}\n}\n";
+        let items = crate::parse::parse_file(&PathBuf::from("src/lib.rs"), source).unwrap();
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            Module::for_test("crate", "src/lib.rs").with_items(items),
+        )];
+
+        let output = generate_api_surface(
+            &crates,
+            &AnnotationStore::default(),
+            Layout::Grouped,
+            VisibilityFilter::All,
+            &BodyFilter::default(),
+            0,
+            false,
+        );
+        assert_eq!(output.matches("## Impl Foo\n").count(), 1);
+        assert!(output.contains(
+            "## Impl Foo\n\nimpl Foo {\n    pub fn new() -> Self;\n    pub fn reset(&mut self);\n}\n\
+             \n## Impl Clone for Foo\n"
+        ));
+    }
+
+    #[test]
+    fn test_compact_impls() {
+        let source = "pub struct Foo<T>(T);\nimpl<T> Foo<T> where T: Clone {\n    \
+                      /// Make one\n    pub fn new(t: T) -> Self { Foo(t) }\n    \
+                      pub fn get(&self) -> T { self.0.clone() }\n}\n\
+                      impl<T> Iterator for Foo<T> {\n    type Item = T;\n    \
+                      fn next(&mut self) -> Option<T> { None }\n}\n\
+                      unsafe impl<T> Send for Foo<T> {}\n";
+        let items = crate::parse::parse_file(&PathBuf::from("src/lib.rs"), source).unwrap();
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            Module::for_test("crate", "src/lib.rs").with_items(items),
+        )];
+        let render = |compact_impls| {
+            generate_api_surface(
+                &crates,
+                &AnnotationStore::default(),
+                Layout::Grouped,
+                VisibilityFilter::All,
+                &BodyFilter::default(),
+                0,
+                compact_impls,
+            )
+        };
+
+        let compact = render(true);
+        assert!(compact.contains("impl<T> Foo<T> where T: Clone { new, get }\n"));
+        assert!(compact.contains("impl<T> Iterator for Foo<T> { next }\n"));
+        assert!(compact.contains("unsafe impl<T> Send for Foo<T> {}\n"));
+        assert!(!compact.contains("fn get(&self)"));
+        assert!(!compact.contains("/// Make one"));
+        // Other items keep their signatures
+        assert!(compact.contains("pub struct Foo"));
+
+        let full = render(false);
+        assert!(full.contains("    pub fn get(&self) -> T;"));
+    }
+
+    #[test]
+    fn test_visibility_filter() {
+        let source = "pub fn public() {}\nfn private() {}\npub(crate) fn internal() {}\n\
+                      pub struct Widget;\nimpl Widget {\n    /// Make one\n    \
+                      pub fn new() -> Self { Widget }\n    /// Internal only\n    fn helper(&self) {}\n}\nimpl Clone for Widget {\n    \
+                      fn clone(&self) -> Self { Widget }\n}\n\
+                      struct Secret;\nimpl Default for Secret {\n    \
+                      fn default() -> Self { Secret }\n}\n";
+        let items = crate::parse::parse_file(&PathBuf::from("src/lib.rs"), source).unwrap();
+        let empty = Module {
+            visibility: Visibility::Private,
+            ..Module::for_test("crate::hidden", "src/hidden.rs").with_source("fn secret() {}")
+        };
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            Module::for_test("crate", "src/lib.rs")
+                .with_items(items)
+                .with_submodules(vec![empty]),
+        )];
+        let surface = |visibility| {
+            let annotations = AnnotationStore::default();
+            let bodies = BodyFilter::default();
+            let layout = Layout::Grouped;
+            generate_api_surface(&crates, &annotations, layout, visibility, &bodies, 0, false)
+        };
+
+        let public = surface(VisibilityFilter::Pub);
+        assert!(public.contains("pub fn public();"));
+        assert!(!public.contains("fn private"));
+        assert!(!public.contains("fn internal"));
+        assert!(public.contains("impl Widget {\n    /// Make one\n    pub fn new() -> Self;\n}"));
+        assert!(!public.contains("fn helper"));
+        assert!(!public.contains("Internal only"));
+        assert!(public.contains("fn clone(&self) -> Self;"));
+        assert!(!public.contains("Secret"));
+        assert!(!public.contains("# crate::hidden"));
+
+        let crate_wide = surface(VisibilityFilter::PubCrate);
+        assert!(crate_wide.contains("pub(crate) fn internal();"));
+        assert!(!crate_wide.contains("fn private"));
+
+        let all = surface(VisibilityFilter::All);
+        assert!(all.contains("fn private();"));
+        assert!(all.contains("impl Default for Secret"));
+        assert!(all.contains("    /// Internal only\n    fn helper(&self);"));
+        assert!(all.contains("# crate::hidden"));
+    }
+
+    #[test]
+    fn test_doc_hidden_excluded_from_public() {
+        let source = "pub fn open() {}\n#[doc(hidden)]\npub fn __private_api() {}\n";
+        let items = crate::parse::parse_file(&PathBuf::from("src/lib.rs"), source).unwrap();
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            Module::for_test("crate", "src/lib.rs").with_items(items),
+        )];
+        let surface = |visibility| {
+            let annotations = AnnotationStore::default();
+            let bodies = BodyFilter::default();
+            let layout = Layout::Grouped;
+            generate_api_surface(&crates, &annotations, layout, visibility, &bodies, 0, false)
+        };
+
+        let public = surface(VisibilityFilter::Pub);
+        assert!(public.contains("pub fn open();"));
+        assert!(!public.contains("__private_api"));
+
+        let all = surface(VisibilityFilter::All);
+        assert!(all.contains("// doc(hidden)\npub fn __private_api();"));
+    }
+
+    #[test]
+    fn test_private_modules_hidden_from_public() {
+        let module = |path: &str, visibility, source: &str, submodules| Module {
+            visibility,
+            is_inline: true,
+            ..Module::for_test(path, "src/lib.rs")
+                .with_source(source)
+                .with_submodules(submodules)
+        };
+        // `utils` is private, so neither its pub fn nor its pub submodule is reachable
+        let io = module("crate::utils::io", Visibility::Pub, "pub fn read() {}", vec![]);
+        let utils = module("crate::utils", Visibility::Private, "pub fn helper() {}", vec![io]);
+        let api = module("crate::api", Visibility::Pub, "pub fn serve() {}", vec![]);
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            module("crate", Visibility::Pub, "pub fn run() {}", vec![utils, api]),
+        )];
+        let annotations = AnnotationStore::default();
+        let bodies = BodyFilter::default();
+        let surface = |visibility| {
+            let layout = Layout::Grouped;
+            generate_api_surface(&crates, &annotations, layout, visibility, &bodies, 0, false)
+        };
+        let descriptions = crate::annotations::DescriptionOverrides::default();
+        let overview = |visibility| {
+            crate::layer0::generate_overview(&crates, &annotations, &descriptions, None, visibility)
+        };
+
+        let public = surface(VisibilityFilter::Pub);
+        assert!(public.contains("pub fn run();") && public.contains("pub fn serve();"));
+        assert!(!public.contains("crate::utils"));
+        assert!(!public.contains("fn helper") && !public.contains("fn read"));
+        let public = overview(VisibilityFilter::Pub);
+        assert!(public.contains("  - api"));
+        assert!(!public.contains("utils") && !public.contains("- io"));
+
+        let all = surface(VisibilityFilter::All);
+        assert!(all.contains("pub fn helper();") && all.contains("pub fn read();"));
+        assert!(overview(VisibilityFilter::All).contains("    - io"));
+    }
+
+    #[test]
+    fn test_test_modules_grouped_separately() {
+        let test_module = Module {
+            visibility: Visibility::Private,
+            is_inline: true,
+            is_test: true,
+            ..Module::for_test("crate::tests", "src/lib.rs")
+        };
+        let other_module = Module::for_test("crate::other", "src/other.rs");
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            Module::for_test("crate", "src/lib.rs")
+                .with_submodules(vec![test_module, other_module]),
+        )];
+
+        let output = generate_api_surface(
+            &crates,
+            &AnnotationStore::default(),
+            Layout::Grouped,
+            VisibilityFilter::All,
+            &BodyFilter::default(),
+            0,
+            false,
+        );
+        let section = output.find("# Test Modules").unwrap();
+        let tests = output.find("# crate::tests").unwrap();
+        let other = output.find("# crate::other").unwrap();
+        assert!(other < section);
+        assert!(section < tests);
     }
 }
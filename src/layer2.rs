@@ -1,29 +1,131 @@
+use serde::Serialize;
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use syn::parse::Parser;
+use syn::visit::Visit;
 
 use crate::model::{CrateInfo, ItemKind, Module};
+use crate::output;
+use crate::parse;
+
+/// Default `--hotspot-threshold`: how many modules must reference a type for
+/// it to be listed under Key Types
+pub const DEFAULT_HOTSPOT_THRESHOLD: usize = 3;
+
+/// Relationship data collected across all crates
+#[derive(Debug, Default)]
+struct Relationships {
+    trait_impls: BTreeMap<String, BTreeSet<Implementor>>,
+    /// Headers of blanket impls, e.g. `impl<T: Display> Render for T`
+    blanket_impls: BTreeSet<String>,
+    trait_hierarchy: BTreeMap<String, BTreeSet<String>>,
+    from_impls: BTreeSet<(String, String)>,
+    module_deps: BTreeMap<String, BTreeSet<String>>,
+    type_usage: BTreeMap<String, BTreeSet<String>>,
+    unsafe_items: BTreeMap<String, Vec<UnsafeItem>>,
+}
+
+impl Relationships {
+    fn collect(crates: &[CrateInfo]) -> Self {
+        let mut rel = Relationships::default();
+        for crate_info in crates {
+            collect_relationships(&crate_info.root_module, &mut rel);
+        }
+        rel
+    }
+}
+
+/// A type implementing a trait. Concrete types sort before generic ones
+/// (`MyType<T>`, or `T` in a blanket impl), each group by name.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Implementor {
+    generic: bool,
+    name: String,
+}
+
+impl Serialize for Implementor {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.name.serialize(serializer)
+    }
+}
+
+/// An `unsafe fn` (including impl and trait methods), `unsafe trait` or
+/// `unsafe impl` and where it is defined
+#[derive(Debug, Clone, Serialize)]
+struct UnsafeItem {
+    /// e.g. `unsafe fn read_raw`, `unsafe fn Handle::read` or `unsafe impl Send for Handle`
+    item: String,
+    file: String,
+    line: usize,
+}
+
+/// A `From<source> for target` conversion
+#[derive(Debug, Serialize)]
+struct FromEdge {
+    from: String,
+    to: String,
+}
+
+/// JSON form of the relationships layer (relationships.json)
+#[derive(Debug, Serialize)]
+struct RelationshipsJson {
+    trait_impls: BTreeMap<String, BTreeSet<Implementor>>,
+    blanket_impls: BTreeSet<String>,
+    trait_hierarchy: BTreeMap<String, BTreeSet<String>>,
+    from_impls: Vec<FromEdge>,
+    error_chains: Vec<String>,
+    module_deps: BTreeMap<String, BTreeSet<String>>,
+    dependency_cycles: Vec<String>,
+    type_usage: BTreeMap<String, BTreeSet<String>>,
+    unsafe_items: BTreeMap<String, Vec<UnsafeItem>>,
+}
+
+/// Generate Layer 2 as JSON (relationships.json)
+///
+/// Serializes the same data as `generate_relationships` for programmatic use.
+pub fn generate_relationships_json(crates: &[CrateInfo]) -> String {
+    let rel = Relationships::collect(crates);
+    let from_impls_vec: Vec<_> = rel.from_impls.iter().cloned().collect();
+    let json = RelationshipsJson {
+        error_chains: build_error_chains(&from_impls_vec),
+        from_impls: from_impls_vec
+            .into_iter()
+            .map(|(from, to)| FromEdge { from, to })
+            .collect(),
+        trait_impls: rel.trait_impls,
+        blanket_impls: rel.blanket_impls,
+        trait_hierarchy: rel.trait_hierarchy,
+        dependency_cycles: find_dependency_cycles(&rel.module_deps),
+        module_deps: rel.module_deps,
+        type_usage: rel.type_usage,
+        unsafe_items: rel.unsafe_items,
+    };
+
+    serde_json::to_string_pretty(&json).unwrap_or_else(|_| "{}".to_string())
+}
 
 /// Generate Layer 2: Relationships (relationships.md)
 ///
-/// Includes trait implementation map, error chains, module dependencies,
-/// and type usage hotspots.
-pub fn generate_relationships(crates: &[CrateInfo]) -> String {
+/// Includes trait implementation map, blanket impls, trait hierarchy, error
+/// chains, module dependencies, type usage hotspots, and where `unsafe` items
+/// live. Hotspots are the types referenced from at least `hotspot_threshold`
+/// modules, the most used first, cut to `hotspot_top` if given.
+pub fn generate_relationships(
+    crates: &[CrateInfo],
+    hotspot_threshold: usize,
+    hotspot_top: Option<usize>,
+) -> String {
     let mut out = String::new();
 
     // Collect all data across crates
-    let mut trait_impls: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
-    let mut from_impls: BTreeSet<(String, String)> = BTreeSet::new();
-    let mut module_deps: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
-    let mut type_usage: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
-
-    for crate_info in crates {
-        collect_relationships(
-            &crate_info.root_module,
-            &mut trait_impls,
-            &mut from_impls,
-            &mut module_deps,
-            &mut type_usage,
-        );
-    }
+    let Relationships {
+        trait_impls,
+        blanket_impls,
+        trait_hierarchy,
+        from_impls,
+        module_deps,
+        type_usage,
+        unsafe_items,
+    } = Relationships::collect(crates);
 
     // Section 1: Trait Implementation Map
     out.push_str("## Trait Implementations\n\n");
@@ -33,18 +135,46 @@ pub fn generate_relationships(crates: &[CrateInfo]) -> String {
         // Find the longest trait name for alignment
         let max_len = trait_impls.keys().map(|k| k.len()).max().unwrap_or(0);
         for (trait_name, implementors) in &trait_impls {
-            let impls: Vec<&str> = implementors.iter().map(|s| s.as_str()).collect();
             out.push_str(&format!(
                 "{:<width$} <- {}\n",
                 trait_name,
-                impls.join(", "),
+                format_implementors(implementors),
                 width = max_len
             ));
         }
         out.push('\n');
     }
 
-    // Section 2: Error Chains
+    // Section 2: Blanket impls, which the map above leaves out
+    out.push_str("## Blanket Implementations\n\n");
+    if blanket_impls.is_empty() {
+        out.push_str("(none found)\n\n");
+    } else {
+        for header in &blanket_impls {
+            out.push_str(&format!("{}\n", header));
+        }
+        out.push('\n');
+    }
+
+    // Section 3: Trait Hierarchy (trait -> supertraits)
+    out.push_str("## Trait Hierarchy\n\n");
+    if trait_hierarchy.is_empty() {
+        out.push_str("(no supertraits found)\n\n");
+    } else {
+        let max_len = trait_hierarchy.keys().map(|k| k.len()).max().unwrap_or(0);
+        for (trait_name, supertraits) in &trait_hierarchy {
+            let supers: Vec<&str> = supertraits.iter().map(|s| s.as_str()).collect();
+            out.push_str(&format!(
+                "{:<width$} : {}\n",
+                trait_name,
+                supers.join(" + "),
+                width = max_len
+            ));
+        }
+        out.push('\n');
+    }
+
+    // Section 4: Error Chains
     out.push_str("## Error Chains\n\n");
     let from_impls_vec: Vec<_> = from_impls.into_iter().collect();
     let error_chains = build_error_chains(&from_impls_vec);
@@ -57,7 +187,7 @@ pub fn generate_relationships(crates: &[CrateInfo]) -> String {
         out.push('\n');
     }
 
-    // Section 3: Module Dependencies
+    // Section 5: Module Dependencies
     out.push_str("## Module Dependencies\n\n");
     if module_deps.is_empty() {
         out.push_str("(none found)\n\n");
@@ -83,17 +213,36 @@ pub fn generate_relationships(crates: &[CrateInfo]) -> String {
         out.push('\n');
     }
 
-    // Section 4: Type Usage Hotspots
-    out.push_str("## Key Types (referenced from 3+ modules)\n\n");
-    let mut hotspots: Vec<(&String, usize)> = type_usage
-        .iter()
-        .filter(|(_, modules)| modules.len() >= 3)
-        .map(|(ty, modules)| (ty, modules.len()))
-        .collect();
-    hotspots.sort_by(|a, b| b.1.cmp(&a.1));
+    // Section 6: Dependency Cycles
+    out.push_str("## Dependency Cycles\n\n");
+    let cycles = find_dependency_cycles(&module_deps);
+    if cycles.is_empty() {
+        out.push_str("(none)\n\n");
+    } else {
+        for cycle in &cycles {
+            out.push_str(&format!("{}\n", cycle));
+        }
+        out.push('\n');
+    }
+
+    // Section 7: Type Usage Hotspots
+    match hotspot_top {
+        Some(top) => out.push_str(&format!(
+            "## Key Types (referenced from {}+ modules, top {})\n\n",
+            hotspot_threshold, top
+        )),
+        None => out.push_str(&format!(
+            "## Key Types (referenced from {}+ modules)\n\n",
+            hotspot_threshold
+        )),
+    }
+    let hotspots = hotspots(&type_usage, hotspot_threshold, hotspot_top);
 
     if hotspots.is_empty() {
-        out.push_str("(no types referenced from 3+ modules)\n\n");
+        out.push_str(&format!(
+            "(no types referenced from {}+ modules)\n\n",
+            hotspot_threshold
+        ));
     } else {
         let max_len = hotspots.iter().map(|(k, _)| k.len()).max().unwrap_or(0);
         for (type_name, count) in &hotspots {
@@ -107,16 +256,131 @@ pub fn generate_relationships(crates: &[CrateInfo]) -> String {
         out.push('\n');
     }
 
+    // Section 8: Unsafe Code, modules with the most unsafe items first
+    out.push_str("## Unsafe Code\n\n");
+    if unsafe_items.is_empty() {
+        out.push_str("(no unsafe items)\n\n");
+    } else {
+        let mut modules: Vec<(&String, &Vec<UnsafeItem>)> = unsafe_items.iter().collect();
+        modules.sort_by_key(|(_, items)| std::cmp::Reverse(items.len()));
+        for (module, items) in modules {
+            out.push_str(&format!("{} ({})\n", module, items.len()));
+            for item in items {
+                out.push_str(&format!("  {} — {}:{}\n", item.item, item.file, item.line));
+            }
+        }
+        out.push('\n');
+    }
+
     out
 }
 
-fn collect_relationships(
-    module: &Module,
-    trait_impls: &mut BTreeMap<String, BTreeSet<String>>,
-    from_impls: &mut BTreeSet<(String, String)>,
-    module_deps: &mut BTreeMap<String, BTreeSet<String>>,
-    type_usage: &mut BTreeMap<String, BTreeSet<String>>,
-) {
+/// Types referenced from at least `threshold` modules with their module
+/// counts, the most used first (ties by name), at most `top` of them
+fn hotspots(
+    type_usage: &BTreeMap<String, BTreeSet<String>>,
+    threshold: usize,
+    top: Option<usize>,
+) -> Vec<(&String, usize)> {
+    let mut hotspots: Vec<(&String, usize)> = type_usage
+        .iter()
+        .filter(|(_, modules)| modules.len() >= threshold)
+        .map(|(ty, modules)| (ty, modules.len()))
+        .collect();
+    // Stable, so types with the same count stay in name order
+    hotspots.sort_by_key(|b| std::cmp::Reverse(b.1));
+    if let Some(top) = top {
+        hotspots.truncate(top);
+    }
+    hotspots
+}
+
+/// Mermaid diagrams of the module dependency graph and the trait
+/// implementation map, as fenced `mermaid` code blocks under their own
+/// headings, for appending to relationships.md (`--mermaid`)
+pub fn generate_mermaid(crates: &[CrateInfo]) -> String {
+    let Relationships {
+        trait_impls,
+        module_deps,
+        ..
+    } = Relationships::collect(crates);
+
+    let mut out = String::from("## Module Dependency Graph\n\n");
+    let edges = module_deps
+        .iter()
+        .flat_map(|(module, deps)| deps.iter().map(move |dep| (module.as_str(), dep.as_str())));
+    out.push_str(&mermaid_graph(module_deps.keys().map(String::as_str), edges));
+
+    out.push_str("\n## Trait Implementation Graph\n\n");
+    let implementors: BTreeSet<&str> = trait_impls
+        .values()
+        .flatten()
+        .map(|implementor| implementor.name.as_str())
+        .collect();
+    let edges = trait_impls.iter().flat_map(|(trait_name, implementors)| {
+        implementors
+            .iter()
+            .map(move |implementor| (implementor.name.as_str(), trait_name.as_str()))
+    });
+    let nodes = trait_impls.keys().map(String::as_str).chain(implementors);
+    out.push_str(&mermaid_graph(nodes, edges));
+    out
+}
+
+/// A `graph LR` block declaring each node with its name as the label, then
+/// one `a --> b` line per edge
+fn mermaid_graph<'a>(
+    nodes: impl Iterator<Item = &'a str>,
+    edges: impl Iterator<Item = (&'a str, &'a str)>,
+) -> String {
+    let mut graph = String::from("graph LR\n");
+    for node in nodes.collect::<BTreeSet<_>>() {
+        graph.push_str(&format!("    {}[\"{}\"]\n", mermaid_id(node), mermaid_label(node)));
+    }
+    for (from, to) in edges {
+        graph.push_str(&format!("    {} --> {}\n", mermaid_id(from), mermaid_id(to)));
+    }
+    format!("{}\n", output::code_block(graph.trim_end(), "mermaid"))
+}
+
+/// A valid Mermaid node id for a module or type name: each run of characters
+/// other than ASCII letters, digits and `_` becomes one `_`, so `engine::eval`
+/// is `engine_eval` and `Wrapper < T >` is `Wrapper_T`
+fn mermaid_id(name: &str) -> String {
+    let mut id = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            id.push(c);
+        } else if !id.is_empty() && !id.ends_with('_') {
+            id.push('_');
+        }
+    }
+    let id = id.trim_end_matches('_');
+    // A lowercase `end` closes a subgraph and breaks the diagram
+    if id.is_empty() || id == "end" {
+        format!("{}_", id)
+    } else {
+        id.to_string()
+    }
+}
+
+/// A node label, with the characters Mermaid treats specially escaped
+fn mermaid_label(name: &str) -> String {
+    name.replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+}
+
+fn collect_relationships(module: &Module, rel: &mut Relationships) {
+    let Relationships {
+        trait_impls,
+        blanket_impls,
+        trait_hierarchy,
+        from_impls,
+        module_deps,
+        type_usage,
+        unsafe_items,
+    } = rel;
     let mod_short = module
         .path
         .strip_prefix("crate::")
@@ -127,34 +391,85 @@ fn collect_relationships(
     module_deps.entry(mod_short.clone()).or_default();
 
     for item in &module.items {
+        // Blanket impls get their own section instead of a `T` implementor
+        if let ItemKind::Impl {
+            trait_name: Some(_),
+            blanket: true,
+            ..
+        } = item.kind
+        {
+            let header = item
+                .signature
+                .split_once(" {")
+                .map_or(item.signature.as_str(), |(header, _)| header);
+            blanket_impls.insert(header.to_string());
+        }
+
         // Collect trait implementations
         if let ItemKind::Impl {
             ref self_ty,
-            ref trait_name,
+            trait_name: Some(ref tn),
+            blanket: false,
         } = item.kind
         {
-            if let Some(ref tn) = trait_name {
-                let clean_trait = clean_type_name(tn);
-                let clean_self = clean_type_name(self_ty);
+            let clean_trait = clean_type_name(tn);
+            let clean_self = clean_type_name(self_ty);
 
-                trait_impls
-                    .entry(clean_trait.clone())
-                    .or_default()
-                    .insert(clean_self.clone());
+            trait_impls
+                .entry(clean_trait.clone())
+                .or_default()
+                .insert(Implementor {
+                    generic: is_generic_self_type(self_ty, &item.generics),
+                    name: clean_self.clone(),
+                });
 
-                // Track From impls for error chains
-                if clean_trait.starts_with("From") {
-                    // Extract the source type from From<SourceType>
-                    if let Some(source) = extract_from_source(tn) {
-                        from_impls.insert((source, clean_self));
-                    }
+            // Track From impls for error chains
+            if clean_trait.starts_with("From") {
+                // Extract the source type from From<SourceType>
+                if let Some(source) = extract_from_source(tn) {
+                    from_impls.insert((source, clean_self));
                 }
             }
         }
 
+        // Collect supertrait edges
+        if matches!(item.kind, ItemKind::Trait { .. }) && !item.supertraits.is_empty() {
+            trait_hierarchy
+                .entry(item.name.clone())
+                .or_default()
+                .extend(item.supertraits.iter().cloned());
+        }
+
+        // Collect unsafe fns, traits and impls
+        if item.is_unsafe {
+            let label = match &item.kind {
+                ItemKind::Function => format!("unsafe fn {}", item.name),
+                ItemKind::Trait { .. } => format!("unsafe trait {}", item.name),
+                kind => parse::normalize_signature(&format!("unsafe {}", kind)),
+            };
+            unsafe_items.entry(mod_short.clone()).or_default().push(UnsafeItem {
+                item: label,
+                file: item.file_path.display().to_string(),
+                line: item.line_start,
+            });
+        }
+        let owner = match &item.kind {
+            ItemKind::Impl { self_ty, .. } => Some(clean_type_name(self_ty)),
+            ItemKind::Trait { .. } => Some(item.name.clone()),
+            _ => None,
+        };
+        if let Some(owner) = owner {
+            for member in item.members.iter().filter(|m| m.is_unsafe) {
+                unsafe_items.entry(mod_short.clone()).or_default().push(UnsafeItem {
+                    item: format!("unsafe fn {}::{}", owner, member.name),
+                    file: item.file_path.display().to_string(),
+                    line: member.line_start,
+                });
+            }
+        }
+
         // Track type references for hotspot analysis
-        // We approximate this by looking at type names mentioned in signatures
-        let types_in_sig = extract_type_names_from_signature(&item.signature);
+        let types_in_sig = extract_type_names_from_signature(&item.signature, &item.name);
         for ty in types_in_sig {
             type_usage.entry(ty).or_default().insert(mod_short.clone());
         }
@@ -174,10 +489,43 @@ fn collect_relationships(
 
     // Recurse into submodules
     for sub in &module.submodules {
-        collect_relationships(sub, trait_impls, from_impls, module_deps, type_usage);
+        collect_relationships(sub, rel);
+    }
+}
+
+/// Concrete implementors, then generic ones in a group: `Expr, Literal (generic: MyType<T>)`
+fn format_implementors(implementors: &BTreeSet<Implementor>) -> String {
+    let names = |generic: bool| -> Vec<&str> {
+        implementors
+            .iter()
+            .filter(|i| i.generic == generic)
+            .map(|i| i.name.as_str())
+            .collect()
+    };
+    let (concrete, generic) = (names(false), names(true));
+    match (concrete.is_empty(), generic.is_empty()) {
+        (_, true) => concrete.join(", "),
+        (true, false) => format!("(generic: {})", generic.join(", ")),
+        (false, false) => format!("{} (generic: {})", concrete.join(", "), generic.join(", ")),
     }
 }
 
+/// Whether an impl's self type mentions one of the impl's type or const
+/// parameters, e.g. `MyType<T>`, or `T` in `impl<T: Display> Trait for T`
+fn is_generic_self_type(self_ty: &str, generics: &[String]) -> bool {
+    let params: Vec<&str> = generics
+        .iter()
+        .filter(|g| !g.starts_with('\''))
+        .map(|g| {
+            let g = g.strip_prefix("const ").unwrap_or(g);
+            g.split([':', '=']).next().unwrap_or(g).trim()
+        })
+        .collect();
+    self_ty
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .any(|word| params.contains(&word))
+}
+
 /// Clean a type name by removing generics and whitespace
 fn clean_type_name(name: &str) -> String {
     // Remove leading/trailing whitespace
@@ -281,6 +629,134 @@ fn follow_chain(
     }
 }
 
+/// Find module dependency cycles: every strongly-connected component (Tarjan)
+/// with more than one module, rendered as a cycle such as `a -> b -> a`.
+///
+/// The rendered path is the shortest cycle through the component's first
+/// module; any other members of the component are appended in brackets.
+fn find_dependency_cycles(module_deps: &BTreeMap<String, BTreeSet<String>>) -> Vec<String> {
+    struct Tarjan<'a> {
+        graph: &'a BTreeMap<String, BTreeSet<String>>,
+        index: HashMap<&'a str, usize>,
+        lowlink: HashMap<&'a str, usize>,
+        stack: Vec<&'a str>,
+        on_stack: HashSet<&'a str>,
+        next_index: usize,
+        components: Vec<Vec<&'a str>>,
+    }
+
+    impl<'a> Tarjan<'a> {
+        fn visit(&mut self, node: &'a str) {
+            self.index.insert(node, self.next_index);
+            self.lowlink.insert(node, self.next_index);
+            self.next_index += 1;
+            self.stack.push(node);
+            self.on_stack.insert(node);
+
+            let graph = self.graph;
+            for next in graph.get(node).into_iter().flatten() {
+                let next = next.as_str();
+                if !self.index.contains_key(next) {
+                    self.visit(next);
+                    let low = self.lowlink[node].min(self.lowlink[next]);
+                    self.lowlink.insert(node, low);
+                } else if self.on_stack.contains(next) {
+                    let low = self.lowlink[node].min(self.index[next]);
+                    self.lowlink.insert(node, low);
+                }
+            }
+
+            if self.lowlink[node] == self.index[node] {
+                let mut component = Vec::new();
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack.remove(member);
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                self.components.push(component);
+            }
+        }
+    }
+
+    let mut tarjan = Tarjan {
+        graph: module_deps,
+        index: HashMap::new(),
+        lowlink: HashMap::new(),
+        stack: Vec::new(),
+        on_stack: HashSet::new(),
+        next_index: 0,
+        components: Vec::new(),
+    };
+    for node in module_deps.keys() {
+        if !tarjan.index.contains_key(node.as_str()) {
+            tarjan.visit(node);
+        }
+    }
+
+    let mut cycles: Vec<String> = tarjan
+        .components
+        .into_iter()
+        .filter(|c| c.len() > 1)
+        .map(|mut component| {
+            component.sort();
+            let members: HashSet<&str> = component.iter().copied().collect();
+            let path = shortest_cycle(module_deps, component[0], &members);
+            let mut line = path.join(" -> ");
+            let extra: Vec<&str> = component
+                .iter()
+                .copied()
+                .filter(|m| !path.contains(m))
+                .collect();
+            if !extra.is_empty() {
+                line.push_str(&format!(" [also: {}]", extra.join(", ")));
+            }
+            line
+        })
+        .collect();
+    cycles.sort();
+    cycles
+}
+
+/// Breadth-first search for the shortest path from `start` back to itself,
+/// staying inside `members`
+fn shortest_cycle<'a>(
+    graph: &'a BTreeMap<String, BTreeSet<String>>,
+    start: &'a str,
+    members: &HashSet<&str>,
+) -> Vec<&'a str> {
+    let mut parent: HashMap<&str, &str> = HashMap::new();
+    let mut queue = std::collections::VecDeque::from([start]);
+
+    while let Some(node) = queue.pop_front() {
+        for next in graph.get(node).into_iter().flatten() {
+            let next = next.as_str();
+            if !members.contains(next) {
+                continue;
+            }
+            if next == start {
+                let mut path = vec![start];
+                let mut cur = node;
+                while cur != start {
+                    path.push(cur);
+                    cur = parent[cur];
+                }
+                let last = path.len();
+                path[1..last].reverse();
+                path.push(start);
+                return path;
+            }
+            if !parent.contains_key(next) {
+                parent.insert(next, node);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    vec![start]
+}
+
 /// Extract internal module dependency from a use path
 fn extract_internal_module_dep(use_path: &str) -> Option<String> {
     if use_path.starts_with("crate::") {
@@ -305,7 +781,7 @@ fn extract_internal_module_dep(use_path: &str) -> Option<String> {
         if parts.len() >= 2 {
             // If last segment starts with uppercase or is *, it's an item, not a module
             let last = parts.last().unwrap();
-            if last.chars().next().map_or(false, |c| c.is_uppercase()) || *last == "*" {
+            if last.chars().next().is_some_and(|c| c.is_uppercase()) || *last == "*" {
                 if parts.len() > 2 {
                     Some(parts[..parts.len() - 1].join("::"))
                 } else {
@@ -322,31 +798,122 @@ fn extract_internal_module_dep(use_path: &str) -> Option<String> {
     }
 }
 
-/// Extract type names from a signature string (heuristic)
-fn extract_type_names_from_signature(sig: &str) -> Vec<String> {
-    let mut types = Vec::new();
-
-    // Simple heuristic: find capitalized words that look like type names
-    for word in sig.split(|c: char| !c.is_alphanumeric() && c != '_') {
-        let trimmed = word.trim();
-        if !trimmed.is_empty()
-            && trimmed.chars().next().map_or(false, |c| c.is_uppercase())
-            && trimmed.len() > 1
-            && !is_keyword(trimmed)
-        {
-            types.push(trimmed.to_string());
+/// Extract the types a signature refers to, parsed with syn.
+///
+/// Only real type positions count: `Vec<Value>` yields just `Value`, enum
+/// variant names and derives are ignored, and the item's own name and its
+/// generic parameters are left out. Common std wrappers and primitives are
+/// skipped. Each type appears at most once.
+fn extract_type_names_from_signature(sig: &str, own_name: &str) -> Vec<String> {
+    // Defaulted trait methods show their body as `{ ... }`, which isn't valid Rust
+    let sig = sig.replace(" { ... }", ";");
+    let Ok(item) = syn::parse_str::<syn::Item>(&sig) else {
+        return Vec::new();
+    };
+    let mut visitor = TypeRefVisitor::default();
+    visitor.visit_item(&item);
+
+    let mut types = BTreeSet::new();
+    for (name, qualified) in visitor.paths {
+        let generic = !qualified && visitor.generics.contains(&name);
+        if !generic && name != own_name && !is_common_type(&name) {
+            types.insert(name);
+        }
+    }
+    types.into_iter().collect()
+}
+
+/// Collects the last segment of every type path, plus declared generic params
+#[derive(Default)]
+struct TypeRefVisitor {
+    /// (type name, whether it was written with a `::` qualifier)
+    paths: Vec<(String, bool)>,
+    generics: HashSet<String>,
+}
+
+impl TypeRefVisitor {
+    fn record(&mut self, path: &syn::Path) {
+        if let Some(last) = path.segments.last() {
+            self.paths
+                .push((parse::ident_name(&last.ident), path.segments.len() > 1));
+        }
+    }
+
+    fn record_bounds<'a>(&mut self, bounds: impl Iterator<Item = &'a syn::TypeParamBound>) {
+        for bound in bounds {
+            if let syn::TypeParamBound::Trait(t) = bound {
+                self.record(&t.path);
+            }
+        }
+    }
+
+    /// Signatures omit bodies (`fn f();`, `const X: T;`), which syn keeps as
+    /// verbatim tokens; re-parse those as bodiless foreign or trait items.
+    fn visit_verbatim(&mut self, tokens: &proc_macro2::TokenStream) {
+        if let Ok(item) = syn::parse2::<syn::ForeignItem>(tokens.clone()) {
+            if !matches!(item, syn::ForeignItem::Verbatim(_)) {
+                self.visit_foreign_item(&item);
+                return;
+            }
+        }
+        let trait_item = |input: syn::parse::ParseStream| {
+            input.call(syn::Attribute::parse_outer)?;
+            input.parse::<syn::Visibility>()?;
+            input.parse::<syn::TraitItem>()
+        };
+        if let Ok(item) = trait_item.parse2(tokens.clone()) {
+            self.visit_trait_item(&item);
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for TypeRefVisitor {
+    fn visit_item(&mut self, item: &'ast syn::Item) {
+        match item {
+            syn::Item::Verbatim(tokens) => self.visit_verbatim(tokens),
+            _ => syn::visit::visit_item(self, item),
+        }
+    }
+
+    fn visit_impl_item(&mut self, item: &'ast syn::ImplItem) {
+        match item {
+            syn::ImplItem::Verbatim(tokens) => self.visit_verbatim(tokens),
+            _ => syn::visit::visit_impl_item(self, item),
+        }
+    }
+
+    fn visit_type_param(&mut self, param: &'ast syn::TypeParam) {
+        self.generics.insert(param.ident.to_string());
+        syn::visit::visit_type_param(self, param);
+    }
+
+    fn visit_type_path(&mut self, ty: &'ast syn::TypePath) {
+        if ty.qself.is_none() {
+            self.record(&ty.path);
         }
+        syn::visit::visit_type_path(self, ty);
+    }
+
+    fn visit_type_trait_object(&mut self, ty: &'ast syn::TypeTraitObject) {
+        self.record_bounds(ty.bounds.iter());
+        syn::visit::visit_type_trait_object(self, ty);
     }
 
-    types
+    fn visit_type_impl_trait(&mut self, ty: &'ast syn::TypeImplTrait) {
+        self.record_bounds(ty.bounds.iter());
+        syn::visit::visit_type_impl_trait(self, ty);
+    }
 }
 
-fn is_keyword(word: &str) -> bool {
+/// Std wrappers and primitives that would otherwise dominate the hotspots
+fn is_common_type(name: &str) -> bool {
     matches!(
-        word,
-        "Self" | "String" | "Vec" | "Box" | "Option" | "Result" | "Ok" | "Err" | "Some" | "None"
-            | "HashMap" | "HashSet" | "BTreeMap" | "BTreeSet" | "Rc" | "Arc" | "Mutex"
-            | "RwLock" | "Pin" | "Cow" | "PhantomData" | "Where" | "Fn" | "FnMut" | "FnOnce"
+        name,
+        "Self" | "String" | "Vec" | "Box" | "Option" | "Result" | "HashMap" | "HashSet"
+            | "BTreeMap" | "BTreeSet" | "Rc" | "Arc" | "Mutex" | "RwLock" | "Pin" | "Cow"
+            | "PhantomData" | "Fn" | "FnMut" | "FnOnce" | "bool" | "char" | "str" | "u8"
+            | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16" | "i32" | "i64"
+            | "i128" | "isize" | "f32" | "f64"
     )
 }
 
@@ -388,11 +955,267 @@ mod tests {
 
     #[test]
     fn test_extract_type_names() {
-        let sig = "pub fn evaluate(expr: &Expr, ctx: &mut EvalContext) -> Result<Value, EvalError>;";
-        let types = extract_type_names_from_signature(sig);
-        assert!(types.contains(&"Expr".to_string()));
-        assert!(types.contains(&"EvalContext".to_string()));
-        assert!(types.contains(&"EvalError".to_string()));
+        let sig = "pub fn evaluate(expr : & Expr, ctx : & mut EvalContext) -> Result < Value , EvalError >;";
+        let types = extract_type_names_from_signature(sig, "evaluate");
+        assert_eq!(types, vec!["EvalContext", "EvalError", "Expr", "Value"]);
+    }
+
+    #[test]
+    fn test_extract_type_names_generic_args() {
+        // `Vec<Value>` counts only `Value`, and a type in both params and return counts once
+        let sig = "pub fn batch(xs : Vec < Value >) -> Vec < Value >;";
+        assert_eq!(extract_type_names_from_signature(sig, "batch"), vec!["Value"]);
+
+        // Own name, generic params and variant names are not type references
+        let sig = "pub enum Tree< T > {\n    Leaf(T),\n    Node(Vec < Tree < T > >, meters),\n}";
+        assert_eq!(extract_type_names_from_signature(sig, "Tree"), vec!["meters"]);
+
+        let sig = "impl Record {\n    pub fn get(& self, name : & str) -> Option < & Value >;\n    pub const LIMIT: Limit;\n}";
+        assert_eq!(
+            extract_type_names_from_signature(sig, "Record"),
+            vec!["Limit", "Value"]
+        );
+    }
+
+    #[test]
+    fn test_type_usage_once_per_module() {
+        use crate::model::*;
+        use std::path::PathBuf;
+
+        let items = crate::parse::parse_file(
+            &PathBuf::from("src/lib.rs"),
+            "pub fn a(v: Value) -> Value { v }\npub fn b(v: &[Value]) {}",
+        )
+        .unwrap();
+        let root = Module::for_test("crate", "src/lib.rs").with_items(items);
+        let crates = vec![CrateInfo::for_test("test", root)];
+
+        let rel = Relationships::collect(&crates);
+        assert_eq!(rel.type_usage["Value"], BTreeSet::from(["crate".to_string()]));
+        assert!(!rel.type_usage.contains_key("Vec"));
+    }
+
+    #[test]
+    fn test_hotspot_counts_impl_and_dyn_traits() {
+        use crate::model::*;
+
+        let a = Module::for_test("crate::a", "src/lib.rs")
+            .with_source("pub fn make() -> impl Evaluable { todo!() }");
+        let b = Module::for_test("crate::b", "src/lib.rs")
+            .with_source("pub fn boxed() -> Box<dyn Evaluable> { todo!() }");
+        let root = Module::for_test("crate", "src/lib.rs")
+            .with_source(
+                "pub fn all() -> impl Iterator<Item = Box<dyn Evaluable + Send>> { todo!() }",
+            )
+            .with_submodules(vec![a, b]);
+        let crates = vec![CrateInfo::for_test("test", root)];
+
+        let rel = Relationships::collect(&crates);
+        assert_eq!(rel.type_usage["Evaluable"].len(), 3);
+        let output = generate_relationships(&crates, DEFAULT_HOTSPOT_THRESHOLD, None);
+        assert!(output.contains("Evaluable — used in 3 modules\n"));
+    }
+
+    #[test]
+    fn test_hotspot_trait_default_method() {
+        use crate::model::*;
+
+        let root = Module::for_test("crate", "src/lib.rs").with_source(
+            "pub trait Eval {\n    fn eval(&self, c: &Ctx) -> Value { todo!() }\n    \
+             const LIMIT: Limit = Limit(3);\n}",
+        );
+        let crates = vec![CrateInfo::for_test("test", root)];
+
+        let output = generate_relationships(&crates, 1, None);
+        assert!(output.contains(
+            "Ctx   — used in 1 modules\nLimit — used in 1 modules\nValue — used in 1 modules\n"
+        ));
+    }
+
+    #[test]
+    fn test_hotspot_threshold_and_top() {
+        let modules = |n: usize| -> BTreeSet<String> {
+            (0..n).map(|i| format!("m{}", i)).collect()
+        };
+        let type_usage = BTreeMap::from([
+            ("Config".to_string(), modules(2)),
+            ("Expr".to_string(), modules(4)),
+            ("Record".to_string(), modules(3)),
+            ("Value".to_string(), modules(5)),
+            ("Ast".to_string(), modules(4)),
+        ]);
+        let listed = |threshold, top| -> Vec<(&str, usize)> {
+            hotspots(&type_usage, threshold, top)
+                .into_iter()
+                .map(|(ty, count)| (ty.as_str(), count))
+                .collect()
+        };
+
+        // The default floor drops Config; ties keep name order
+        assert_eq!(
+            listed(DEFAULT_HOTSPOT_THRESHOLD, None),
+            vec![("Value", 5), ("Ast", 4), ("Expr", 4), ("Record", 3)]
+        );
+        assert_eq!(listed(4, None), vec![("Value", 5), ("Ast", 4), ("Expr", 4)]);
+        assert_eq!(listed(2, Some(2)), vec![("Value", 5), ("Ast", 4)]);
+        assert!(listed(6, None).is_empty());
+        assert!(listed(1, Some(0)).is_empty());
+
+        let output = generate_relationships(&[], 5, Some(10));
+        assert!(output.contains("## Key Types (referenced from 5+ modules, top 10)\n\n"));
+        assert!(output.contains("(no types referenced from 5+ modules)"));
+    }
+
+    #[test]
+    fn test_generate_relationships_json() {
+        use crate::model::*;
+        use std::path::PathBuf;
+
+        let items = crate::parse::parse_file(
+            &PathBuf::from("src/lib.rs"),
+            "pub struct AppError; impl From<IoError> for AppError { fn from(e: IoError) -> Self { AppError } }",
+        )
+        .unwrap();
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            Module {
+                use_statements: vec!["crate::model::Item".to_string()],
+                ..Module::for_test("crate", "src/lib.rs").with_items(items)
+            },
+        )];
+
+        let json: serde_json::Value =
+            serde_json::from_str(&generate_relationships_json(&crates)).unwrap();
+        assert_eq!(json["trait_impls"]["From<IoError>"][0], "AppError");
+        assert_eq!(json["from_impls"][0]["from"], "IoError");
+        assert_eq!(json["from_impls"][0]["to"], "AppError");
+        assert_eq!(json["error_chains"][0], "IoError -> AppError");
+        assert_eq!(json["module_deps"]["crate"][0], "model");
+        assert!(json["type_usage"]["AppError"].is_array());
+    }
+
+    fn deps(edges: &[(&str, &str)]) -> BTreeMap<String, BTreeSet<String>> {
+        let mut map: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for (from, to) in edges {
+            map.entry(from.to_string()).or_default().insert(to.to_string());
+            map.entry(to.to_string()).or_default();
+        }
+        map
+    }
+
+    #[test]
+    fn test_mermaid_ids() {
+        assert_eq!(mermaid_id("engine::eval"), "engine_eval");
+        assert_eq!(mermaid_id("std :: fmt :: Display"), "std_fmt_Display");
+        assert_eq!(mermaid_id("From < engine :: Error >"), "From_engine_Error");
+        assert_eq!(mermaid_id("[T ; 2]"), "T_2");
+        assert_eq!(mermaid_id("end"), "end_");
+        assert_eq!(mermaid_label("Wrapper < T >"), "Wrapper #lt; T #gt;");
+    }
+
+    #[test]
+    fn test_find_dependency_cycles() {
+        let module_deps = deps(&[
+            // 3-node cycle
+            ("engine", "models"),
+            ("models", "utils"),
+            ("utils", "engine"),
+            // disjoint acyclic pair
+            ("cli", "config"),
+        ]);
+        let cycles = find_dependency_cycles(&module_deps);
+        assert_eq!(cycles, vec!["engine -> models -> utils -> engine"]);
+    }
+
+    #[test]
+    fn test_find_dependency_cycles_none() {
+        let module_deps = deps(&[("cli", "config"), ("config", "model")]);
+        assert!(find_dependency_cycles(&module_deps).is_empty());
+    }
+
+    #[test]
+    fn test_trait_hierarchy() {
+        use crate::model::*;
+        use std::path::PathBuf;
+
+        let items = crate::parse::parse_file(
+            &PathBuf::from("src/lib.rs"),
+            "pub trait Bar {}\npub trait Baz {}\npub trait Foo: Bar + Baz {}",
+        )
+        .unwrap();
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            Module::for_test("crate", "src/lib.rs").with_items(items),
+        )];
+
+        let rel = Relationships::collect(&crates);
+        assert_eq!(rel.trait_hierarchy.len(), 1);
+        assert_eq!(
+            rel.trait_hierarchy["Foo"],
+            BTreeSet::from(["Bar".to_string(), "Baz".to_string()])
+        );
+
+        let output = generate_relationships(&crates, DEFAULT_HOTSPOT_THRESHOLD, None);
+        assert!(output.contains("## Trait Hierarchy\n\nFoo : Bar + Baz\n"));
+        let json: serde_json::Value =
+            serde_json::from_str(&generate_relationships_json(&crates)).unwrap();
+        assert_eq!(json["trait_hierarchy"]["Foo"][1], "Baz");
+    }
+
+    #[test]
+    fn test_generic_implementors_listed_last() {
+        use crate::model::*;
+        use std::path::PathBuf;
+
+        let source = "pub trait Evaluable {}\npub struct Expr;\npub struct MyType<T>(T);\n\
+                      impl<T> Evaluable for MyType<T> {}\nimpl Evaluable for Expr {}\n\
+                      impl<'a> Evaluable for &'a str {}\nimpl<T: Copy> Evaluable for [T; 2] {}";
+        let items = crate::parse::parse_file(&PathBuf::from("src/lib.rs"), source).unwrap();
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            Module::for_test("crate", "src/lib.rs").with_items(items),
+        )];
+
+        // Lifetime parameters alone don't make an impl generic
+        let output = generate_relationships(&crates, DEFAULT_HOTSPOT_THRESHOLD, None);
+        assert!(output.contains("Evaluable <- &'a str, Expr (generic: MyType<T>, [T; 2])\n"));
+        let json: serde_json::Value =
+            serde_json::from_str(&generate_relationships_json(&crates)).unwrap();
+        assert_eq!(
+            json["trait_impls"]["Evaluable"],
+            serde_json::json!(["&'a str", "Expr", "MyType<T>", "[T; 2]"])
+        );
+    }
+
+    #[test]
+    fn test_blanket_impls() {
+        use crate::model::*;
+        use std::path::PathBuf;
+
+        let source = "pub trait Render {}\npub struct Page;\nimpl Render for Page {}\n\
+                      impl<T: std::fmt::Display> Render for T {}\n\
+                      impl<T> Render for Vec<T> where T: Render {}";
+        let items = crate::parse::parse_file(&PathBuf::from("src/lib.rs"), source).unwrap();
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            Module::for_test("crate", "src/lib.rs").with_items(items),
+        )];
+
+        // `T` stays out of the implementor map; `Vec<T>` is generic but not blanket
+        let output = generate_relationships(&crates, DEFAULT_HOTSPOT_THRESHOLD, None);
+        assert!(output.contains("Render <- Page (generic: Vec<T>)\n"));
+        assert!(output
+            .contains("## Blanket Implementations\n\nimpl<T: std::fmt::Display> Render for T\n"));
+        let json: serde_json::Value =
+            serde_json::from_str(&generate_relationships_json(&crates)).unwrap();
+        assert_eq!(json["trait_impls"]["Render"], serde_json::json!(["Page", "Vec<T>"]));
+        assert_eq!(json["blanket_impls"][0], "impl<T: std::fmt::Display> Render for T");
+    }
+
+    #[test]
+    fn test_dependency_cycles_section() {
+        let output = generate_relationships(&[], DEFAULT_HOTSPOT_THRESHOLD, None);
+        assert!(output.contains("## Dependency Cycles\n\n(none)"));
     }
 
     #[test]
@@ -406,4 +1229,46 @@ mod tests {
         // Should find IoError -> ConfigError -> AppError
         assert!(chains.iter().any(|c| c.contains("IoError") && c.contains("AppError")));
     }
+
+    #[test]
+    fn test_unsafe_items() {
+        use crate::model::*;
+        use std::path::PathBuf;
+
+        let mut ffi = Module::for_test("crate::ffi", "src/ffi.rs").with_source(
+            "pub struct Handle;\nunsafe impl Send for Handle {}\npub unsafe trait Raw {}\n\
+             pub trait Peek {\n    unsafe fn peek(&self);\n}\n",
+        );
+        // Items from include!("gen.rs") keep the included file's path
+        let generated = "// @generated\n\n\n\n\n\npub unsafe fn raw() {}\n";
+        ffi.items.extend(
+            crate::parse::parse_file(&PathBuf::from("src/gen.rs"), generated).unwrap(),
+        );
+        let root = Module::for_test("crate", "src/lib.rs")
+            .with_source(
+                "pub fn read() {}\n\npub unsafe fn read_raw() {}\n\
+                 pub struct Foo;\nimpl Foo {\n    pub async unsafe fn go() {}\n}\n",
+            )
+            .with_submodules(vec![ffi]);
+        let crates = vec![CrateInfo::for_test("test", root)];
+
+        let rel = Relationships::collect(&crates);
+        let root_items: Vec<&str> =
+            rel.unsafe_items["crate"].iter().map(|u| u.item.as_str()).collect();
+        assert_eq!(root_items, vec!["unsafe fn read_raw", "unsafe fn Foo::go"]);
+
+        let output = generate_relationships(&crates, DEFAULT_HOTSPOT_THRESHOLD, None);
+        // Modules with more unsafe items come first
+        assert!(output.contains(
+            "## Unsafe Code\n\nffi (4)\n  unsafe impl Send for Handle — src/ffi.rs:2\n  \
+             unsafe trait Raw — src/ffi.rs:3\n  unsafe fn Peek::peek — src/ffi.rs:5\n  \
+             unsafe fn raw — src/gen.rs:7\n\
+             crate (2)\n  unsafe fn read_raw — src/lib.rs:3\n  unsafe fn Foo::go — src/lib.rs:6\n"
+        ));
+        assert!(!output.contains("unsafe fn read "));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&generate_relationships_json(&crates)).unwrap();
+        assert_eq!(json["unsafe_items"]["crate"][0]["line"], 3);
+    }
 }
@@ -1,43 +1,355 @@
-use serde::Serialize;
-use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
 
-use crate::model::{CrateInfo, Item, ItemKind, Module, Visibility};
+use crate::error::{Result, RsmapError};
+use crate::model::{CrateInfo, ItemKind, Module, Param, ReturnTrait, StructKind};
+use crate::output;
 
 /// An entry in the JSON lookup index
-#[derive(Debug, Serialize)]
-struct IndexEntry {
-    file: String,
-    line_start: usize,
-    line_end: usize,
-    kind: String,
-    visibility: String,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub file: String,
+    pub line_start: usize,
+    pub line_end: usize,
+    /// Byte offsets of the item's text in `file`, `byte_start..byte_end`
+    /// (not recorded for members)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub byte_start: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub byte_end: Option<usize>,
+    pub kind: String,
+    pub visibility: String,
+    /// For a struct, whether its fields are named, positional or absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub struct_kind: Option<StructKind>,
+    /// For a struct, how many fields it declares
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field_count: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub generics: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub where_clause: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abi: Option<String>,
+    #[serde(default)]
+    pub deprecated: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<Param>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub return_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub return_trait: Option<ReturnTrait>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_async: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_unsafe: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_const: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub has_default: bool,
+    /// Impl or trait function with a `self` receiver, as opposed to an associated function
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_method: bool,
+    /// `#[cfg(...)]` predicate of the item (or of the item a member belongs to)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cfg: Option<String>,
+    /// `#[doc(hidden)]` on the item (or on the item a member belongs to)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub doc_hidden: bool,
+    /// For a re-export, the full path of the item it points at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reexport_of: Option<String>,
+    /// For a trait, the self types of its impls (blanket impls aside), by
+    /// full path where they're defined in the indexed crates
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub implementors: Vec<String>,
+    /// For a trait impl, the trait: its full path if it's indexed, else as written
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub implements: Option<String>,
+    /// First line of the doc comment, written with `--index-docs`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc_summary: Option<String>,
+}
+
+/// Longest `doc_summary` kept in the index, in bytes
+const DOC_SUMMARY_MAX_LEN: usize = 120;
+
+/// Version of index.json's layout, written as its `schema_version`.
+///
+/// Bump this whenever a change could break a reader: a field renamed, removed
+/// or retyped, or the envelope restructured. New optional fields don't need a
+/// bump.
+pub const INDEX_SCHEMA_VERSION: u32 = 1;
+
+/// index.json: the lookup entries wrapped with the schema version and a timestamp
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexFile {
+    pub schema_version: u32,
+    /// RFC 3339 time the index was generated
+    pub generated_at: String,
+    pub entries: BTreeMap<String, IndexEntry>,
 }
 
 /// Generate Layer 3: JSON Lookup Index (index.json)
 ///
 /// A lookup table mapping fully-qualified item paths to their file locations
 /// and line ranges. Designed for tooling to fetch specific source ranges.
-pub fn generate_index(crates: &[CrateInfo]) -> String {
-    let mut index: BTreeMap<String, IndexEntry> = BTreeMap::new();
+/// The entries sit under `entries`, next to `schema_version` and `generated_at`.
+pub fn generate_index(crates: &[CrateInfo], doc_summaries: bool) -> String {
+    let file = IndexFile {
+        schema_version: INDEX_SCHEMA_VERSION,
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        entries: build_index(crates, doc_summaries),
+    };
+    serde_json::to_string_pretty(&file).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// index.json in the pre-versioning layout: the bare map of entries
+pub fn generate_legacy_index(crates: &[CrateInfo], doc_summaries: bool) -> String {
+    serde_json::to_string_pretty(&build_index(crates, doc_summaries))
+        .unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Read the entries of the index.json file at `path`
+pub fn read_index(path: &Path) -> Result<BTreeMap<String, IndexEntry>> {
+    let content = std::fs::read_to_string(path).map_err(|e| RsmapError::io(path, e))?;
+    parse_index(&content).map_err(|e| match e {
+        RsmapError::Index { message, .. } => RsmapError::index(Some(path), message),
+        e => e,
+    })
+}
+
+/// Read the entries of an index.json in either the versioned or the legacy layout
+pub fn parse_index(content: &str) -> Result<BTreeMap<String, IndexEntry>> {
+    let invalid = |e: serde_json::Error| RsmapError::index(None, e);
+    let value: serde_json::Value = serde_json::from_str(content).map_err(invalid)?;
+    let Some(version) = value.get("schema_version") else {
+        return serde_json::from_value(value).map_err(invalid);
+    };
+    if version.as_u64().is_none_or(|v| v > u64::from(INDEX_SCHEMA_VERSION)) {
+        return Err(RsmapError::index(
+            None,
+            format!(
+                "Unsupported index schema version {} (this rsmap reads up to {})",
+                version, INDEX_SCHEMA_VERSION
+            ),
+        ));
+    }
+    let file: IndexFile = serde_json::from_value(value).map_err(invalid)?;
+    Ok(file.entries)
+}
+
+/// Generate Layer 3 as YAML (index.yaml), with the same entries as index.json
+pub fn generate_index_yaml(crates: &[CrateInfo], doc_summaries: bool) -> String {
+    serde_yaml::to_string(&build_index(crates, doc_summaries))
+        .unwrap_or_else(|_| "{}\n".to_string())
+}
+
+/// An index entry with its path, as one line of index.ndjson
+#[derive(Serialize)]
+struct NdjsonEntry<'a> {
+    path: &'a str,
+    #[serde(flatten)]
+    entry: &'a IndexEntry,
+}
 
+/// Generate Layer 3 as newline-delimited JSON (index.ndjson): the entries of
+/// index.json in path order, one `{"path": ..., <entry fields>}` object per line
+pub fn generate_index_ndjson(crates: &[CrateInfo], doc_summaries: bool) -> String {
+    let mut out = String::new();
+    for (path, entry) in &build_index(crates, doc_summaries) {
+        if let Ok(line) = serde_json::to_string(&NdjsonEntry { path, entry }) {
+            out.push_str(&line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// With `doc_summaries`, each entry carries the first line of its docs
+fn build_index(crates: &[CrateInfo], doc_summaries: bool) -> BTreeMap<String, IndexEntry> {
+    indexed_items(crates)
+        .into_iter()
+        .map(|i| {
+            let mut entry = i.entry;
+            if doc_summaries {
+                entry.doc_summary = i
+                    .doc_comment
+                    .and_then(output::first_doc_line)
+                    .map(|line| output::truncate(line, DOC_SUMMARY_MAX_LEN));
+            }
+            (i.path, entry)
+        })
+        .collect()
+}
+
+/// An index entry along with the signature and docs of what it describes
+#[derive(Debug, Clone)]
+pub struct IndexedItem<'a> {
+    pub path: String,
+    pub entry: IndexEntry,
+    pub signature: &'a str,
+    pub doc_comment: Option<&'a str>,
+}
+
+/// Every entry of the index in traversal order. A path may repeat (e.g. a fn
+/// and a macro of the same name); as in index.json, the last one wins.
+pub fn indexed_items(crates: &[CrateInfo]) -> Vec<IndexedItem<'_>> {
+    let mut items = Vec::new();
+    let mut seen = BTreeSet::new();
     for crate_info in crates {
-        collect_index_entries(&crate_info.root_module, &mut index);
+        collect_indexed_items(&crate_info.root_module, &mut items, &mut seen);
     }
 
-    serde_json::to_string_pretty(&index).unwrap_or_else(|_| "{}".to_string())
+    // Link trait impls and traits both ways
+    let links = trait_impl_links(crates);
+    let implementors = implementors_by_trait(&links);
+    let implements: BTreeMap<&str, &str> = links
+        .iter()
+        .map(|link| (link.impl_path.as_str(), link.trait_path.as_str()))
+        .collect();
+    for item in &mut items {
+        if item.entry.kind == "trait" {
+            if let Some(types) = implementors.get(&item.path) {
+                item.entry.implementors = types.clone();
+            }
+        } else if item.entry.kind.starts_with("impl ") {
+            item.entry.implements = implements.get(item.path.as_str()).map(|t| t.to_string());
+        }
+    }
+    items
+}
+
+/// The self types of each trait's impls (blanket impls aside), sorted. Traits
+/// and types defined in the crates are keyed and listed by their index path,
+/// others as written.
+pub fn trait_implementors(crates: &[CrateInfo]) -> BTreeMap<String, Vec<String>> {
+    implementors_by_trait(&trait_impl_links(crates))
 }
 
-fn collect_index_entries(module: &Module, index: &mut BTreeMap<String, IndexEntry>) {
+fn implementors_by_trait(links: &[ImplLink]) -> BTreeMap<String, Vec<String>> {
+    let mut implementors: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for link in links {
+        if let Some(ref self_path) = link.self_path {
+            implementors.entry(link.trait_path.clone()).or_default().insert(self_path.clone());
+        }
+    }
+    implementors
+        .into_iter()
+        .map(|(trait_path, types)| (trait_path, types.into_iter().collect()))
+        .collect()
+}
+
+/// A trait impl's index path, with what its trait and self type resolve to
+struct ImplLink {
+    impl_path: String,
+    trait_path: String,
+    /// `None` for a blanket impl, whose self type is a type parameter
+    self_path: Option<String>,
+}
+
+/// Every trait impl in the crates, with its trait and self type resolved to
+/// index paths where they name an indexed trait or type
+fn trait_impl_links(crates: &[CrateInfo]) -> Vec<ImplLink> {
+    let mut defined: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for crate_info in crates {
+        for module in crate_info.root_module.all_modules() {
+            for item in &module.items {
+                if matches!(
+                    item.kind,
+                    ItemKind::Struct { .. }
+                        | ItemKind::Enum
+                        | ItemKind::Trait { .. }
+                        | ItemKind::TypeAlias
+                ) {
+                    defined.entry(&item.name).or_default().push(item.full_path(&module.path));
+                }
+            }
+        }
+    }
+
+    let mut links = Vec::new();
+    for crate_info in crates {
+        for module in crate_info.root_module.all_modules() {
+            for item in &module.items {
+                if let ItemKind::Impl {
+                    ref self_ty,
+                    trait_name: Some(ref trait_name),
+                    blanket,
+                } = item.kind
+                {
+                    links.push(ImplLink {
+                        impl_path: item.full_path(&module.path),
+                        trait_path: resolve_type_path(trait_name, &module.path, &defined),
+                        self_path: (!blanket)
+                            .then(|| resolve_type_path(self_ty, &module.path, &defined)),
+                    });
+                }
+            }
+        }
+    }
+    links
+}
+
+/// The index path of a trait or type as written in `module_path`: a `crate::`
+/// path as is, a `self::`/`super::` path relative to the module, else a bare
+/// name defined in that module, else the one item of that name in the crates.
+/// Anything else (external or ambiguous) stays as written.
+fn resolve_type_path(
+    written: &str,
+    module_path: &str,
+    defined: &BTreeMap<&str, Vec<String>>,
+) -> String {
+    let base = written.split('<').next().unwrap_or(written).trim();
+    if base.starts_with("crate::") {
+        return base.to_string();
+    }
+    // `self::` and `super::` are relative to the impl's module
+    if base.starts_with("self::") || base.starts_with("super::") {
+        let mut module = module_path.to_string();
+        let mut rest = base;
+        loop {
+            if let Some(tail) = rest.strip_prefix("self::") {
+                rest = tail;
+            } else if let Some(tail) = rest.strip_prefix("super::") {
+                module = module.rsplit_once("::").map_or(module.clone(), |(p, _)| p.to_string());
+                rest = tail;
+            } else {
+                break;
+            }
+        }
+        return format!("{}::{}", module, rest);
+    }
+    let Some(paths) = defined.get(base) else {
+        return written.to_string();
+    };
+    let local = format!("{}::{}", module_path, base);
+    if paths.contains(&local) {
+        local
+    } else if let [only] = paths.as_slice() {
+        only.clone()
+    } else {
+        written.to_string()
+    }
+}
+
+fn collect_indexed_items<'a>(
+    module: &'a Module,
+    out: &mut Vec<IndexedItem<'a>>,
+    seen: &mut BTreeSet<String>,
+) {
     for item in &module.items {
-        let full_path = item_full_path(&module.path, item);
+        let full_path = item.full_path(&module.path);
         let kind_str = match &item.kind {
             ItemKind::Function => "function".to_string(),
-            ItemKind::Struct => "struct".to_string(),
+            ItemKind::Struct { .. } => "struct".to_string(),
             ItemKind::Enum => "enum".to_string(),
-            ItemKind::Trait => "trait".to_string(),
+            ItemKind::Trait { .. } => "trait".to_string(),
             ItemKind::Impl {
                 self_ty,
                 trait_name,
+                ..
             } => {
                 if let Some(tn) = trait_name {
                     format!("impl {} for {}", tn, self_ty)
@@ -49,47 +361,118 @@ fn collect_index_entries(module: &Module, index: &mut BTreeMap<String, IndexEntr
             ItemKind::Const => "const".to_string(),
             ItemKind::Static => "static".to_string(),
             ItemKind::Macro => "macro".to_string(),
+            ItemKind::ProcMacro { .. } => item.kind.to_string(),
             ItemKind::Use => "use".to_string(),
         };
 
-        let vis_str = match item.visibility {
-            Visibility::Pub => "pub",
-            Visibility::PubCrate => "pub(crate)",
-            Visibility::PubSuper => "pub(super)",
-            Visibility::Private => "private",
+        // pub, pub(crate), pub(super), pub(in path) or private
+        let vis_str = item.visibility.to_string();
+
+        let (struct_kind, field_count) = match item.kind {
+            ItemKind::Struct {
+                struct_kind,
+                field_count,
+            } => (Some(struct_kind), Some(field_count)),
+            _ => (None, None),
+        };
+
+        // Members get their own entries, e.g. crate::Config::port. Impl items
+        // are keyed by the self type (crate::Expr::eval), falling back to the
+        // impl's path when another entry already has that name.
+        let member_prefix = match &item.kind {
+            ItemKind::Impl { self_ty, .. } => {
+                format!("{}::{}", module.path, impl_self_name(self_ty))
+            }
+            _ => full_path.clone(),
         };
+        for member in &item.members {
+            let mut member_path = format!("{}::{}", member_prefix, member.name);
+            if matches!(item.kind, ItemKind::Impl { .. }) && seen.contains(&member_path) {
+                member_path = format!("{}::{}", full_path, member.name);
+            }
+            seen.insert(member_path.clone());
+            out.push(IndexedItem {
+                path: member_path,
+                entry: IndexEntry {
+                    file: item.file_path.display().to_string(),
+                    line_start: member.line_start,
+                    line_end: member.line_end,
+                    byte_start: None,
+                    byte_end: None,
+                    kind: member.kind.to_string(),
+                    visibility: member.visibility.to_string(),
+                    struct_kind: None,
+                    field_count: None,
+                    generics: Vec::new(),
+                    where_clause: None,
+                    abi: None,
+                    deprecated: false,
+                    params: Vec::new(),
+                    return_type: None,
+                    return_trait: None,
+                    is_async: member.is_async,
+                    is_unsafe: member.is_unsafe,
+                    is_const: member.is_const,
+                    has_default: member.has_default,
+                    is_method: member.is_method,
+                    cfg: item.cfg.clone(),
+                    doc_hidden: item.doc_hidden,
+                    reexport_of: None,
+                    implementors: Vec::new(),
+                    implements: None,
+                    doc_summary: None,
+                },
+                signature: &member.signature,
+                doc_comment: member.doc_comment.as_deref(),
+            });
+        }
 
-        index.insert(
-            full_path,
-            IndexEntry {
-                file: module.file_path.display().to_string(),
+        seen.insert(full_path.clone());
+        out.push(IndexedItem {
+            path: full_path,
+            entry: IndexEntry {
+                file: item.file_path.display().to_string(),
                 line_start: item.line_start,
                 line_end: item.line_end,
+                byte_start: Some(item.byte_start),
+                byte_end: Some(item.byte_end),
                 kind: kind_str,
-                visibility: vis_str.to_string(),
+                visibility: vis_str,
+                struct_kind,
+                field_count,
+                generics: item.generics.clone(),
+                where_clause: item.where_clause.clone(),
+                abi: item.abi.clone(),
+                deprecated: item.deprecated.is_some(),
+                params: item.params.clone(),
+                return_type: item.return_type.clone(),
+                return_trait: item.return_trait.clone(),
+                is_async: item.is_async,
+                is_unsafe: item.is_unsafe,
+                is_const: item.is_const,
+                has_default: false,
+                is_method: false,
+                cfg: item.cfg.clone(),
+                doc_hidden: item.doc_hidden,
+                reexport_of: item.reexport_of.clone(),
+                implementors: Vec::new(),
+                implements: None,
+                doc_summary: None,
             },
-        );
+            signature: &item.signature,
+            doc_comment: item.doc_comment.as_deref(),
+        });
     }
 
     for sub in &module.submodules {
-        collect_index_entries(sub, index);
+        collect_indexed_items(sub, out, seen);
     }
 }
 
-fn item_full_path(module_path: &str, item: &Item) -> String {
-    match &item.kind {
-        ItemKind::Impl {
-            self_ty,
-            trait_name,
-        } => {
-            if let Some(tn) = trait_name {
-                format!("{}::impl {} for {}", module_path, tn, self_ty)
-            } else {
-                format!("{}::impl {}", module_path, self_ty)
-            }
-        }
-        _ => format!("{}::{}", module_path, item.name),
-    }
+/// Bare name of an impl's self type: `EvalContext<'a>` -> `EvalContext`
+fn impl_self_name(self_ty: &str) -> &str {
+    let base = self_ty.split('<').next().unwrap_or(self_ty);
+    base.rsplit("::").next().unwrap_or(base).trim()
 }
 
 #[cfg(test)]
@@ -98,53 +481,91 @@ mod tests {
     use crate::model::*;
     use std::path::PathBuf;
 
+    fn module_index(module: &Module) -> BTreeMap<String, IndexEntry> {
+        let mut items = Vec::new();
+        collect_indexed_items(module, &mut items, &mut BTreeSet::new());
+        items.into_iter().map(|i| (i.path, i.entry)).collect()
+    }
+
     #[test]
     fn test_generate_index() {
-        let crates = vec![CrateInfo {
-            name: "test".to_string(),
-            kind: CrateKind::Lib,
-            edition: "2021".to_string(),
-            version: "0.1.0".to_string(),
-            external_deps: vec![],
-            root_module: Module {
-                path: "crate".to_string(),
-                file_path: PathBuf::from("src/lib.rs"),
-                file_hash: "abc".to_string(),
-                doc_comment: None,
-                visibility: Visibility::Pub,
-                items: vec![
-                    Item {
-                        name: "Config".to_string(),
-                        kind: ItemKind::Struct,
-                        visibility: Visibility::Pub,
-                        signature: "pub struct Config {}".to_string(),
-                        doc_comment: None,
-                        file_path: PathBuf::from("src/lib.rs"),
-                        line_start: 1,
-                        line_end: 5,
-                        content_hash: "h1".to_string(),
-                    },
-                    Item {
-                        name: "init".to_string(),
-                        kind: ItemKind::Function,
-                        visibility: Visibility::Pub,
-                        signature: "pub fn init();".to_string(),
-                        doc_comment: None,
-                        file_path: PathBuf::from("src/lib.rs"),
-                        line_start: 7,
-                        line_end: 15,
-                        content_hash: "h2".to_string(),
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            Module::for_test("crate", "src/lib.rs").with_items(vec![
+                Item {
+                    name: "Config".to_string(),
+                    kind: ItemKind::Struct {
+                        struct_kind: StructKind::Named,
+                        field_count: 0,
                     },
-                ],
-                submodules: vec![],
-                use_statements: vec![],
-                is_inline: false,
-            },
-        }];
+                    visibility: Visibility::Pub,
+                    signature: "pub struct Config {}".to_string(),
+                    doc_comment: None,
+                    derives: vec![],
+                    attributes: vec![],
+                    deprecated: None,
+                    generics: vec![],
+                    where_clause: None,
+                    supertraits: Vec::new(),
+                    abi: None,
+                    members: vec![],
+                    params: vec![],
+                    return_type: None,
+                    return_trait: None,
+                    is_async: false,
+                    is_unsafe: false,
+                    is_const: false,
+                    repr: None,
+                    cfg: None,
+                    doc_hidden: false,
+                    reexport_of: None,
+                    file_path: PathBuf::from("src/lib.rs"),
+                    line_start: 1,
+                    line_end: 5,
+                    byte_start: 0,
+                    byte_end: 0,
+                    content_hash: "h1".to_string(),
+                },
+                Item {
+                    name: "init".to_string(),
+                    kind: ItemKind::Function,
+                    visibility: Visibility::Pub,
+                    signature: "pub fn init();".to_string(),
+                    doc_comment: None,
+                    derives: vec![],
+                    attributes: vec![],
+                    deprecated: None,
+                    generics: vec![],
+                    where_clause: None,
+                    supertraits: Vec::new(),
+                    abi: None,
+                    members: vec![],
+                    params: vec![],
+                    return_type: None,
+                    return_trait: None,
+                    is_async: false,
+                    is_unsafe: false,
+                    is_const: false,
+                    repr: None,
+                    cfg: None,
+                    doc_hidden: false,
+                    reexport_of: None,
+                    file_path: PathBuf::from("src/lib.rs"),
+                    line_start: 7,
+                    line_end: 15,
+                    byte_start: 0,
+                    byte_end: 0,
+                    content_hash: "h2".to_string(),
+                },
+            ]),
+        )];
 
-        let json = generate_index(&crates);
-        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let json = generate_index(&crates, false);
+        let file: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(file["schema_version"], INDEX_SCHEMA_VERSION);
+        assert!(file["generated_at"].is_string());
 
+        let parsed = &file["entries"];
         assert!(parsed.get("crate::Config").is_some());
         assert!(parsed.get("crate::init").is_some());
 
@@ -153,5 +574,277 @@ mod tests {
         assert_eq!(config["visibility"], "pub");
         assert_eq!(config["line_start"], 1);
         assert_eq!(config["line_end"], 5);
+        assert_eq!(config["struct_kind"], "named");
+        assert_eq!(config["field_count"], 0);
+        assert!(parsed["crate::init"].get("struct_kind").is_none());
+        assert!(parsed["crate::init"].get("field_count").is_none());
+        assert!(config.get("generics").is_none());
+        assert!(config.get("where_clause").is_none());
+        assert_eq!(config["deprecated"], false);
+    }
+
+    #[test]
+    fn test_parse_index_layouts() {
+        let items = crate::parse::parse_file(&PathBuf::from("src/lib.rs"), "pub fn run() {}")
+            .unwrap();
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            Module::for_test("crate", "src/lib.rs").with_items(items),
+        )];
+
+        let versioned = parse_index(&generate_index(&crates, false)).unwrap();
+        let legacy = parse_index(&generate_legacy_index(&crates, false)).unwrap();
+        assert_eq!(versioned.keys().collect::<Vec<_>>(), vec!["crate::run"]);
+        assert_eq!(legacy.keys().collect::<Vec<_>>(), vec!["crate::run"]);
+
+        let legacy_json: serde_json::Value =
+            serde_json::from_str(&generate_legacy_index(&crates, false)).unwrap();
+        assert!(legacy_json.get("schema_version").is_none());
+        assert_eq!(legacy_json["crate::run"]["kind"], "function");
+
+        let future = r#"{"schema_version": 99, "generated_at": "", "entries": {}}"#;
+        let err = parse_index(future).unwrap_err().to_string();
+        assert!(err.contains("Unsupported index schema version 99"));
+    }
+
+    #[test]
+    fn test_generate_index_yaml() {
+        let items = crate::parse::parse_file(
+            &PathBuf::from("src/lib.rs"),
+            "pub fn zeta() {}\npub fn alpha<T>(x: T) where\n    T: Clone,\n{}",
+        )
+        .unwrap();
+        let mut crates = vec![CrateInfo::for_test(
+            "test",
+            Module::for_test("crate", "src/lib.rs").with_items(items),
+        )];
+        // Multiline strings must survive a round trip
+        crates[0].root_module.items[1].where_clause = Some("where\n    T: Clone".to_string());
+
+        let yaml = generate_index_yaml(&crates, false);
+        assert!(yaml.find("crate::alpha:").unwrap() < yaml.find("crate::zeta:").unwrap());
+
+        let parsed: BTreeMap<String, IndexEntry> = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(parsed["crate::alpha"].line_start, 2);
+        assert_eq!(
+            parsed["crate::alpha"].where_clause.as_deref(),
+            Some("where\n    T: Clone")
+        );
+        assert_eq!(parsed.len(), 2);
+    }
+
+    #[test]
+    fn test_generate_index_ndjson() {
+        let source = "/// Start here\npub fn zeta() {}\npub struct Alpha {\n    pub x: u8,\n}\n";
+        let items = crate::parse::parse_file(&PathBuf::from("src/lib.rs"), source).unwrap();
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            Module::for_test("crate", "src/lib.rs").with_items(items),
+        )];
+
+        let ndjson = generate_index_ndjson(&crates, true);
+        let lines: Vec<serde_json::Value> = ndjson
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("each line is one JSON object"))
+            .collect();
+        let paths: Vec<&str> = lines.iter().map(|l| l["path"].as_str().unwrap()).collect();
+        assert_eq!(paths, vec!["crate::Alpha", "crate::Alpha::x", "crate::zeta"]);
+        assert_eq!(lines[0]["kind"], "struct");
+        assert_eq!(lines[0]["field_count"], 1);
+        assert_eq!(lines[2]["line_start"], 1);
+        assert_eq!(lines[2]["doc_summary"], "Start here");
+        assert!(ndjson.ends_with("}\n"));
+    }
+
+    #[test]
+    fn test_index_impl_methods() {
+        let source = "pub struct Expr;\n\
+                      impl Expr {\n    pub fn new() -> Self { Expr }\n}\n\
+                      impl Evaluable for Expr {\n    fn eval(&self) -> u32 {\n        0\n    }\n}\n\
+                      impl From<u8> for Expr {\n    fn from(_: u8) -> Self { Expr }\n}\n\
+                      impl From<u16> for Expr {\n    fn from(_: u16) -> Self { Expr }\n}\n";
+        let items = crate::parse::parse_file(&PathBuf::from("src/lib.rs"), source).unwrap();
+        let module = Module::for_test("crate", "src/lib.rs").with_items(items);
+        let index = module_index(&module);
+
+        let new = &index["crate::Expr::new"];
+        assert_eq!(new.kind, "method");
+        assert_eq!(new.visibility, "pub");
+        assert_eq!((new.line_start, new.line_end), (3, 3));
+        assert!(!new.is_method);
+
+        let eval = &index["crate::Expr::eval"];
+        assert_eq!(eval.kind, "method");
+        assert_eq!((eval.line_start, eval.line_end), (6, 8));
+        assert!(eval.is_method);
+        let json = serde_json::to_value(new).unwrap();
+        assert!(json.get("is_method").is_none());
+
+        // A second `from` can't reuse crate::Expr::from
+        assert_eq!(index["crate::Expr::from"].line_start, 11);
+        assert_eq!(index["crate::impl From<u16> for Expr::from"].line_start, 14);
+    }
+
+    #[test]
+    fn test_index_restricted_visibility() {
+        let source = "pub(in crate::engine) fn step() {}\n\
+                      pub struct Engine {\n    pub(in crate::engine) state: u8,\n}\n";
+        let items = crate::parse::parse_file(&PathBuf::from("src/lib.rs"), source).unwrap();
+        let module = Module::for_test("crate", "src/lib.rs").with_items(items);
+        let index = module_index(&module);
+        assert_eq!(index["crate::step"].visibility, "pub(in crate::engine)");
+        assert_eq!(index["crate::Engine::state"].visibility, "pub(in crate::engine)");
+    }
+
+    #[test]
+    fn test_index_generics() {
+        let items = crate::parse::parse_file(
+            &PathBuf::from("src/lib.rs"),
+            "pub fn pick<'a, T: Clone + Default, I: Iterator<Item = &'a T>>(xs: I) -> T \
+             where T: Send { todo!() }\n\
+             pub enum Tree<T> where T: Ord { Leaf(T) }",
+        )
+        .unwrap();
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            Module::for_test("crate", "src/lib.rs").with_items(items),
+        )];
+
+        let json = generate_index(&crates, false);
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let pick = &parsed["entries"]["crate::pick"];
+        assert_eq!(
+            pick["generics"],
+            serde_json::json!(["'a", "T: Clone + Default", "I: Iterator<Item = &'a T>"])
+        );
+        assert_eq!(pick["where_clause"], "where T: Send");
+        let tree = &parsed["entries"]["crate::Tree"];
+        assert_eq!(tree["generics"], serde_json::json!(["T"]));
+        assert_eq!(tree["where_clause"], "where T: Ord");
+    }
+
+    #[test]
+    fn test_index_trait_impl_links() {
+        let parse = |source: &str| crate::parse::parse_file(&PathBuf::from("src/lib.rs"), source);
+        let module = |path: &str, items, submodules| Module {
+            is_inline: true,
+            ..Module::for_test(path, "src/lib.rs").with_items(items).with_submodules(submodules)
+        };
+        let shapes = module(
+            "crate::shapes",
+            parse(
+                "pub struct Circle;\nimpl super::Shape for Circle {}\n\
+                 impl std::fmt::Display for Circle {}\n",
+            )
+            .unwrap(),
+            vec![],
+        );
+        let root = module(
+            "crate",
+            parse("pub trait Shape {}\npub struct Square;\nimpl Shape for Square {}\n\
+                   impl<T: Copy> Shape for T {}\n")
+            .unwrap(),
+            vec![shapes],
+        );
+        let crates = vec![CrateInfo::for_test("test", root)];
+
+        let index = build_index(&crates, false);
+        // The blanket impl adds no implementor; Circle resolves across modules
+        assert_eq!(
+            index["crate::Shape"].implementors,
+            vec!["crate::Square", "crate::shapes::Circle"]
+        );
+        let implements = |path: &str| index[path].implements.clone();
+        assert_eq!(implements("crate::impl Shape for Square").as_deref(), Some("crate::Shape"));
+        assert_eq!(implements("crate::impl Shape for T").as_deref(), Some("crate::Shape"));
+        assert_eq!(
+            implements("crate::shapes::impl super::Shape for Circle").as_deref(),
+            Some("crate::Shape")
+        );
+        assert_eq!(
+            implements("crate::shapes::impl std::fmt::Display for Circle").as_deref(),
+            Some("std::fmt::Display")
+        );
+        assert!(index["crate::Square"].implementors.is_empty());
+    }
+
+    #[test]
+    fn test_index_fn_qualifiers() {
+        let items = crate::parse::parse_file(
+            &PathBuf::from("src/lib.rs"),
+            "pub async unsafe fn fetch() {}\npub const fn answer() -> u32 { 42 }\n\
+             impl Pool {\n    pub const fn new() -> Self { Pool }\n    \
+             pub async unsafe fn take(&self) {}\n}",
+        )
+        .unwrap();
+        let module = Module::for_test("crate", "src/lib.rs").with_items(items);
+        let index = module_index(&module);
+
+        let fetch = &index["crate::fetch"];
+        assert!(fetch.is_async && fetch.is_unsafe && !fetch.is_const);
+        let answer = serde_json::to_value(&index["crate::answer"]).unwrap();
+        assert_eq!(answer["is_const"], true);
+        assert!(answer.get("is_async").is_none());
+
+        assert!(index["crate::Pool::new"].is_const);
+        let take = &index["crate::Pool::take"];
+        assert!(take.is_async && take.is_unsafe && !take.is_const);
+    }
+
+    #[test]
+    fn test_index_cfg() {
+        let items = crate::parse::parse_file(
+            &PathBuf::from("src/lib.rs"),
+            "#[cfg(unix)]\npub struct Pipe { pub fd: i32 }\npub fn open() {}",
+        )
+        .unwrap();
+        let module = Module::for_test("crate", "src/lib.rs").with_items(items);
+        let index = module_index(&module);
+
+        assert_eq!(index["crate::Pipe"].cfg.as_deref(), Some("unix"));
+        assert_eq!(index["crate::Pipe::fd"].cfg.as_deref(), Some("unix"));
+        let open = serde_json::to_value(&index["crate::open"]).unwrap();
+        assert!(open.get("cfg").is_none());
+    }
+
+    #[test]
+    fn test_index_doc_hidden() {
+        let items = crate::parse::parse_file(
+            &PathBuf::from("src/lib.rs"),
+            "#[doc(hidden)]\npub struct Raw { pub ptr: usize }\npub fn open() {}",
+        )
+        .unwrap();
+        let module = Module::for_test("crate", "src/lib.rs").with_items(items);
+        let index = module_index(&module);
+
+        let raw = serde_json::to_value(&index["crate::Raw"]).unwrap();
+        assert_eq!(raw["doc_hidden"], true);
+        assert!(index["crate::Raw::ptr"].doc_hidden);
+        let open = serde_json::to_value(&index["crate::open"]).unwrap();
+        assert!(open.get("doc_hidden").is_none());
+    }
+
+    #[test]
+    fn test_index_doc_summaries() {
+        let long = "x".repeat(DOC_SUMMARY_MAX_LEN + 10);
+        let source = format!(
+            "/// Opens the pipe.\n///\n/// More detail.\npub fn open() {{}}\n\
+             /// {long}\npub fn verbose() {{}}\npub fn bare() {{}}"
+        );
+        let items = crate::parse::parse_file(&PathBuf::from("src/lib.rs"), &source).unwrap();
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            Module::for_test("crate", "src/lib.rs").with_items(items),
+        )];
+
+        let plain = build_index(&crates, false);
+        assert!(plain["crate::open"].doc_summary.is_none());
+
+        let index = build_index(&crates, true);
+        assert_eq!(index["crate::open"].doc_summary.as_deref(), Some("Opens the pipe."));
+        let verbose = index["crate::verbose"].doc_summary.as_deref().unwrap();
+        assert!(verbose.len() <= DOC_SUMMARY_MAX_LEN);
+        let bare = serde_json::to_value(&index["crate::bare"]).unwrap();
+        assert!(bare.get("doc_summary").is_none());
     }
 }
@@ -0,0 +1,267 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
+use syn::visit::Visit;
+
+use crate::model::{CrateInfo, ItemKind, Module};
+use crate::output::SourceFiles;
+use crate::parse;
+
+/// Function-to-function reference edges collected across all crates
+#[derive(Debug, Default, Serialize)]
+struct CallGraph {
+    /// caller -> functions it references
+    calls: BTreeMap<String, BTreeSet<String>>,
+    /// callee -> functions that reference it
+    callers: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl CallGraph {
+    fn collect(crates: &[CrateInfo], project_root: &Path) -> Self {
+        let mut graph = CallGraph::default();
+        let mut sources = SourceFiles::default();
+
+        for crate_info in crates {
+            let modules = crate_info.root_module.all_modules();
+            let functions = function_paths(&modules);
+
+            for module in &modules {
+                for item in &module.items {
+                    if item.abi.is_some() {
+                        continue;
+                    }
+                    // Items pulled in with include! live in the included file
+                    let file = project_root.join(&item.file_path);
+                    let Some(text) = sources.lines(&file, item.line_start, item.line_end) else {
+                        continue;
+                    };
+
+                    // (caller path, references found in its body)
+                    let mut bodies: Vec<(String, ReferenceVisitor)> = Vec::new();
+                    match &item.kind {
+                        ItemKind::Function | ItemKind::ProcMacro { .. } => {
+                            let Ok(func) = syn::parse_str::<syn::ItemFn>(&text) else {
+                                continue;
+                            };
+                            let mut visitor = ReferenceVisitor::default();
+                            visitor.visit_item_fn(&func);
+                            bodies.push((format!("{}::{}", module.path, item.name), visitor));
+                        }
+                        ItemKind::Impl { self_ty, .. } => {
+                            let Ok(block) = syn::parse_str::<syn::ItemImpl>(&text) else {
+                                continue;
+                            };
+                            for impl_item in &block.items {
+                                if let syn::ImplItem::Fn(method) = impl_item {
+                                    let mut visitor = ReferenceVisitor::default();
+                                    visitor.visit_impl_item_fn(method);
+                                    let method_name = parse::ident_name(&method.sig.ident);
+                                    let caller =
+                                        format!("{}::{}::{}", module.path, self_ty, method_name);
+                                    bodies.push((caller, visitor));
+                                }
+                            }
+                        }
+                        _ => continue,
+                    }
+
+                    for (caller, visitor) in bodies {
+                        for path in &visitor.paths {
+                            for callee in resolve_reference(path, &module.path, &functions) {
+                                graph.add_edge(&caller, callee);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        graph
+    }
+
+    fn add_edge(&mut self, caller: &str, callee: String) {
+        self.calls
+            .entry(caller.to_string())
+            .or_default()
+            .insert(callee.clone());
+        self.callers
+            .entry(callee)
+            .or_default()
+            .insert(caller.to_string());
+    }
+}
+
+/// Generate Layer 4 as JSON (call-graph.json)
+pub fn generate_call_graph_json(crates: &[CrateInfo], project_root: &Path) -> String {
+    let graph = CallGraph::collect(crates, project_root);
+    serde_json::to_string_pretty(&graph).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Generate Layer 4: Call Graph (call-graph.md)
+///
+/// For each function and impl method, the free functions its body references
+/// by name, and the reverse "called by" map. Heuristic: method calls and
+/// macro bodies are not tracked.
+pub fn generate_call_graph(crates: &[CrateInfo], project_root: &Path) -> String {
+    let graph = CallGraph::collect(crates, project_root);
+    let mut out = String::new();
+
+    out.push_str("## Calls\n\n");
+    write_edges(&mut out, &graph.calls, "->");
+
+    out.push_str("## Called By\n\n");
+    write_edges(&mut out, &graph.callers, "<-");
+
+    out
+}
+
+fn write_edges(out: &mut String, edges: &BTreeMap<String, BTreeSet<String>>, arrow: &str) {
+    if edges.is_empty() {
+        out.push_str("(none found)\n\n");
+        return;
+    }
+    let max_len = edges.keys().map(|k| k.len()).max().unwrap_or(0);
+    for (from, to) in edges {
+        let targets: Vec<&str> = to.iter().map(|s| s.as_str()).collect();
+        out.push_str(&format!(
+            "{:<width$} {} {}\n",
+            from,
+            arrow,
+            targets.join(", "),
+            width = max_len
+        ));
+    }
+    out.push('\n');
+}
+
+/// Map of function name -> full paths of every free function with that name
+fn function_paths(modules: &[&Module]) -> HashMap<String, Vec<String>> {
+    let mut functions: HashMap<String, Vec<String>> = HashMap::new();
+    for module in modules {
+        for item in &module.items {
+            if item.kind == ItemKind::Function {
+                functions
+                    .entry(item.name.clone())
+                    .or_default()
+                    .push(format!("{}::{}", module.path, item.name));
+            }
+        }
+    }
+    functions
+}
+
+/// Resolve a referenced path (e.g. `["eval", "evaluate"]`) to known functions.
+///
+/// Qualified paths must match the end of the function's path; among several
+/// candidates, one in the caller's own module wins.
+fn resolve_reference(
+    segments: &[String],
+    module_path: &str,
+    functions: &HashMap<String, Vec<String>>,
+) -> Vec<String> {
+    let Some(name) = segments.last() else {
+        return Vec::new();
+    };
+    let Some(candidates) = functions.get(name) else {
+        return Vec::new();
+    };
+
+    let qualified: Vec<&str> = segments
+        .iter()
+        .map(|s| s.as_str())
+        .filter(|s| !matches!(*s, "crate" | "self" | "super"))
+        .collect();
+    let suffix = format!("::{}", qualified.join("::"));
+    let matching: Vec<&String> = candidates
+        .iter()
+        .filter(|c| c.ends_with(&suffix))
+        .collect();
+
+    let local = format!("{}::{}", module_path, name);
+    if segments.len() == 1 && matching.iter().any(|c| **c == local) {
+        return vec![local];
+    }
+    matching.into_iter().cloned().collect()
+}
+
+/// Collects path expressions in a function body, skipping names bound locally
+#[derive(Default)]
+struct ReferenceVisitor {
+    paths: BTreeSet<Vec<String>>,
+    locals: HashSet<String>,
+}
+
+impl<'ast> Visit<'ast> for ReferenceVisitor {
+    fn visit_pat_ident(&mut self, pat: &'ast syn::PatIdent) {
+        self.locals.insert(pat.ident.to_string());
+        syn::visit::visit_pat_ident(self, pat);
+    }
+
+    fn visit_expr_path(&mut self, expr: &'ast syn::ExprPath) {
+        let segments: Vec<String> = expr
+            .path
+            .segments
+            .iter()
+            .map(|s| parse::ident_name(&s.ident))
+            .collect();
+        // `Self::new()` and `<T as Trait>::f()` refer to associated functions
+        let associated = expr.qself.is_some() || segments.first().is_some_and(|s| s == "Self");
+        let shadowed = segments.len() == 1 && self.locals.contains(&segments[0]);
+        if !associated && !shadowed {
+            self.paths.insert(segments);
+        }
+        syn::visit::visit_expr_path(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_call_graph() {
+        let dir = tempfile::tempdir().unwrap();
+        let lib = "pub fn run() {\n    let helper = |x: u32| x;\n    helper(1);\n    eval::evaluate();\n    setup();\n}\n\nfn setup() {}\n";
+        let eval = "pub fn evaluate() {\n    crate::setup();\n    Self::ignored();\n}\n";
+        std::fs::write(dir.path().join("lib.rs"), lib).unwrap();
+        std::fs::write(dir.path().join("eval.rs"), eval).unwrap();
+
+        // Items from include!("gen.rs") are read from the included file
+        let generated = "pub fn generated() {\n    setup();\n}\n";
+        std::fs::write(dir.path().join("gen.rs"), generated).unwrap();
+
+        let mut root = Module::for_test("crate", "lib.rs").with_source(lib);
+        root.items.extend(
+            crate::parse::parse_file(&PathBuf::from("gen.rs"), generated).unwrap(),
+        );
+        root.submodules.push(Module::for_test("crate::eval", "eval.rs").with_source(eval));
+        let crates = vec![CrateInfo::for_test("test", root)];
+
+        let graph = CallGraph::collect(&crates, dir.path());
+        assert_eq!(
+            graph.calls["crate::run"],
+            BTreeSet::from([
+                "crate::eval::evaluate".to_string(),
+                "crate::setup".to_string()
+            ])
+        );
+        assert_eq!(
+            graph.calls["crate::generated"],
+            BTreeSet::from(["crate::setup".to_string()])
+        );
+        assert_eq!(
+            graph.callers["crate::setup"],
+            BTreeSet::from([
+                "crate::eval::evaluate".to_string(),
+                "crate::generated".to_string(),
+                "crate::run".to_string()
+            ])
+        );
+
+        let md = generate_call_graph(&crates, dir.path());
+        assert!(md.contains("## Called By"));
+        assert!(md.contains("<- crate::eval::evaluate, crate::generated, crate::run\n"));
+    }
+}
@@ -0,0 +1,242 @@
+//! Generate multi-layered, LLM-friendly index files for Rust codebases.
+//!
+//! The `rsmap` binary is a thin CLI over this library. Use [`generate_index`]
+//! to build every layer in memory, or the individual modules for finer control
+//! (e.g. incremental parsing via [`parse_crates`] with a cache). To answer
+//! many queries about the parsed crates, wrap them in an [`index::Index`].
+
+pub mod annotations;
+pub mod cache;
+pub mod config;
+pub mod coverage;
+pub mod diff;
+pub mod error;
+pub mod html;
+pub mod index;
+pub mod layer0;
+pub mod layer1;
+pub mod layer2;
+pub mod layer3;
+pub mod layer4;
+pub mod metadata;
+pub mod model;
+pub mod output;
+pub mod parse;
+pub mod resolve;
+pub mod search;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod todos;
+
+use std::path::Path;
+
+use crate::annotations::{AnnotationStore, DescriptionOverrides};
+use crate::cache::{Cache, ModuleCache};
+use crate::error::{Result, RsmapError};
+use crate::metadata::CrateMetadata;
+use crate::model::{CrateInfo, CrateKind};
+use crate::resolve::{
+    DiskSource, FileFilter, ResolveOptions, ResolveStats, SourceProvider, Verbosity,
+};
+
+/// Options for [`generate_index`]
+#[derive(Debug, Clone)]
+pub struct GenerateOptions {
+    /// Target kinds to index (lib and bin by default)
+    pub targets: Vec<CrateKind>,
+    /// Also index `#[cfg(test)]` modules
+    pub include_tests: bool,
+    /// Item ordering in api-surface.md
+    pub layout: layer1::Layout,
+    /// Which items api-surface.md lists, by visibility; `Pub` also drops private
+    /// modules from the overview
+    pub visibility: layer1::VisibilityFilter,
+    /// Wrap fn signatures in api-surface.md longer than this, one parameter per line (0: never)
+    pub max_signature_width: usize,
+    /// Show impl blocks in api-surface.md as their header and method names only
+    pub compact_impls: bool,
+    /// Append Mermaid diagrams of module dependencies and trait impls to the relationships
+    pub mermaid: bool,
+    /// List types under Key Types once this many modules reference them
+    pub hotspot_threshold: usize,
+    /// List at most this many Key Types
+    pub hotspot_top: Option<usize>,
+    /// Leave dev- and build-dependencies out of the overview
+    pub no_dev_deps: bool,
+    /// Collapse the overview's module tree below this depth
+    pub overview_max_depth: Option<usize>,
+    /// Record absolute file paths instead of workspace-relative ones
+    pub absolute_paths: bool,
+    /// How much parsing progress to print on stderr
+    pub verbosity: Verbosity,
+    /// Globs (relative to the project path) of module files to skip
+    pub exclude: Vec<String>,
+    /// Skip module files ignored by .gitignore
+    pub respect_gitignore: bool,
+    /// Fail on the first file that doesn't parse instead of indexing it empty
+    pub strict: bool,
+    /// Give each index.json entry the first line of its doc comment
+    pub index_docs: bool,
+    /// Globs of item paths whose full source api-surface.md shows, e.g. `crate::init`
+    pub include_body: Vec<String>,
+    /// Annotations to inline into the overview and API surface
+    pub annotations: AnnotationStore,
+    /// Module descriptions (as from descriptions.toml) that outrank annotations
+    pub descriptions: DescriptionOverrides,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        GenerateOptions {
+            targets: CrateKind::DEFAULT_TARGETS.to_vec(),
+            include_tests: false,
+            layout: layer1::Layout::default(),
+            visibility: layer1::VisibilityFilter::default(),
+            max_signature_width: layer1::DEFAULT_SIGNATURE_WIDTH,
+            compact_impls: false,
+            mermaid: false,
+            hotspot_threshold: layer2::DEFAULT_HOTSPOT_THRESHOLD,
+            hotspot_top: None,
+            no_dev_deps: false,
+            overview_max_depth: None,
+            absolute_paths: false,
+            verbosity: Verbosity::default(),
+            exclude: Vec::new(),
+            respect_gitignore: false,
+            strict: false,
+            index_docs: false,
+            include_body: Vec::new(),
+            annotations: AnnotationStore::default(),
+            descriptions: DescriptionOverrides::default(),
+        }
+    }
+}
+
+/// The generated layers, held in memory
+#[derive(Debug, Clone)]
+pub struct GeneratedOutput {
+    /// The parsed crates the layers were generated from
+    pub crates: Vec<CrateInfo>,
+    /// Layer 0 (overview.md)
+    pub overview: String,
+    /// Layer 1 (api-surface.md)
+    pub api_surface: String,
+    /// Layer 2 (relationships.md)
+    pub relationships: String,
+    /// Layer 3 (index.json)
+    pub index: String,
+}
+
+/// Parse a Cargo project and generate all four layers without writing any files
+pub fn generate_index(project_path: &Path, options: &GenerateOptions) -> Result<GeneratedOutput> {
+    let project_path = std::fs::canonicalize(project_path)
+        .map_err(|e| RsmapError::io(project_path, e))?;
+
+    let mut crate_infos = metadata::resolve_crates(&project_path, &options.targets)?;
+    if options.no_dev_deps {
+        metadata::strip_dev_deps(&mut crate_infos);
+    }
+
+    let resolve_options = ResolveOptions {
+        include_tests: options.include_tests,
+        filter: FileFilter::new(&project_path, &options.exclude, options.respect_gitignore)?,
+        strict: options.strict,
+        absolute_paths: options.absolute_paths,
+        verbosity: options.verbosity,
+    };
+    let workspace_root = metadata::workspace_root(&crate_infos, &project_path);
+    let bodies = layer1::BodyFilter::new(&workspace_root, &options.include_body)?;
+    let mut stats = ResolveStats::default();
+    let crates = parse_crates(
+        &DiskSource,
+        &workspace_root,
+        &crate_infos,
+        None,
+        None,
+        &resolve_options,
+        &mut stats,
+    )?;
+
+    Ok(GeneratedOutput {
+        overview: layer0::generate_overview(
+            &crates,
+            &options.annotations,
+            &options.descriptions,
+            options.overview_max_depth,
+            options.visibility,
+        ),
+        api_surface: layer1::generate_api_surface(
+            &crates,
+            &options.annotations,
+            options.layout,
+            options.visibility,
+            &bodies,
+            options.max_signature_width,
+            options.compact_impls,
+        ),
+        relationships: {
+            let relationships = layer2::generate_relationships(
+                &crates,
+                options.hotspot_threshold,
+                options.hotspot_top,
+            );
+            if options.mermaid {
+                relationships + &layer2::generate_mermaid(&crates)
+            } else {
+                relationships
+            }
+        },
+        index: layer3::generate_index(&crates, options.index_docs),
+        crates,
+    })
+}
+
+/// Parse and resolve the module tree of every crate, reading source files
+/// from `source` (e.g. [`resolve::DiskSource`]).
+///
+/// With a `cache` and `module_cache`, files unchanged since the cached run are
+/// not re-parsed; `stats` records how many files were parsed versus reused.
+pub fn parse_crates(
+    source: &dyn SourceProvider,
+    project_root: &Path,
+    crate_infos: &[CrateMetadata],
+    cache: Option<&Cache>,
+    module_cache: Option<&ModuleCache>,
+    options: &ResolveOptions,
+    stats: &mut ResolveStats,
+) -> Result<Vec<CrateInfo>> {
+    let mut crates = Vec::new();
+    for crate_info in crate_infos {
+        if options.verbosity != Verbosity::Quiet {
+            eprintln!("Parsing crate: {} ({})...", crate_info.name, crate_info.kind);
+        }
+        let root_module = resolve::resolve_module_tree_from(
+            source,
+            crate_info,
+            project_root,
+            cache,
+            module_cache,
+            options,
+            stats,
+        )?;
+
+        // Cached root modules skip parsing, so read the attributes separately
+        let crate_attributes = source
+            .read(&crate_info.root_file)
+            .map(|source| parse::parse_crate_attributes(&source))
+            .unwrap_or_default();
+
+        crates.push(CrateInfo {
+            name: crate_info.name.clone(),
+            kind: crate_info.kind,
+            edition: crate_info.edition.clone(),
+            version: crate_info.version.clone(),
+            external_deps: crate_info.external_deps.clone(),
+            dev_deps: crate_info.dev_deps.clone(),
+            build_deps: crate_info.build_deps.clone(),
+            crate_attributes,
+            root_module,
+        });
+    }
+    Ok(crates)
+}
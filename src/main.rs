@@ -1,29 +1,15 @@
-#[allow(dead_code)]
-mod annotations;
-#[allow(dead_code)]
-mod cache;
-#[allow(dead_code)]
-mod layer0;
-#[allow(dead_code)]
-mod layer1;
-#[allow(dead_code)]
-mod layer2;
-#[allow(dead_code)]
-mod layer3;
-#[allow(dead_code)]
-mod metadata;
-#[allow(dead_code)]
-mod model;
-#[allow(dead_code)]
-mod output;
-#[allow(dead_code)]
-mod parse;
-#[allow(dead_code)]
-mod resolve;
-
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+
+use rsmap::config::{Config, OutputFormat};
+use rsmap::model::{CrateInfo, CrateKind};
+use rsmap::{
+    annotations, cache, coverage, diff, html, layer0, layer1, layer2, layer3, layer4, metadata,
+    output, parse, resolve, search, todos,
+};
 
 #[derive(Parser)]
 #[command(name = "rsmap")]
@@ -36,27 +22,311 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Generate index files (full or incremental)
-    Generate {
-        /// Path to the Rust project (default: current directory)
-        #[arg(long, default_value = ".")]
-        path: PathBuf,
+    Generate(GenerateArgs),
+
+    /// Manage annotations for LLM consumption
+    Annotate {
+        #[command(subcommand)]
+        action: AnnotateAction,
+    },
+
+    /// Parse a single .rs file (no Cargo project needed) and print its items as JSON
+    Parse {
+        /// Source file to parse
+        #[arg(required_unless_present = "stdin")]
+        file: Option<PathBuf>,
+
+        /// Read the source from stdin instead of a file
+        #[arg(long, conflicts_with = "file")]
+        stdin: bool,
+    },
+
+    /// Find items in an existing index.json by path
+    Search {
+        /// Case-insensitive substring of the fully-qualified path
+        query: String,
 
-        /// Output directory (default: .codebase-index/)
+        /// Index directory
         #[arg(long, default_value = ".codebase-index")]
         output: PathBuf,
 
-        /// Force full rebuild, ignoring cache
+        /// Treat the query as a regular expression
+        #[arg(long)]
+        regex: bool,
+
+        /// Only show items of this kind (function, struct, enum, trait, impl, field, ...)
+        #[arg(long)]
+        kind: Option<String>,
+
+        /// Only show items with this visibility (pub, pub(crate), pub(super), private)
         #[arg(long)]
-        no_cache: bool,
+        visibility: Option<String>,
     },
 
-    /// Manage annotations for LLM consumption
-    Annotate {
-        #[command(subcommand)]
-        action: AnnotateAction,
+    /// Compare two index.json snapshots (files or index directories)
+    Diff {
+        /// The older index.json
+        old: PathBuf,
+
+        /// The newer index.json
+        new: PathBuf,
+
+        /// Print the diff as JSON instead of a report
+        #[arg(long)]
+        json: bool,
+
+        /// Exit with an error if any breaking change is found
+        #[arg(long)]
+        fail_on_breaking: bool,
     },
+
+    /// Report how many public items have doc comments, per crate and module
+    Coverage {
+        /// Path to the Rust project (default: current directory)
+        #[arg(long, default_value = ".")]
+        path: PathBuf,
+
+        /// Print the report as JSON instead of a summary
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Args)]
+struct GenerateArgs {
+    /// Path to the Rust project (default: current directory)
+    #[arg(long, default_value = ".")]
+    path: PathBuf,
+
+    /// Read settings from this file instead of <path>/rsmap.toml (command-line flags still win)
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Output directory (default: .codebase-index/)
+    #[arg(long, default_value = ".codebase-index")]
+    output: PathBuf,
+
+    /// Force full rebuild, ignoring cache
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Target kinds to index, comma-separated (lib also covers proc-macro)
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = CrateKind::DEFAULT_TARGETS)]
+    targets: Vec<CrateKind>,
+
+    /// Also index #[cfg(test)] modules
+    #[arg(long)]
+    include_tests: bool,
+
+    /// Which relationships files (and whether index.yaml or index.ndjson) to write
+    #[arg(long, value_enum, default_value_t = OutputFormat::Md)]
+    format: OutputFormat,
+
+    /// Item ordering in api-surface.md
+    #[arg(long, value_enum, default_value_t = layer1::Layout::Grouped)]
+    layout: layer1::Layout,
+
+    /// Which items api-surface.md lists, by visibility (pub also drops private modules)
+    #[arg(long, value_enum, default_value_t = layer1::VisibilityFilter::All)]
+    visibility: layer1::VisibilityFilter,
+
+    /// Give fn signatures longer than this one parameter per line in api-surface.md (0: never)
+    #[arg(long, value_name = "N", default_value_t = layer1::DEFAULT_SIGNATURE_WIDTH)]
+    max_signature_width: usize,
+
+    /// Show each impl in api-surface.md as its header and method names, without signatures
+    #[arg(long)]
+    compact_impls: bool,
+
+    /// Append Mermaid diagrams of the module dependencies and trait impls to relationships.md
+    #[arg(long)]
+    mermaid: bool,
+
+    /// List a type under relationships.md's Key Types once this many modules reference it
+    #[arg(long, value_name = "N", default_value_t = layer2::DEFAULT_HOTSPOT_THRESHOLD)]
+    hotspot_threshold: usize,
+
+    /// List at most this many Key Types in relationships.md, the most used first
+    #[arg(long, value_name = "K")]
+    hotspot_top: Option<usize>,
+
+    /// Also write todos.md: TODO, FIXME, HACK and XXX comments, grouped by module
+    #[arg(long)]
+    scan_todos: bool,
+
+    /// Also write call-graph.md and call-graph.json (re-reads every function body)
+    #[arg(long)]
+    call_graph: bool,
+
+    /// Leave dev- and build-dependencies out of overview.md
+    #[arg(long)]
+    no_dev_deps: bool,
+
+    /// Collapse overview.md's module tree below this depth (the crate root is depth 0)
+    #[arg(long, value_name = "N")]
+    overview_max_depth: Option<usize>,
+
+    /// Record absolute file paths instead of paths relative to the workspace root
+    #[arg(long)]
+    absolute_paths: bool,
+
+    /// Skip module files matching this glob (relative to the project path; repeatable)
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Only write layers for modules matching this glob, e.g. `engine::*` (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    filter_module: Vec<String>,
+
+    /// Show the full source of items whose path matches this glob, e.g. `crate::init` (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    include_body: Vec<String>,
+
+    /// Skip module files ignored by .gitignore
+    #[arg(long)]
+    respect_gitignore: bool,
+
+    /// Fail on the first file that doesn't parse (default: warn and index it as empty)
+    #[arg(long)]
+    strict: bool,
+
+    /// Also write index.html, a self-contained browsable page of the module tree and API
+    #[arg(long)]
+    html: bool,
+
+    /// Write each crate's layers to <output>/<crate>/, with a top-level overview.md linking them
+    #[arg(long)]
+    split_by_crate: bool,
+
+    /// Write the markdown layers and index.json into a single CODEBASE.md with a table of contents
+    #[arg(long)]
+    combined: bool,
+
+    /// Include the first line of each item's doc comment in index.json as `doc_summary`
+    #[arg(long)]
+    index_docs: bool,
+
+    /// Write index.json as the bare map of entries, without the versioned envelope
+    #[arg(long)]
+    legacy_index: bool,
+
+    /// Prefix for every output file name, e.g. `rsmap-` for rsmap-overview.md
+    #[arg(long, default_value = "")]
+    prefix: String,
+
+    /// After generating, keep running and regenerate when .rs files change
+    #[arg(long)]
+    watch: bool,
+
+    /// Generate in memory and report which output files would change, writing nothing
+    #[arg(long, conflicts_with = "watch")]
+    dry_run: bool,
+
+    /// With --dry-run, fail if any output file would change
+    #[arg(long, requires = "dry_run")]
+    check: bool,
+
+    /// List .rs files in the crates' source directories that no `mod` declaration reaches
+    #[arg(long)]
+    report_orphans: bool,
+
+    /// Only print warnings and errors
+    #[arg(long, short, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// List every file as it is parsed
+    #[arg(long, short)]
+    verbose: bool,
+
+    /// Also write the index to a SQLite database at this path (relative to the project path)
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "PATH")]
+    sqlite: Option<PathBuf>,
 }
 
+impl GenerateArgs {
+    /// Take every setting not given on the command line from `config`
+    fn apply_config(&mut self, config: Config, matches: &ArgMatches) {
+        let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+
+        if let Some(output) = config.output.filter(|_| unset("output")) {
+            self.output = output;
+        }
+        if let Some(exclude) = config.exclude.filter(|_| unset("exclude")) {
+            self.exclude = exclude;
+        }
+        if let Some(format) = config.format.filter(|_| unset("format")) {
+            self.format = format;
+        }
+        if let Some(layout) = config.layout.filter(|_| unset("layout")) {
+            self.layout = layout;
+        }
+        if let Some(visibility) = config.visibility.filter(|_| unset("visibility")) {
+            self.visibility = visibility;
+        }
+        if let Some(width) = config.max_signature_width.filter(|_| unset("max_signature_width")) {
+            self.max_signature_width = width;
+        }
+        if let Some(n) = config.hotspot_threshold.filter(|_| unset("hotspot_threshold")) {
+            self.hotspot_threshold = n;
+        }
+        if let Some(top) = config.hotspot_top.filter(|_| unset("hotspot_top")) {
+            self.hotspot_top = Some(top);
+        }
+        if let Some(depth) = config.overview_max_depth.filter(|_| unset("overview_max_depth")) {
+            self.overview_max_depth = Some(depth);
+        }
+        if let Some(prefix) = config.prefix.filter(|_| unset("prefix")) {
+            self.prefix = prefix;
+        }
+
+        let flags = [
+            (&mut self.include_tests, config.include_tests, "include_tests"),
+            (&mut self.call_graph, config.call_graph, "call_graph"),
+            (&mut self.compact_impls, config.compact_impls, "compact_impls"),
+            (&mut self.mermaid, config.mermaid, "mermaid"),
+            (&mut self.scan_todos, config.scan_todos, "scan_todos"),
+            (&mut self.no_dev_deps, config.no_dev_deps, "no_dev_deps"),
+            (&mut self.absolute_paths, config.absolute_paths, "absolute_paths"),
+            (&mut self.respect_gitignore, config.respect_gitignore, "respect_gitignore"),
+            (&mut self.strict, config.strict, "strict"),
+            (&mut self.html, config.html, "html"),
+            (&mut self.split_by_crate, config.split_by_crate, "split_by_crate"),
+            (&mut self.combined, config.combined, "combined"),
+            (&mut self.legacy_index, config.legacy_index, "legacy_index"),
+            (&mut self.index_docs, config.index_docs, "index_docs"),
+        ];
+        for (flag, value, id) in flags {
+            if let Some(value) = value.filter(|_| unset(id)) {
+                *flag = value;
+            }
+        }
+    }
+
+    fn verbosity(&self) -> resolve::Verbosity {
+        if self.quiet {
+            resolve::Verbosity::Quiet
+        } else if self.verbose {
+            resolve::Verbosity::Verbose
+        } else {
+            resolve::Verbosity::Normal
+        }
+    }
+
+    /// Output file name with `--prefix` applied
+    fn file_name(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name)
+    }
+
+    /// The file overview.md's content goes to: overview.md, or CODEBASE.md with `--combined`
+    fn overview_file(&self) -> String {
+        self.file_name(if self.combined { COMBINED_FILE } else { "overview.md" })
+    }
+}
+
+/// File name (before `--prefix`) written by `--combined`
+const COMBINED_FILE: &str = "CODEBASE.md";
+
 #[derive(Subcommand)]
 enum AnnotateAction {
     /// Export unannotated/stale items for LLM annotation
@@ -68,11 +338,15 @@ enum AnnotateAction {
         /// Index directory
         #[arg(long, default_value = ".codebase-index")]
         output: PathBuf,
+
+        /// Print a JSON array of {path, kind, signature, existing_doc, reason}
+        #[arg(long)]
+        json: bool,
     },
 
     /// Import LLM-generated annotations
     Import {
-        /// Path to the TOML file with annotations
+        /// Path to the TOML or JSON file with annotations (by extension, else content)
         file: PathBuf,
 
         /// Index directory
@@ -82,134 +356,673 @@ enum AnnotateAction {
 }
 
 fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
     match cli.command {
-        Commands::Generate {
-            path,
-            output,
-            no_cache,
-        } => run_generate(&path, &output, no_cache),
+        Commands::Generate(mut args) => {
+            let project_path = std::fs::canonicalize(&args.path).with_context(|| {
+                format!("Cannot resolve project path: {}", args.path.display())
+            })?;
+            let config = Config::find(&project_path, args.config.as_deref())?;
+            if let Some((_, generate_matches)) = matches.subcommand() {
+                args.apply_config(config, generate_matches);
+            }
+            run_generate(&args)
+        }
         Commands::Annotate { action } => match action {
-            AnnotateAction::Export { path, output } => run_annotate_export(&path, &output),
+            AnnotateAction::Export { path, output, json } => {
+                run_annotate_export(&path, &output, json)
+            }
             AnnotateAction::Import { file, output } => run_annotate_import(&file, &output),
         },
+        Commands::Parse { file, .. } => run_parse(file.as_deref()),
+        Commands::Search {
+            query,
+            output,
+            regex,
+            kind,
+            visibility,
+        } => run_search(
+            &output,
+            &search::SearchQuery {
+                pattern: query,
+                regex,
+                kind,
+                visibility,
+            },
+        ),
+        Commands::Diff {
+            old,
+            new,
+            json,
+            fail_on_breaking,
+        } => run_diff(&old, &new, json, fail_on_breaking),
+        Commands::Coverage { path, json } => run_coverage(&path, json),
     }
 }
 
-fn run_generate(project_path: &PathBuf, output_dir: &PathBuf, no_cache: bool) -> Result<()> {
-    let project_path = std::fs::canonicalize(project_path)
-        .with_context(|| format!("Cannot resolve project path: {}", project_path.display()))?;
+fn run_generate(args: &GenerateArgs) -> Result<()> {
+    let project_path = std::fs::canonicalize(&args.path)
+        .with_context(|| format!("Cannot resolve project path: {}", args.path.display()))?;
 
-    let output_dir = if output_dir.is_relative() {
-        project_path.join(output_dir)
+    let output_dir = if args.output.is_relative() {
+        project_path.join(&args.output)
     } else {
-        output_dir.clone()
+        args.output.clone()
     };
 
-    std::fs::create_dir_all(&output_dir)
-        .with_context(|| format!("Cannot create output directory: {}", output_dir.display()))?;
+    if !args.dry_run {
+        std::fs::create_dir_all(&output_dir).with_context(|| {
+            format!("Cannot create output directory: {}", output_dir.display())
+        })?;
+    }
 
     // Load existing cache (if any)
-    let existing_cache = if no_cache {
+    let existing_cache = if args.no_cache {
         None
     } else {
-        cache::Cache::load(&output_dir).ok()
+        match cache::Cache::load(&output_dir) {
+            Ok(cache) => Some(cache),
+            Err(e) => {
+                if output_dir.join("cache.json").exists() {
+                    eprintln!("Ignoring existing cache ({:#}), doing a full rebuild", e);
+                }
+                None
+            }
+        }
     };
 
-    eprintln!("Resolving cargo metadata...");
-    let crate_infos =
-        metadata::resolve_crates(&project_path).context("Failed to resolve cargo metadata")?;
-
-    eprintln!(
-        "Found {} crate(s): {}",
-        crate_infos.len(),
-        crate_infos
-            .iter()
-            .map(|c| c.name.as_str())
-            .collect::<Vec<_>>()
-            .join(", ")
+    if !args.quiet {
+        eprintln!("Resolving cargo metadata...");
+    }
+    let mut crate_infos = metadata::resolve_crates(&project_path, &args.targets)
+        .context("Failed to resolve cargo metadata")?;
+    if args.no_dev_deps {
+        metadata::strip_dev_deps(&mut crate_infos);
+    }
+
+    if !args.quiet {
+        eprintln!(
+            "Found {} crate(s): {}",
+            crate_infos.len(),
+            crate_infos
+                .iter()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    let resolve_options = resolve::ResolveOptions {
+        include_tests: args.include_tests,
+        filter: resolve::FileFilter::new(&project_path, &args.exclude, args.respect_gitignore)?,
+        strict: args.strict,
+        absolute_paths: args.absolute_paths,
+        verbosity: args.verbosity(),
+    };
+    let workspace_root = metadata::workspace_root(&crate_infos, &project_path);
+    let module_filter = if args.filter_module.is_empty() {
+        None
+    } else {
+        Some(resolve::ModuleFilter::new(&args.filter_module)?)
+    };
+    let dry_run = RefCell::new(Vec::new());
+    let build = Build {
+        args,
+        dry_run: args.dry_run.then_some(&dry_run),
+        project_path: &project_path,
+        workspace_root: &workspace_root,
+        output_dir: &output_dir,
+        crate_infos: &crate_infos,
+        resolve_options: &resolve_options,
+        module_filter: module_filter.as_ref(),
+    };
+    let cache = build.run(existing_cache.as_ref(), args.quiet)?;
+
+    if args.dry_run {
+        let report = dry_run.borrow();
+        let changed = report.iter().filter(|(_, status)| *status != FileStatus::Unchanged).count();
+        println!("Dry run: {} of {} file(s) would change", changed, report.len());
+        for (path, status) in report.iter() {
+            let path = path.strip_prefix(&output_dir).unwrap_or(path);
+            println!("  {:<9} {}", status.label(), path.display());
+        }
+        if args.check && changed > 0 {
+            anyhow::bail!("{} output file(s) out of date", changed);
+        }
+    } else if !args.quiet {
+        eprintln!("Done! Output written to {}", output_dir.display());
+        if args.split_by_crate {
+            eprintln!("  - {}", args.file_name("overview.md"));
+            eprintln!("  - <crate>/ (per-crate layers)");
+        } else {
+            print_layer_files(args);
+        }
+        eprintln!("  - annotations.toml");
+        eprintln!("  - cache.json");
+        eprintln!("  - modules/");
+        #[cfg(feature = "sqlite")]
+        if let Some(ref db) = args.sqlite {
+            eprintln!("SQLite database written to {}", project_path.join(db).display());
+        }
+    }
+
+    if args.report_orphans {
+        let orphans = resolve::find_orphan_files(&crate_infos, &workspace_root)?;
+        if orphans.is_empty() {
+            eprintln!("No orphaned module files found.");
+        } else {
+            eprintln!("Orphaned module files (not reached by any `mod` declaration):");
+            for file in &orphans {
+                eprintln!("  - {}", file.display());
+            }
+        }
+    }
+
+    if args.watch {
+        watch(&build, cache)?;
+    }
+
+    Ok(())
+}
+
+/// Everything a (re)build needs that stays fixed across watch-mode cycles
+struct Build<'a> {
+    args: &'a GenerateArgs,
+    /// Set under `--dry-run`, collecting each output file's status; nothing is written
+    dry_run: Option<&'a RefCell<Vec<(PathBuf, FileStatus)>>>,
+    /// Holds descriptions.toml, and is the base for a relative `--sqlite` path
+    project_path: &'a Path,
+    /// Root that indexed file paths are relative to
+    workspace_root: &'a Path,
+    output_dir: &'a Path,
+    crate_infos: &'a [metadata::CrateMetadata],
+    resolve_options: &'a resolve::ResolveOptions,
+    /// Narrows the written layers (not the cache) to some modules
+    module_filter: Option<&'a resolve::ModuleFilter>,
+}
+
+impl Build<'_> {
+    /// Parse (reusing `previous_cache` for unchanged files), write every layer
+    /// and save the cache and annotations. Returns the new cache. A dry run
+    /// only compares the layers with the files on disk, and saves nothing.
+    fn run(&self, previous_cache: Option<&cache::Cache>, quiet: bool) -> Result<cache::Cache> {
+        let (args, output_dir) = (self.args, self.output_dir);
+        let out = Writer {
+            dir: output_dir.to_path_buf(),
+            dry_run: self.dry_run,
+        };
+
+        // Parse and resolve module trees
+        let module_cache = cache::ModuleCache::new(output_dir);
+        let mut stats = resolve::ResolveStats::default();
+        let crates = rsmap::parse_crates(
+            &resolve::DiskSource,
+            self.workspace_root,
+            self.crate_infos,
+            previous_cache,
+            self.dry_run.is_none().then_some(&module_cache),
+            self.resolve_options,
+            &mut stats,
+        )?;
+
+        if !quiet {
+            eprintln!(
+                "Parsed {} file(s), reused {} unchanged file(s) from cache",
+                stats.parsed_files, stats.cached_files
+            );
+        }
+
+        // Load existing annotations, and the hand-kept module descriptions
+        let annotations = annotations::AnnotationStore::load(output_dir).unwrap_or_default();
+        let descriptions = annotations::DescriptionOverrides::load(self.project_path)?;
+
+        // The cache and annotations cover every module; the layers only the filtered ones
+        let filtered;
+        let layer_crates = match self.module_filter {
+            Some(filter) => {
+                filtered = filter.apply(&crates);
+                if filtered.is_empty() {
+                    eprintln!("Warning: no module matches --filter-module");
+                }
+                &filtered
+            }
+            None => &crates,
+        };
+
+        // Generate all layers
+        if args.split_by_crate {
+            let dirs = crate_dir_names(layer_crates);
+            for (crate_info, dir) in layer_crates.iter().zip(&dirs) {
+                if !quiet {
+                    eprintln!("Generating layers for crate {}...", crate_info.name);
+                }
+                write_layers(
+                    &out.subdir(dir)?,
+                    std::slice::from_ref(crate_info),
+                    &annotations,
+                    &descriptions,
+                    self.workspace_root,
+                    args,
+                    quiet,
+                )?;
+            }
+
+            let entries: Vec<_> =
+                layer_crates.iter().zip(dirs.iter().map(String::as_str)).collect();
+            let api_surface_file = if args.combined {
+                format!("{}#api-surface", args.overview_file())
+            } else {
+                args.file_name("api-surface.md")
+            };
+            let overview =
+                layer0::generate_crate_index(&entries, &args.overview_file(), &api_surface_file);
+            out.write(&args.file_name("overview.md"), &overview)?;
+        } else {
+            write_layers(
+                &out,
+                layer_crates,
+                &annotations,
+                &descriptions,
+                self.workspace_root,
+                args,
+                quiet,
+            )?;
+        }
+
+        #[cfg(feature = "sqlite")]
+        if let Some(ref db) = args.sqlite.as_ref().filter(|_| self.dry_run.is_none()) {
+            if !quiet {
+                eprintln!("Writing SQLite database...");
+            }
+            rsmap::sqlite::write_sqlite(layer_crates, &self.project_path.join(db))?;
+        }
+
+        // Build new cache (needed for annotation staleness detection)
+        if !quiet {
+            eprintln!("Building cache...");
+        }
+        let new_cache = cache::Cache::from_crates(&crates);
+        if self.dry_run.is_some() {
+            return Ok(new_cache);
+        }
+
+        // Update annotations (mark stale, add new entries)
+        if !quiet {
+            eprintln!("Updating annotations...");
+        }
+        let updated_annotations =
+            annotations::update_annotations(&annotations, &crates, previous_cache, &new_cache);
+        updated_annotations
+            .save(output_dir)
+            .context("Failed to save annotations")?;
+
+        // Save cache
+        if !quiet {
+            eprintln!("Saving cache...");
+        }
+        new_cache.save(output_dir).context("Failed to save cache")?;
+        module_cache
+            .prune()
+            .context("Failed to prune module cache")?;
+
+        Ok(new_cache)
+    }
+}
+
+/// What the watch loop reacts to
+enum WatchEvent {
+    /// Paths of `.rs` files that were created, modified or removed
+    Changed(Vec<PathBuf>),
+    Shutdown,
+}
+
+/// Rebuild whenever a `.rs` file under a crate's source directory changes,
+/// until Ctrl-C. The cache from each build is kept in memory for the next.
+fn watch(build: &Build, mut cache: cache::Cache) -> Result<()> {
+    use notify::Watcher;
+    use std::collections::BTreeSet;
+    use std::sync::mpsc;
+    use std::time::{Duration, Instant};
+
+    const DEBOUNCE: Duration = Duration::from_millis(300);
+
+    let (tx, rx) = mpsc::channel();
+    let shutdown_tx = tx.clone();
+    ctrlc::set_handler(move || {
+        let _ = shutdown_tx.send(WatchEvent::Shutdown);
+    })
+    .context("Failed to install Ctrl-C handler")?;
+
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        let Ok(event) = res else { return };
+        if event.kind.is_access() {
+            return;
+        }
+        let paths: Vec<PathBuf> = event
+            .paths
+            .into_iter()
+            .filter(|p| p.extension().is_some_and(|e| e == "rs"))
+            .collect();
+        if !paths.is_empty() {
+            let _ = tx.send(WatchEvent::Changed(paths));
+        }
+    })
+    .context("Failed to start file watcher")?;
+
+    // Each crate's root file lives in its source directory (src/, examples/, ...)
+    let dirs: BTreeSet<&Path> = build
+        .crate_infos
+        .iter()
+        .filter_map(|c| c.root_file.parent())
+        .collect();
+    for dir in &dirs {
+        watcher
+            .watch(dir, notify::RecursiveMode::Recursive)
+            .with_context(|| format!("Cannot watch {}", dir.display()))?;
+    }
+    let quiet = build.args.quiet;
+    if !quiet {
+        eprintln!(
+            "Watching {} director{} for changes (Ctrl-C to stop)...",
+            dirs.len(),
+            if dirs.len() == 1 { "y" } else { "ies" }
+        );
+    }
+
+    while let Ok(event) = rx.recv() {
+        let WatchEvent::Changed(paths) = event else {
+            break;
+        };
+        let mut changed: BTreeSet<PathBuf> = paths.into_iter().collect();
+
+        // Debounce: keep collecting until the tree has been quiet for a moment
+        let mut shutdown = false;
+        loop {
+            match rx.recv_timeout(DEBOUNCE) {
+                Ok(WatchEvent::Changed(paths)) => changed.extend(paths),
+                Ok(WatchEvent::Shutdown) => {
+                    shutdown = true;
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+        if shutdown {
+            break;
+        }
+
+        let started = Instant::now();
+        match build.run(Some(&cache), true) {
+            Ok(new_cache) => {
+                cache = new_cache;
+                if !quiet {
+                    eprintln!(
+                        "Regenerated in {}ms ({} file(s) changed)",
+                        started.elapsed().as_millis(),
+                        changed.len()
+                    );
+                }
+            }
+            // Keep watching: the next save will likely fix it
+            Err(err) => eprintln!("Regeneration failed: {:#}", err),
+        }
+    }
+
+    if !quiet {
+        eprintln!("Stopped watching.");
+    }
+    Ok(())
+}
+
+/// One output subdirectory per crate; a lib and bin sharing a name get a kind suffix
+fn crate_dir_names(crates: &[CrateInfo]) -> Vec<String> {
+    crates
+        .iter()
+        .map(|c| {
+            if crates.iter().filter(|other| other.name == c.name).count() > 1 {
+                format!("{}-{}", c.name, c.kind)
+            } else {
+                c.name.clone()
+            }
+        })
+        .collect()
+}
+
+/// How a dry run found an output file compared with the one on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileStatus {
+    New,
+    Changed,
+    Unchanged,
+}
+
+impl FileStatus {
+    fn label(self) -> &'static str {
+        match self {
+            FileStatus::New => "new",
+            FileStatus::Changed => "changed",
+            FileStatus::Unchanged => "unchanged",
+        }
+    }
+}
+
+/// Writes generated files into `dir`, or under `--dry-run` compares each with
+/// the file already there and records its status instead
+struct Writer<'a> {
+    dir: PathBuf,
+    dry_run: Option<&'a RefCell<Vec<(PathBuf, FileStatus)>>>,
+}
+
+impl Writer<'_> {
+    /// A writer for a subdirectory, created unless this is a dry run
+    fn subdir(&self, name: &str) -> Result<Self> {
+        let dir = self.dir.join(name);
+        if self.dry_run.is_none() {
+            std::fs::create_dir_all(&dir)
+                .with_context(|| format!("Cannot create output directory: {}", dir.display()))?;
+        }
+        Ok(Writer {
+            dir,
+            dry_run: self.dry_run,
+        })
+    }
+
+    fn write(&self, file: &str, contents: &str) -> Result<()> {
+        let path = self.dir.join(file);
+        let Some(report) = self.dry_run else {
+            return std::fs::write(&path, contents)
+                .with_context(|| format!("Failed to write {}", file));
+        };
+        let status = match std::fs::read_to_string(&path) {
+            Err(_) => FileStatus::New,
+            Ok(old) if without_timestamps(&old) == without_timestamps(contents) => {
+                FileStatus::Unchanged
+            }
+            Ok(_) => FileStatus::Changed,
+        };
+        report.borrow_mut().push((path, status));
+        Ok(())
+    }
+}
+
+/// Generated text minus the index's `generated_at` line, which differs on
+/// every run
+fn without_timestamps(contents: &str) -> String {
+    contents
+        .lines()
+        .filter(|line| {
+            let line = line.trim_start();
+            !line.starts_with("\"generated_at\":") && !line.starts_with("generated_at:")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Write every layer for `crates` through `out`
+fn write_layers(
+    out: &Writer,
+    crates: &[CrateInfo],
+    annotations: &annotations::AnnotationStore,
+    descriptions: &annotations::DescriptionOverrides,
+    workspace_root: &Path,
+    args: &GenerateArgs,
+    quiet: bool,
+) -> Result<()> {
+    let format = args.format;
+    let write = |name: &str, contents: &str| out.write(&args.file_name(name), contents);
+
+    if !quiet {
+        eprintln!("Generating Layer 0 (overview)...");
+    }
+    let overview = layer0::generate_overview(
+        crates,
+        annotations,
+        descriptions,
+        args.overview_max_depth,
+        args.visibility,
     );
 
-    // Parse and resolve module trees
-    let mut crates = Vec::new();
-    for crate_info in &crate_infos {
-        eprintln!("Parsing crate: {} ({})...", crate_info.name, crate_info.kind);
-        let root_module = resolve::resolve_module_tree(
-            crate_info,
-            &project_path,
-            existing_cache.as_ref(),
-        )
-        .with_context(|| format!("Failed to resolve module tree for {}", crate_info.name))?;
-
-        crates.push(model::CrateInfo {
-            name: crate_info.name.clone(),
-            kind: crate_info.kind.clone(),
-            edition: crate_info.edition.clone(),
-            version: crate_info.version.clone(),
-            external_deps: crate_info.external_deps.clone(),
-            root_module,
-        });
-    }
-
-    // Load existing annotations
-    let annotations = annotations::AnnotationStore::load(&output_dir).unwrap_or_default();
-
-    // Generate all layers
-    eprintln!("Generating Layer 0 (overview)...");
-    let overview = layer0::generate_overview(&crates, &annotations);
-    std::fs::write(output_dir.join("overview.md"), &overview)
-        .context("Failed to write overview.md")?;
-
-    eprintln!("Generating Layer 1 (API surface)...");
-    let api_surface = layer1::generate_api_surface(&crates, &annotations);
-    std::fs::write(output_dir.join("api-surface.md"), &api_surface)
-        .context("Failed to write api-surface.md")?;
-
-    eprintln!("Generating Layer 2 (relationships)...");
-    let relationships = layer2::generate_relationships(&crates);
-    std::fs::write(output_dir.join("relationships.md"), &relationships)
-        .context("Failed to write relationships.md")?;
-
-    eprintln!("Generating Layer 3 (JSON index)...");
-    let index = layer3::generate_index(&crates);
-    std::fs::write(output_dir.join("index.json"), &index)
-        .context("Failed to write index.json")?;
-
-    // Build new cache (needed for annotation staleness detection)
-    eprintln!("Building cache...");
-    let new_cache = cache::Cache::from_crates(&crates);
-
-    // Update annotations (mark stale, add new entries)
-    eprintln!("Updating annotations...");
-    let updated_annotations = annotations::update_annotations(
-        &annotations,
-        &crates,
-        existing_cache.as_ref(),
-        &new_cache,
+    if !quiet {
+        eprintln!("Generating Layer 1 (API surface)...");
+    }
+    let bodies = layer1::BodyFilter::new(workspace_root, &args.include_body)?;
+    let api_surface = layer1::generate_api_surface(
+        crates,
+        annotations,
+        args.layout,
+        args.visibility,
+        &bodies,
+        args.max_signature_width,
+        args.compact_impls,
     );
-    updated_annotations
-        .save(&output_dir)
-        .context("Failed to save annotations")?;
 
-    // Save cache
-    eprintln!("Saving cache...");
-    new_cache
-        .save(&output_dir)
-        .context("Failed to save cache")?;
+    if !quiet {
+        eprintln!("Generating Layer 2 (relationships)...");
+    }
+    let relationships = (args.combined || format.relationships_md()).then(|| {
+        let mut relationships =
+            layer2::generate_relationships(crates, args.hotspot_threshold, args.hotspot_top);
+        if args.mermaid {
+            relationships.push_str(&layer2::generate_mermaid(crates));
+        }
+        relationships
+    });
+    if format.relationships_json() {
+        write("relationships.json", &layer2::generate_relationships_json(crates))?;
+    }
+
+    if !quiet {
+        eprintln!("Generating Layer 3 (JSON index)...");
+    }
+    let index = if args.legacy_index {
+        layer3::generate_legacy_index(crates, args.index_docs)
+    } else {
+        layer3::generate_index(crates, args.index_docs)
+    };
+    if format.index_yaml() {
+        write("index.yaml", &layer3::generate_index_yaml(crates, args.index_docs))?;
+    }
+    if format.index_ndjson() {
+        write("index.ndjson", &layer3::generate_index_ndjson(crates, args.index_docs))?;
+    }
+
+    if args.scan_todos {
+        if !quiet {
+            eprintln!("Scanning TODO comments...");
+        }
+        write("todos.md", &todos::generate_todos(crates, workspace_root))?;
+    }
 
-    eprintln!("Done! Output written to {}", output_dir.display());
-    eprintln!("  - overview.md");
-    eprintln!("  - api-surface.md");
-    eprintln!("  - relationships.md");
-    eprintln!("  - index.json");
-    eprintln!("  - annotations.toml");
-    eprintln!("  - cache.json");
+    let mut call_graph = None;
+    if args.call_graph {
+        if !quiet {
+            eprintln!("Generating Layer 4 (call graph)...");
+        }
+        call_graph = Some(layer4::generate_call_graph(crates, workspace_root));
+        write("call-graph.json", &layer4::generate_call_graph_json(crates, workspace_root))?;
+    }
+
+    if args.combined {
+        let mut sections = vec![
+            ("Overview", overview),
+            ("API Surface", api_surface),
+            ("Relationships", relationships.unwrap_or_default()),
+            ("Index", output::code_block(&index, "json")),
+        ];
+        if let Some(call_graph) = call_graph {
+            sections.push(("Call Graph", call_graph));
+        }
+        let title = match crates {
+            [krate] => format!("Codebase: {}", krate.name),
+            _ => "Codebase".to_string(),
+        };
+        write(COMBINED_FILE, &output::combine_sections(&title, &sections))?;
+    } else {
+        write("overview.md", &overview)?;
+        write("api-surface.md", &api_surface)?;
+        if let Some(relationships) = relationships {
+            write("relationships.md", &relationships)?;
+        }
+        write("index.json", &index)?;
+        if let Some(call_graph) = call_graph {
+            write("call-graph.md", &call_graph)?;
+        }
+    }
+
+    if args.html {
+        if !quiet {
+            eprintln!("Generating HTML index...");
+        }
+        write("index.html", &html::generate_html(crates, annotations, descriptions))?;
+    }
 
     Ok(())
 }
 
-fn run_annotate_export(project_path: &PathBuf, output_dir: &PathBuf) -> Result<()> {
+/// List the layer files in the summary
+fn print_layer_files(args: &GenerateArgs) {
+    let format = args.format;
+    let mut files = Vec::new();
+    if args.combined {
+        files.push(COMBINED_FILE);
+    } else {
+        files.extend(["overview.md", "api-surface.md"]);
+        if format.relationships_md() {
+            files.push("relationships.md");
+        }
+    }
+    if format.relationships_json() {
+        files.push("relationships.json");
+    }
+    if !args.combined {
+        files.push("index.json");
+    }
+    if format.index_yaml() {
+        files.push("index.yaml");
+    }
+    if format.index_ndjson() {
+        files.push("index.ndjson");
+    }
+    if args.scan_todos {
+        files.push("todos.md");
+    }
+    if args.call_graph {
+        if !args.combined {
+            files.push("call-graph.md");
+        }
+        files.push("call-graph.json");
+    }
+    if args.html {
+        files.push("index.html");
+    }
+    for file in files {
+        eprintln!("  - {}", args.file_name(file));
+    }
+}
+
+fn run_annotate_export(project_path: &PathBuf, output_dir: &PathBuf, json: bool) -> Result<()> {
     let project_path = std::fs::canonicalize(project_path)
         .with_context(|| format!("Cannot resolve project path: {}", project_path.display()))?;
 
@@ -222,7 +1035,12 @@ fn run_annotate_export(project_path: &PathBuf, output_dir: &PathBuf) -> Result<(
     let annotations = annotations::AnnotationStore::load(&output_dir)
         .context("No annotations.toml found. Run 'generate' first.")?;
 
-    let export = annotations::export_for_annotation(&annotations);
+    let export = if json {
+        let crates = parse_project(&project_path)?;
+        annotations::export_for_annotation_json(&annotations, &crates)
+    } else {
+        annotations::export_for_annotation(&annotations)
+    };
     println!("{}", export);
 
     Ok(())
@@ -241,7 +1059,8 @@ fn run_annotate_import(file: &PathBuf, output_dir: &PathBuf) -> Result<()> {
     let import_content =
         std::fs::read_to_string(file).with_context(|| format!("Cannot read {}", file.display()))?;
 
-    annotations::import_annotations(&mut annotations, &import_content)
+    let format = annotations::ImportFormat::detect(file, &import_content);
+    annotations::import_annotations_as(&mut annotations, &import_content, format)
         .context("Failed to parse import file")?;
 
     annotations
@@ -252,3 +1071,105 @@ fn run_annotate_import(file: &PathBuf, output_dir: &PathBuf) -> Result<()> {
 
     Ok(())
 }
+
+fn run_search(output_dir: &Path, query: &search::SearchQuery) -> Result<()> {
+    let index = search::load_index(output_dir)
+        .context("No index.json found. Run 'generate' first.")?;
+
+    let results = search::search(&index, query)?;
+    if results.is_empty() {
+        eprintln!("No matches for '{}'.", query.pattern);
+        return Ok(());
+    }
+
+    print!("{}", search::format_results(&results));
+
+    Ok(())
+}
+
+fn run_diff(old: &Path, new: &Path, json: bool, fail_on_breaking: bool) -> Result<()> {
+    let old_index = diff::load_index(old)?;
+    let new_index = diff::load_index(new)?;
+
+    let result = diff::diff_indexes(&old_index, &new_index);
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&result).context("Failed to serialize diff")?
+        );
+    } else {
+        print!("{}", diff::format_diff(&result));
+    }
+
+    let breaking = result.breaking_count();
+    if fail_on_breaking && breaking > 0 {
+        anyhow::bail!("{} breaking change(s) found", breaking);
+    }
+
+    Ok(())
+}
+
+fn run_coverage(project_path: &Path, json: bool) -> Result<()> {
+    let project_path = std::fs::canonicalize(project_path)
+        .with_context(|| format!("Cannot resolve project path: {}", project_path.display()))?;
+    let crates = parse_project(&project_path)?;
+
+    let report = coverage::compute_coverage(&crates);
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).context("Failed to serialize coverage")?
+        );
+    } else {
+        print!("{}", coverage::format_coverage(&report));
+    }
+
+    Ok(())
+}
+
+/// Parse the default targets of a (canonicalized) project with default options
+fn parse_project(project_path: &Path) -> Result<Vec<CrateInfo>> {
+    let crate_infos = metadata::resolve_crates(project_path, &CrateKind::DEFAULT_TARGETS)
+        .context("Failed to resolve cargo metadata")?;
+    let options = resolve::ResolveOptions {
+        include_tests: false,
+        filter: resolve::FileFilter::new(project_path, &[], false)?,
+        strict: false,
+        absolute_paths: false,
+        verbosity: resolve::Verbosity::Normal,
+    };
+    let crates = rsmap::parse_crates(
+        &resolve::DiskSource,
+        &metadata::workspace_root(&crate_infos, project_path),
+        &crate_infos,
+        None,
+        None,
+        &options,
+        &mut resolve::ResolveStats::default(),
+    )?;
+    Ok(crates)
+}
+
+fn run_parse(file: Option<&Path>) -> Result<()> {
+    let (file_path, source) = match file {
+        Some(file) => {
+            let source = std::fs::read_to_string(file)
+                .with_context(|| format!("Cannot read {}", file.display()))?;
+            (file.to_path_buf(), source)
+        }
+        None => {
+            let mut source = String::new();
+            std::io::Read::read_to_string(&mut std::io::stdin(), &mut source)
+                .context("Cannot read stdin")?;
+            (PathBuf::from("<stdin>"), source)
+        }
+    };
+
+    let items = parse::parse_file(&file_path, &source)?;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&items).context("Failed to serialize items")?
+    );
+
+    Ok(())
+}
@@ -1,4 +1,5 @@
-use anyhow::{Context, Result};
+use crate::error::Result;
+use std::collections::BTreeSet;
 use std::path::{Path, PathBuf};
 
 use crate::model::{CrateKind, Visibility};
@@ -11,12 +12,38 @@ pub struct CrateMetadata {
     pub edition: String,
     pub version: String,
     pub external_deps: Vec<String>,
+    pub dev_deps: Vec<String>,
+    pub build_deps: Vec<String>,
     pub root_file: PathBuf,
     pub manifest_dir: PathBuf,
+    /// Root of the cargo workspace the crate belongs to
+    pub workspace_root: PathBuf,
+}
+
+/// A target left out because its root source file doesn't exist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedTarget {
+    pub name: String,
+    pub kind: CrateKind,
+    pub root_file: PathBuf,
+}
+
+/// A workspace member left out because its manifest couldn't be loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedMember {
+    pub manifest_path: PathBuf,
+    pub reason: String,
 }
 
 /// Resolve all crates in the workspace using `cargo metadata`
-pub fn resolve_crates(project_path: &Path) -> Result<Vec<CrateMetadata>> {
+///
+/// Only targets whose kind is in `targets` are returned; selecting `Lib` also
+/// selects proc-macro crates. Targets whose root file is missing are skipped
+/// with a warning listing them, so one broken member doesn't stop the rest
+/// from being indexed. If cargo rejects the workspace outright, e.g. because
+/// a member manifest is broken, the members are read from their manifests
+/// instead (see [`crates_from_manifests`]).
+pub fn resolve_crates(project_path: &Path, targets: &[CrateKind]) -> Result<Vec<CrateMetadata>> {
     let manifest = project_path.join("Cargo.toml");
 
     // Try full metadata first; fall back to --no-deps if dependency resolution fails
@@ -30,13 +57,56 @@ pub fn resolve_crates(project_path: &Path) -> Result<Vec<CrateMetadata>> {
                 .features(cargo_metadata::CargoOpt::NoDefaultFeatures)
                 .other_options(vec!["--no-deps".to_string()])
                 .exec()
-        })
-        .context("Failed to run cargo metadata. Is this a valid Cargo project?")?;
+        });
 
+    let (crates, skipped, skipped_members) = match metadata {
+        Ok(metadata) => {
+            let (crates, skipped) = crates_from_metadata(&metadata, project_path, targets);
+            (crates, skipped, Vec::new())
+        }
+        Err(err) => match crates_from_manifests(project_path, targets) {
+            Some(found) => {
+                eprintln!("Warning: {}\nReading the workspace members' manifests instead...", err);
+                found
+            }
+            None => return Err(err.into()),
+        },
+    };
+    if !skipped.is_empty() || !skipped_members.is_empty() {
+        eprintln!(
+            "Warning: skipped {} target(s) whose root file doesn't exist and {} workspace \
+             member(s) that couldn't be loaded:",
+            skipped.len(),
+            skipped_members.len()
+        );
+        for target in &skipped {
+            eprintln!("  {} ({}): {}", target.name, target.kind, target.root_file.display());
+        }
+        for member in &skipped_members {
+            eprintln!("  {}: {}", member.manifest_path.display(), member.reason);
+        }
+    }
+    Ok(crates)
+}
+
+/// Whether a target of `kind` is selected by `targets`
+fn is_selected(kind: CrateKind, targets: &[CrateKind]) -> bool {
+    targets.contains(&kind) || (kind == CrateKind::ProcMacro && targets.contains(&CrateKind::Lib))
+}
+
+/// The selected targets of the workspace members in `metadata`, and the ones
+/// skipped because their root file doesn't exist
+pub fn crates_from_metadata(
+    metadata: &cargo_metadata::Metadata,
+    project_path: &Path,
+    targets: &[CrateKind],
+) -> (Vec<CrateMetadata>, Vec<SkippedTarget>) {
+    let workspace_root = PathBuf::from(metadata.workspace_root.as_std_path());
     let workspace_members: std::collections::HashSet<_> =
         metadata.workspace_members.iter().collect();
 
     let mut crates = Vec::new();
+    let mut skipped = Vec::new();
 
     for package in &metadata.packages {
         if !workspace_members.contains(&package.id) {
@@ -49,13 +119,21 @@ pub fn resolve_crates(project_path: &Path) -> Result<Vec<CrateMetadata>> {
             .map(|p| PathBuf::from(p.as_std_path()))
             .unwrap_or_else(|| project_path.to_path_buf());
 
-        // Collect external dependencies (direct only)
-        let external_deps: Vec<String> = package
-            .dependencies
-            .iter()
-            .filter(|d| d.kind == cargo_metadata::DependencyKind::Normal)
-            .map(|d| d.name.clone())
-            .collect();
+        // Collect external dependencies (direct only), sorted. A dependency
+        // listed under several targets (e.g. per-platform) appears once.
+        let deps_of_kind = |kind: cargo_metadata::DependencyKind| -> Vec<String> {
+            package
+                .dependencies
+                .iter()
+                .filter(|d| d.kind == kind)
+                .map(|d| d.name.clone())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect()
+        };
+        let external_deps = deps_of_kind(cargo_metadata::DependencyKind::Normal);
+        let dev_deps = deps_of_kind(cargo_metadata::DependencyKind::Development);
+        let build_deps = deps_of_kind(cargo_metadata::DependencyKind::Build);
 
         // Process each target in the package
         for target in &package.targets {
@@ -67,11 +145,27 @@ pub fn resolve_crates(project_path: &Path) -> Result<Vec<CrateMetadata>> {
                 CrateKind::Lib
             } else if target.kind.contains(&"bin".to_string()) {
                 CrateKind::Bin
+            } else if target.kind.contains(&"example".to_string()) {
+                CrateKind::Example
+            } else if target.kind.contains(&"bench".to_string()) {
+                CrateKind::Bench
             } else {
-                continue; // skip examples, tests, benches
+                continue; // skip integration tests and build scripts
             };
 
+            if !is_selected(kind, targets) {
+                continue;
+            }
+
             let root_file = PathBuf::from(target.src_path.as_std_path());
+            if !root_file.is_file() {
+                skipped.push(SkippedTarget {
+                    name: target.name.clone(),
+                    kind,
+                    root_file,
+                });
+                continue;
+            }
 
             crates.push(CrateMetadata {
                 name: target.name.clone(),
@@ -79,15 +173,248 @@ pub fn resolve_crates(project_path: &Path) -> Result<Vec<CrateMetadata>> {
                 edition: package.edition.to_string(),
                 version: package.version.to_string(),
                 external_deps: external_deps.clone(),
+                dev_deps: dev_deps.clone(),
+                build_deps: build_deps.clone(),
                 root_file,
                 manifest_dir: manifest_dir.clone(),
+                workspace_root: workspace_root.clone(),
             });
         }
     }
 
+    (crates, skipped)
+}
+
+/// The selected lib and bin targets of the workspace at `workspace_root`,
+/// read straight from the member manifests, for when `cargo metadata` fails
+///
+/// This is a best-effort reading of what cargo would report: members come
+/// from `[workspace] members` (globs expanded, `exclude` honoured) plus the
+/// root package, targets from `[lib]`, `[[bin]]`, `src/lib.rs`, `src/main.rs`
+/// and `src/bin/*.rs`, and examples and benches are left out. A member whose
+/// manifest doesn't parse or has no targets is returned as skipped. Returns
+/// `None` if `workspace_root` has no `[workspace]` manifest.
+pub fn crates_from_manifests(
+    workspace_root: &Path,
+    targets: &[CrateKind],
+) -> Option<(Vec<CrateMetadata>, Vec<SkippedTarget>, Vec<SkippedMember>)> {
+    let root_manifest = read_manifest(&workspace_root.join("Cargo.toml")).ok()?;
+    let workspace = root_manifest.get("workspace")?.as_table()?;
+    let strings = |key: &str| -> Vec<&str> {
+        workspace
+            .get(key)
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_str()).collect())
+            .unwrap_or_default()
+    };
+
+    let mut member_dirs = Vec::new();
+    if root_manifest.contains_key("package") {
+        member_dirs.push(workspace_root.to_path_buf());
+    }
+    for dir in expand_members(workspace_root, &strings("members"), &strings("exclude")) {
+        if !member_dirs.contains(&dir) {
+            member_dirs.push(dir);
+        }
+    }
+
+    let mut crates = Vec::new();
+    let mut skipped = Vec::new();
+    let mut skipped_members = Vec::new();
+    for dir in member_dirs {
+        let manifest_path = dir.join("Cargo.toml");
+        let package_crates = read_manifest(&manifest_path)
+            .and_then(|manifest| manifest_crates(&manifest, &dir, workspace, workspace_root));
+        match package_crates {
+            Ok(package_crates) => {
+                for crate_meta in package_crates {
+                    if !is_selected(crate_meta.kind, targets) {
+                        continue;
+                    }
+                    if crate_meta.root_file.is_file() {
+                        crates.push(crate_meta);
+                    } else {
+                        skipped.push(SkippedTarget {
+                            name: crate_meta.name,
+                            kind: crate_meta.kind,
+                            root_file: crate_meta.root_file,
+                        });
+                    }
+                }
+            }
+            Err(reason) => skipped_members.push(SkippedMember {
+                manifest_path,
+                reason,
+            }),
+        }
+    }
+    Some((crates, skipped, skipped_members))
+}
+
+fn read_manifest(path: &Path) -> std::result::Result<toml::Table, String> {
+    let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    source.parse::<toml::Table>().map_err(|e| e.message().to_string())
+}
+
+/// The directories named by `[workspace] members` that hold a Cargo.toml,
+/// minus those in `exclude`
+fn expand_members(workspace_root: &Path, members: &[&str], exclude: &[&str]) -> Vec<PathBuf> {
+    let excluded: Vec<PathBuf> = exclude.iter().map(|e| workspace_root.join(e)).collect();
+    let mut dirs = Vec::new();
+    for member in members {
+        if !member.contains(['*', '?', '[']) {
+            dirs.push(workspace_root.join(member));
+            continue;
+        }
+        let Ok(glob) = globset::GlobBuilder::new(member).literal_separator(true).build() else {
+            continue;
+        };
+        let matcher = glob.compile_matcher();
+        let depth = Path::new(member).components().count();
+        let mut matches: Vec<PathBuf> = walkdir::WalkDir::new(workspace_root)
+            .min_depth(depth)
+            .max_depth(depth)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_dir() && e.path().join("Cargo.toml").is_file())
+            .filter(|e| {
+                e.path().strip_prefix(workspace_root).is_ok_and(|rel| matcher.is_match(rel))
+            })
+            .map(|e| e.into_path())
+            .collect();
+        matches.sort();
+        dirs.extend(matches);
+    }
+    dirs.retain(|dir| !excluded.contains(dir));
+    dirs
+}
+
+/// Every lib and bin target of the package described by `manifest`
+fn manifest_crates(
+    manifest: &toml::Table,
+    manifest_dir: &Path,
+    workspace: &toml::Table,
+    workspace_root: &Path,
+) -> std::result::Result<Vec<CrateMetadata>, String> {
+    let package = manifest
+        .get("package")
+        .and_then(|p| p.as_table())
+        .ok_or("no [package] section")?;
+    let name = package.get("name").and_then(|n| n.as_str()).ok_or("package has no name")?;
+    // `version.workspace = true` takes the value from `[workspace.package]`
+    let package_field = |key: &str, default: &str| -> String {
+        let value = package.get(key);
+        let value = match value.and_then(|v| v.get("workspace")) {
+            Some(_) => workspace.get("package").and_then(|p| p.get(key)),
+            None => value,
+        };
+        value.and_then(|v| v.as_str()).unwrap_or(default).to_string()
+    };
+
+    // Dependency names, including target-specific ones, sorted and deduplicated
+    let deps_of_kind = |section: &str| -> Vec<String> {
+        let platform_tables = manifest
+            .get("target")
+            .and_then(|t| t.as_table())
+            .into_iter()
+            .flat_map(|t| t.values());
+        std::iter::once(manifest.get(section))
+            .chain(platform_tables.map(|platform| platform.get(section)))
+            .flatten()
+            .filter_map(|deps| deps.as_table())
+            .flat_map(|deps| deps.iter())
+            .map(|(key, dep)| {
+                dep.get("package").and_then(|p| p.as_str()).unwrap_or(key).to_string()
+            })
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    };
+
+    let crate_meta = |name: String, kind: CrateKind, root_file: PathBuf| CrateMetadata {
+        name,
+        kind,
+        edition: package_field("edition", "2015"),
+        version: package_field("version", "0.0.0"),
+        external_deps: deps_of_kind("dependencies"),
+        dev_deps: deps_of_kind("dev-dependencies"),
+        build_deps: deps_of_kind("build-dependencies"),
+        root_file: manifest_dir.join(root_file),
+        manifest_dir: manifest_dir.to_path_buf(),
+        workspace_root: workspace_root.to_path_buf(),
+    };
+
+    let mut crates = Vec::new();
+    let lib = manifest.get("lib").and_then(|l| l.as_table());
+    if lib.is_some() || manifest_dir.join("src/lib.rs").is_file() {
+        let lib_field = |key: &str| lib.and_then(|l| l.get(key));
+        let kind = if lib_field("proc-macro").and_then(|p| p.as_bool()) == Some(true) {
+            CrateKind::ProcMacro
+        } else {
+            CrateKind::Lib
+        };
+        let lib_name = lib_field("name")
+            .and_then(|n| n.as_str())
+            .map_or_else(|| name.replace('-', "_"), str::to_string);
+        let path = lib_field("path").and_then(|p| p.as_str()).unwrap_or("src/lib.rs");
+        crates.push(crate_meta(lib_name, kind, PathBuf::from(path)));
+    }
+
+    let bins = manifest.get("bin").and_then(|b| b.as_array());
+    for bin in bins.into_iter().flatten() {
+        let Some(bin_name) = bin.get("name").and_then(|n| n.as_str()) else {
+            continue;
+        };
+        let path = match bin.get("path").and_then(|p| p.as_str()) {
+            Some(path) => PathBuf::from(path),
+            None if bin_name == name => PathBuf::from("src/main.rs"),
+            None => Path::new("src/bin").join(format!("{}.rs", bin_name)),
+        };
+        crates.push(crate_meta(bin_name.to_string(), CrateKind::Bin, path));
+    }
+    if bins.is_none() {
+        if manifest_dir.join("src/main.rs").is_file() {
+            crates.push(crate_meta(name.to_string(), CrateKind::Bin, "src/main.rs".into()));
+        }
+        let mut bin_files: Vec<PathBuf> = std::fs::read_dir(manifest_dir.join("src/bin"))
+            .into_iter()
+            .flatten()
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "rs"))
+            .collect();
+        bin_files.sort();
+        for path in bin_files {
+            let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            crates.push(crate_meta(stem.to_string(), CrateKind::Bin, path.clone()));
+        }
+    }
+
+    if crates.is_empty() {
+        return Err("no lib or bin target (no src/lib.rs, src/main.rs, [lib] or [[bin]])".into());
+    }
     Ok(crates)
 }
 
+/// Directory that indexed file paths are relative to: the cargo workspace
+/// root, which may lie above `project_path` when it points at a member crate
+pub fn workspace_root(crates: &[CrateMetadata], project_path: &Path) -> PathBuf {
+    crates
+        .first()
+        .map(|c| c.workspace_root.clone())
+        .unwrap_or_else(|| project_path.to_path_buf())
+}
+
+/// Drop dev- and build-dependencies, keeping only normal dependencies
+pub fn strip_dev_deps(crates: &mut [CrateMetadata]) {
+    for crate_meta in crates {
+        crate_meta.dev_deps.clear();
+        crate_meta.build_deps.clear();
+    }
+}
+
 /// Convert syn visibility to our Visibility enum
 pub fn convert_visibility(vis: &syn::Visibility) -> Visibility {
     match vis {
@@ -100,9 +427,117 @@ pub fn convert_visibility(vis: &syn::Visibility) -> Visibility {
             match path_str.as_str() {
                 "crate" => Visibility::PubCrate,
                 "super" => Visibility::PubSuper,
-                _ => Visibility::PubCrate, // pub(in path) treated as pub(crate)
+                _ => Visibility::PubIn(path_str),
             }
         }
         syn::Visibility::Inherited => Visibility::Private,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_missing_root_file() {
+        let root = tempfile::tempdir().unwrap();
+        let root_dir = root.path().display().to_string();
+        std::fs::create_dir_all(root.path().join("good/src")).unwrap();
+        std::fs::write(root.path().join("good/src/lib.rs"), "").unwrap();
+
+        let package = |name: &str| {
+            serde_json::json!({
+                "name": name,
+                "version": "0.1.0",
+                "id": format!("{} 0.1.0 (path+file://{}/{})", name, root_dir, name),
+                "dependencies": [],
+                "features": {},
+                "manifest_path": format!("{}/{}/Cargo.toml", root_dir, name),
+                "edition": "2021",
+                "targets": [{
+                    "name": name,
+                    "kind": ["lib"],
+                    "crate_types": ["lib"],
+                    "src_path": format!("{}/{}/src/lib.rs", root_dir, name),
+                    "edition": "2021",
+                }],
+            })
+        };
+        let metadata: cargo_metadata::Metadata = serde_json::from_value(serde_json::json!({
+            "packages": [package("good"), package("broken")],
+            "workspace_members": [
+                format!("good 0.1.0 (path+file://{}/good)", root_dir),
+                format!("broken 0.1.0 (path+file://{}/broken)", root_dir),
+            ],
+            "target_directory": format!("{}/target", root_dir),
+            "workspace_root": root_dir,
+            "version": 1,
+        }))
+        .unwrap();
+
+        let (crates, skipped) =
+            crates_from_metadata(&metadata, root.path(), &CrateKind::DEFAULT_TARGETS);
+        let names: Vec<_> = crates.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["good"]);
+        assert_eq!(
+            skipped,
+            vec![SkippedTarget {
+                name: "broken".to_string(),
+                kind: CrateKind::Lib,
+                root_file: root.path().join("broken/src/lib.rs"),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_broken_member_falls_back_to_manifests() {
+        let root = tempfile::tempdir().unwrap();
+        let write = |path: &str, contents: &str| {
+            let path = root.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"crates/*\", \"app\"]\nexclude = [\"crates/ignored\"]\n\n\
+             [workspace.package]\nversion = \"1.2.3\"\n",
+        );
+        write(
+            "crates/good/Cargo.toml",
+            "[package]\nname = \"good-lib\"\nversion.workspace = true\nedition = \"2021\"\n\n\
+             [dependencies]\nserde = \"1\"\nre = { package = \"regex\", version = \"1\" }\n",
+        );
+        write("crates/good/src/lib.rs", "");
+        // No src/lib.rs or src/main.rs, so cargo rejects the whole workspace
+        write("crates/broken/Cargo.toml", "[package]\nname = \"broken\"\nversion = \"0.1.0\"\n");
+        write("crates/ignored/Cargo.toml", "[package]\nname = \"ignored\"\n");
+        write(
+            "app/Cargo.toml",
+            "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[lib]\npath = \"src/gone.rs\"\n",
+        );
+        write("app/src/main.rs", "fn main() {}\n");
+
+        let (crates, skipped, skipped_members) =
+            crates_from_manifests(root.path(), &CrateKind::DEFAULT_TARGETS).unwrap();
+        let names: Vec<_> = crates.iter().map(|c| (c.name.as_str(), c.kind)).collect();
+        assert_eq!(names, vec![("good_lib", CrateKind::Lib), ("app", CrateKind::Bin)]);
+        assert_eq!(crates[0].version, "1.2.3");
+        assert_eq!(crates[0].edition, "2021");
+        assert_eq!(crates[0].external_deps, vec!["regex", "serde"]);
+        assert_eq!(crates[1].edition, "2015");
+        assert_eq!(
+            skipped,
+            vec![SkippedTarget {
+                name: "app".to_string(),
+                kind: CrateKind::Lib,
+                root_file: root.path().join("app/src/gone.rs"),
+            }]
+        );
+        assert_eq!(skipped_members.len(), 1);
+        assert_eq!(skipped_members[0].manifest_path, root.path().join("crates/broken/Cargo.toml"));
+
+        let crates = resolve_crates(root.path(), &CrateKind::DEFAULT_TARGETS).unwrap();
+        let names: Vec<_> = crates.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["good_lib", "app"]);
+    }
+}
@@ -8,14 +8,28 @@ pub struct CrateInfo {
     pub edition: String,
     pub version: String,
     pub external_deps: Vec<String>,
+    #[serde(default)]
+    pub dev_deps: Vec<String>,
+    #[serde(default)]
+    pub build_deps: Vec<String>,
+    /// Non-doc inner attributes of the crate root, e.g. `#![no_std]`
+    #[serde(default)]
+    pub crate_attributes: Vec<String>,
     pub root_module: Module,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, clap::ValueEnum)]
 pub enum CrateKind {
     Bin,
     Lib,
     ProcMacro,
+    Example,
+    Bench,
+}
+
+impl CrateKind {
+    /// Target kinds indexed when none are chosen explicitly
+    pub const DEFAULT_TARGETS: [CrateKind; 2] = [CrateKind::Lib, CrateKind::Bin];
 }
 
 impl std::fmt::Display for CrateKind {
@@ -24,6 +38,8 @@ impl std::fmt::Display for CrateKind {
             CrateKind::Bin => write!(f, "bin"),
             CrateKind::Lib => write!(f, "lib"),
             CrateKind::ProcMacro => write!(f, "proc-macro"),
+            CrateKind::Example => write!(f, "example"),
+            CrateKind::Bench => write!(f, "bench"),
         }
     }
 }
@@ -34,7 +50,14 @@ pub struct Module {
     pub path: String,
     pub file_path: PathBuf,
     pub file_hash: String,
+    /// The module's `//!` docs, or failing that the `///` docs on its `mod` declaration
     pub doc_comment: Option<String>,
+    /// The `//!` docs inside the module
+    #[serde(default)]
+    pub inner_doc: Option<String>,
+    /// The `///` docs on the `mod` declaration, which live in the parent's file
+    #[serde(default)]
+    pub outer_doc: Option<String>,
     pub visibility: Visibility,
     pub items: Vec<Item>,
     pub submodules: Vec<Module>,
@@ -42,51 +65,245 @@ pub struct Module {
     pub use_statements: Vec<String>,
     /// Whether this is an inline module (mod foo { ... })
     pub is_inline: bool,
+    /// Whether this is a `#[cfg(test)]` module (only indexed with --include-tests)
+    #[serde(default)]
+    pub is_test: bool,
+    /// Lines of source in the module (the `mod { ... }` block for inline modules)
+    #[serde(default)]
+    pub loc: usize,
+    /// Whether the file was loaded through a `#[path = "..."]` attribute
+    #[serde(default)]
+    pub path_attr: bool,
+    /// Why the file failed to parse; such modules are indexed without items
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Files a `mod foo;` declared here could have been loaded from, none of
+    /// which existed, so a cached copy is re-parsed once one of them appears
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing_mod_files: Vec<PathBuf>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Item {
+    /// Item name, without the `r#` of raw identifiers (the signature keeps it)
     pub name: String,
     pub kind: ItemKind,
     pub visibility: Visibility,
     /// Signature text with body stripped
     pub signature: String,
     pub doc_comment: Option<String>,
+    /// Derive macros applied to the item, in source order (including cfg_attr-gated ones)
+    #[serde(default)]
+    pub derives: Vec<String>,
+    /// Non-doc attributes on the item, in source order
+    #[serde(default)]
+    pub attributes: Vec<String>,
+    /// Set when the item carries `#[deprecated]`
+    #[serde(default)]
+    pub deprecated: Option<DeprecationInfo>,
+    /// Generic parameters with inline bounds, e.g. `["'a", "T: Clone"]`
+    #[serde(default)]
+    pub generics: Vec<String>,
+    /// The item's `where` clause, if any
+    #[serde(default)]
+    pub where_clause: Option<String>,
+    /// Supertraits of a trait, e.g. `["Bar", "Baz"]` for `trait Foo: Bar + Baz`
+    #[serde(default)]
+    pub supertraits: Vec<String>,
+    /// ABI of items declared in an `extern` block, e.g. `"C"`
+    #[serde(default)]
+    pub abi: Option<String>,
+    /// Fields of a struct, variants of an enum, or the items of a trait or impl
+    #[serde(default)]
+    pub members: Vec<Member>,
+    /// Parameters of a function, in order
+    #[serde(default)]
+    pub params: Vec<Param>,
+    /// Return type of a function (`None` for `()` or non-functions)
+    #[serde(default)]
+    pub return_type: Option<String>,
+    /// Set when the return type is, or wraps, an `impl Trait` or `dyn Trait`
+    #[serde(default)]
+    pub return_trait: Option<ReturnTrait>,
+    /// `async fn`
+    #[serde(default)]
+    pub is_async: bool,
+    /// `unsafe fn`, `unsafe trait` or `unsafe impl`
+    #[serde(default)]
+    pub is_unsafe: bool,
+    /// `const fn`
+    #[serde(default)]
+    pub is_const: bool,
+    /// `#[repr(...)]` of a struct or enum, e.g. `u8` or `C`
+    #[serde(default)]
+    pub repr: Option<String>,
+    /// `#[cfg(...)]` predicate the item is gated on, e.g. `unix` or `feature = "serde"`
+    #[serde(default)]
+    pub cfg: Option<String>,
+    /// Marked `#[doc(hidden)]`: public, but not part of the supported API
+    #[serde(default)]
+    pub doc_hidden: bool,
+    /// For a `pub use`, the full path of the item it re-exports, following
+    /// chains of re-exports; `None` if the target is external or unresolved
+    #[serde(default)]
+    pub reexport_of: Option<String>,
     pub file_path: PathBuf,
     pub line_start: usize,
     pub line_end: usize,
-    /// Hash of the item's full source text
+    /// Byte offsets of the item's text in its file, `byte_start..byte_end`,
+    /// from its first attribute or doc comment to its last token
+    #[serde(default)]
+    pub byte_start: usize,
+    #[serde(default)]
+    pub byte_end: usize,
+    /// Hash of the item's tokens, insensitive to formatting
     pub content_hash: String,
 }
 
+/// A function parameter, e.g. `ctx: &mut EvalContext` or `&self`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Param {
+    /// Binding name; `None` for patterns such as `(a, b): (u32, u32)`
+    pub name: Option<String>,
+    pub ty: String,
+    pub is_self: bool,
+}
+
+/// An opaque return type, holding the trait paths of its bounds, e.g.
+/// `Impl(["Iterator"])` for `-> impl Iterator<Item = u8>`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ReturnTrait {
+    Impl(Vec<String>),
+    Dyn(Vec<String>),
+}
+
+/// Details from a `#[deprecated(since = "...", note = "...")]` attribute
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DeprecationInfo {
+    pub since: Option<String>,
+    pub note: Option<String>,
+}
+
+/// A named part of an item, e.g. a struct field, enum variant or trait item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Member {
+    /// Field name (index for tuple fields), variant name or trait item name
+    pub name: String,
+    pub kind: MemberKind,
+    pub visibility: Visibility,
+    /// Declaration text, e.g. `port: u16`, `Io(std::io::Error)` or `type Output: Clone;`
+    pub signature: String,
+    pub doc_comment: Option<String>,
+    /// Trait item with a default (body, type or value), so optional to implement
+    #[serde(default)]
+    pub has_default: bool,
+    /// Function taking `self` in some form; `false` for associated functions like `new`
+    #[serde(default)]
+    pub is_method: bool,
+    /// `async fn` method
+    #[serde(default)]
+    pub is_async: bool,
+    /// `unsafe fn` method
+    #[serde(default)]
+    pub is_unsafe: bool,
+    /// `const fn` method
+    #[serde(default)]
+    pub is_const: bool,
+    /// Explicit enum discriminant, e.g. `1` for `Red = 1`
+    #[serde(default)]
+    pub discriminant: Option<String>,
+    pub line_start: usize,
+    pub line_end: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MemberKind {
+    Field,
+    Variant,
+    Method,
+    AssocType,
+    AssocConst,
+}
+
+impl std::fmt::Display for MemberKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MemberKind::Field => write!(f, "field"),
+            MemberKind::Variant => write!(f, "variant"),
+            MemberKind::Method => write!(f, "method"),
+            MemberKind::AssocType => write!(f, "assoc_type"),
+            MemberKind::AssocConst => write!(f, "assoc_const"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum ItemKind {
     Function,
-    Struct,
+    Struct {
+        struct_kind: StructKind,
+        /// Named or positional fields; 0 for a unit struct
+        field_count: usize,
+    },
     Enum,
-    Trait,
+    Trait {
+        /// Heuristic guess at whether the trait can be used as `dyn Trait`;
+        /// `None` when its items come from macros
+        #[serde(default)]
+        object_safe: Option<bool>,
+    },
     Impl {
         self_ty: String,
         trait_name: Option<String>,
+        /// `impl<T: Bound> Trait for T`: the self type is one of the impl's own
+        /// type parameters
+        #[serde(default)]
+        blanket: bool,
     },
     TypeAlias,
     Const,
     Static,
     Macro,
+    /// A `#[proc_macro]`, `#[proc_macro_derive]` or `#[proc_macro_attribute]` fn
+    ProcMacro { kind: ProcMacroKind },
     /// Re-exports only (pub use)
     Use,
 }
 
+/// How a struct declares its fields
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StructKind {
+    /// `struct Config { name: String }`
+    Named,
+    /// `struct Meters(f64);`
+    Tuple,
+    /// `struct Marker;`
+    Unit,
+}
+
+/// Which kind of macro a proc-macro function exports
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ProcMacroKind {
+    /// `#[proc_macro_derive(Name)]`, with the derive's name
+    Derive(String),
+    /// `#[proc_macro_attribute]`
+    Attribute,
+    /// `#[proc_macro]`
+    FunctionLike,
+}
+
 impl std::fmt::Display for ItemKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ItemKind::Function => write!(f, "function"),
-            ItemKind::Struct => write!(f, "struct"),
+            ItemKind::Struct { .. } => write!(f, "struct"),
             ItemKind::Enum => write!(f, "enum"),
-            ItemKind::Trait => write!(f, "trait"),
+            ItemKind::Trait { .. } => write!(f, "trait"),
             ItemKind::Impl {
                 self_ty,
                 trait_name,
+                ..
             } => {
                 if let Some(t) = trait_name {
                     write!(f, "impl {} for {}", t, self_ty)
@@ -98,6 +315,11 @@ impl std::fmt::Display for ItemKind {
             ItemKind::Const => write!(f, "const"),
             ItemKind::Static => write!(f, "static"),
             ItemKind::Macro => write!(f, "macro"),
+            ItemKind::ProcMacro { kind } => match kind {
+                ProcMacroKind::Derive(name) => write!(f, "proc_macro_derive({})", name),
+                ProcMacroKind::Attribute => write!(f, "proc_macro_attribute"),
+                ProcMacroKind::FunctionLike => write!(f, "proc_macro"),
+            },
             ItemKind::Use => write!(f, "use"),
         }
     }
@@ -108,6 +330,8 @@ pub enum Visibility {
     Pub,
     PubCrate,
     PubSuper,
+    /// `pub(in path)`, holding the path, e.g. `crate::engine`
+    PubIn(String),
     Private,
 }
 
@@ -117,6 +341,7 @@ impl std::fmt::Display for Visibility {
             Visibility::Pub => write!(f, "pub"),
             Visibility::PubCrate => write!(f, "pub(crate)"),
             Visibility::PubSuper => write!(f, "pub(super)"),
+            Visibility::PubIn(path) => write!(f, "pub(in {})", path),
             Visibility::Private => write!(f, "private"),
         }
     }
@@ -124,16 +349,78 @@ impl std::fmt::Display for Visibility {
 
 impl Visibility {
     /// Returns the prefix to use in output, or empty string for private
-    pub fn prefix(&self) -> &str {
+    pub fn prefix(&self) -> String {
         match self {
-            Visibility::Pub => "pub ",
-            Visibility::PubCrate => "pub(crate) ",
-            Visibility::PubSuper => "pub(super) ",
-            Visibility::Private => "",
+            Visibility::Private => String::new(),
+            _ => format!("{} ", self),
+        }
+    }
+}
+
+impl Item {
+    /// Path of the item inside `module_path`, as used for index keys, e.g.
+    /// `crate::engine::evaluate` or `crate::engine::impl Display for Value`.
+    /// A single-item `pub use a::b::Name` sits at `module_path::Name`, the path
+    /// it is re-exported under.
+    pub fn full_path(&self, module_path: &str) -> String {
+        match &self.kind {
+            ItemKind::Use => match self.name.rsplit_once("::") {
+                Some((_, name)) if name != "*" => {
+                    format!("{}::{}", module_path, name)
+                }
+                _ => format!("{}::{}", module_path, self.name),
+            },
+            ItemKind::Impl {
+                self_ty,
+                trait_name,
+                ..
+            } => {
+                if let Some(tn) = trait_name {
+                    format!("{}::impl {} for {}", module_path, tn, self_ty)
+                } else {
+                    format!("{}::impl {}", module_path, self_ty)
+                }
+            }
+            _ => format!("{}::{}", module_path, self.name),
         }
     }
 }
 
+impl CrateInfo {
+    /// Items whose full path ends with `suffix` at a `::` boundary, so
+    /// `eval::evaluate` matches `crate::engine::eval::evaluate` but
+    /// `evaluate` doesn't match `crate::re_evaluate`
+    pub fn find_items_by_suffix(&self, suffix: &str) -> Vec<(String, &Item)> {
+        let boundary = format!("::{}", suffix);
+        self.items_with_paths()
+            .into_iter()
+            .filter(|(path, _)| path == suffix || path.ends_with(&boundary))
+            .collect()
+    }
+
+    /// The item at exactly `path`, e.g. `crate::engine::eval::evaluate`
+    pub fn find_item_exact(&self, path: &str) -> Option<&Item> {
+        self.items_with_paths()
+            .into_iter()
+            .find(|(item_path, _)| item_path == path)
+            .map(|(_, item)| item)
+    }
+
+    /// Every item in the crate with its full path, in module order
+    pub fn items_with_paths(&self) -> Vec<(String, &Item)> {
+        self.root_module
+            .all_modules()
+            .into_iter()
+            .flat_map(|module| {
+                module
+                    .items
+                    .iter()
+                    .map(|item| (item.full_path(&module.path), item))
+            })
+            .collect()
+    }
+}
+
 impl Module {
     /// Recursively collect all items across this module and submodules
     pub fn all_items(&self) -> Vec<&Item> {
@@ -158,3 +445,117 @@ impl Module {
         self.path.rsplit("::").next().unwrap_or(&self.path)
     }
 }
+
+#[cfg(test)]
+impl Module {
+    /// An empty `pub` module at `path`, read from `file`, to build test
+    /// fixtures from; set any other field with `..Module::for_test(..)`
+    pub(crate) fn for_test(path: &str, file: &str) -> Module {
+        Module {
+            path: path.to_string(),
+            file_path: PathBuf::from(file),
+            file_hash: String::new(),
+            doc_comment: None,
+            inner_doc: None,
+            outer_doc: None,
+            visibility: Visibility::Pub,
+            items: vec![],
+            submodules: vec![],
+            use_statements: vec![],
+            is_inline: false,
+            is_test: false,
+            loc: 0,
+            path_attr: false,
+            error: None,
+            missing_mod_files: vec![],
+        }
+    }
+
+    pub(crate) fn with_items(mut self, items: Vec<Item>) -> Module {
+        self.items = items;
+        self
+    }
+
+    /// The module with the items parsed from `source`, as its file's contents
+    pub(crate) fn with_source(self, source: &str) -> Module {
+        let items = crate::parse::parse_file(&self.file_path, source).unwrap();
+        self.with_items(items)
+    }
+
+    pub(crate) fn with_submodules(mut self, submodules: Vec<Module>) -> Module {
+        self.submodules = submodules;
+        self
+    }
+}
+
+#[cfg(test)]
+impl CrateInfo {
+    /// A 2021-edition lib crate at version 0.1.0, without dependencies
+    pub(crate) fn for_test(name: &str, root_module: Module) -> CrateInfo {
+        CrateInfo {
+            name: name.to_string(),
+            kind: CrateKind::Lib,
+            edition: "2021".to_string(),
+            version: "0.1.0".to_string(),
+            external_deps: vec![],
+            dev_deps: vec![],
+            build_deps: vec![],
+            crate_attributes: vec![],
+            root_module,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_crate() -> CrateInfo {
+        let eval = Module::for_test("crate::engine::eval", "src/lib.rs").with_source(
+            "pub fn evaluate() {}\npub struct Context;\nimpl Context { pub fn new() {} }\n",
+        );
+        let engine = Module::for_test("crate::engine", "src/lib.rs")
+            .with_source("pub fn re_evaluate() {}\n")
+            .with_submodules(vec![eval]);
+        let models = Module::for_test("crate::models", "src/lib.rs")
+            .with_source("pub struct Context;\npub fn evaluate() {}\n");
+        let root = Module::for_test("crate", "src/lib.rs")
+            .with_source("pub fn init() {}\n")
+            .with_submodules(vec![engine, models]);
+        CrateInfo::for_test("test", root)
+    }
+
+    #[test]
+    fn test_find_items_by_suffix() {
+        let krate = sample_crate();
+
+        let paths = |suffix: &str| -> Vec<String> {
+            krate.find_items_by_suffix(suffix).into_iter().map(|(path, _)| path).collect()
+        };
+        // Ambiguous: one match per module, and `re_evaluate` is not a match
+        assert_eq!(
+            paths("evaluate"),
+            vec!["crate::engine::eval::evaluate", "crate::models::evaluate"]
+        );
+        assert_eq!(paths("Context").len(), 2);
+        // Qualifying the suffix narrows it down
+        assert_eq!(paths("eval::evaluate"), vec!["crate::engine::eval::evaluate"]);
+        assert_eq!(paths("crate::init"), vec!["crate::init"]);
+        assert_eq!(paths("impl Context"), vec!["crate::engine::eval::impl Context"]);
+        assert!(paths("valuate").is_empty());
+    }
+
+    #[test]
+    fn test_find_item_exact() {
+        let krate = sample_crate();
+
+        let item = krate.find_item_exact("crate::models::Context").unwrap();
+        assert!(matches!(item.kind, ItemKind::Struct { .. }));
+        assert_eq!(
+            krate.find_item_exact("crate::engine::eval::evaluate").unwrap().kind,
+            ItemKind::Function
+        );
+        assert!(krate.find_item_exact("evaluate").is_none());
+        assert!(krate.find_item_exact("crate::engine::evaluate").is_none());
+    }
+}
@@ -1,4 +1,7 @@
-/// Markdown/text formatting utilities
+//! Markdown/text formatting utilities
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 /// Indent every line of text by the given number of spaces
 pub fn indent(text: &str, spaces: usize) -> String {
@@ -31,20 +34,134 @@ pub fn display_module_path(path: &str) -> &str {
     path.strip_prefix("crate::").unwrap_or(path)
 }
 
-/// Truncate a string to a maximum length, adding "..." if truncated
+/// The first line of a doc comment, trimmed, or `None` if it is blank
+pub fn first_doc_line(doc: &str) -> Option<&str> {
+    let line = doc.lines().next().unwrap_or("").trim();
+    (!line.is_empty()).then_some(line)
+}
+
+/// Truncate a string to a maximum length in bytes, adding "..." if truncated.
+///
+/// The cut always lands on a char boundary, so multibyte text never panics.
 pub fn truncate(s: &str, max_len: usize) -> String {
     if s.len() <= max_len {
         s.to_string()
     } else {
-        format!("{}...", &s[..max_len.saturating_sub(3)])
+        let limit = max_len.saturating_sub(3);
+        let cut = s
+            .char_indices()
+            .map(|(i, _)| i)
+            .take_while(|&i| i <= limit)
+            .last()
+            .unwrap_or(0);
+        format!("{}...", &s[..cut])
     }
 }
 
+/// Estimate the number of LLM tokens in a piece of text.
+///
+/// Uses the common ~4 characters per token heuristic, rounding up.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
 /// Format a code block in markdown
 pub fn code_block(code: &str, language: &str) -> String {
     format!("```{}\n{}\n```", language, code)
 }
 
+/// Join markdown documents into one, each under a `# title` header and with a
+/// table of contents up front. Headings inside a document move down a level.
+pub fn combine_sections(title: &str, sections: &[(&str, String)]) -> String {
+    let mut out = format!("# {}\n\n## Contents\n\n", title);
+    for (name, _) in sections {
+        out.push_str(&format!("- [{}](#{})\n", name, heading_anchor(name)));
+    }
+    for (name, body) in sections {
+        out.push_str(&format!("\n# {}\n\n", name));
+        out.push_str(&demote_headings(body));
+    }
+    out
+}
+
+/// The anchor GitHub generates for a heading: lowercase, spaces to dashes,
+/// other punctuation dropped
+pub fn heading_anchor(heading: &str) -> String {
+    heading
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c.to_ascii_lowercase()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Add a `#` to every markdown heading outside fenced code blocks
+fn demote_headings(text: &str) -> String {
+    let mut in_fence = false;
+    let mut out = String::with_capacity(text.len());
+    for line in text.lines() {
+        let is_heading = line.starts_with('#') && line.trim_start_matches('#').starts_with(' ');
+        if line.starts_with("```") {
+            in_fence = !in_fence;
+        } else if is_heading && !in_fence {
+            out.push('#');
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Format a code block in HTML, escaping the code
+pub fn html_code_block(code: &str, language: &str) -> String {
+    format!(
+        "<pre><code class=\"language-{}\">{}</code></pre>",
+        language,
+        html_escape(code)
+    )
+}
+
+/// Escape text for use in HTML content and attribute values
+pub fn html_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Source files read so far, split into lines
+#[derive(Debug, Default)]
+pub struct SourceFiles {
+    files: HashMap<PathBuf, Option<Vec<String>>>,
+}
+
+impl SourceFiles {
+    /// The text of lines `start..=end` (1-based) of `file`
+    pub fn lines(&mut self, file: &Path, start: usize, end: usize) -> Option<String> {
+        let lines = self
+            .files
+            .entry(file.to_path_buf())
+            .or_insert_with(|| {
+                std::fs::read_to_string(file)
+                    .ok()
+                    .map(|s| s.lines().map(String::from).collect())
+            })
+            .as_ref()?;
+        let range = lines.get(start.checked_sub(1)?..end.min(lines.len()))?;
+        Some(range.join("\n"))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -62,9 +179,67 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_combine_sections() {
+        let combined = combine_sections(
+            "Codebase",
+            &[
+                ("Overview", "# Crate: app\n\n## Module Tree\n- crate\n".to_string()),
+                ("API Surface", "```rust\n# fn hidden() {}\n```".to_string()),
+            ],
+        );
+        assert_eq!(
+            combined,
+            "# Codebase\n\n## Contents\n\n\
+             - [Overview](#overview)\n- [API Surface](#api-surface)\n\
+             \n# Overview\n\n## Crate: app\n\n### Module Tree\n- crate\n\
+             \n# API Surface\n\n```rust\n# fn hidden() {}\n```\n"
+        );
+    }
+
     #[test]
     fn test_display_module_path() {
         assert_eq!(display_module_path("crate::engine::eval"), "engine::eval");
         assert_eq!(display_module_path("crate"), "crate");
     }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abc"), 1);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        // Counts chars, not bytes
+        assert_eq!(estimate_tokens("éééé"), 1);
+    }
+
+    #[test]
+    fn test_html_code_block() {
+        assert_eq!(
+            html_code_block("fn f<T>(x: &T) -> \"a\"", "rust"),
+            "<pre><code class=\"language-rust\">fn f&lt;T&gt;(x: &amp;T) -&gt; &quot;a&quot;</code></pre>"
+        );
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("hello", 10), "hello");
+        assert_eq!(truncate("hello world", 8), "hello...");
+    }
+
+    #[test]
+    fn test_truncate_multibyte() {
+        let s = "héllo wörld 🦀🦀🦀 ünïcödé";
+        for max_len in 0..=s.len() + 1 {
+            let out = truncate(s, max_len);
+            if s.len() > max_len {
+                let prefix = out.strip_suffix("...").expect("truncated output ends with ...");
+                assert!(s.starts_with(prefix));
+                assert!(s.is_char_boundary(prefix.len()));
+                assert!(out.len() <= max_len.max(3));
+            } else {
+                assert_eq!(out, s);
+            }
+        }
+    }
 }
@@ -1,95 +1,453 @@
-use anyhow::{Context, Result};
-use proc_macro2::Span;
+use proc_macro2::{Delimiter, LineColumn, Span, TokenStream, TokenTree};
 use quote::ToTokens;
 use std::path::Path;
-use syn;
+use syn::ext::IdentExt;
+use syn::punctuated::Punctuated;
+use syn::visit::Visit;
 
+use crate::error::{Result, RsmapError};
 use crate::metadata::convert_visibility;
-use crate::model::{Item, ItemKind, Visibility};
+use crate::model::{
+    DeprecationInfo, Item, ItemKind, Member, MemberKind, Param, ProcMacroKind, ReturnTrait,
+    StructKind, Visibility,
+};
 
 /// Parse a single Rust source file and extract all top-level items
 pub fn parse_file(file_path: &Path, source: &str) -> Result<Vec<Item>> {
-    let syntax = syn::parse_file(source)
-        .with_context(|| format!("Failed to parse {}", file_path.display()))?;
+    let syntax = parse_syntax(file_path, source)?;
 
     let mut items = Vec::new();
     extract_items(&syntax.items, file_path, source, &mut items);
     Ok(items)
 }
 
+/// Parse source into a syn AST; errors carry the `file:line:column` of the
+/// syntax error
+pub fn parse_syntax(file_path: &Path, source: &str) -> Result<syn::File> {
+    syn::parse_file(source).map_err(|e| {
+        let start = e.span().start();
+        RsmapError::Parse {
+            file: file_path.to_path_buf(),
+            line: start.line,
+            column: start.column + 1,
+            message: e.to_string(),
+        }
+    })
+}
+
+/// Identifier text with any `r#` prefix removed, so `r#type` becomes `type`.
+/// Signatures keep the raw form.
+pub fn ident_name(ident: &syn::Ident) -> String {
+    ident.unraw().to_string()
+}
+
 /// Extract doc comment from attributes
 pub fn extract_doc_comment(attrs: &[syn::Attribute]) -> Option<String> {
-    let doc_lines: Vec<String> = attrs
+    join_doc_lines(attrs.iter().filter_map(doc_attr_value))
+}
+
+/// Extract inner doc comments (//! style) from file attributes
+pub fn extract_inner_doc_comment(attrs: &[syn::Attribute]) -> Option<String> {
+    join_doc_lines(
+        attrs
+            .iter()
+            .filter(|attr| matches!(attr.style, syn::AttrStyle::Inner(_)))
+            .filter_map(doc_attr_value),
+    )
+}
+
+/// Non-doc inner attributes of a crate root, e.g. `#![no_std]` or `#![feature(..)]`
+pub fn extract_crate_attributes(attrs: &[syn::Attribute]) -> Vec<String> {
+    attrs
         .iter()
-        .filter_map(|attr| {
-            if attr.path().is_ident("doc") {
-                if let syn::Meta::NameValue(nv) = &attr.meta {
-                    if let syn::Expr::Lit(syn::ExprLit {
-                        lit: syn::Lit::Str(s),
-                        ..
-                    }) = &nv.value
-                    {
-                        return Some(s.value());
-                    }
-                }
-            }
-            None
+        .filter(|attr| matches!(attr.style, syn::AttrStyle::Inner(_)))
+        .filter(|attr| !attr.path().is_ident("doc"))
+        .map(|attr| {
+            let meta = attr.meta.to_token_stream().to_string();
+            format!("#![{}]", normalize_signature(&meta))
         })
-        .collect();
+        .collect()
+}
+
+/// Crate attributes from the source of a crate root, without parsing its items
+pub fn parse_crate_attributes(source: &str) -> Vec<String> {
+    let parser = |input: syn::parse::ParseStream| {
+        let attrs = input.call(syn::Attribute::parse_inner)?;
+        input.parse::<TokenStream>()?;
+        Ok(attrs)
+    };
+    syn::parse::Parser::parse_str(parser, source)
+        .map(|attrs| extract_crate_attributes(&attrs))
+        .unwrap_or_default()
+}
+
+/// The string of a `#[doc = "..."]` attribute (which `///` and `/** */` desugar to)
+fn doc_attr_value(attr: &syn::Attribute) -> Option<String> {
+    if !attr.path().is_ident("doc") {
+        return None;
+    }
+    let syn::Meta::NameValue(nv) = &attr.meta else {
+        return None;
+    };
+    string_literal(&nv.value)
+}
+
+/// Join doc fragments into one string, stripping the whitespace prefix common
+/// to all non-blank lines (like rustdoc) so nested indentation survives.
+///
+/// Block comments (`/** ... */`) contribute several lines; a leading `*`
+/// column on every line is removed first.
+fn join_doc_lines(fragments: impl Iterator<Item = String>) -> Option<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for fragment in fragments {
+        if fragment.contains('\n') {
+            lines.extend(block_doc_lines(&fragment));
+        } else {
+            lines.push(fragment);
+        }
+    }
+
+    let indent = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.chars().take_while(|c| c.is_whitespace()).count())
+        .min()?;
 
-    if doc_lines.is_empty() {
+    let text = lines
+        .iter()
+        .map(|l| l.chars().skip(indent).collect::<String>().trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let text = text.trim_matches('\n');
+    if text.is_empty() {
         None
     } else {
-        Some(
-            doc_lines
-                .iter()
-                .map(|l| l.strip_prefix(' ').unwrap_or(l))
-                .collect::<Vec<_>>()
-                .join("\n")
-                .trim()
-                .to_string(),
-        )
+        Some(text.to_string())
     }
 }
 
-/// Extract inner doc comments (//! style) from file attributes
-pub fn extract_inner_doc_comment(attrs: &[syn::Attribute]) -> Option<String> {
-    let doc_lines: Vec<String> = attrs
-        .iter()
-        .filter_map(|attr| {
-            if attr.path().is_ident("doc") {
-                if matches!(attr.style, syn::AttrStyle::Inner(_)) {
-                    if let syn::Meta::NameValue(nv) = &attr.meta {
-                        if let syn::Expr::Lit(syn::ExprLit {
-                            lit: syn::Lit::Str(s),
-                            ..
-                        }) = &nv.value
-                        {
-                            return Some(s.value());
+/// Split a block doc comment into lines, dropping blank first/last lines and a
+/// decorative ` * ` column
+fn block_doc_lines(fragment: &str) -> Vec<String> {
+    let mut lines: Vec<&str> = fragment.lines().collect();
+    while lines.first().is_some_and(|l| l.trim().is_empty()) {
+        lines.remove(0);
+    }
+    while lines.last().is_some_and(|l| l.trim().is_empty()) {
+        lines.pop();
+    }
+
+    let starred = lines.iter().all(|l| l.trim_start().starts_with('*'));
+    lines
+        .into_iter()
+        .map(|l| {
+            if starred {
+                l.trim_start()[1..].to_string()
+            } else {
+                l.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Extract derive macro names from `#[derive(...)]` and `#[cfg_attr(..., derive(...))]`
+/// attributes, in source order
+pub fn extract_derives(attrs: &[syn::Attribute]) -> Vec<String> {
+    let mut derives = Vec::new();
+    for attr in attrs {
+        if attr.path().is_ident("derive") {
+            if let Ok(paths) =
+                attr.parse_args_with(Punctuated::<syn::Path, syn::Token![,]>::parse_terminated)
+            {
+                derives.extend(paths.iter().map(path_to_string));
+            }
+        } else if attr.path().is_ident("cfg_attr") {
+            let Ok(metas) =
+                attr.parse_args_with(Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated)
+            else {
+                continue;
+            };
+            // The first entry is the cfg predicate; the rest are the gated attributes
+            for meta in metas.iter().skip(1) {
+                if let syn::Meta::List(list) = meta {
+                    if list.path.is_ident("derive") {
+                        if let Ok(paths) = list.parse_args_with(
+                            Punctuated::<syn::Path, syn::Token![,]>::parse_terminated,
+                        ) {
+                            derives.extend(paths.iter().map(path_to_string));
                         }
                     }
                 }
             }
-            None
+        }
+    }
+    derives
+}
+
+/// Extract all non-doc, non-derive attributes as source text, in source order
+pub fn extract_attributes(attrs: &[syn::Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| !attr.path().is_ident("doc") && !attr.path().is_ident("derive"))
+        .map(|attr| attr.to_token_stream().to_string())
+        .collect()
+}
+
+/// Read `#[deprecated]`, `#[deprecated = "note"]` or
+/// `#[deprecated(since = "..", note = "..")]`
+pub fn extract_deprecation(attrs: &[syn::Attribute]) -> Option<DeprecationInfo> {
+    let attr = attrs.iter().find(|a| a.path().is_ident("deprecated"))?;
+    let mut info = DeprecationInfo::default();
+    match &attr.meta {
+        syn::Meta::Path(_) => {}
+        syn::Meta::NameValue(nv) => info.note = string_literal(&nv.value),
+        syn::Meta::List(list) => {
+            let args = list
+                .parse_args_with(Punctuated::<syn::MetaNameValue, syn::Token![,]>::parse_terminated)
+                .unwrap_or_default();
+            for arg in args {
+                if arg.path.is_ident("since") {
+                    info.since = string_literal(&arg.value);
+                } else if arg.path.is_ident("note") {
+                    info.note = string_literal(&arg.value);
+                }
+            }
+        }
+    }
+    Some(info)
+}
+
+fn string_literal(expr: &syn::Expr) -> Option<String> {
+    match expr {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(s),
+            ..
+        }) => Some(s.value()),
+        _ => None,
+    }
+}
+
+/// Render each generic parameter with its inline bounds, e.g. `["'a", "T: Clone"]`,
+/// normalized like signatures
+pub fn generic_params(generics: &syn::Generics) -> Vec<String> {
+    generics
+        .params
+        .iter()
+        .map(|param| match param {
+            syn::GenericParam::Lifetime(l) => {
+                if l.bounds.is_empty() {
+                    l.lifetime.to_string()
+                } else {
+                    let bounds: Vec<String> = l.bounds.iter().map(|b| b.to_string()).collect();
+                    format!("{}: {}", l.lifetime, bounds.join(" + "))
+                }
+            }
+            syn::GenericParam::Type(t) => {
+                let mut out = t.ident.to_string();
+                if !t.bounds.is_empty() {
+                    let bounds: Vec<String> = t
+                        .bounds
+                        .iter()
+                        .map(|b| b.to_token_stream().to_string())
+                        .collect();
+                    out.push_str(&format!(": {}", bounds.join(" + ")));
+                }
+                if let Some(ref default) = t.default {
+                    out.push_str(&format!(" = {}", default.to_token_stream()));
+                }
+                out
+            }
+            syn::GenericParam::Const(c) => {
+                format!("const {}: {}", c.ident, c.ty.to_token_stream())
+            }
         })
-        .collect();
+        .map(|param| normalize_signature(&param))
+        .collect()
+}
 
-    if doc_lines.is_empty() {
-        None
+/// Render the where clause of a generics list, if any, normalized like signatures
+pub fn where_clause_text(generics: &syn::Generics) -> Option<String> {
+    generics
+        .where_clause
+        .as_ref()
+        .filter(|w| !w.predicates.is_empty())
+        .map(|w| normalize_signature(&w.to_token_stream().to_string()))
+}
+
+fn path_to_string(path: &syn::Path) -> String {
+    let segments: Vec<String> = path.segments.iter().map(|s| ident_name(&s.ident)).collect();
+    let joined = segments.join("::");
+    if path.leading_colon.is_some() {
+        format!("::{}", joined)
     } else {
-        Some(
-            doc_lines
-                .iter()
-                .map(|l| l.strip_prefix(' ').unwrap_or(l))
-                .collect::<Vec<_>>()
-                .join("\n")
-                .trim()
-                .to_string(),
-        )
+        joined
+    }
+}
+
+/// Paths of a trait's supertraits (lifetime bounds are skipped)
+fn supertrait_names(t: &syn::ItemTrait) -> Vec<String> {
+    trait_bound_names(&t.supertraits)
+}
+
+/// Best-effort guess at whether the trait can be used as `dyn Trait`.
+///
+/// `Some(false)` for a `Sized` supertrait (or `where Self: Sized` on the trait),
+/// an associated const or generic associated type, or a method that has type
+/// parameters (`impl Trait` arguments included), returns `Self` or an opaque
+/// type (`async fn` included), or takes no receiver; methods bounded by `where
+/// Self: Sized` are exempt. `None` when the trait has items a macro expands.
+/// This is a heuristic: supertraits' own object safety and other uses of
+/// `Self` in method signatures aren't checked.
+fn object_safety(t: &syn::ItemTrait) -> Option<bool> {
+    let sized = |s: &String| s == "Sized" || s.ends_with("::Sized");
+    if trait_bound_names(&t.supertraits).iter().any(sized)
+        || bounds_self_sized(t.generics.where_clause.as_ref())
+    {
+        return Some(false);
+    }
+
+    let impl_trait_arg = |arg: &syn::FnArg| {
+        matches!(arg, syn::FnArg::Typed(pt) if matches!(*pt.ty, syn::Type::ImplTrait(_)))
+    };
+    let mut decidable = true;
+    for item in &t.items {
+        match item {
+            syn::TraitItem::Fn(m) => {
+                if bounds_self_sized(m.sig.generics.where_clause.as_ref()) {
+                    continue;
+                }
+                let generic = m
+                    .sig
+                    .generics
+                    .params
+                    .iter()
+                    .any(|p| !matches!(p, syn::GenericParam::Lifetime(_)))
+                    || m.sig.inputs.iter().any(impl_trait_arg);
+                let bad_return = m.sig.asyncness.is_some()
+                    || matches!(&m.sig.output, syn::ReturnType::Type(_, ty)
+                        if is_self_type(ty) || matches!(**ty, syn::Type::ImplTrait(_)));
+                if generic || bad_return || m.sig.receiver().is_none() {
+                    return Some(false);
+                }
+            }
+            syn::TraitItem::Const(_) => return Some(false),
+            syn::TraitItem::Type(ty) if !ty.generics.params.is_empty() => return Some(false),
+            syn::TraitItem::Type(_) => {}
+            _ => decidable = false,
+        }
+    }
+    decidable.then_some(true)
+}
+
+/// Whether a `where` clause bounds `Self: Sized`
+fn bounds_self_sized(where_clause: Option<&syn::WhereClause>) -> bool {
+    where_clause.is_some_and(|w| {
+        w.predicates.iter().any(|pred| match pred {
+            syn::WherePredicate::Type(pt) if is_self_type(&pt.bounded_ty) => {
+                trait_bound_names(&pt.bounds).iter().any(|b| b == "Sized" || b.ends_with("::Sized"))
+            }
+            _ => false,
+        })
+    })
+}
+
+/// The bare `Self` type
+fn is_self_type(ty: &syn::Type) -> bool {
+    matches!(ty, syn::Type::Path(p) if p.qself.is_none() && p.path.is_ident("Self"))
+}
+
+/// Paths of the trait bounds in a `+`-separated list, skipping lifetimes
+fn trait_bound_names<'a>(
+    bounds: impl IntoIterator<Item = &'a syn::TypeParamBound>,
+) -> Vec<String> {
+    bounds
+        .into_iter()
+        .filter_map(|bound| match bound {
+            syn::TypeParamBound::Trait(tb) => Some(path_to_string(&tb.path)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Render a `#[derive(...)]` line for a signature, or an empty string if there are none
+fn derive_prefix(attrs: &[syn::Attribute]) -> String {
+    let derives = extract_derives(attrs);
+    let mut prefix = if derives.is_empty() {
+        String::new()
+    } else {
+        format!("#[derive({})]\n", derives.join(", "))
+    };
+    if let Some(repr) = extract_repr(attrs) {
+        prefix.push_str(&format!("#[repr({})]\n", repr));
+    }
+    prefix
+}
+
+/// Read `#[repr(...)]`, e.g. `u8` or `C, packed`
+pub fn extract_repr(attrs: &[syn::Attribute]) -> Option<String> {
+    let attr = attrs.iter().find(|a| a.path().is_ident("repr"))?;
+    let list = attr.meta.require_list().ok()?;
+    Some(normalize_signature(&list.tokens.to_string()))
+}
+
+/// Which macro a proc-macro crate's function exports, from its
+/// `#[proc_macro]`, `#[proc_macro_derive(Name)]` or `#[proc_macro_attribute]`
+pub fn proc_macro_kind(attrs: &[syn::Attribute]) -> Option<ProcMacroKind> {
+    attrs.iter().find_map(|attr| {
+        let path = attr.path();
+        if path.is_ident("proc_macro") {
+            Some(ProcMacroKind::FunctionLike)
+        } else if path.is_ident("proc_macro_attribute") {
+            Some(ProcMacroKind::Attribute)
+        } else if path.is_ident("proc_macro_derive") {
+            // `#[proc_macro_derive(Name, attributes(helper))]`: the name comes first
+            let list = attr.meta.require_list().ok()?;
+            let name = list.parse_args_with(|input: syn::parse::ParseStream| {
+                let name: syn::Ident = input.parse()?;
+                input.parse::<TokenStream>()?;
+                Ok(name)
+            });
+            Some(ProcMacroKind::Derive(ident_name(&name.ok()?)))
+        } else {
+            None
+        }
+    })
+}
+
+/// The `#[cfg(...)]` predicate an item is compiled under, e.g. `unix` or
+/// `feature = "serde"`. Several `cfg` attributes are combined with `all(..)`.
+pub fn extract_cfg(attrs: &[syn::Attribute]) -> Option<String> {
+    let predicates: Vec<String> = attrs
+        .iter()
+        .filter(|a| a.path().is_ident("cfg"))
+        .filter_map(|a| a.meta.require_list().ok())
+        .map(|list| normalize_signature(&list.tokens.to_string()))
+        .collect();
+    match predicates.as_slice() {
+        [] => None,
+        [predicate] => Some(predicate.clone()),
+        _ => Some(format!("all({})", predicates.join(", "))),
     }
 }
 
-fn extract_items(
+/// Whether the attributes include `#[doc(hidden)]`
+pub fn is_doc_hidden(attrs: &[syn::Attribute]) -> bool {
+    attrs
+        .iter()
+        .filter(|a| a.path().is_ident("doc"))
+        .filter_map(|a| a.meta.require_list().ok())
+        .any(|list| {
+            list.tokens
+                .clone()
+                .into_iter()
+                .any(|t| matches!(t, TokenTree::Ident(ref i) if i == "hidden"))
+        })
+}
+
+/// Extract items from already-parsed syn items. `source` must be the text the
+/// items were parsed from so line numbers and content hashes match the file;
+/// used directly for inline module bodies.
+pub fn extract_items(
     syn_items: &[syn::Item],
     file_path: &Path,
     source: &str,
@@ -99,72 +457,164 @@ fn extract_items(
         match item {
             syn::Item::Fn(f) => {
                 let sig = fn_signature(f);
-                let (start, end) = span_lines(&f.sig.fn_token.span, source, item);
+                let (start, end) = span_lines(&f.sig.fn_token.span, &f.attrs, source, item);
+                let (byte_start, byte_end) = span_bytes(source, item);
+                let kind = proc_macro_kind(&f.attrs)
+                    .map_or(ItemKind::Function, |kind| ItemKind::ProcMacro { kind });
                 items.push(Item {
-                    name: f.sig.ident.to_string(),
-                    kind: ItemKind::Function,
+                    name: ident_name(&f.sig.ident),
+                    kind,
                     visibility: convert_visibility(&f.vis),
-                    signature: sig,
+                    signature: normalize_signature(&sig),
                     doc_comment: extract_doc_comment(&f.attrs),
+                    derives: extract_derives(&f.attrs),
+                    attributes: extract_attributes(&f.attrs),
+                    deprecated: extract_deprecation(&f.attrs),
+                    generics: generic_params(&f.sig.generics),
+                    where_clause: where_clause_text(&f.sig.generics),
+                    supertraits: Vec::new(),
+                    abi: None,
+                    members: Vec::new(),
+                    params: fn_params(&f.sig),
+                    return_type: return_type(&f.sig),
+                    return_trait: return_trait(&f.sig),
+                    is_async: f.sig.asyncness.is_some(),
+                    is_unsafe: f.sig.unsafety.is_some(),
+                    is_const: f.sig.constness.is_some(),
+                    repr: None,
+                    cfg: extract_cfg(&f.attrs),
+                    doc_hidden: is_doc_hidden(&f.attrs),
+                    reexport_of: None,
                     file_path: file_path.to_path_buf(),
                     line_start: start,
                     line_end: end,
-                    content_hash: hash_item_source(source, start, end),
+                    byte_start,
+                    byte_end,
+                    content_hash: hash_item_tokens(item),
                 });
             }
             syn::Item::Struct(s) => {
-                let sig = struct_signature(s);
-                let (start, end) = span_lines(&s.struct_token.span, source, item);
+                let (sig, struct_kind) = struct_signature(s);
+                let (start, end) = span_lines(&s.struct_token.span, &s.attrs, source, item);
+                let (byte_start, byte_end) = span_bytes(source, item);
                 items.push(Item {
-                    name: s.ident.to_string(),
-                    kind: ItemKind::Struct,
+                    name: ident_name(&s.ident),
+                    kind: ItemKind::Struct {
+                        struct_kind,
+                        field_count: s.fields.len(),
+                    },
                     visibility: convert_visibility(&s.vis),
-                    signature: sig,
+                    signature: normalize_signature(&sig),
                     doc_comment: extract_doc_comment(&s.attrs),
+                    derives: extract_derives(&s.attrs),
+                    attributes: extract_attributes(&s.attrs),
+                    deprecated: extract_deprecation(&s.attrs),
+                    generics: generic_params(&s.generics),
+                    where_clause: where_clause_text(&s.generics),
+                    supertraits: Vec::new(),
+                    abi: None,
+                    members: struct_members(s),
+                    params: Vec::new(),
+                    return_type: None,
+                    return_trait: None,
+                    is_async: false,
+                    is_unsafe: false,
+                    is_const: false,
+                    repr: extract_repr(&s.attrs),
+                    cfg: extract_cfg(&s.attrs),
+                    doc_hidden: is_doc_hidden(&s.attrs),
+                    reexport_of: None,
                     file_path: file_path.to_path_buf(),
                     line_start: start,
                     line_end: end,
-                    content_hash: hash_item_source(source, start, end),
+                    byte_start,
+                    byte_end,
+                    content_hash: hash_item_tokens(item),
                 });
             }
             syn::Item::Enum(e) => {
                 let sig = enum_signature(e);
-                let (start, end) = span_lines(&e.enum_token.span, source, item);
+                let (start, end) = span_lines(&e.enum_token.span, &e.attrs, source, item);
+                let (byte_start, byte_end) = span_bytes(source, item);
                 items.push(Item {
-                    name: e.ident.to_string(),
+                    name: ident_name(&e.ident),
                     kind: ItemKind::Enum,
                     visibility: convert_visibility(&e.vis),
-                    signature: sig,
+                    signature: normalize_signature(&sig),
                     doc_comment: extract_doc_comment(&e.attrs),
+                    derives: extract_derives(&e.attrs),
+                    attributes: extract_attributes(&e.attrs),
+                    deprecated: extract_deprecation(&e.attrs),
+                    generics: generic_params(&e.generics),
+                    where_clause: where_clause_text(&e.generics),
+                    supertraits: Vec::new(),
+                    abi: None,
+                    members: enum_members(e),
+                    params: Vec::new(),
+                    return_type: None,
+                    return_trait: None,
+                    is_async: false,
+                    is_unsafe: false,
+                    is_const: false,
+                    repr: extract_repr(&e.attrs),
+                    cfg: extract_cfg(&e.attrs),
+                    doc_hidden: is_doc_hidden(&e.attrs),
+                    reexport_of: None,
                     file_path: file_path.to_path_buf(),
                     line_start: start,
                     line_end: end,
-                    content_hash: hash_item_source(source, start, end),
+                    byte_start,
+                    byte_end,
+                    content_hash: hash_item_tokens(item),
                 });
             }
             syn::Item::Trait(t) => {
                 let sig = trait_signature(t);
-                let (start, end) = span_lines(&t.trait_token.span, source, item);
+                let (start, end) = span_lines(&t.trait_token.span, &t.attrs, source, item);
+                let (byte_start, byte_end) = span_bytes(source, item);
                 items.push(Item {
-                    name: t.ident.to_string(),
-                    kind: ItemKind::Trait,
+                    name: ident_name(&t.ident),
+                    kind: ItemKind::Trait {
+                        object_safe: object_safety(t),
+                    },
                     visibility: convert_visibility(&t.vis),
                     signature: sig,
                     doc_comment: extract_doc_comment(&t.attrs),
+                    derives: extract_derives(&t.attrs),
+                    attributes: extract_attributes(&t.attrs),
+                    deprecated: extract_deprecation(&t.attrs),
+                    generics: generic_params(&t.generics),
+                    where_clause: where_clause_text(&t.generics),
+                    supertraits: supertrait_names(t),
+                    abi: None,
+                    members: trait_members(t),
+                    params: Vec::new(),
+                    return_type: None,
+                    return_trait: None,
+                    is_async: false,
+                    is_unsafe: t.unsafety.is_some(),
+                    is_const: false,
+                    repr: None,
+                    cfg: extract_cfg(&t.attrs),
+                    doc_hidden: is_doc_hidden(&t.attrs),
+                    reexport_of: None,
                     file_path: file_path.to_path_buf(),
                     line_start: start,
                     line_end: end,
-                    content_hash: hash_item_source(source, start, end),
+                    byte_start,
+                    byte_end,
+                    content_hash: hash_item_tokens(item),
                 });
             }
             syn::Item::Impl(i) => {
-                let self_ty = i.self_ty.to_token_stream().to_string();
+                let self_ty = normalize_signature(&i.self_ty.to_token_stream().to_string());
                 let trait_name = i
                     .trait_
                     .as_ref()
-                    .map(|(_, path, _)| path.to_token_stream().to_string());
+                    .map(|(_, path, _)| normalize_signature(&path.to_token_stream().to_string()));
                 let sig = impl_signature(i);
-                let (start, end) = span_lines(&i.impl_token.span, source, item);
+                let (start, end) = span_lines(&i.impl_token.span, &i.attrs, source, item);
+                let (byte_start, byte_end) = span_bytes(source, item);
 
                 let name = if let Some(ref tn) = trait_name {
                     format!("{} for {}", tn, self_ty)
@@ -177,34 +627,84 @@ fn extract_items(
                     kind: ItemKind::Impl {
                         self_ty,
                         trait_name,
+                        blanket: is_blanket_impl(i),
                     },
                     visibility: Visibility::Private, // impls don't have visibility
                     signature: sig,
                     doc_comment: extract_doc_comment(&i.attrs),
+                    derives: extract_derives(&i.attrs),
+                    attributes: extract_attributes(&i.attrs),
+                    deprecated: extract_deprecation(&i.attrs),
+                    generics: generic_params(&i.generics),
+                    where_clause: where_clause_text(&i.generics),
+                    supertraits: Vec::new(),
+                    abi: None,
+                    members: impl_members(i),
+                    params: Vec::new(),
+                    return_type: None,
+                    return_trait: None,
+                    is_async: false,
+                    is_unsafe: i.unsafety.is_some(),
+                    is_const: false,
+                    repr: None,
+                    cfg: extract_cfg(&i.attrs),
+                    doc_hidden: is_doc_hidden(&i.attrs),
+                    reexport_of: None,
                     file_path: file_path.to_path_buf(),
                     line_start: start,
                     line_end: end,
-                    content_hash: hash_item_source(source, start, end),
+                    byte_start,
+                    byte_end,
+                    content_hash: hash_item_tokens(item),
                 });
             }
             syn::Item::Type(t) => {
+                let where_clause = t
+                    .generics
+                    .where_clause
+                    .as_ref()
+                    .map(|w| format!(" {}", w.to_token_stream()))
+                    .unwrap_or_default();
                 let sig = format!(
-                    "{}type {} = {};",
+                    "{}type {}{}{} = {};",
                     visibility_prefix(&t.vis),
                     t.ident,
+                    t.generics.to_token_stream(),
+                    where_clause,
                     t.ty.to_token_stream()
                 );
-                let (start, end) = span_lines(&t.type_token.span, source, item);
+                let (start, end) = span_lines(&t.type_token.span, &t.attrs, source, item);
+                let (byte_start, byte_end) = span_bytes(source, item);
                 items.push(Item {
-                    name: t.ident.to_string(),
+                    name: ident_name(&t.ident),
                     kind: ItemKind::TypeAlias,
                     visibility: convert_visibility(&t.vis),
-                    signature: sig,
+                    signature: normalize_signature(&sig),
                     doc_comment: extract_doc_comment(&t.attrs),
+                    derives: extract_derives(&t.attrs),
+                    attributes: extract_attributes(&t.attrs),
+                    deprecated: extract_deprecation(&t.attrs),
+                    generics: generic_params(&t.generics),
+                    where_clause: where_clause_text(&t.generics),
+                    supertraits: Vec::new(),
+                    abi: None,
+                    members: Vec::new(),
+                    params: Vec::new(),
+                    return_type: None,
+                    return_trait: None,
+                    is_async: false,
+                    is_unsafe: false,
+                    is_const: false,
+                    repr: None,
+                    cfg: extract_cfg(&t.attrs),
+                    doc_hidden: is_doc_hidden(&t.attrs),
+                    reexport_of: None,
                     file_path: file_path.to_path_buf(),
                     line_start: start,
                     line_end: end,
-                    content_hash: hash_item_source(source, start, end),
+                    byte_start,
+                    byte_end,
+                    content_hash: hash_item_tokens(item),
                 });
             }
             syn::Item::Const(c) => {
@@ -214,17 +714,38 @@ fn extract_items(
                     c.ident,
                     c.ty.to_token_stream()
                 );
-                let (start, end) = span_lines(&c.const_token.span, source, item);
+                let (start, end) = span_lines(&c.const_token.span, &c.attrs, source, item);
+                let (byte_start, byte_end) = span_bytes(source, item);
                 items.push(Item {
-                    name: c.ident.to_string(),
+                    name: ident_name(&c.ident),
                     kind: ItemKind::Const,
                     visibility: convert_visibility(&c.vis),
-                    signature: sig,
+                    signature: normalize_signature(&sig),
                     doc_comment: extract_doc_comment(&c.attrs),
+                    derives: extract_derives(&c.attrs),
+                    attributes: extract_attributes(&c.attrs),
+                    deprecated: extract_deprecation(&c.attrs),
+                    generics: Vec::new(),
+                    where_clause: None,
+                    supertraits: Vec::new(),
+                    abi: None,
+                    members: Vec::new(),
+                    params: Vec::new(),
+                    return_type: None,
+                    return_trait: None,
+                    is_async: false,
+                    is_unsafe: false,
+                    is_const: false,
+                    repr: None,
+                    cfg: extract_cfg(&c.attrs),
+                    doc_hidden: is_doc_hidden(&c.attrs),
+                    reexport_of: None,
                     file_path: file_path.to_path_buf(),
                     line_start: start,
                     line_end: end,
-                    content_hash: hash_item_source(source, start, end),
+                    byte_start,
+                    byte_end,
+                    content_hash: hash_item_tokens(item),
                 });
             }
             syn::Item::Static(s) => {
@@ -240,54 +761,251 @@ fn extract_items(
                     s.ident,
                     s.ty.to_token_stream()
                 );
-                let (start, end) = span_lines(&s.static_token.span, source, item);
+                let (start, end) = span_lines(&s.static_token.span, &s.attrs, source, item);
+                let (byte_start, byte_end) = span_bytes(source, item);
                 items.push(Item {
-                    name: s.ident.to_string(),
+                    name: ident_name(&s.ident),
                     kind: ItemKind::Static,
                     visibility: convert_visibility(&s.vis),
-                    signature: sig,
+                    signature: normalize_signature(&sig),
                     doc_comment: extract_doc_comment(&s.attrs),
+                    derives: extract_derives(&s.attrs),
+                    attributes: extract_attributes(&s.attrs),
+                    deprecated: extract_deprecation(&s.attrs),
+                    generics: Vec::new(),
+                    where_clause: None,
+                    supertraits: Vec::new(),
+                    abi: None,
+                    members: Vec::new(),
+                    params: Vec::new(),
+                    return_type: None,
+                    return_trait: None,
+                    is_async: false,
+                    is_unsafe: false,
+                    is_const: false,
+                    repr: None,
+                    cfg: extract_cfg(&s.attrs),
+                    doc_hidden: is_doc_hidden(&s.attrs),
+                    reexport_of: None,
                     file_path: file_path.to_path_buf(),
                     line_start: start,
                     line_end: end,
-                    content_hash: hash_item_source(source, start, end),
+                    byte_start,
+                    byte_end,
+                    content_hash: hash_item_tokens(item),
                 });
             }
             syn::Item::Macro(m) => {
                 if let Some(ref ident) = m.ident {
-                    let sig = format!("macro_rules! {} {{ ... }}", ident);
-                    let (start, end) = span_lines(&m.mac.path.segments[0].ident.span(), source, item);
+                    let sig = macro_signature(ident, &m.mac.tokens);
+                    let (start, end) = span_lines(
+                        &m.mac.path.segments[0].ident.span(),
+                        &m.attrs,
+                        source,
+                        item,
+                    );
+                    let (byte_start, byte_end) = span_bytes(source, item);
                     items.push(Item {
-                        name: ident.to_string(),
+                        name: ident_name(ident),
                         kind: ItemKind::Macro,
-                        visibility: Visibility::Private, // macro_rules are effectively pub in the crate
-                        signature: sig,
+                        // Only #[macro_export] macros are usable outside their module
+                        visibility: if m.attrs.iter().any(|a| a.path().is_ident("macro_export")) {
+                            Visibility::Pub
+                        } else {
+                            Visibility::Private
+                        },
+                        signature: normalize_signature(&sig),
                         doc_comment: extract_doc_comment(&m.attrs),
+                        derives: extract_derives(&m.attrs),
+                        attributes: extract_attributes(&m.attrs),
+                        deprecated: extract_deprecation(&m.attrs),
+                        generics: Vec::new(),
+                        where_clause: None,
+                        supertraits: Vec::new(),
+                        abi: None,
+                        members: Vec::new(),
+                        params: Vec::new(),
+                        return_type: None,
+                        return_trait: None,
+                        is_async: false,
+                        is_unsafe: false,
+                        is_const: false,
+                        repr: None,
+                        cfg: extract_cfg(&m.attrs),
+                        doc_hidden: is_doc_hidden(&m.attrs),
+                        reexport_of: None,
                         file_path: file_path.to_path_buf(),
                         line_start: start,
                         line_end: end,
-                        content_hash: hash_item_source(source, start, end),
+                        byte_start,
+                        byte_end,
+                        content_hash: hash_item_tokens(item),
                     });
                 }
             }
             syn::Item::Use(u) => {
-                // Only record pub use (re-exports)
+                // Only record pub use (re-exports), one item per name re-exported
                 if matches!(u.vis, syn::Visibility::Public(_)) {
-                    let sig = format!("pub use {};", u.tree.to_token_stream());
-                    let (start, end) = span_lines(&u.use_token.span, source, item);
-                    items.push(Item {
-                        name: use_tree_name(&u.tree),
-                        kind: ItemKind::Use,
-                        visibility: Visibility::Pub,
-                        signature: sig,
-                        doc_comment: extract_doc_comment(&u.attrs),
-                        file_path: file_path.to_path_buf(),
-                        line_start: start,
-                        line_end: end,
-                        content_hash: hash_item_source(source, start, end),
-                    });
+                    let (start, end) = span_lines(&u.use_token.span, &u.attrs, source, item);
+                    let (byte_start, byte_end) = span_bytes(source, item);
+                    for reexport in reexports(u) {
+                        let signature = normalize_signature(&reexport.signature);
+                        // Each leaf of a group has its own position and hash, so
+                        // editing one name leaves the others' annotations alone
+                        let (start, end, byte_start, byte_end, content_hash) = match reexport.leaf {
+                            Some(leaf) => {
+                                let first = leaf.to_token_stream().into_iter().next();
+                                let span = first.map_or_else(Span::call_site, |tt| tt.span());
+                                let (start, end) = span_lines(&span, &[], source, leaf);
+                                let (byte_start, byte_end) = span_bytes(source, leaf);
+                                let hash = blake3::hash(signature.as_bytes()).to_hex().to_string();
+                                (start, end, byte_start, byte_end, hash)
+                            }
+                            None => (start, end, byte_start, byte_end, hash_item_tokens(item)),
+                        };
+                        items.push(Item {
+                            name: reexport.name,
+                            kind: ItemKind::Use,
+                            visibility: Visibility::Pub,
+                            signature,
+                            doc_comment: extract_doc_comment(&u.attrs),
+                            derives: extract_derives(&u.attrs),
+                            attributes: extract_attributes(&u.attrs),
+                            deprecated: extract_deprecation(&u.attrs),
+                            generics: Vec::new(),
+                            where_clause: None,
+                            supertraits: Vec::new(),
+                            abi: None,
+                            members: Vec::new(),
+                            params: Vec::new(),
+                            return_type: None,
+                            return_trait: None,
+                            is_async: false,
+                            is_unsafe: false,
+                            is_const: false,
+                            repr: None,
+                            cfg: extract_cfg(&u.attrs),
+                            doc_hidden: is_doc_hidden(&u.attrs),
+                            reexport_of: None,
+                            file_path: file_path.to_path_buf(),
+                            line_start: start,
+                            line_end: end,
+                            byte_start,
+                            byte_end,
+                            content_hash,
+                        });
+                    }
                 }
             }
+            syn::Item::ForeignMod(fm) => {
+                // `extern { ... }` without an explicit ABI defaults to "C"
+                let abi = fm
+                    .abi
+                    .name
+                    .as_ref()
+                    .map(|n| n.value())
+                    .unwrap_or_else(|| "C".to_string());
+                extract_foreign_items(&fm.items, &abi, file_path, source, items);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Extract fns and statics declared inside an `extern "ABI" { ... }` block
+fn extract_foreign_items(
+    foreign_items: &[syn::ForeignItem],
+    abi: &str,
+    file_path: &Path,
+    source: &str,
+    items: &mut Vec<Item>,
+) {
+    for foreign in foreign_items {
+        match foreign {
+            syn::ForeignItem::Fn(f) => {
+                let sig = foreign_fn_signature(f, abi);
+                let (start, end) = span_lines(&f.sig.fn_token.span, &f.attrs, source, foreign);
+                let (byte_start, byte_end) = span_bytes(source, foreign);
+                items.push(Item {
+                    name: ident_name(&f.sig.ident),
+                    kind: ItemKind::Function,
+                    visibility: convert_visibility(&f.vis),
+                    signature: normalize_signature(&sig),
+                    doc_comment: extract_doc_comment(&f.attrs),
+                    derives: Vec::new(),
+                    attributes: extract_attributes(&f.attrs),
+                    deprecated: extract_deprecation(&f.attrs),
+                    generics: generic_params(&f.sig.generics),
+                    where_clause: where_clause_text(&f.sig.generics),
+                    supertraits: Vec::new(),
+                    abi: Some(abi.to_string()),
+                    members: Vec::new(),
+                    params: fn_params(&f.sig),
+                    return_type: return_type(&f.sig),
+                    return_trait: return_trait(&f.sig),
+                    is_async: f.sig.asyncness.is_some(),
+                    is_unsafe: f.sig.unsafety.is_some(),
+                    is_const: f.sig.constness.is_some(),
+                    repr: None,
+                    cfg: extract_cfg(&f.attrs),
+                    doc_hidden: is_doc_hidden(&f.attrs),
+                    reexport_of: None,
+                    file_path: file_path.to_path_buf(),
+                    line_start: start,
+                    line_end: end,
+                    byte_start,
+                    byte_end,
+                    content_hash: hash_item_tokens(foreign),
+                });
+            }
+            syn::ForeignItem::Static(s) => {
+                let mutability = if matches!(s.mutability, syn::StaticMutability::Mut(_)) {
+                    "mut "
+                } else {
+                    ""
+                };
+                let sig = format!(
+                    "extern \"{}\" {{ {}static {}{}: {}; }}",
+                    abi,
+                    visibility_prefix(&s.vis),
+                    mutability,
+                    s.ident,
+                    s.ty.to_token_stream()
+                );
+                let (start, end) = span_lines(&s.static_token.span, &s.attrs, source, foreign);
+                let (byte_start, byte_end) = span_bytes(source, foreign);
+                items.push(Item {
+                    name: ident_name(&s.ident),
+                    kind: ItemKind::Static,
+                    visibility: convert_visibility(&s.vis),
+                    signature: normalize_signature(&sig),
+                    doc_comment: extract_doc_comment(&s.attrs),
+                    derives: Vec::new(),
+                    attributes: extract_attributes(&s.attrs),
+                    deprecated: extract_deprecation(&s.attrs),
+                    generics: Vec::new(),
+                    where_clause: None,
+                    supertraits: Vec::new(),
+                    abi: Some(abi.to_string()),
+                    members: Vec::new(),
+                    params: Vec::new(),
+                    return_type: None,
+                    return_trait: None,
+                    is_async: false,
+                    is_unsafe: false,
+                    is_const: false,
+                    repr: None,
+                    cfg: extract_cfg(&s.attrs),
+                    doc_hidden: is_doc_hidden(&s.attrs),
+                    reexport_of: None,
+                    file_path: file_path.to_path_buf(),
+                    line_start: start,
+                    line_end: end,
+                    byte_start,
+                    byte_end,
+                    content_hash: hash_item_tokens(foreign),
+                });
+            }
             _ => {}
         }
     }
@@ -328,7 +1046,7 @@ fn fn_signature(f: &syn::ItemFn) -> String {
         .sig
         .inputs
         .iter()
-        .map(|arg| arg.to_token_stream().to_string())
+        .map(fn_arg_signature)
         .collect();
 
     let output = match &f.sig.output {
@@ -350,8 +1068,285 @@ fn fn_signature(f: &syn::ItemFn) -> String {
     )
 }
 
-/// Generate struct signature with fields
-fn struct_signature(s: &syn::ItemStruct) -> String {
+/// Generate signature for a fn declared in an extern block, wrapped in its ABI
+fn foreign_fn_signature(f: &syn::ForeignItemFn, abi: &str) -> String {
+    let vis = visibility_prefix(&f.vis);
+    let mut inputs: Vec<String> = f
+        .sig
+        .inputs
+        .iter()
+        .map(fn_arg_signature)
+        .collect();
+    if f.sig.variadic.is_some() {
+        inputs.push("...".to_string());
+    }
+
+    let output = match &f.sig.output {
+        syn::ReturnType::Default => String::new(),
+        syn::ReturnType::Type(_, ty) => format!(" -> {}", ty.to_token_stream()),
+    };
+
+    format!(
+        "extern \"{}\" {{ {}fn {}({}){}; }}",
+        abi,
+        vis,
+        f.sig.ident,
+        inputs.join(", "),
+        output
+    )
+}
+
+/// Structured parameters of a function signature, including any `self` receiver
+pub fn fn_params(sig: &syn::Signature) -> Vec<Param> {
+    sig.inputs
+        .iter()
+        .map(|arg| match arg {
+            syn::FnArg::Receiver(r) => Param {
+                name: Some("self".to_string()),
+                ty: receiver_type(r),
+                is_self: true,
+            },
+            syn::FnArg::Typed(t) => Param {
+                name: match &*t.pat {
+                    syn::Pat::Ident(p) => Some(ident_name(&p.ident)),
+                    _ => None,
+                },
+                ty: t.ty.to_token_stream().to_string(),
+                is_self: false,
+            },
+        })
+        .collect()
+}
+
+/// `self`, `&self`, `&'a mut self`, or the explicit type of `self: Box<Self>`
+fn receiver_type(r: &syn::Receiver) -> String {
+    if r.colon_token.is_some() {
+        return r.ty.to_token_stream().to_string();
+    }
+    let mutability = if r.mutability.is_some() { "mut " } else { "" };
+    match &r.reference {
+        Some((_, Some(lifetime))) => format!("&{} {}self", lifetime, mutability),
+        Some((_, None)) => format!("&{}self", mutability),
+        None => format!("{}self", mutability),
+    }
+}
+
+/// One parameter as written in a signature. Receivers are matched explicitly
+/// so an arbitrary self type keeps its `self: Type` form, e.g.
+/// `self: Pin<&mut Self>`.
+fn fn_arg_signature(arg: &syn::FnArg) -> String {
+    match arg {
+        syn::FnArg::Receiver(r) if r.colon_token.is_some() => {
+            let mutability = if r.mutability.is_some() { "mut " } else { "" };
+            format!("{}self: {}", mutability, r.ty.to_token_stream())
+        }
+        syn::FnArg::Receiver(r) => receiver_type(r),
+        syn::FnArg::Typed(t) => t.to_token_stream().to_string(),
+    }
+}
+
+/// The return type of a function, or `None` for `()`
+pub fn return_type(sig: &syn::Signature) -> Option<String> {
+    match &sig.output {
+        syn::ReturnType::Default => None,
+        syn::ReturnType::Type(_, ty) => Some(ty.to_token_stream().to_string()),
+    }
+}
+
+/// The traits behind an `impl Trait` or `dyn Trait` return type, including
+/// one wrapped in another type such as `Box<dyn Error>`
+pub fn return_trait(sig: &syn::Signature) -> Option<ReturnTrait> {
+    let syn::ReturnType::Type(_, ty) = &sig.output else {
+        return None;
+    };
+    let mut finder = ReturnTraitFinder(None);
+    finder.visit_type(ty);
+    finder.0
+}
+
+/// Finds the first `impl Trait` or `dyn Trait` in a type
+struct ReturnTraitFinder(Option<ReturnTrait>);
+
+impl<'ast> Visit<'ast> for ReturnTraitFinder {
+    fn visit_type_impl_trait(&mut self, ty: &'ast syn::TypeImplTrait) {
+        if self.0.is_none() {
+            self.0 = Some(ReturnTrait::Impl(trait_bound_names(&ty.bounds)));
+        }
+    }
+
+    fn visit_type_trait_object(&mut self, ty: &'ast syn::TypeTraitObject) {
+        if self.0.is_none() {
+            self.0 = Some(ReturnTrait::Dyn(trait_bound_names(&ty.bounds)));
+        }
+    }
+}
+
+/// Extract a struct's fields (named, or indexed for tuple structs) as members
+fn struct_members(s: &syn::ItemStruct) -> Vec<Member> {
+    s.fields
+        .iter()
+        .enumerate()
+        .map(|(idx, f)| {
+            let name = f
+                .ident
+                .as_ref()
+                .map(ident_name)
+                .unwrap_or_else(|| idx.to_string());
+            let signature = match f.ident {
+                Some(ref ident) => format!("{}: {}", ident, f.ty.to_token_stream()),
+                None => f.ty.to_token_stream().to_string(),
+            };
+            let (line_start, line_end) = token_lines(f);
+            Member {
+                name,
+                kind: MemberKind::Field,
+                visibility: convert_visibility(&f.vis),
+                signature: normalize_signature(&signature),
+                doc_comment: extract_doc_comment(&f.attrs),
+                has_default: false,
+                is_method: false,
+                is_async: false,
+                is_unsafe: false,
+                is_const: false,
+                discriminant: None,
+                line_start,
+                line_end,
+            }
+        })
+        .collect()
+}
+
+/// Extract a trait's methods, associated types and consts as members; they
+/// share the trait's visibility
+fn trait_members(t: &syn::ItemTrait) -> Vec<Member> {
+    t.items
+        .iter()
+        .filter_map(|item| {
+            let (name, kind, signature, attrs, has_default) = match item {
+                syn::TraitItem::Fn(m) => (
+                    ident_name(&m.sig.ident),
+                    MemberKind::Method,
+                    trait_method_signature(m),
+                    &m.attrs,
+                    m.default.is_some(),
+                ),
+                syn::TraitItem::Type(ty) => (
+                    ident_name(&ty.ident),
+                    MemberKind::AssocType,
+                    trait_type_signature(ty),
+                    &ty.attrs,
+                    ty.default.is_some(),
+                ),
+                syn::TraitItem::Const(c) => (
+                    ident_name(&c.ident),
+                    MemberKind::AssocConst,
+                    trait_const_signature(c),
+                    &c.attrs,
+                    c.default.is_some(),
+                ),
+                _ => return None,
+            };
+            let sig = match item {
+                syn::TraitItem::Fn(m) => Some(&m.sig),
+                _ => None,
+            };
+            let is_method = sig.is_some_and(|sig| sig.receiver().is_some());
+            let (line_start, line_end) = token_lines(item);
+            Some(Member {
+                name,
+                kind,
+                visibility: convert_visibility(&t.vis),
+                signature: normalize_signature(&signature),
+                doc_comment: extract_doc_comment(attrs),
+                has_default,
+                is_method,
+                is_async: sig.is_some_and(|sig| sig.asyncness.is_some()),
+                is_unsafe: sig.is_some_and(|sig| sig.unsafety.is_some()),
+                is_const: sig.is_some_and(|sig| sig.constness.is_some()),
+                discriminant: None,
+                line_start,
+                line_end,
+            })
+        })
+        .collect()
+}
+
+/// Extract an enum's variants as members; variants share the enum's visibility
+fn enum_members(e: &syn::ItemEnum) -> Vec<Member> {
+    e.variants
+        .iter()
+        .map(|v| {
+            let (line_start, line_end) = token_lines(v);
+            Member {
+                name: ident_name(&v.ident),
+                kind: MemberKind::Variant,
+                visibility: convert_visibility(&e.vis),
+                signature: normalize_signature(&variant_signature(v)),
+                doc_comment: extract_doc_comment(&v.attrs),
+                has_default: false,
+                is_method: false,
+                is_async: false,
+                is_unsafe: false,
+                is_const: false,
+                discriminant: variant_discriminant(v),
+                line_start,
+                line_end,
+            }
+        })
+        .collect()
+}
+
+/// First and last source line covered by a syntax node's tokens
+fn token_lines(node: &impl ToTokens) -> (usize, usize) {
+    let mut start = usize::MAX;
+    let mut end = 0;
+    for tt in node.to_token_stream() {
+        let span = tt.span();
+        start = start.min(span.start().line);
+        end = end.max(span.end().line);
+    }
+    if start == usize::MAX {
+        (0, 0)
+    } else {
+        (start, end)
+    }
+}
+
+/// Rule matchers of a `macro_rules!` with their expansions elided, e.g.
+/// `($ x : expr) => { ... };`. Long matchers are truncated.
+fn macro_signature(ident: &syn::Ident, tokens: &TokenStream) -> String {
+    let trees: Vec<TokenTree> = tokens.clone().into_iter().collect();
+    let mut rules = Vec::new();
+    let mut i = 0;
+    while i < trees.len() {
+        let is_rule = matches!(
+            (&trees[i], trees.get(i + 1), trees.get(i + 2), trees.get(i + 3)),
+            (
+                TokenTree::Group(_),
+                Some(TokenTree::Punct(eq)),
+                Some(TokenTree::Punct(gt)),
+                Some(TokenTree::Group(_)),
+            ) if eq.as_char() == '=' && gt.as_char() == '>'
+        );
+        if is_rule {
+            let matcher = crate::output::truncate(&trees[i].to_string(), 80);
+            rules.push(format!("    {} => {{ ... }};", matcher));
+            i += 4;
+        } else {
+            i += 1;
+        }
+    }
+
+    if rules.is_empty() {
+        format!("macro_rules! {} {{ ... }}", ident)
+    } else {
+        format!("macro_rules! {} {{\n{}\n}}", ident, rules.join("\n"))
+    }
+}
+
+/// Generate struct signature with fields, and the way the fields are declared
+fn struct_signature(s: &syn::ItemStruct) -> (String, StructKind) {
+    let derives = derive_prefix(&s.attrs);
     let vis = visibility_prefix(&s.vis);
     let generics = if s.generics.params.is_empty() {
         String::new()
@@ -378,14 +1373,16 @@ fn struct_signature(s: &syn::ItemStruct) -> String {
                 })
                 .collect();
 
-            format!(
-                "{}struct {}{}{} {{\n{}\n}}",
+            let sig = format!(
+                "{}{}struct {}{}{} {{\n{}\n}}",
+                derives,
                 vis,
                 s.ident,
                 generics,
                 where_clause,
                 field_sigs.join("\n")
-            )
+            );
+            (sig, StructKind::Named)
         }
         syn::Fields::Unnamed(fields) => {
             let field_sigs: Vec<String> = fields
@@ -397,70 +1394,99 @@ fn struct_signature(s: &syn::ItemStruct) -> String {
                 })
                 .collect();
 
-            format!(
-                "{}struct {}{}({});",
+            // A tuple struct's where clause follows its fields
+            let sig = format!(
+                "{}{}struct {}{}({}){};",
+                derives,
                 vis,
                 s.ident,
                 generics,
-                field_sigs.join(", ")
-            )
-        }
-        syn::Fields::Unit => {
-            format!("{}struct {}{};", vis, s.ident, generics)
+                field_sigs.join(", "),
+                where_clause
+            );
+            (sig, StructKind::Tuple)
         }
+        syn::Fields::Unit => (
+            format!("{}{}struct {}{}{};", derives, vis, s.ident, generics, where_clause),
+            StructKind::Unit,
+        ),
     }
 }
 
 /// Generate enum signature with variants
 fn enum_signature(e: &syn::ItemEnum) -> String {
+    let derives = derive_prefix(&e.attrs);
     let vis = visibility_prefix(&e.vis);
     let generics = if e.generics.params.is_empty() {
         String::new()
     } else {
         e.generics.to_token_stream().to_string()
     };
+    let where_clause = e
+        .generics
+        .where_clause
+        .as_ref()
+        .map(|w| format!(" {}", w.to_token_stream()))
+        .unwrap_or_default();
 
     let variant_sigs: Vec<String> = e
         .variants
         .iter()
-        .map(|v| {
-            let name = &v.ident;
-            match &v.fields {
-                syn::Fields::Named(fields) => {
-                    let fs: Vec<String> = fields
-                        .named
-                        .iter()
-                        .map(|f| {
-                            let fname = f.ident.as_ref().unwrap();
-                            let ty = f.ty.to_token_stream();
-                            format!("{}: {}", fname, ty)
-                        })
-                        .collect();
-                    format!("    {} {{ {} }},", name, fs.join(", "))
-                }
-                syn::Fields::Unnamed(fields) => {
-                    let fs: Vec<String> = fields
-                        .unnamed
-                        .iter()
-                        .map(|f| f.ty.to_token_stream().to_string())
-                        .collect();
-                    format!("    {}({}),", name, fs.join(", "))
-                }
-                syn::Fields::Unit => format!("    {},", name),
-            }
-        })
+        .map(|v| format!("    {},", variant_signature(v)))
         .collect();
 
     format!(
-        "{}enum {}{} {{\n{}\n}}",
+        "{}{}enum {}{}{} {{\n{}\n}}",
+        derives,
         vis,
         e.ident,
         generics,
+        where_clause,
         variant_sigs.join("\n")
     )
 }
 
-/// Generate trait signature with method signatures
+/// Render a single enum variant, e.g. `Io(std::io::Error)` or `Point { x: i32 }`
+fn variant_signature(v: &syn::Variant) -> String {
+    let name = &v.ident;
+    let shape = match &v.fields {
+        syn::Fields::Named(fields) => {
+            let fs: Vec<String> = fields
+                .named
+                .iter()
+                .map(|f| {
+                    let fname = f.ident.as_ref().unwrap();
+                    let ty = f.ty.to_token_stream();
+                    format!("{}: {}", fname, ty)
+                })
+                .collect();
+            format!("{} {{ {} }}", name, fs.join(", "))
+        }
+        syn::Fields::Unnamed(fields) => {
+            let fs: Vec<String> = fields
+                .unnamed
+                .iter()
+                .map(|f| f.ty.to_token_stream().to_string())
+                .collect();
+            format!("{}({})", name, fs.join(", "))
+        }
+        syn::Fields::Unit => name.to_string(),
+    };
+    match variant_discriminant(v) {
+        Some(value) => format!("{} = {}", shape, value),
+        None => shape,
+    }
+}
+
+/// The explicit discriminant of a variant, e.g. `1` for `Red = 1`
+fn variant_discriminant(v: &syn::Variant) -> Option<String> {
+    v.discriminant
+        .as_ref()
+        .map(|(_, expr)| expr.to_token_stream().to_string())
+}
+
+/// Generate trait signature with method signatures and their doc comments.
+/// Normalized piecewise so the doc text is left as written.
 fn trait_signature(t: &syn::ItemTrait) -> String {
     let vis = visibility_prefix(&t.vis);
     let unsafety = if t.unsafety.is_some() {
@@ -495,43 +1521,57 @@ fn trait_signature(t: &syn::ItemTrait) -> String {
         .items
         .iter()
         .filter_map(|item| match item {
-            syn::TraitItem::Fn(m) => {
-                let msig = trait_method_signature(m);
-                Some(format!("    {}", msig))
-            }
+            syn::TraitItem::Fn(m) => Some(format!(
+                "{}    {}",
+                method_doc_lines(&m.attrs),
+                normalize_signature(&trait_method_signature(m))
+            )),
             syn::TraitItem::Type(t) => {
-                let bounds = if t.bounds.is_empty() {
-                    String::new()
-                } else {
-                    let bs: Vec<String> = t
-                        .bounds
-                        .iter()
-                        .map(|b| b.to_token_stream().to_string())
-                        .collect();
-                    format!(": {}", bs.join(" + "))
-                };
-                Some(format!("    type {}{};", t.ident, bounds))
+                Some(format!("    {}", normalize_signature(&trait_type_signature(t))))
             }
             syn::TraitItem::Const(c) => {
-                Some(format!("    const {}: {};", c.ident, c.ty.to_token_stream()))
+                Some(format!("    {}", normalize_signature(&trait_const_signature(c))))
             }
             _ => None,
         })
         .collect();
 
-    format!(
-        "{}{}trait {}{}{}{} {{\n{}\n}}",
-        vis,
-        unsafety,
-        t.ident,
-        generics,
-        supertraits,
-        where_clause,
-        items.join("\n")
-    )
+    let header = format!(
+        "{}{}trait {}{}{}{}",
+        vis, unsafety, t.ident, generics, supertraits, where_clause
+    );
+    format!("{} {{\n{}\n}}", normalize_signature(&header), items.join("\n"))
+}
+
+fn trait_type_signature(t: &syn::TraitItemType) -> String {
+    let bounds = if t.bounds.is_empty() {
+        String::new()
+    } else {
+        let bs: Vec<String> = t
+            .bounds
+            .iter()
+            .map(|b| b.to_token_stream().to_string())
+            .collect();
+        format!(": {}", bs.join(" + "))
+    };
+    format!("type {}{};", t.ident, bounds)
+}
+
+fn trait_const_signature(c: &syn::TraitItemConst) -> String {
+    let default = c
+        .default
+        .as_ref()
+        .map(|(_, value)| format!(" = {}", value.to_token_stream()))
+        .unwrap_or_default();
+    format!("const {}: {}{};", c.ident, c.ty.to_token_stream(), default)
 }
 
 fn trait_method_signature(m: &syn::TraitItemFn) -> String {
+    let constness = if m.sig.constness.is_some() {
+        "const "
+    } else {
+        ""
+    };
     let asyncness = if m.sig.asyncness.is_some() {
         "async "
     } else {
@@ -553,7 +1593,7 @@ fn trait_method_signature(m: &syn::TraitItemFn) -> String {
         .sig
         .inputs
         .iter()
-        .map(|arg| arg.to_token_stream().to_string())
+        .map(fn_arg_signature)
         .collect();
 
     let output = match &m.sig.output {
@@ -561,18 +1601,99 @@ fn trait_method_signature(m: &syn::TraitItemFn) -> String {
         syn::ReturnType::Type(_, ty) => format!(" -> {}", ty.to_token_stream()),
     };
 
+    // Defaulted methods are optional to implement; required ones end in `;`
+    let body = if m.default.is_some() { " { ... }" } else { ";" };
+
     format!(
-        "{}{}fn {}{}({}){};",
+        "{}{}{}fn {}{}({}){}{}",
+        constness,
         asyncness,
         unsafety,
         m.sig.ident,
         generics,
         inputs.join(", "),
-        output
+        output,
+        body
     )
 }
 
+/// Whether an impl's self type is a bare type parameter of the impl itself,
+/// as in `impl<T: Display> ToString for T`
+fn is_blanket_impl(i: &syn::ItemImpl) -> bool {
+    let syn::Type::Path(ty) = &*i.self_ty else {
+        return false;
+    };
+    let Some(ident) = ty.path.get_ident().filter(|_| ty.qself.is_none()) else {
+        return false;
+    };
+    i.generics.type_params().any(|p| p.ident == *ident)
+}
+
 /// Generate impl block signature with method signatures
+fn impl_type_signature(t: &syn::ImplItemType) -> String {
+    format!("type {} = {};", t.ident, t.ty.to_token_stream())
+}
+
+fn impl_const_signature(c: &syn::ImplItemConst) -> String {
+    format!("const {}: {};", c.ident, c.ty.to_token_stream())
+}
+
+/// Methods, associated types and consts defined in an impl block
+fn impl_members(i: &syn::ItemImpl) -> Vec<Member> {
+    i.items
+        .iter()
+        .filter_map(|item| {
+            let (name, kind, vis, signature, attrs) = match item {
+                syn::ImplItem::Fn(m) => (
+                    ident_name(&m.sig.ident),
+                    MemberKind::Method,
+                    &m.vis,
+                    impl_method_signature(m),
+                    &m.attrs,
+                ),
+                syn::ImplItem::Type(ty) => (
+                    ident_name(&ty.ident),
+                    MemberKind::AssocType,
+                    &ty.vis,
+                    impl_type_signature(ty),
+                    &ty.attrs,
+                ),
+                syn::ImplItem::Const(c) => (
+                    ident_name(&c.ident),
+                    MemberKind::AssocConst,
+                    &c.vis,
+                    impl_const_signature(c),
+                    &c.attrs,
+                ),
+                _ => return None,
+            };
+            let sig = match item {
+                syn::ImplItem::Fn(m) => Some(&m.sig),
+                _ => None,
+            };
+            let is_method = sig.is_some_and(|sig| sig.receiver().is_some());
+            let (line_start, line_end) = token_lines(item);
+            Some(Member {
+                name,
+                kind,
+                visibility: convert_visibility(vis),
+                signature: normalize_signature(&signature),
+                doc_comment: extract_doc_comment(attrs),
+                has_default: false,
+                is_method,
+                is_async: sig.is_some_and(|sig| sig.asyncness.is_some()),
+                is_unsafe: sig.is_some_and(|sig| sig.unsafety.is_some()),
+                is_const: sig.is_some_and(|sig| sig.constness.is_some()),
+                discriminant: None,
+                line_start,
+                line_end,
+            })
+        })
+        .collect()
+}
+
+/// Impl block signature with method signatures and their doc comments,
+/// normalized like [`trait_signature`]
 fn impl_signature(i: &syn::ItemImpl) -> String {
     let unsafety = if i.unsafety.is_some() {
         "unsafe "
@@ -606,37 +1727,56 @@ fn impl_signature(i: &syn::ItemImpl) -> String {
         .items
         .iter()
         .filter_map(|item| match item {
-            syn::ImplItem::Fn(m) => {
-                let sig = impl_method_signature(m);
-                Some(format!("    {}", sig))
-            }
-            syn::ImplItem::Type(t) => Some(format!(
-                "    type {} = {};",
-                t.ident,
-                t.ty.to_token_stream()
-            )),
-            syn::ImplItem::Const(c) => Some(format!(
-                "    const {}: {};",
-                c.ident,
-                c.ty.to_token_stream()
+            syn::ImplItem::Fn(m) => Some(format!(
+                "{}    {}",
+                method_doc_lines(&m.attrs),
+                normalize_signature(&impl_method_signature(m))
             )),
+            syn::ImplItem::Type(t) => {
+                Some(format!("    {}", normalize_signature(&impl_type_signature(t))))
+            }
+            syn::ImplItem::Const(c) => {
+                Some(format!("    {}", normalize_signature(&impl_const_signature(c))))
+            }
             _ => None,
         })
         .collect();
 
-    format!(
-        "{}impl {}{}{}{} {{\n{}\n}}",
-        unsafety,
-        generics,
-        trait_part,
-        self_ty,
-        where_clause,
-        methods.join("\n")
-    )
+    let header = format!(
+        "{}impl{} {}{}{}",
+        unsafety, generics, trait_part, self_ty, where_clause
+    );
+    format!("{} {{\n{}\n}}", normalize_signature(&header), methods.join("\n"))
+}
+
+/// A method's doc comment as indented `///` lines for an impl or trait body
+/// signature, or nothing if it has none
+fn method_doc_lines(attrs: &[syn::Attribute]) -> String {
+    extract_doc_comment(attrs)
+        .map(|doc| doc_comment_lines(&doc, "    "))
+        .unwrap_or_default()
+}
+
+/// Render `doc` as `///` lines, each prefixed by `indent` and ending in a newline
+pub fn doc_comment_lines(doc: &str, indent: &str) -> String {
+    doc.lines()
+        .map(|line| {
+            if line.is_empty() {
+                format!("{}///\n", indent)
+            } else {
+                format!("{}/// {}\n", indent, line)
+            }
+        })
+        .collect()
 }
 
 fn impl_method_signature(m: &syn::ImplItemFn) -> String {
     let vis = visibility_prefix(&m.vis);
+    let constness = if m.sig.constness.is_some() {
+        "const "
+    } else {
+        ""
+    };
     let asyncness = if m.sig.asyncness.is_some() {
         "async "
     } else {
@@ -658,7 +1798,7 @@ fn impl_method_signature(m: &syn::ImplItemFn) -> String {
         .sig
         .inputs
         .iter()
-        .map(|arg| arg.to_token_stream().to_string())
+        .map(fn_arg_signature)
         .collect();
 
     let output = match &m.sig.output {
@@ -667,8 +1807,9 @@ fn impl_method_signature(m: &syn::ImplItemFn) -> String {
     };
 
     format!(
-        "{}{}{}fn {}{}({}){};",
+        "{}{}{}{}fn {}{}({}){};",
         vis,
+        constness,
         asyncness,
         unsafety,
         m.sig.ident,
@@ -678,31 +1819,271 @@ fn impl_method_signature(m: &syn::ImplItemFn) -> String {
     )
 }
 
-fn visibility_prefix(vis: &syn::Visibility) -> &str {
-    match vis {
-        syn::Visibility::Public(_) => "pub ",
-        syn::Visibility::Restricted(r) => {
-            let path_str = r.path.segments.iter()
-                .map(|s| s.ident.to_string())
-                .collect::<Vec<_>>()
-                .join("::");
-            match path_str.as_str() {
-                "crate" => "pub(crate) ",
-                "super" => "pub(super) ",
-                _ => "pub(crate) ",
+fn visibility_prefix(vis: &syn::Visibility) -> String {
+    convert_visibility(vis).prefix()
+}
+
+/// Keywords after which a space before `(` or `&` is kept, e.g. `impl Foo for &T`
+const SPACED_KEYWORDS: &[&str] = &[
+    "as", "const", "dyn", "for", "impl", "in", "mut", "return", "static", "where",
+];
+
+/// Tidy the spacing syn's token printing leaves in a signature so it reads
+/// like rustfmt output: `& 'a str` -> `&'a str`, `Vec < T >` -> `Vec<T>`,
+/// `name : Type` -> `name: Type`, `std :: fmt` -> `std::fmt`, `a , b` -> `a, b`.
+/// String literals are left untouched.
+pub fn normalize_signature(s: &str) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    let mut out = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '"' {
+            i = copy_string_literal(&chars, i, &mut out);
+            continue;
+        }
+        if c == ' ' && drop_space(&out, &chars[i + 1..]) {
+            i += 1;
+            continue;
+        }
+        out.push(c);
+        i += 1;
+    }
+    out
+}
+
+/// Copy the string literal opening at `chars[start]` verbatim (raw strings
+/// included) and return the index just past it
+fn copy_string_literal(chars: &[char], start: usize, out: &mut String) -> usize {
+    // `r"..."` / `r#"..."#`: the prefix has already been copied to `out`
+    let hashes = out.chars().rev().take_while(|&c| c == '#').count();
+    let is_raw = out[..out.len() - hashes].ends_with('r');
+    let closing: String = std::iter::once('"').chain("#".repeat(hashes).chars()).collect();
+
+    out.push('"');
+    let mut i = start + 1;
+    while i < chars.len() {
+        if is_raw && chars[i..].iter().take(closing.len()).copied().eq(closing.chars()) {
+            out.push_str(&closing);
+            return i + closing.len();
+        }
+        let c = chars[i];
+        out.push(c);
+        i += 1;
+        if c == '\\' && !is_raw {
+            if let Some(&escaped) = chars.get(i) {
+                out.push(escaped);
+                i += 1;
             }
+        } else if c == '"' && !is_raw {
+            break;
         }
-        syn::Visibility::Inherited => "",
+    }
+    i
+}
+
+/// Whether the space between `out` (the text so far) and `rest` is noise
+fn drop_space(out: &str, rest: &[char]) -> bool {
+    let Some(prev) = out.chars().last() else {
+        return true;
+    };
+    let Some(&next) = rest.first() else {
+        return true;
+    };
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let next_word: String = rest.iter().take_while(|c| is_word(**c)).collect();
+
+    match (prev, next) {
+        // `a , b`, `x ;`, `( a )`, `[ T ]`
+        (_, ',' | ';' | ')' | ']') | ('(' | '[', _) => true,
+        // `name : Type`, `std :: fmt`
+        (_, ':') => true,
+        (':', _) => out.ends_with("::"),
+        // `Vec < T >`, `for < 'a >`, `impl < T >`; `->` and `=>` keep their spaces
+        (_, '<') => is_word(prev),
+        ('<', _) => true,
+        (_, '>') => true,
+        // `& 'a mut T`, `* const T`, `$ x`, `? Sized`
+        ('&', _) => {
+            let before = out[..out.len() - 1].trim_end();
+            let unary = before
+                .chars()
+                .last()
+                .is_none_or(|c| !(is_word(c) || c == ')' || c == ']'))
+                || SPACED_KEYWORDS.contains(&last_word(before));
+            unary && (is_word(next) || matches!(next, '\'' | '[' | '(' | '&' | '*'))
+        }
+        ('*', _) => next_word == "const" || next_word == "mut",
+        ('$', _) | ('#', '[') => true,
+        ('?', _) => is_word(next),
+        // `Fn (u8)`, `fn (u8)`, but not `impl Trait for (A, B)`
+        (_, '(') => is_word(prev) && !SPACED_KEYWORDS.contains(&last_word(out)),
+        _ => false,
+    }
+}
+
+/// The trailing identifier or keyword of `s`, if it ends in one
+fn last_word(s: &str) -> &str {
+    let start = s
+        .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .map_or(0, |i| i + 1);
+    &s[start..]
+}
+
+/// Break the parameter list of each `fn` line of `signature` that is longer
+/// than `max_width` onto indented lines, one parameter per line, the way
+/// rustfmt does. A `max_width` of 0 leaves every line as is.
+pub fn wrap_signature(signature: &str, max_width: usize) -> String {
+    if max_width == 0 {
+        return signature.to_string();
+    }
+    signature
+        .lines()
+        .map(|line| {
+            if line.chars().count() > max_width {
+                wrap_fn_params(line).unwrap_or_else(|| line.to_string())
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `line` with its fn parameters one per line, or `None` if it isn't a fn
+/// signature or takes no parameters
+fn wrap_fn_params(line: &str) -> Option<String> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let name = line
+        .match_indices("fn ")
+        .find(|(i, _)| line[..*i].chars().last().is_none_or(|c| c == ' '))
+        .map(|(i, _)| i + 3)
+        .filter(|&i| line[i..].starts_with(is_word))?;
+
+    // The first `(` outside the generics opens the parameter list
+    let mut angles = 0;
+    let mut open = None;
+    let mut prev = ' ';
+    for (i, c) in line[name..].char_indices() {
+        match c {
+            '<' => angles += 1,
+            '>' if prev != '-' => angles -= 1,
+            '(' if angles == 0 => {
+                open = Some(name + i);
+                break;
+            }
+            _ => {}
+        }
+        prev = c;
+    }
+    let open = open?;
+
+    // Split at top-level commas up to the matching `)`
+    let mut params = Vec::new();
+    let mut depth = 0;
+    let mut start = open + 1;
+    let mut close = None;
+    let mut prev = ' ';
+    for (i, c) in line[open + 1..].char_indices() {
+        let i = open + 1 + i;
+        match c {
+            '(' | '[' | '{' | '<' => depth += 1,
+            '>' if prev == '-' => {}
+            ')' if depth == 0 => {
+                params.push(line[start..i].trim());
+                close = Some(i);
+                break;
+            }
+            ')' | ']' | '}' | '>' => depth -= 1,
+            ',' if depth == 0 => {
+                params.push(line[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+        prev = c;
+    }
+    let close = close?;
+    params.retain(|p| !p.is_empty());
+    if params.is_empty() {
+        return None;
+    }
+
+    let indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
+    let mut out = format!("{}\n", &line[..=open]);
+    for param in params {
+        out.push_str(&format!("{}    {},\n", indent, param));
+    }
+    out.push_str(&format!("{}{}", indent, &line[close..]));
+    Some(out)
+}
+
+/// A name a `pub use` re-exports
+struct Reexport<'a> {
+    name: String,
+    signature: String,
+    /// The leaf of a grouped use it comes from; `None` for a use without a group
+    leaf: Option<&'a syn::UseTree>,
+}
+
+/// The names a `pub use` re-exports. A grouped use such as
+/// `pub use engine::{eval, Error as EngineError};` becomes one `pub use` per
+/// leaf; nested groups are flattened the same way.
+fn reexports(u: &syn::ItemUse) -> Vec<Reexport<'_>> {
+    if !has_group(&u.tree) {
+        return vec![Reexport {
+            name: use_tree_name(&u.tree),
+            signature: format!("pub use {};", u.tree.to_token_stream()),
+            leaf: None,
+        }];
+    }
+
+    let leading_colon = if u.leading_colon.is_some() { "::" } else { "" };
+    let mut leaves = Vec::new();
+    collect_use_leaves(&u.tree, &mut String::new(), &mut leaves);
+    leaves
+        .into_iter()
+        .filter(|leaf| !leaf.path.is_empty())
+        .map(|leaf| {
+            // `a::{self}` re-exports `a` itself
+            let path = leaf.path.strip_suffix("::self").unwrap_or(&leaf.path);
+            let (parent, last) = match path.rsplit_once("::") {
+                Some((parent, last)) => (Some(parent), last),
+                None => (None, path),
+            };
+            let exported = leaf.rename.as_deref().unwrap_or(last);
+            let name = match parent {
+                Some(parent) => format!("{}::{}", parent, exported),
+                None => exported.to_string(),
+            };
+            let signature = match leaf.rename {
+                Some(ref rename) => format!("pub use {}{} as {};", leading_colon, path, rename),
+                None => format!("pub use {}{};", leading_colon, path),
+            };
+            Reexport {
+                name,
+                signature,
+                leaf: Some(leaf.tree),
+            }
+        })
+        .collect()
+}
+
+fn has_group(tree: &syn::UseTree) -> bool {
+    match tree {
+        syn::UseTree::Path(p) => has_group(&p.tree),
+        syn::UseTree::Group(_) => true,
+        _ => false,
     }
 }
 
 fn use_tree_name(tree: &syn::UseTree) -> String {
     match tree {
         syn::UseTree::Path(p) => {
-            format!("{}::{}", p.ident, use_tree_name(&p.tree))
+            format!("{}::{}", ident_name(&p.ident), use_tree_name(&p.tree))
         }
-        syn::UseTree::Name(n) => n.ident.to_string(),
-        syn::UseTree::Rename(r) => r.rename.to_string(),
+        syn::UseTree::Name(n) => ident_name(&n.ident),
+        syn::UseTree::Rename(r) => ident_name(&r.rename),
         syn::UseTree::Glob(_) => "*".to_string(),
         syn::UseTree::Group(_) => "{...}".to_string(),
     }
@@ -710,36 +2091,107 @@ fn use_tree_name(tree: &syn::UseTree) -> String {
 
 /// Get line numbers for an item. We use a heuristic: find the span start line
 /// and then count to the end of the item's token stream.
-fn span_lines(keyword_span: &Span, _source: &str, item: &syn::Item) -> (usize, usize) {
-    let start = keyword_span.start().line;
+///
+/// The start line is the earliest outer attribute (including `///` doc comments)
+/// when the item has any, otherwise the line of the item's keyword. Both lines
+/// come from token spans, so they never point past the end of the source, and
+/// `end >= start` always holds.
+pub fn span_lines(
+    keyword_span: &Span,
+    attrs: &[syn::Attribute],
+    _source: &str,
+    item: &impl ToTokens,
+) -> (usize, usize) {
+    let keyword_line = keyword_span.start().line;
+    let start = attrs
+        .iter()
+        .filter(|attr| matches!(attr.style, syn::AttrStyle::Outer))
+        .map(|attr| attr.pound_token.span.start().line)
+        .filter(|&line| line > 0)
+        .min()
+        .map_or(keyword_line, |line| line.min(keyword_line));
 
-    // Try to get end from the item's token stream
-    let tokens = item.to_token_stream();
-    let mut end = start;
-    for tt in tokens {
-        let span = tt.span();
-        let line = span.end().line;
-        if line > end {
-            end = line;
-        }
+    // The last line any of the item's tokens reaches. Tokens without location
+    // info report line 0, which leaves a single-line item at `start`.
+    let end = item
+        .to_token_stream()
+        .into_iter()
+        .map(|tt| tt.span().end().line)
+        .fold(start, usize::max);
+
+    (start, end)
+}
+
+/// Byte range of an item in `source`, from its first token (an outer
+/// attribute or doc comment, if it has any) to the end of its last, so that
+/// `&source[start..end]` is the item's text. Span columns count chars, so each
+/// position is mapped back through its line; lines may end in `\n` or `\r\n`.
+/// `(0, 0)` if the tokens carry no location info.
+pub fn span_bytes(source: &str, item: &impl ToTokens) -> (usize, usize) {
+    let spans: Vec<Span> = item
+        .to_token_stream()
+        .into_iter()
+        .map(|tt| tt.span())
+        .filter(|span| span.start().line > 0)
+        .collect();
+    let start = spans.iter().map(Span::start).min_by_key(|pos| (pos.line, pos.column));
+    let end = spans.iter().map(Span::end).max_by_key(|pos| (pos.line, pos.column));
+    match (start, end) {
+        (Some(start), Some(end)) => (byte_offset(source, start), byte_offset(source, end)),
+        _ => (0, 0),
     }
+}
 
-    // If we couldn't get a good end, estimate from source
-    if end <= start {
-        // Count lines in the token stream string as a fallback
-        let item_str = item.to_token_stream().to_string();
-        end = start + item_str.lines().count().saturating_sub(1);
+/// Byte offset in `source` of a span position (1-based line, column in chars)
+fn byte_offset(source: &str, pos: LineColumn) -> usize {
+    let line_start: usize = source
+        .split_inclusive('\n')
+        .take(pos.line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    let mut rest = &source[line_start..];
+    // syn skips a leading byte order mark, so first-line columns don't count it
+    if pos.line == 1 {
+        rest = rest.strip_prefix('\u{feff}').unwrap_or(rest);
     }
+    let column_start = source.len() - rest.len();
+    rest.char_indices()
+        .nth(pos.column)
+        .map_or(source.len(), |(i, _)| column_start + i)
+}
 
-    (start, end)
+/// Hash an item's token stream rather than its raw text, so reformatting
+/// (whitespace, line breaks, `//` comments, trailing commas) leaves the hash
+/// unchanged
+fn hash_item_tokens(item: &impl ToTokens) -> String {
+    let mut normalized = String::new();
+    normalize_tokens(item.to_token_stream(), &mut normalized);
+    blake3::hash(normalized.as_bytes()).to_hex().to_string()
 }
 
-fn hash_item_source(source: &str, line_start: usize, line_end: usize) -> String {
-    let lines: Vec<&str> = source.lines().collect();
-    let start = line_start.saturating_sub(1);
-    let end = line_end.min(lines.len());
-    let item_source = lines[start..end].join("\n");
-    blake3::hash(item_source.as_bytes()).to_hex().to_string()
+/// Write tokens space-separated, dropping a trailing comma before a closing delimiter
+fn normalize_tokens(tokens: TokenStream, out: &mut String) {
+    let trees: Vec<TokenTree> = tokens.into_iter().collect();
+    let len = trees.len();
+    for (i, tree) in trees.into_iter().enumerate() {
+        match tree {
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::None => ("", ""),
+                };
+                out.push_str(open);
+                out.push(' ');
+                normalize_tokens(group.stream(), out);
+                out.push_str(close);
+            }
+            TokenTree::Punct(p) if p.as_char() == ',' && i + 1 == len => continue,
+            other => out.push_str(&other.to_string()),
+        }
+        out.push(' ');
+    }
 }
 
 /// Hash the entire contents of a file
@@ -747,34 +2199,29 @@ pub fn hash_file_contents(contents: &str) -> String {
     blake3::hash(contents.as_bytes()).to_hex().to_string()
 }
 
-/// Parse use statements from a file (for dependency analysis)
-pub fn parse_use_statements(source: &str) -> Vec<String> {
+/// Parse use statements from a file (for dependency analysis), including
+/// those in `#[cfg(test)]` modules only with `include_tests`
+pub fn parse_use_statements(source: &str, include_tests: bool) -> Vec<String> {
     let syntax = match syn::parse_file(source) {
         Ok(s) => s,
         Err(_) => return Vec::new(),
     };
 
     let mut uses = Vec::new();
-    collect_use_paths(&syntax.items, &mut uses);
+    collect_use_paths(&syntax.items, include_tests, &mut uses);
     uses
 }
 
-fn collect_use_paths(items: &[syn::Item], uses: &mut Vec<String>) {
+fn collect_use_paths(items: &[syn::Item], include_tests: bool, uses: &mut Vec<String>) {
     for item in items {
         match item {
             syn::Item::Use(u) => {
                 collect_use_tree_paths(&u.tree, &mut String::new(), uses);
             }
-            syn::Item::Mod(m) => {
-                // Skip #[cfg(test)] modules
-                let is_test = m.attrs.iter().any(|attr| {
-                    attr.path().is_ident("cfg")
-                        && attr.meta.to_token_stream().to_string().contains("test")
-                });
-                if !is_test {
-                    if let Some((_, ref inner_items)) = m.content {
-                        collect_use_paths(inner_items, uses);
-                    }
+            // Skip #[cfg(test)] modules unless they're indexed
+            syn::Item::Mod(m) if include_tests || !crate::resolve::is_cfg_test(&m.attrs) => {
+                if let Some((_, ref inner_items)) = m.content {
+                    collect_use_paths(inner_items, include_tests, uses);
                 }
             }
             _ => {}
@@ -783,42 +2230,66 @@ fn collect_use_paths(items: &[syn::Item], uses: &mut Vec<String>) {
 }
 
 fn collect_use_tree_paths(tree: &syn::UseTree, prefix: &mut String, paths: &mut Vec<String>) {
+    let mut leaves = Vec::new();
+    collect_use_leaves(tree, prefix, &mut leaves);
+    paths.extend(leaves.into_iter().map(|leaf| leaf.path));
+}
+
+/// A name a `use` tree brings in: its path (`a::b::C`, or `a::b::*` for a
+/// glob), the `as` rename, if any, and the leaf of the tree it comes from
+struct UseLeaf<'a> {
+    path: String,
+    rename: Option<String>,
+    tree: &'a syn::UseTree,
+}
+
+/// Flatten a `use` tree, groups included, into its leaves
+fn collect_use_leaves<'a>(
+    tree: &'a syn::UseTree,
+    prefix: &mut String,
+    leaves: &mut Vec<UseLeaf<'a>>,
+) {
+    let leaf_path = |prefix: &str, name: &str| {
+        if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}::{}", prefix, name)
+        }
+    };
     match tree {
         syn::UseTree::Path(p) => {
             let old_len = prefix.len();
             if !prefix.is_empty() {
                 prefix.push_str("::");
             }
-            prefix.push_str(&p.ident.to_string());
-            collect_use_tree_paths(&p.tree, prefix, paths);
+            prefix.push_str(&ident_name(&p.ident));
+            collect_use_leaves(&p.tree, prefix, leaves);
             prefix.truncate(old_len);
         }
-        syn::UseTree::Name(n) => {
-            let mut full_path = prefix.clone();
-            if !full_path.is_empty() {
-                full_path.push_str("::");
-            }
-            full_path.push_str(&n.ident.to_string());
-            paths.push(full_path);
-        }
-        syn::UseTree::Rename(r) => {
-            let mut full_path = prefix.clone();
-            if !full_path.is_empty() {
-                full_path.push_str("::");
-            }
-            full_path.push_str(&r.ident.to_string());
-            paths.push(full_path);
-        }
+        syn::UseTree::Name(n) => leaves.push(UseLeaf {
+            path: leaf_path(prefix, &ident_name(&n.ident)),
+            rename: None,
+            tree,
+        }),
+        syn::UseTree::Rename(r) => leaves.push(UseLeaf {
+            path: leaf_path(prefix, &ident_name(&r.ident)),
+            rename: Some(ident_name(&r.rename)),
+            tree,
+        }),
         syn::UseTree::Glob(_) => {
             let mut full_path = prefix.clone();
             if !full_path.is_empty() {
                 full_path.push_str("::*");
             }
-            paths.push(full_path);
+            leaves.push(UseLeaf {
+                path: full_path,
+                rename: None,
+                tree,
+            });
         }
         syn::UseTree::Group(g) => {
             for tree in &g.items {
-                collect_use_tree_paths(tree, prefix, paths);
+                collect_use_leaves(tree, prefix, leaves);
             }
         }
     }
@@ -841,7 +2312,103 @@ pub fn hello(name: &str) -> String {
         assert_eq!(items[0].name, "hello");
         assert!(matches!(items[0].kind, ItemKind::Function));
         assert_eq!(items[0].visibility, Visibility::Pub);
-        assert!(items[0].signature.contains("pub fn hello(name : & str) -> String"));
+        assert!(items[0].signature.contains("pub fn hello(name: &str) -> String"));
+    }
+
+    #[test]
+    fn test_parse_function_params() {
+        let source = "pub fn evaluate(expr: &Expr, ctx: &mut EvalContext) -> Result<Value, Error> {}\npub fn noop() {}";
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        assert_eq!(
+            items[0].params,
+            vec![
+                Param {
+                    name: Some("expr".to_string()),
+                    ty: "& Expr".to_string(),
+                    is_self: false,
+                },
+                Param {
+                    name: Some("ctx".to_string()),
+                    ty: "& mut EvalContext".to_string(),
+                    is_self: false,
+                },
+            ]
+        );
+        assert_eq!(
+            items[0].return_type.as_deref(),
+            Some("Result < Value , Error >")
+        );
+        assert!(items[1].params.is_empty());
+        assert_eq!(items[1].return_type, None);
+    }
+
+    #[test]
+    fn test_parse_macro_rules() {
+        let source = r#"
+/// Exported
+#[macro_export]
+macro_rules! square {
+    ($x:expr) => { $x * $x };
+    ($x:expr, $y:expr) => { $x * $y };
+}
+
+macro_rules! local {
+    () => {};
+}
+"#;
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        assert_eq!(items[0].name, "square");
+        assert_eq!(items[0].visibility, Visibility::Pub);
+        assert_eq!(
+            items[0].signature,
+            "macro_rules! square {\n    ($x: expr) => { ... };\n    ($x: expr, $y: expr) => { ... };\n}"
+        );
+        assert_eq!(items[1].name, "local");
+        assert_eq!(items[1].visibility, Visibility::Private);
+        assert_eq!(items[1].signature, "macro_rules! local {\n    () => { ... };\n}");
+    }
+
+    #[test]
+    fn test_parse_fn_qualifiers() {
+        let source = "pub async unsafe fn fetch() {}\npub const fn answer() -> u32 { 42 }\nfn plain() {}\nunsafe impl Send for Handle {}";
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        assert!(items[0].is_async && items[0].is_unsafe && !items[0].is_const);
+        assert!(items[1].is_const && !items[1].is_async && !items[1].is_unsafe);
+        assert!(!items[2].is_async && !items[2].is_unsafe && !items[2].is_const);
+        assert!(items[3].is_unsafe);
+    }
+
+    #[test]
+    fn test_parse_method_qualifiers() {
+        let source = "impl Handle {\n    pub const fn new() -> Self { Handle }\n    \
+                      pub async unsafe fn go(&self) {}\n}\n\
+                      trait Run {\n    async fn run(&self);\n}\n";
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        let (new, go) = (&items[0].members[0], &items[0].members[1]);
+        assert_eq!(new.signature, "pub const fn new() -> Self;");
+        assert!(new.is_const && !new.is_async && !new.is_unsafe);
+        assert_eq!(go.signature, "pub async unsafe fn go(&self);");
+        assert!(go.is_async && go.is_unsafe && !go.is_const);
+        assert!(items[0].signature.contains("    pub const fn new() -> Self;\n"));
+        let run = &items[1].members[0];
+        assert!(run.is_async && !run.is_unsafe && !run.is_const);
+    }
+
+    #[test]
+    fn test_fn_params_receivers() {
+        let method: syn::ImplItemFn = syn::parse_str("fn get(&self, key: u32) {}").unwrap();
+        let params = fn_params(&method.sig);
+        assert_eq!(params[0].name.as_deref(), Some("self"));
+        assert_eq!(params[0].ty, "&self");
+        assert!(params[0].is_self);
+        assert_eq!(params[1].name.as_deref(), Some("key"));
+        assert!(!params[1].is_self);
+
+        let method: syn::ImplItemFn = syn::parse_str("fn set(&mut self) {}").unwrap();
+        assert_eq!(fn_params(&method.sig)[0].ty, "&mut self");
+
+        let method: syn::ImplItemFn = syn::parse_str("fn into(self: Box<Self>) {}").unwrap();
+        assert_eq!(fn_params(&method.sig)[0].ty, "Box < Self >");
     }
 
     #[test]
@@ -855,11 +2422,60 @@ pub struct Config {
         let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
         assert_eq!(items.len(), 1);
         assert_eq!(items[0].name, "Config");
-        assert!(matches!(items[0].kind, ItemKind::Struct));
+        assert!(matches!(items[0].kind, ItemKind::Struct { .. }));
         assert!(items[0].signature.contains("pub name: String"));
         assert!(items[0].signature.contains("port: u16"));
     }
 
+    #[test]
+    fn test_parse_tuple_struct_field_visibility() {
+        let source = "pub struct Wrapper(pub i32, String);\n\
+                      pub struct Restricted(pub(crate) Vec<u8>, pub(super) (u8, u8));\n";
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        assert_eq!(items[0].signature, "pub struct Wrapper(pub i32, String);");
+        let fields: Vec<_> = items[0]
+            .members
+            .iter()
+            .map(|m| (m.name.as_str(), m.visibility.clone(), m.signature.as_str()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![("0", Visibility::Pub, "i32"), ("1", Visibility::Private, "String")]
+        );
+
+        assert_eq!(
+            items[1].signature,
+            "pub struct Restricted(pub(crate) Vec<u8>, pub(super) (u8, u8));"
+        );
+        assert_eq!(items[1].members[0].visibility, Visibility::PubCrate);
+        assert_eq!(items[1].members[1].visibility, Visibility::PubSuper);
+        assert_eq!(items[1].members[1].signature, "(u8, u8)");
+    }
+
+    #[test]
+    fn test_parse_struct_members() {
+        let source = r#"
+pub struct Config {
+    /// The name
+    pub name: String,
+    port: u16,
+}
+"#;
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        let members = &items[0].members;
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].name, "name");
+        assert_eq!(members[0].kind, MemberKind::Field);
+        assert_eq!(members[0].visibility, Visibility::Pub);
+        assert_eq!(members[0].doc_comment.as_deref(), Some("The name"));
+        assert_eq!(members[0].line_start, 3);
+        assert_eq!(members[0].line_end, 4);
+        assert_eq!(members[1].name, "port");
+        assert_eq!(members[1].visibility, Visibility::Private);
+        assert_eq!(members[1].signature, "port: u16");
+        assert_eq!(members[1].line_start, 5);
+    }
+
     #[test]
     fn test_parse_enum() {
         let source = r#"
@@ -874,6 +2490,475 @@ pub enum Color {
         assert_eq!(items.len(), 1);
         assert_eq!(items[0].name, "Color");
         assert!(matches!(items[0].kind, ItemKind::Enum));
+        let variants: Vec<&str> = items[0].members.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(variants, vec!["Red", "Green", "Blue", "Custom"]);
+        assert_eq!(items[0].members[3].kind, MemberKind::Variant);
+        assert_eq!(items[0].members[3].signature, "Custom(u8, u8, u8)");
+        assert_eq!(items[0].members[3].line_start, 6);
+    }
+
+    #[test]
+    fn test_parse_trait_members() {
+        let source = r#"
+pub trait Evaluable {
+    /// The evaluation result
+    type Output: Clone + Send;
+    const MAX_DEPTH: usize;
+    fn evaluate(&self) -> Self::Output;
+}
+"#;
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        let members = &items[0].members;
+        assert_eq!(members.len(), 3);
+        assert_eq!(members[0].name, "Output");
+        assert_eq!(members[0].kind, MemberKind::AssocType);
+        assert_eq!(members[0].signature, "type Output: Clone + Send;");
+        assert_eq!(members[0].doc_comment.as_deref(), Some("The evaluation result"));
+        assert_eq!(members[0].line_start, 3);
+        assert_eq!(members[1].name, "MAX_DEPTH");
+        assert_eq!(members[1].kind, MemberKind::AssocConst);
+        assert_eq!(members[1].signature, "const MAX_DEPTH: usize;");
+        assert_eq!(members[2].name, "evaluate");
+        assert_eq!(members[2].kind, MemberKind::Method);
+        assert_eq!(members[2].visibility, Visibility::Pub);
+        assert!(members[2].signature.contains("fn evaluate"));
+        assert_eq!(members[2].line_start, 6);
+    }
+
+    #[test]
+    fn test_parse_trait_default_methods() {
+        let source = r#"
+pub trait Shape {
+    fn area(&self) -> f64;
+    fn describe(&self) -> String {
+        format!("area {}", self.area())
+    }
+    const SIDES: usize = 3;
+}
+"#;
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        let members = &items[0].members;
+        assert!(!members[0].has_default);
+        assert_eq!(members[0].signature, "fn area(&self) -> f64;");
+        assert!(members[1].has_default);
+        assert_eq!(members[1].signature, "fn describe(&self) -> String { ... }");
+        assert!(members[2].has_default);
+        assert_eq!(members[2].signature, "const SIDES: usize = 3;");
+        assert!(items[0]
+            .signature
+            .contains("    fn describe(&self) -> String { ... }\n    const SIDES: usize = 3;\n}"));
+    }
+
+    #[test]
+    fn test_parse_impl_method_docs() {
+        let source = r#"
+impl Widget {
+    /// Build a widget.
+    ///
+    /// Panics if `size` is zero, "unlike" `try_new`.
+    pub fn new(size: usize) -> Self { Widget { size } }
+    fn helper(&self) {}
+    const LIMIT: usize = 4;
+}
+
+pub trait Shape {
+    /// Area in square units
+    fn area(&self) -> f64;
+}
+"#;
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        assert_eq!(
+            items[0].signature,
+            "impl Widget {\n    /// Build a widget.\n    ///\n    \
+             /// Panics if `size` is zero, \"unlike\" `try_new`.\n    \
+             pub fn new(size: usize) -> Self;\n    fn helper(&self);\n    const LIMIT: usize;\n}"
+        );
+        // The member keeps its doc separately; its signature stays bare
+        assert_eq!(items[0].members[0].signature, "pub fn new(size: usize) -> Self;");
+        assert_eq!(
+            items[1].signature,
+            "pub trait Shape {\n    /// Area in square units\n    fn area(&self) -> f64;\n}"
+        );
+    }
+
+    #[test]
+    fn test_parse_method_receivers() {
+        let source = r#"
+impl Record {
+    pub fn new() -> Self { Record }
+    pub fn get(&self) -> u32 { 0 }
+    pub fn into_inner(self: Box<Self>) {}
+    const EMPTY: u32 = 0;
+}
+
+pub trait Store {
+    fn open(path: &str) -> Self;
+    fn put(&mut self, key: u32);
+}
+"#;
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        let methods = |item: &Item| -> Vec<(String, bool)> {
+            item.members.iter().map(|m| (m.name.clone(), m.is_method)).collect()
+        };
+        assert_eq!(
+            methods(&items[0]),
+            vec![
+                ("new".to_string(), false),
+                ("get".to_string(), true),
+                ("into_inner".to_string(), true),
+                ("EMPTY".to_string(), false),
+            ]
+        );
+        assert_eq!(
+            methods(&items[1]),
+            vec![("open".to_string(), false), ("put".to_string(), true)]
+        );
+    }
+
+    #[test]
+    fn test_parse_where_clauses() {
+        let source = "pub enum Tree<T> where T: Ord {\n    Leaf(T),\n    Node(Box<Tree<T>>),\n}\n\
+                      pub struct Wrapper<T>(pub T) where T: Clone;\n\
+                      pub struct Marker<T> where T: Copy;\n\
+                      pub type Pairs<K> where K: Hash = Vec<(K, K)>;\n\
+                      pub enum Plain { A }\n";
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        let wheres: Vec<Option<&str>> = items.iter().map(|i| i.where_clause.as_deref()).collect();
+        assert_eq!(
+            wheres,
+            vec![
+                Some("where T: Ord"),
+                Some("where T: Clone"),
+                Some("where T: Copy"),
+                Some("where K: Hash"),
+                None,
+            ]
+        );
+        assert_eq!(
+            items[0].signature,
+            "pub enum Tree<T> where T: Ord {\n    Leaf(T),\n    Node(Box<Tree<T>>),\n}"
+        );
+        assert_eq!(items[1].signature, "pub struct Wrapper<T>(pub T) where T: Clone;");
+        assert_eq!(items[2].signature, "pub struct Marker<T> where T: Copy;");
+        assert_eq!(items[3].signature, "pub type Pairs<K> where K: Hash = Vec<(K, K)>;");
+        assert_eq!(items[4].signature, "pub enum Plain {\n    A,\n}");
+    }
+
+    #[test]
+    fn test_parse_struct_kinds() {
+        let source = "pub struct Config {\n    pub name: String,\n    port: u16,\n}\n\
+                      pub struct Pair(pub u32, u32);\n\
+                      pub struct Marker;\n\
+                      pub struct Empty {}\n";
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        let shapes: Vec<&ItemKind> = items.iter().map(|i| &i.kind).collect();
+        assert_eq!(
+            shapes,
+            vec![
+                &ItemKind::Struct {
+                    struct_kind: StructKind::Named,
+                    field_count: 2
+                },
+                &ItemKind::Struct {
+                    struct_kind: StructKind::Tuple,
+                    field_count: 2
+                },
+                &ItemKind::Struct {
+                    struct_kind: StructKind::Unit,
+                    field_count: 0
+                },
+                &ItemKind::Struct {
+                    struct_kind: StructKind::Named,
+                    field_count: 0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_trait_object_safety() {
+        let source = r#"
+pub trait Shape {
+    type Unit;
+    fn area(&self) -> f64;
+    fn scaled(&self, factor: f64) -> Box<dyn Shape<Unit = Self::Unit>>;
+    fn new() -> Self where Self: Sized;
+}
+pub trait Visitor {
+    fn visit<T: Debug>(&self, value: &T);
+}
+pub trait Builder {
+    fn build(self: Box<Self>) -> Self;
+}
+pub trait Named: Clone + Sized {
+    fn name(&self) -> &str;
+}
+pub trait Printer {
+    fn print(&self, value: impl Display);
+}
+pub trait Generated {
+    my_macro!();
+}
+"#;
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        let safety: Vec<(&str, Option<bool>)> = items
+            .iter()
+            .map(|i| match i.kind {
+                ItemKind::Trait { object_safe } => (i.name.as_str(), object_safe),
+                _ => panic!("expected a trait"),
+            })
+            .collect();
+        assert_eq!(
+            safety,
+            vec![
+                ("Shape", Some(true)),
+                ("Visitor", Some(false)),
+                ("Builder", Some(false)),
+                ("Named", Some(false)),
+                ("Printer", Some(false)),
+                ("Generated", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_arbitrary_self_types() {
+        let source = r#"
+impl Task {
+    pub fn into_inner(mut self: Box<Self>) -> u32 { 0 }
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) {}
+}
+
+pub trait Future {
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>);
+}
+"#;
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        assert_eq!(
+            items[0].signature,
+            "impl Task {\n    pub fn into_inner(mut self: Box<Self>) -> u32;\n    \
+             fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>);\n}"
+        );
+        assert_eq!(items[0].members[0].signature, "pub fn into_inner(mut self: Box<Self>) -> u32;");
+        assert_eq!(
+            items[1].members[0].signature,
+            "fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>);"
+        );
+        assert!(items[0].members.iter().all(|m| m.is_method));
+        assert!(items[1].members[0].is_method);
+
+        let method: syn::ImplItemFn =
+            syn::parse_str("fn poll(self: Pin<&mut Self>) {}").unwrap();
+        let receiver = &fn_params(&method.sig)[0];
+        assert!(receiver.is_self);
+        assert_eq!(receiver.ty, "Pin < & mut Self >");
+    }
+
+    #[test]
+    fn test_wrap_signature() {
+        let source = "pub fn connect<F: Fn(u8) -> bool>(host: &str, port: u16, timeout: Duration, \
+                      retries: u32, filter: F, opts: HashMap<String, (u8, u8)>) -> Result<()> {}\n\
+                      impl Pool {\n    pub fn get(&self) -> Conn { todo!() }\n    \
+                      pub fn open(host: &str, port: u16, timeout: Duration, retries: u32, \
+                      tls: bool, name: String) {}\n}\n";
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+
+        assert_eq!(
+            wrap_signature(&items[0].signature, 80),
+            "pub fn connect<F: Fn(u8) -> bool>(\n    host: &str,\n    port: u16,\n    \
+             timeout: Duration,\n    retries: u32,\n    filter: F,\n    \
+             opts: HashMap<String, (u8, u8)>,\n) -> Result<()>;"
+        );
+        // Lines in an impl block keep their indentation; short ones are left alone
+        assert_eq!(
+            wrap_signature(&items[1].signature, 80),
+            "impl Pool {\n    pub fn get(&self) -> Conn;\n    pub fn open(\n        \
+             host: &str,\n        port: u16,\n        timeout: Duration,\n        \
+             retries: u32,\n        tls: bool,\n        name: String,\n    );\n}"
+        );
+        assert_eq!(wrap_signature(&items[0].signature, 0), items[0].signature);
+        assert_eq!(wrap_signature(&items[0].signature, 200), items[0].signature);
+    }
+
+    #[test]
+    fn test_parse_blanket_impl() {
+        let source = "impl<T: Display> Render for T {}\nimpl<T> Render for Vec<T> {}\n\
+                      impl Render for T {}\nimpl<T> Render for <T as Deref>::Target {}";
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        let blanket: Vec<bool> = items
+            .iter()
+            .map(|i| matches!(i.kind, ItemKind::Impl { blanket: true, .. }))
+            .collect();
+        assert_eq!(blanket, vec![true, false, false, false]);
+    }
+
+    #[test]
+    fn test_parse_restricted_visibility() {
+        let source = "pub(in crate::engine) fn step() {}\npub(super) fn up() {}\n";
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        assert_eq!(
+            items[0].visibility,
+            Visibility::PubIn("crate::engine".to_string())
+        );
+        assert_eq!(items[0].signature, "pub(in crate::engine) fn step();");
+        assert_eq!(items[1].visibility, Visibility::PubSuper);
+
+        let json = serde_json::to_string(&items[0]).unwrap();
+        let back: Item = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.visibility, items[0].visibility);
+    }
+
+    #[test]
+    fn test_parse_return_trait() {
+        let source = "pub fn values() -> impl Iterator<Item = Value> + Send { todo!() }\n\
+                      pub fn boxed() -> Result<Box<dyn std::error::Error>, ()> { todo!() }\n\
+                      pub fn plain() -> Vec<u8> { vec![] }\n";
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        assert_eq!(
+            items[0].return_trait,
+            Some(ReturnTrait::Impl(vec!["Iterator".to_string(), "Send".to_string()]))
+        );
+        assert_eq!(
+            items[1].return_trait,
+            Some(ReturnTrait::Dyn(vec!["std::error::Error".to_string()]))
+        );
+        assert_eq!(items[2].return_trait, None);
+
+        let json = serde_json::to_value(&items[0]).unwrap();
+        assert_eq!(json["return_trait"]["Impl"][0], "Iterator");
+    }
+
+    #[test]
+    fn test_crate_attributes() {
+        let source = "//! A kernel\n#![no_std]\n#![feature(let_chains, never_type)]\n\
+                      #![deny(missing_docs)]\n\n#[inline]\npub fn f() {}\n";
+        let expected = vec![
+            "#![no_std]",
+            "#![feature(let_chains, never_type)]",
+            "#![deny(missing_docs)]",
+        ];
+        assert_eq!(parse_crate_attributes(source), expected);
+
+        let file = parse_syntax(&PathBuf::from("lib.rs"), source).unwrap();
+        assert_eq!(extract_crate_attributes(&file.attrs), expected);
+        assert!(parse_crate_attributes("pub fn f() {}\n").is_empty());
+    }
+
+    #[test]
+    fn test_parse_raw_identifiers() {
+        let source =
+            "pub fn r#async() {}\nmod r#type {\n    pub struct r#match { pub r#fn: u8 }\n}\n";
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        assert_eq!(items[0].name, "async");
+        assert_eq!(items[0].signature, "pub fn r#async();");
+
+        let file: syn::File = syn::parse_str(source).unwrap();
+        let syn::Item::Mod(module) = &file.items[1] else {
+            panic!("expected a module");
+        };
+        assert_eq!(ident_name(&module.ident), "type");
+        let mut inner = Vec::new();
+        let (_, content) = module.content.as_ref().unwrap();
+        extract_items(content, &PathBuf::from("test.rs"), source, &mut inner);
+        assert_eq!(inner[0].name, "match");
+        assert_eq!(inner[0].members[0].name, "fn");
+        assert_eq!(inner[0].full_path("crate::type"), "crate::type::match");
+    }
+
+    #[test]
+    fn test_parse_supertraits() {
+        let source = "pub trait Foo: Bar + std::fmt::Debug + 'static {}\npub trait Plain {}\n";
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        assert_eq!(items[0].supertraits, vec!["Bar", "std::fmt::Debug"]);
+        assert!(items[0].signature.contains("trait Foo: Bar + std::fmt::Debug + 'static"));
+        assert!(items[1].supertraits.is_empty());
+    }
+
+    #[test]
+    fn test_normalize_signature() {
+        let cases = [
+            ("pub fn f(name : & str) -> String", "pub fn f(name: &str) -> String"),
+            ("fn get< 'a >(& 'a self) -> & 'a str", "fn get<'a>(&'a self) -> &'a str"),
+            ("fn m(& mut self , x : & mut [u8])", "fn m(&mut self, x: &mut [u8])"),
+            ("Vec < Vec < T > >", "Vec<Vec<T>>"),
+            (
+                "impl < T : Clone > From < T > for Wrapper < T >",
+                "impl<T: Clone> From<T> for Wrapper<T>",
+            ),
+            ("x : std :: fmt :: Result", "x: std::fmt::Result"),
+            ("where T : ? Sized + 'static", "where T: ?Sized + 'static"),
+            ("f : Box < dyn Fn (u8) -> u8 >", "f: Box<dyn Fn(u8) -> u8>"),
+            ("p : * const u8 , q : * mut u8", "p: *const u8, q: *mut u8"),
+            ("a : [u8 ; 4]", "a: [u8; 4]"),
+            ("for < 'a > Fn (& 'a T)", "for<'a> Fn(&'a T)"),
+            ("impl Trait for & T", "impl Trait for &T"),
+            ("impl Trait for (A , B)", "impl Trait for (A, B)"),
+            // `) *` could be a multiplication, so repetitions keep their space
+            ("($ x : expr , $ ($ y : tt) *) => { ... };", "($x: expr, $($y: tt) *) => { ... };"),
+            ("const MASK : u8 = A & B ;", "const MASK: u8 = A & B;"),
+            ("-> Result < () , Error >", "-> Result<(), Error>"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(normalize_signature(input), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_normalize_signature_keeps_literals() {
+        assert_eq!(
+            normalize_signature("const S : & str = \"a , b < c >\" ;"),
+            "const S: &str = \"a , b < c >\";"
+        );
+        assert_eq!(
+            normalize_signature("const S : & str = \"say \\\" hi , \" ;"),
+            "const S: &str = \"say \\\" hi , \";"
+        );
+        assert_eq!(
+            normalize_signature("const R : & str = r#\"a \" , b\"# ;"),
+            "const R: &str = r#\"a \" , b\"#;"
+        );
+        // Already-normalized text is unchanged
+        let tidy = "pub fn pick<'a, T: Clone>(xs: &'a [T]) -> Option<&'a T> where T: Send;";
+        assert_eq!(normalize_signature(tidy), tidy);
+    }
+
+    #[test]
+    fn test_content_hash_ignores_formatting() {
+        let original = "pub fn add(a: i32, b: i32) -> i32 { a + b }\n";
+        let reformatted = "\n\npub fn add(\n    a: i32,\n    b: i32,\n) -> i32 {\n    // sum\n    a + b\n}\n";
+        let changed = "pub fn add(a: i32, b: i32) -> i32 { a - b }\n";
+        let hash = |source| parse_file(&PathBuf::from("test.rs"), source).unwrap()[0]
+            .content_hash
+            .clone();
+        assert_eq!(hash(original), hash(reformatted));
+        assert_ne!(hash(original), hash(changed));
+    }
+
+    #[test]
+    fn test_parse_enum_discriminants() {
+        let source = r#"
+#[derive(Clone, Copy)]
+#[repr(u8)]
+pub enum Color {
+    Red = 1,
+    Green = 2 + 3,
+    Blue,
+}
+
+#[repr(C, packed)]
+pub struct Packed(u8);
+"#;
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        assert_eq!(items[0].repr.as_deref(), Some("u8"));
+        assert!(items[0]
+            .signature
+            .starts_with("#[derive(Clone, Copy)]\n#[repr(u8)]\npub enum Color {\n    Red = 1,"));
+        let members = &items[0].members;
+        assert_eq!(members[0].discriminant.as_deref(), Some("1"));
+        assert_eq!(members[1].discriminant.as_deref(), Some("2 + 3"));
+        assert_eq!(members[1].signature, "Green = 2 + 3");
+        assert_eq!(members[2].discriminant, None);
+        assert_eq!(items[1].repr.as_deref(), Some("C, packed"));
     }
 
     #[test]
@@ -890,17 +2975,367 @@ pub fn documented() {}
         assert!(doc.contains("It does important things."));
     }
 
+    #[test]
+    fn test_doc_comment_keeps_relative_indentation() {
+        let source = r#"
+///  Runs the engine:
+///
+///  ```
+///  if ready {
+///      run();
+///  }
+///  ```
+pub fn documented() {}
+"#;
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        assert_eq!(
+            items[0].doc_comment.as_deref(),
+            Some("Runs the engine:\n\n```\nif ready {\n    run();\n}\n```")
+        );
+    }
+
+    #[test]
+    fn test_doc_comment_attribute_and_block_forms() {
+        let source = r#"
+#[doc = "Attribute docs."]
+#[doc = "Second line."]
+pub fn attr_form() {}
+
+/**
+ * Block docs.
+ *   Indented.
+ */
+pub fn block_form() {}
+"#;
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        assert_eq!(
+            items[0].doc_comment.as_deref(),
+            Some("Attribute docs.\nSecond line.")
+        );
+        assert_eq!(
+            items[1].doc_comment.as_deref(),
+            Some("Block docs.\n  Indented.")
+        );
+    }
+
+    #[test]
+    fn test_line_start_includes_docs_and_attributes() {
+        let source = r#"
+/// Adds two numbers.
+///
+/// Returns their sum.
+#[inline]
+pub fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+"#;
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        assert_eq!(items.len(), 1);
+        // Line 1 is the blank line after the opening quote
+        assert_eq!(items[0].line_start, 2);
+        assert_eq!(items[0].line_end, 8);
+    }
+
+    #[test]
+    fn test_byte_ranges() {
+        let source = "\u{feff}pub const A: u8 = 1;\n\n/// Café ☕\n#[inline]\n\
+                      pub fn add(a: i32) -> i32 {\n    a + 1\n}\nimpl Foo { fn é(&self) {} }\n";
+        let crlf = source.replace('\n', "\r\n");
+        for source in [source, crlf.as_str()] {
+            let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+            let texts: Vec<&str> =
+                items.iter().map(|i| &source[i.byte_start..i.byte_end]).collect();
+            let eol = if source.contains('\r') { "\r\n" } else { "\n" };
+            assert_eq!(texts[0], "pub const A: u8 = 1;");
+            let add = ["/// Café ☕", "#[inline]", "pub fn add(a: i32) -> i32 {", "    a + 1", "}"];
+            assert_eq!(texts[1], add.join(eol));
+            assert_eq!(texts[2], "impl Foo { fn é(&self) {} }");
+        }
+    }
+
+    #[test]
+    fn test_line_bounds_at_file_edges() {
+        let lines = |source: &str| -> Vec<(usize, usize)> {
+            parse_file(&PathBuf::from("test.rs"), source)
+                .unwrap()
+                .iter()
+                .map(|i| (i.line_start, i.line_end))
+                .collect()
+        };
+
+        // Empty file, and one that is only comments
+        assert!(lines("").is_empty());
+        assert!(lines("// nothing here").is_empty());
+
+        // One-line file, with and without a trailing newline
+        assert_eq!(lines("pub struct Unit;"), vec![(1, 1)]);
+        assert_eq!(lines("pub struct Unit;\n"), vec![(1, 1)]);
+
+        // Single-line items at the very end of a file without a trailing newline
+        assert_eq!(lines("pub fn a() {}\npub fn b() {}"), vec![(1, 1), (2, 2)]);
+        assert_eq!(
+            lines("pub fn a() {\n}\n/// Doc\n#[inline] pub fn b() {}"),
+            vec![(1, 2), (3, 4)]
+        );
+
+        // Every range lies within the file
+        let source = "use std::fmt;\nconst A: u8 = 1;\nmacro_rules! m { () => {} }\n\
+                      impl A for B {}";
+        let line_count = source.lines().count();
+        for (start, end) in lines(source) {
+            assert!(1 <= start && start <= end && end <= line_count);
+        }
+    }
+
+    #[test]
+    fn test_span_lines_without_locations() {
+        // Tokens built in code carry no line info; the item is treated as one line
+        let item: syn::ItemFn = syn::parse_quote! {
+            fn generated() {
+                let x = 1;
+            }
+        };
+        let (start, end) = span_lines(&item.sig.fn_token.span, &item.attrs, "", &item);
+        assert_eq!(end, start);
+    }
+
+    #[test]
+    fn test_content_hash_stable_across_positions() {
+        let hashes = |source: &str| -> Vec<String> {
+            parse_file(&PathBuf::from("test.rs"), source)
+                .unwrap()
+                .into_iter()
+                .map(|i| i.content_hash)
+                .collect()
+        };
+        let at_end = hashes("pub fn other() {}\npub fn same(x: u8) -> u8 { x }");
+        let alone = hashes("pub fn same(x: u8) -> u8 { x }\n");
+        assert_eq!(at_end[1], alone[0]);
+        assert_eq!(hashes("pub fn same(x: u8) -> u8 { x }"), alone);
+    }
+
+    #[test]
+    fn test_parse_deprecated() {
+        let source = r#"
+#[deprecated(since = "1.2", note = "use bar")]
+pub fn foo() {}
+
+#[deprecated]
+pub fn old() {}
+
+pub fn bar() {}
+"#;
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        assert_eq!(
+            items[0].deprecated,
+            Some(DeprecationInfo {
+                since: Some("1.2".to_string()),
+                note: Some("use bar".to_string()),
+            })
+        );
+        assert_eq!(items[1].deprecated, Some(DeprecationInfo::default()));
+        assert_eq!(items[2].deprecated, None);
+    }
+
+    #[test]
+    fn test_parse_proc_macros() {
+        let source = r#"
+#[proc_macro_derive(Builder, attributes(builder))]
+pub fn derive_builder(input: TokenStream) -> TokenStream { input }
+
+#[proc_macro_attribute]
+pub fn route(attr: TokenStream, item: TokenStream) -> TokenStream { item }
+
+#[proc_macro]
+pub fn sql(input: TokenStream) -> TokenStream { input }
+
+pub fn helper() {}
+"#;
+        let items = parse_file(&PathBuf::from("lib.rs"), source).unwrap();
+        assert_eq!(
+            items[0].kind,
+            ItemKind::ProcMacro {
+                kind: ProcMacroKind::Derive("Builder".to_string())
+            }
+        );
+        assert_eq!(items[0].kind.to_string(), "proc_macro_derive(Builder)");
+        assert_eq!(
+            items[1].kind,
+            ItemKind::ProcMacro {
+                kind: ProcMacroKind::Attribute
+            }
+        );
+        assert_eq!(
+            items[1].signature,
+            "pub fn route(attr: TokenStream, item: TokenStream) -> TokenStream;"
+        );
+        assert_eq!(
+            items[2].kind,
+            ItemKind::ProcMacro {
+                kind: ProcMacroKind::FunctionLike
+            }
+        );
+        assert_eq!(items[3].kind, ItemKind::Function);
+    }
+
+    #[test]
+    fn test_parse_cfg() {
+        let source = r#"
+#[cfg(feature = "serde")]
+pub fn to_json() {}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+#[cfg(feature = "fs")]
+pub struct Watcher;
+
+#[cfg_attr(test, derive(Debug))]
+pub fn always() {}
+"#;
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        assert_eq!(items[0].cfg.as_deref(), Some("feature = \"serde\""));
+        assert_eq!(
+            items[1].cfg.as_deref(),
+            Some("all(all(unix, not(target_os = \"macos\")), feature = \"fs\")")
+        );
+        assert_eq!(items[2].cfg, None);
+    }
+
+    #[test]
+    fn test_parse_doc_hidden() {
+        let source = r#"
+#[doc(hidden)]
+pub fn __private() {}
+
+#[doc(alias = "make", hidden)]
+pub struct Builder;
+
+/// Documented
+#[doc(alias = "hidden")]
+pub fn shown() {}
+"#;
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        assert!(items[0].doc_hidden);
+        assert!(items[1].doc_hidden);
+        assert!(!items[2].doc_hidden);
+    }
+
+    #[test]
+    fn test_parse_derives_and_attributes() {
+        let source = r#"
+/// A point
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Hash)]
+#[repr(C)]
+pub struct Point {
+    x: i32,
+}
+"#;
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(
+            items[0].derives,
+            vec!["Debug", "Clone", "serde::Serialize", "Hash"]
+        );
+        assert_eq!(items[0].attributes.len(), 2);
+        assert!(items[0].attributes[0].contains("cfg_attr"));
+        assert!(items[0].attributes[1].contains("repr"));
+        assert!(items[0]
+            .signature
+            .starts_with("#[derive(Debug, Clone, serde::Serialize, Hash)]\n#[repr(C)]\npub struct Point"));
+        assert_eq!(items[0].repr.as_deref(), Some("C"));
+    }
+
+    #[test]
+    fn test_parse_extern_block() {
+        let source = r#"
+extern "C" {
+    /// Compute a checksum
+    pub fn checksum(data: *const u8, len: usize) -> u32;
+    pub static mut ERRNO: i32;
+}
+"#;
+        let items = parse_file(&PathBuf::from("test.rs"), source).unwrap();
+        assert_eq!(items.len(), 2);
+
+        assert_eq!(items[0].name, "checksum");
+        assert!(matches!(items[0].kind, ItemKind::Function));
+        assert_eq!(items[0].abi.as_deref(), Some("C"));
+        assert!(items[0].signature.starts_with("extern \"C\" { pub fn checksum("));
+        assert_eq!(items[0].line_start, 3);
+
+        assert_eq!(items[1].name, "ERRNO");
+        assert!(matches!(items[1].kind, ItemKind::Static));
+        assert_eq!(items[1].abi.as_deref(), Some("C"));
+        assert!(items[1].signature.contains("pub static mut ERRNO: i32;"));
+    }
+
     #[test]
     fn test_parse_use_statements() {
         let source = r#"
 use std::collections::HashMap;
 use crate::model::{Item, Module};
 use super::parse;
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::sample;
+}
 "#;
-        let uses = parse_use_statements(source);
+        let uses = parse_use_statements(source, false);
         assert!(uses.contains(&"std::collections::HashMap".to_string()));
         assert!(uses.contains(&"crate::model::Item".to_string()));
         assert!(uses.contains(&"crate::model::Module".to_string()));
         assert!(uses.contains(&"super::parse".to_string()));
+        assert!(!uses.contains(&"crate::fixtures::sample".to_string()));
+
+        let uses = parse_use_statements(source, true);
+        assert!(uses.contains(&"crate::fixtures::sample".to_string()));
+    }
+
+    #[test]
+    fn test_parse_grouped_reexports() {
+        let source = "pub use engine::{eval, EngineError};\n\
+                      pub use models::{convert::{self, to_text as text}, Value as V, record::*};\n\
+                      pub use config::Config;\n\
+                      pub use shapes::{\n    Circle,\n    Square,\n};\n";
+        let items = parse_file(&PathBuf::from("src/lib.rs"), source).unwrap();
+        let reexports: Vec<(&str, &str)> = items
+            .iter()
+            .map(|i| (i.name.as_str(), i.signature.as_str()))
+            .collect();
+        assert_eq!(
+            reexports,
+            vec![
+                ("engine::eval", "pub use engine::eval;"),
+                ("engine::EngineError", "pub use engine::EngineError;"),
+                ("models::convert", "pub use models::convert;"),
+                ("models::convert::text", "pub use models::convert::to_text as text;"),
+                ("models::V", "pub use models::Value as V;"),
+                ("models::record::*", "pub use models::record::*;"),
+                ("config::Config", "pub use config::Config;"),
+                ("shapes::Circle", "pub use shapes::Circle;"),
+                ("shapes::Square", "pub use shapes::Square;"),
+            ]
+        );
+        assert!(items.iter().all(|i| i.kind == ItemKind::Use));
+        assert_eq!(items[0].line_start, 1);
+        assert_eq!(items[3].line_start, 2);
+        assert_eq!((items[7].line_start, items[7].line_end), (5, 5));
+        assert_eq!((items[8].line_start, items[8].line_end), (6, 6));
+
+        // A leaf of a group spans just its own name; a plain use the statement
+        let text = |i: usize| &source[items[i].byte_start..items[i].byte_end];
+        assert_eq!(text(1), "EngineError");
+        assert_eq!(text(3), "to_text as text");
+        assert_eq!(text(5), "*");
+        assert_eq!(text(6), "pub use config::Config;");
+        let hashes: std::collections::BTreeSet<&str> =
+            items.iter().map(|i| i.content_hash.as_str()).collect();
+        assert_eq!(hashes.len(), items.len());
+
+        let paths: Vec<String> = items.iter().map(|i| i.full_path("crate")).collect();
+        assert_eq!(paths[1], "crate::EngineError");
+        assert_eq!(paths[3], "crate::text");
+        assert_eq!(paths[4], "crate::V");
     }
 }
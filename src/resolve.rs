@@ -1,160 +1,989 @@
-use anyhow::{Context, Result};
-use quote::ToTokens;
-use std::path::{Path, PathBuf};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
 
-use crate::cache::Cache;
+use crate::cache::{Cache, ModuleCache};
+use crate::error::{Result, RsmapError};
 use crate::metadata::{convert_visibility, CrateMetadata};
-use crate::model::{Module, Visibility};
+use crate::model::{CrateInfo, Item, ItemKind, Module, Visibility};
 use crate::parse;
 
+/// Where module resolution reads source files from
+pub trait SourceProvider {
+    /// Contents of the file at `path`
+    fn read(&self, path: &Path) -> Result<String>;
+    /// Whether there is a file at `path`
+    fn exists(&self, path: &Path) -> bool;
+}
+
+/// Source files read from disk
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DiskSource;
+
+impl SourceProvider for DiskSource {
+    fn read(&self, path: &Path) -> Result<String> {
+        std::fs::read_to_string(path).map_err(|e| RsmapError::io(path, e))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+}
+
+/// Source files held in memory, e.g. editor buffers, keyed by path
+#[derive(Debug, Default, Clone)]
+pub struct MemorySource {
+    files: BTreeMap<PathBuf, String>,
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace the file at `path`
+    pub fn insert(&mut self, path: impl Into<PathBuf>, contents: impl Into<String>) {
+        self.files.insert(path.into(), contents.into());
+    }
+}
+
+impl<P: Into<PathBuf>, S: Into<String>> FromIterator<(P, S)> for MemorySource {
+    fn from_iter<I: IntoIterator<Item = (P, S)>>(iter: I) -> Self {
+        let mut source = MemorySource::new();
+        for (path, contents) in iter {
+            source.insert(path, contents);
+        }
+        source
+    }
+}
+
+impl SourceProvider for MemorySource {
+    fn read(&self, path: &Path) -> Result<String> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| RsmapError::io(path, std::io::ErrorKind::NotFound.into()))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+}
+
+/// Counters for how many files were parsed versus reused from the module cache
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ResolveStats {
+    pub parsed_files: usize,
+    pub cached_files: usize,
+}
+
+/// Options that change which modules end up in the tree
+#[derive(Debug, Default)]
+pub struct ResolveOptions {
+    /// Parse `#[cfg(test)]` modules instead of skipping them
+    pub include_tests: bool,
+    /// Module files to leave out of the index
+    pub filter: FileFilter,
+    /// Fail on the first file that doesn't parse instead of indexing it empty
+    pub strict: bool,
+    /// Record absolute file paths instead of paths relative to the project root
+    pub absolute_paths: bool,
+    /// How much progress to report on stderr
+    pub verbosity: Verbosity,
+}
+
+/// How much progress resolution reports on stderr; warnings are always printed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Verbosity {
+    Quiet,
+    /// A line every `PROGRESS_INTERVAL` parsed files
+    #[default]
+    Normal,
+    /// A line for every parsed file
+    Verbose,
+}
+
+/// Parsed files between progress lines at `Verbosity::Normal`
+const PROGRESS_INTERVAL: usize = 100;
+
+impl ResolveOptions {
+    /// Stable summary of the options, mixed into module cache keys
+    pub fn fingerprint(&self) -> String {
+        format!(
+            "{}\n{}\n{}",
+            self.include_tests,
+            self.absolute_paths,
+            self.filter.fingerprint()
+        )
+    }
+}
+
+/// Decides which module files are skipped, from `--exclude` globs and
+/// optionally `.gitignore`
+#[derive(Debug, Default)]
+pub struct FileFilter {
+    project_root: PathBuf,
+    patterns: Vec<String>,
+    globs: GlobSet,
+    /// Files the gitignore-aware walk kept, when `--respect-gitignore` is set
+    gitignore_allowed: Option<BTreeSet<PathBuf>>,
+}
+
+impl FileFilter {
+    /// Build a filter; globs are matched against paths relative to `project_root`
+    pub fn new(project_root: &Path, patterns: &[String], respect_gitignore: bool) -> Result<Self> {
+        let globs = build_globs("--exclude", patterns)?;
+
+        let gitignore_allowed = respect_gitignore.then(|| {
+            ignore::WalkBuilder::new(project_root)
+                .hidden(false)
+                .require_git(false)
+                .build()
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "rs"))
+                .map(|entry| entry.into_path())
+                .collect()
+        });
+
+        Ok(FileFilter {
+            project_root: project_root.to_path_buf(),
+            patterns: patterns.to_vec(),
+            globs,
+            gitignore_allowed,
+        })
+    }
+
+    /// Whether a module file should be left out of the index
+    pub fn is_excluded(&self, file: &Path) -> bool {
+        let Ok(relative) = file.strip_prefix(&self.project_root) else {
+            // Files outside the project (e.g. `#[path = "../x.rs"]`) only match absolute globs
+            return self.globs.is_match(file);
+        };
+        if self.globs.is_match(relative) {
+            return true;
+        }
+        self.gitignore_allowed
+            .as_ref()
+            .is_some_and(|allowed| !allowed.contains(file))
+    }
+
+    fn fingerprint(&self) -> String {
+        format!(
+            "{}\n{}",
+            self.patterns.join("\n"),
+            self.gitignore_allowed.is_some()
+        )
+    }
+}
+
+/// Compile the globs given to `flag`
+pub(crate) fn build_globs(flag: &'static str, patterns: &[String]) -> Result<GlobSet> {
+    let invalid = |pattern: &str, source| RsmapError::Pattern {
+        flag,
+        pattern: pattern.to_string(),
+        source,
+    };
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).map_err(|e| invalid(pattern, e))?);
+    }
+    builder.build().map_err(|e| invalid(&patterns.join(", "), e))
+}
+
+/// Narrows the generated layers to module subtrees matching `--filter-module`
+/// globs, e.g. `engine::*`. Globs match module paths with or without the
+/// leading `crate::`.
+#[derive(Debug, Default)]
+pub struct ModuleFilter {
+    globs: GlobSet,
+}
+
+impl ModuleFilter {
+    pub fn new(patterns: &[String]) -> Result<Self> {
+        let globs = build_globs("--filter-module", patterns)?;
+        Ok(ModuleFilter { globs })
+    }
+
+    fn matches(&self, module_path: &str) -> bool {
+        let relative = module_path.strip_prefix("crate::").unwrap_or(module_path);
+        self.globs.is_match(relative) || self.globs.is_match(module_path)
+    }
+
+    /// The crates cut down to the matching modules (with their whole subtrees)
+    /// and the ancestors needed to reach them, which keep no items or `use`s.
+    /// Crates with no matching module are dropped.
+    pub fn apply(&self, crates: &[CrateInfo]) -> Vec<CrateInfo> {
+        crates
+            .iter()
+            .filter_map(|krate| {
+                Some(CrateInfo {
+                    root_module: self.prune(&krate.root_module)?,
+                    ..krate.clone()
+                })
+            })
+            .collect()
+    }
+
+    fn prune(&self, module: &Module) -> Option<Module> {
+        if self.matches(&module.path) {
+            return Some(module.clone());
+        }
+        let submodules: Vec<Module> =
+            module.submodules.iter().filter_map(|sub| self.prune(sub)).collect();
+        if submodules.is_empty() {
+            return None;
+        }
+        Some(Module {
+            items: Vec::new(),
+            submodules,
+            use_statements: Vec::new(),
+            ..module.clone()
+        })
+    }
+}
+
+/// Shared state threaded through module resolution
+struct ResolveContext<'a> {
+    project_root: &'a Path,
+    source: &'a dyn SourceProvider,
+    cache: Option<&'a Cache>,
+    module_cache: Option<&'a ModuleCache>,
+    options: &'a ResolveOptions,
+    /// Precomputed `options.fingerprint()` for module cache keys
+    fingerprint: String,
+    stats: &'a mut ResolveStats,
+}
+
+impl ResolveContext<'_> {
+    /// The path recorded for a file: relative to the project root, or absolute
+    /// with `absolute_paths`. Files outside the root keep their absolute path.
+    fn display_path(&self, file: &Path) -> PathBuf {
+        if self.options.absolute_paths {
+            return file.to_path_buf();
+        }
+        file.strip_prefix(self.project_root)
+            .unwrap_or(file)
+            .to_path_buf()
+    }
+
+    /// Check the exclusion filter, noting skipped modules on stderr
+    fn skip_excluded(&self, mod_path: &str, file: &Path) -> bool {
+        if !self.options.filter.is_excluded(file) {
+            return false;
+        }
+        if self.options.verbosity != Verbosity::Quiet {
+            eprintln!(
+                "Skipping excluded module {} ({})",
+                mod_path,
+                file.strip_prefix(self.project_root).unwrap_or(file).display()
+            );
+        }
+        true
+    }
+
+    /// Count a freshly parsed file and report progress
+    fn file_parsed(&mut self, file: &Path) {
+        self.stats.parsed_files += 1;
+        match self.options.verbosity {
+            Verbosity::Verbose => eprintln!("  Parsing {}", file.display()),
+            Verbosity::Normal if self.stats.parsed_files.is_multiple_of(PROGRESS_INTERVAL) => {
+                eprintln!("  Parsed {} file(s)...", self.stats.parsed_files)
+            }
+            _ => {}
+        }
+    }
+}
+
 /// Build the complete module tree for a crate
+///
+/// `#[cfg(test)]` modules are skipped unless `options.include_tests` is set,
+/// in which case they are parsed and marked with `is_test`. Module files
+/// matched by `options.filter` are left out entirely. A file that fails to
+/// parse becomes an empty module with `error` set, unless `options.strict`.
+///
+/// Files whose hash matches `cache` are loaded from `module_cache` instead of
+/// being re-parsed. Freshly parsed files are written back to `module_cache`.
 pub fn resolve_module_tree(
     crate_meta: &CrateMetadata,
     project_root: &Path,
     cache: Option<&Cache>,
+    module_cache: Option<&ModuleCache>,
+    options: &ResolveOptions,
+    stats: &mut ResolveStats,
+) -> Result<Module> {
+    resolve_module_tree_from(
+        &DiskSource,
+        crate_meta,
+        project_root,
+        cache,
+        module_cache,
+        options,
+        stats,
+    )
+}
+
+/// [`resolve_module_tree`], reading source files from `source` rather than disk
+pub fn resolve_module_tree_from(
+    source: &dyn SourceProvider,
+    crate_meta: &CrateMetadata,
+    project_root: &Path,
+    cache: Option<&Cache>,
+    module_cache: Option<&ModuleCache>,
+    options: &ResolveOptions,
+    stats: &mut ResolveStats,
 ) -> Result<Module> {
-    let root_file = &crate_meta.root_file;
-    let source = std::fs::read_to_string(root_file)
-        .with_context(|| format!("Cannot read root file: {}", root_file.display()))?;
+    let mut ctx = ResolveContext {
+        project_root,
+        source,
+        cache,
+        module_cache,
+        options,
+        fingerprint: options.fingerprint(),
+        stats,
+    };
+
+    let mut root_module = resolve_file_module(
+        &mut ctx,
+        &crate_meta.root_file,
+        "crate".to_string(),
+        Visibility::Pub,
+        None,
+        false,
+        false,
+    )?;
+
+    link_reexports(&mut root_module);
+    expand_glob_reexports(&mut root_module);
+
+    Ok(root_module)
+}
 
+/// `.rs` files in each crate's source directory that no `mod` declaration
+/// reaches, relative to `project_root`.
+///
+/// Resolves the crates again with test modules included and no exclusions,
+/// so only files that are truly unreferenced are reported. Crate roots, build
+/// scripts and files cargo picks up as targets on its own (`src/main.rs`,
+/// `src/lib.rs`, `src/bin/`) are never reported.
+pub fn find_orphan_files(crates: &[CrateMetadata], project_root: &Path) -> Result<Vec<PathBuf>> {
+    let options = ResolveOptions {
+        include_tests: true,
+        absolute_paths: true,
+        verbosity: Verbosity::Quiet,
+        ..Default::default()
+    };
+
+    let mut referenced = BTreeSet::new();
+    let mut source_dirs = BTreeSet::new();
+    for crate_meta in crates {
+        let root = resolve_module_tree(
+            crate_meta,
+            project_root,
+            None,
+            None,
+            &options,
+            &mut ResolveStats::default(),
+        )?;
+        for module in root.all_modules() {
+            referenced.insert(module.file_path.clone());
+            // Files pulled in with `include!` aren't orphans either
+            referenced.extend(module.items.iter().map(|item| item.file_path.clone()));
+        }
+        referenced.insert(crate_meta.root_file.clone());
+        if let Some(dir) = crate_meta.root_file.parent() {
+            source_dirs.insert(dir.to_path_buf());
+        }
+    }
+
+    let mut orphans = BTreeSet::new();
+    for dir in &source_dirs {
+        for entry in walkdir::WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "rs")
+                && !referenced.contains(path)
+                && !is_implicit_target(path)
+            {
+                orphans.insert(path.strip_prefix(project_root).unwrap_or(path).to_path_buf());
+            }
+        }
+    }
+    Ok(orphans.into_iter().collect())
+}
+
+/// Files cargo treats as targets without a `mod` declaration: build scripts,
+/// `src/main.rs`, `src/lib.rs` and anything under `src/bin/`
+fn is_implicit_target(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    let in_src = path.parent().is_some_and(|p| p.ends_with("src"));
+    name == "build.rs"
+        || (in_src && (name == "main.rs" || name == "lib.rs"))
+        || path.ancestors().any(|a| a.ends_with("src/bin"))
+}
+
+/// Build the module for a single source file and everything below it
+fn resolve_file_module(
+    ctx: &mut ResolveContext,
+    file: &Path,
+    mod_path: String,
+    visibility: Visibility,
+    outer_doc: Option<String>,
+    is_test: bool,
+    path_attr: bool,
+) -> Result<Module> {
+    let source = ctx.source.read(file)?;
     let file_hash = parse::hash_file_contents(&source);
 
-    // Check cache - if hash matches, we could skip parsing, but we still
-    // need the module tree structure. For now, always parse but use cache
-    // for staleness detection in the annotation system.
-    let syntax = syn::parse_file(&source)
-        .with_context(|| format!("Failed to parse {}", root_file.display()))?;
+    let relative_path = ctx.display_path(file);
+    let cache_key = ModuleCache::key(&relative_path, &file_hash, &ctx.fingerprint);
 
-    let doc_comment = parse::extract_inner_doc_comment(&syntax.attrs);
-    let items = parse::parse_file(root_file, &source)?;
+    // Unchanged file: reuse the module parsed on a previous run
+    let unchanged = ctx.cache.is_some_and(|c| {
+        c.is_file_unchanged(&relative_path.display().to_string(), &file_hash)
+    });
+    if unchanged {
+        let cached = ctx.module_cache.and_then(|mc| mc.load(&cache_key));
+        // A file that now exists for one of its `mod` declarations means re-parsing
+        if let Some(mut module) = cached.filter(|m| !mod_file_appeared(ctx, m)) {
+            ctx.stats.cached_files += 1;
+            module.path = mod_path;
+            // The `mod` declaration's docs may have changed even though this file didn't
+            module.doc_comment = module.inner_doc.clone().or_else(|| outer_doc.clone());
+            module.outer_doc = outer_doc;
+            module.visibility = visibility;
+            module.is_test = is_test;
+            module.path_attr = path_attr;
+            refresh_external_submodules(ctx, &mut module)?;
+            return Ok(module);
+        }
+    }
 
-    let relative_path = root_file
-        .strip_prefix(project_root)
-        .unwrap_or(root_file)
-        .to_path_buf();
+    ctx.file_parsed(&relative_path);
+    let syntax = match parse::parse_syntax(file, &source) {
+        Ok(syntax) => syntax,
+        Err(err) if !ctx.options.strict => {
+            // Keep indexing the rest of the crate; the module stays in the tree, empty
+            eprintln!("Warning: {:#}", err);
+            return Ok(Module {
+                path: mod_path,
+                file_path: relative_path,
+                file_hash,
+                doc_comment: outer_doc.clone(),
+                inner_doc: None,
+                outer_doc,
+                visibility,
+                items: Vec::new(),
+                submodules: Vec::new(),
+                use_statements: Vec::new(),
+                is_inline: false,
+                is_test,
+                loc: source.lines().count(),
+                path_attr,
+                error: Some(format!("{:#}", err)),
+                missing_mod_files: Vec::new(),
+            });
+        }
+        Err(err) => return Err(err),
+    };
 
-    let use_statements = parse::parse_use_statements(&source);
+    let inner_doc = parse::extract_inner_doc_comment(&syntax.attrs);
+    let mut items = Vec::new();
+    parse::extract_items(&syntax.items, &relative_path, &source, &mut items);
+    let includes = include_targets(&syntax.items, file);
+    for included in includes.iter().flatten() {
+        items.extend(parse_included_file(ctx, included)?);
+    }
+    let use_statements = parse::parse_use_statements(&source, ctx.options.include_tests);
 
-    let mut root_module = Module {
-        path: "crate".to_string(),
+    let mut module = Module {
+        path: mod_path,
         file_path: relative_path,
         file_hash,
-        doc_comment,
-        visibility: Visibility::Pub,
+        doc_comment: inner_doc.clone().or_else(|| outer_doc.clone()),
+        inner_doc,
+        outer_doc,
+        visibility,
         items,
         submodules: Vec::new(),
         use_statements,
         is_inline: false,
+        is_test,
+        loc: source.lines().count(),
+        path_attr,
+        error: None,
+        missing_mod_files: Vec::new(),
     };
 
-    // Resolve submodules
-    resolve_submodules(
-        &syntax.items,
-        &mut root_module,
-        root_file,
-        project_root,
-        cache,
-    )?;
+    let dir = module_dir(file, module.path == "crate" || path_attr);
+    resolve_submodules(ctx, &syntax.items, &mut module, file, &dir)?;
 
-    Ok(root_module)
+    // The file hash doesn't cover included files, so a module with `include!`
+    // is never reused from the cache
+    if let Some(mc) = ctx.module_cache.filter(|_| includes.is_empty()) {
+        if let Err(e) = mc.store(&cache_key, &module) {
+            eprintln!("Warning: Failed to cache module {}: {:#}", module.path, e);
+        }
+    }
+
+    Ok(module)
+}
+
+/// The files named by module-level `include!(...)` invocations, resolved
+/// against the including file's directory. The argument may be a string
+/// literal or built from `concat!` and `env!`; an entry is `None` when its
+/// path can't be worked out, e.g. `env!("OUT_DIR")` outside a build.
+fn include_targets(syn_items: &[syn::Item], file: &Path) -> Vec<Option<PathBuf>> {
+    let dir = file.parent().unwrap_or(Path::new("."));
+    syn_items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Macro(m)
+                if m.ident.is_none()
+                    && m.mac.path.segments.last().is_some_and(|s| s.ident == "include") =>
+            {
+                let arg: Option<syn::Expr> = m.mac.parse_body().ok();
+                Some(arg.as_ref().and_then(eval_str_expr).map(|path| dir.join(path)))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Best-effort value of a string expression made of literals, `concat!(..)`
+/// and `env!("VAR")` (read from rsmap's own environment)
+fn eval_str_expr(expr: &syn::Expr) -> Option<String> {
+    match expr {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(s),
+            ..
+        }) => Some(s.value()),
+        syn::Expr::Macro(m) => {
+            let args = m
+                .mac
+                .parse_body_with(
+                    syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated,
+                )
+                .ok()?;
+            match m.mac.path.segments.last()?.ident.to_string().as_str() {
+                "concat" => args.iter().map(eval_str_expr).collect(),
+                "env" => std::env::var(eval_str_expr(args.first()?)?).ok(),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Items of a file pulled in with `include!`, recorded against that file.
+/// A missing file is skipped; one that doesn't parse is skipped with a
+/// warning unless `strict`.
+fn parse_included_file(ctx: &mut ResolveContext, file: &Path) -> Result<Vec<Item>> {
+    let file = normalize_path(file);
+    let Ok(source) = ctx.source.read(&file) else {
+        if ctx.options.verbosity == Verbosity::Verbose {
+            eprintln!("  Skipping missing include {}", file.display());
+        }
+        return Ok(Vec::new());
+    };
+    let relative_path = ctx.display_path(&file);
+    ctx.file_parsed(&relative_path);
+    match parse::parse_file(&relative_path, &source) {
+        Ok(items) => Ok(items),
+        Err(err) if !ctx.options.strict => {
+            eprintln!("Warning: {:#}", err);
+            Ok(Vec::new())
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// `path` with `.` and `..` components folded away lexically, so that it
+/// works for any `SourceProvider`
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(out.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                out.pop();
+            }
+            component => out.push(component),
+        }
+    }
+    out
+}
+
+/// Whether a file has appeared for a `mod` declaration of `module` (or of an
+/// inline module in it) that had none when it was cached
+fn mod_file_appeared(ctx: &ResolveContext, module: &Module) -> bool {
+    module.missing_mod_files.iter().any(|file| ctx.source.exists(&ctx.project_root.join(file)))
+        || module.submodules.iter().any(|sub| sub.is_inline && mod_file_appeared(ctx, sub))
+}
+
+/// Re-resolve the file-backed submodules of a module loaded from the cache,
+/// since their files may have changed independently of the parent.
+fn refresh_external_submodules(ctx: &mut ResolveContext, module: &mut Module) -> Result<()> {
+    let mut refreshed = Vec::with_capacity(module.submodules.len());
+    for mut sub in std::mem::take(&mut module.submodules) {
+        if sub.is_inline {
+            refresh_external_submodules(ctx, &mut sub)?;
+            refreshed.push(sub);
+            continue;
+        }
+
+        let file = ctx.project_root.join(&sub.file_path);
+        if !ctx.source.exists(&file) {
+            eprintln!(
+                "Warning: Cannot find module file for `mod {}` ({})",
+                sub.short_name(),
+                file.display()
+            );
+            continue;
+        }
+        if ctx.skip_excluded(&sub.path, &file) {
+            continue;
+        }
+        refreshed.push(resolve_file_module(
+            ctx,
+            &file,
+            sub.path,
+            sub.visibility,
+            sub.outer_doc,
+            sub.is_test,
+            sub.path_attr,
+        )?);
+    }
+    module.submodules = refreshed;
+    Ok(())
+}
+
+/// Expand `pub use path::*` re-exports against the already-parsed target module.
+///
+/// The signature gains a trailing comment with the count and names of the
+/// re-exported public items. Globs whose target isn't part of the crate (e.g.
+/// external crates) are left as the literal `*`.
+pub fn expand_glob_reexports(root: &mut Module) {
+    let mut public_names: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for module in root.all_modules() {
+        public_names.insert(module.path.clone(), module_public_names(module));
+    }
+    apply_glob_expansions(root, &public_names);
+}
+
+fn apply_glob_expansions(module: &mut Module, public_names: &BTreeMap<String, Vec<String>>) {
+    for item in module.items.iter_mut() {
+        if item.kind != ItemKind::Use {
+            continue;
+        }
+        let Some(prefix) = item.name.strip_suffix("::*") else {
+            continue;
+        };
+        let Some(target) = resolve_use_target(&module.path, prefix, public_names) else {
+            continue;
+        };
+        let names = &public_names[&target];
+        item.signature = format!(
+            "{} // ({} items: {})",
+            item.signature,
+            names.len(),
+            names.join(", ")
+        );
+    }
+
+    for sub in module.submodules.iter_mut() {
+        apply_glob_expansions(sub, public_names);
+    }
+}
+
+/// Re-exports followed before giving up, in case of a `pub use` cycle
+const MAX_REEXPORT_DEPTH: usize = 16;
+
+/// Point every single-item `pub use` at the item it re-exports.
+///
+/// Chains of re-exports are followed to the definition, which is recorded in
+/// `reexport_of`; the signature gains a trailing comment with its path and
+/// location. Renames (`as`) are resolved through the original name. Globs,
+/// groups and targets outside the crate are left unlinked.
+pub fn link_reexports(root: &mut Module) {
+    let mut modules = BTreeMap::new();
+    // Full path -> where the item is defined
+    let mut definitions: BTreeMap<String, (PathBuf, usize)> = BTreeMap::new();
+    // Full path of the re-exported name -> (re-exporting module, `use` path)
+    let mut reexports: BTreeMap<String, (String, String)> = BTreeMap::new();
+    for module in root.all_modules() {
+        modules.insert(module.path.clone(), ());
+        for item in &module.items {
+            match item.kind {
+                ItemKind::Impl { .. } => {}
+                ItemKind::Use => {
+                    let Some(source) = use_source_path(&item.signature) else {
+                        continue;
+                    };
+                    reexports.insert(item.full_path(&module.path), (module.path.clone(), source));
+                }
+                _ => {
+                    definitions.insert(
+                        item.full_path(&module.path),
+                        (item.file_path.clone(), item.line_start),
+                    );
+                }
+            }
+        }
+    }
+
+    let resolve = |module_path: &str, source: &str| -> Option<String> {
+        let (mut module_path, mut source) = (module_path.to_string(), source.to_string());
+        for _ in 0..MAX_REEXPORT_DEPTH {
+            let (prefix, name) = source.rsplit_once("::").unwrap_or(("self", &source));
+            let target = resolve_use_target(&module_path, prefix, &modules)?;
+            let path = format!("{}::{}", target, name);
+            if definitions.contains_key(&path) {
+                return Some(path);
+            }
+            (module_path, source) = reexports.get(&path)?.clone();
+        }
+        None
+    };
+    apply_reexport_links(root, &resolve, &definitions);
+}
+
+fn apply_reexport_links(
+    module: &mut Module,
+    resolve: &dyn Fn(&str, &str) -> Option<String>,
+    definitions: &BTreeMap<String, (PathBuf, usize)>,
+) {
+    for item in module.items.iter_mut() {
+        if item.kind != ItemKind::Use {
+            continue;
+        }
+        let Some(source) = use_source_path(&item.signature) else {
+            continue;
+        };
+        let Some(origin) = resolve(&module.path, &source) else {
+            continue;
+        };
+        let (file, line) = &definitions[&origin];
+        item.signature = format!(
+            "{} // -> {} ({}:{})",
+            item.signature,
+            origin,
+            file.display(),
+            line
+        );
+        item.reexport_of = Some(origin);
+    }
+
+    for sub in module.submodules.iter_mut() {
+        apply_reexport_links(sub, resolve, definitions);
+    }
+}
+
+/// The path a single-item `use` imports, with any rename undone:
+/// `pub use a::b::C as D;` -> `a::b::C`. `None` for globs and groups.
+fn use_source_path(signature: &str) -> Option<String> {
+    let item: syn::ItemUse = syn::parse_str(signature).ok()?;
+    let mut segments = Vec::new();
+    let mut tree = &item.tree;
+    loop {
+        match tree {
+            syn::UseTree::Path(p) => {
+                segments.push(parse::ident_name(&p.ident));
+                tree = &p.tree;
+            }
+            syn::UseTree::Name(n) => {
+                segments.push(parse::ident_name(&n.ident));
+                return Some(segments.join("::"));
+            }
+            syn::UseTree::Rename(r) => {
+                segments.push(parse::ident_name(&r.ident));
+                return Some(segments.join("::"));
+            }
+            syn::UseTree::Glob(_) | syn::UseTree::Group(_) => return None,
+        }
+    }
 }
 
+/// Names a glob import of this module would bring into scope
+fn module_public_names(module: &Module) -> Vec<String> {
+    let mut names: Vec<String> = module
+        .items
+        .iter()
+        .filter(|i| i.visibility == Visibility::Pub)
+        .filter(|i| !matches!(i.kind, ItemKind::Impl { .. }))
+        .filter(|i| !i.name.ends_with('*'))
+        .map(|i| i.name.rsplit("::").next().unwrap_or(&i.name).to_string())
+        .collect();
+    names.extend(
+        module
+            .submodules
+            .iter()
+            .filter(|s| s.visibility == Visibility::Pub)
+            .map(|s| s.short_name().to_string()),
+    );
+    names
+}
+
+/// Resolve the module a `use` prefix points at, relative to `module_path`
+fn resolve_use_target<V>(
+    module_path: &str,
+    prefix: &str,
+    known: &BTreeMap<String, V>,
+) -> Option<String> {
+    let mut segments = prefix.split("::").peekable();
+    let mut base: Vec<&str> = module_path.split("::").collect();
+    let mut absolute = false;
+
+    match segments.peek().copied() {
+        Some("crate") => {
+            segments.next();
+            base = vec!["crate"];
+            absolute = true;
+        }
+        Some("self") => {
+            segments.next();
+        }
+        Some("super") => {
+            while segments.peek() == Some(&"super") {
+                segments.next();
+                if base.len() > 1 {
+                    base.pop();
+                }
+            }
+        }
+        _ => {}
+    }
+
+    let rest: Vec<&str> = segments.collect();
+    let mut candidates = vec![base.iter().chain(rest.iter()).copied().collect::<Vec<_>>()];
+    if !absolute {
+        // 2015-style paths are relative to the crate root
+        candidates.push(std::iter::once("crate").chain(rest.iter().copied()).collect());
+    }
+
+    candidates
+        .into_iter()
+        .map(|c| c.join("::"))
+        .find(|c| known.contains_key(c))
+}
+
+/// Directory that `mod foo;` declarations in `file` resolve against: the
+/// file's own directory for crate roots, `mod.rs` and `#[path]` files,
+/// otherwise a directory named after the file (`src/utils.rs` -> `src/utils/`)
+fn module_dir(file: &Path, mod_rs: bool) -> PathBuf {
+    let dir = file.parent().unwrap_or(Path::new("."));
+    if mod_rs || file.file_name().is_some_and(|n| n == "mod.rs") {
+        return dir.to_path_buf();
+    }
+    match file.file_stem() {
+        Some(stem) => dir.join(stem),
+        None => dir.to_path_buf(),
+    }
+}
+
+/// Resolve the `mod` declarations in `syn_items`. `module_dir` is where
+/// `mod foo;` files live for this module (see [`module_dir`]); inline modules
+/// extend it with their own name.
 fn resolve_submodules(
+    ctx: &mut ResolveContext,
     syn_items: &[syn::Item],
     parent_module: &mut Module,
     parent_file: &Path,
-    project_root: &Path,
-    cache: Option<&Cache>,
+    module_dir: &Path,
 ) -> Result<()> {
-    let parent_dir = parent_file.parent().unwrap_or(Path::new("."));
+    // `#[path]` is relative to the file's directory, except inside inline
+    // modules where it is relative to the inline module's directory
+    let path_base = if parent_module.is_inline {
+        module_dir
+    } else {
+        parent_file.parent().unwrap_or(Path::new("."))
+    };
 
     for item in syn_items {
         if let syn::Item::Mod(mod_item) = item {
-            let mod_name = mod_item.ident.to_string();
+            // `mod r#type;` lives in type.rs and is indexed as `type`
+            let mod_name = parse::ident_name(&mod_item.ident);
 
-            // Skip test modules
-            if is_cfg_test(&mod_item.attrs) {
+            // Skip test modules unless requested; children of a test module are tests too
+            let is_test = parent_module.is_test || is_cfg_test(&mod_item.attrs);
+            if is_test && !ctx.options.include_tests {
                 continue;
             }
 
             let visibility = convert_visibility(&mod_item.vis);
             let doc_comment = parse::extract_doc_comment(&mod_item.attrs);
+            let mod_path = format!("{}::{}", parent_module.path, mod_name);
 
             if let Some((_, ref inner_items)) = mod_item.content {
                 // Inline module: mod foo { ... }
-                let source = std::fs::read_to_string(parent_file).unwrap_or_default();
-                let inline_items = extract_inline_module_items(inner_items, parent_file, &source)?;
+                let source = ctx.source.read(parent_file).unwrap_or_default();
+                let relative_path = ctx.display_path(parent_file);
+                let mut inline_items = Vec::new();
+                parse::extract_items(inner_items, &relative_path, &source, &mut inline_items);
 
-                let mod_path = format!("{}::{}", parent_module.path, mod_name);
-                let relative_path = parent_file
-                    .strip_prefix(project_root)
-                    .unwrap_or(parent_file)
-                    .to_path_buf();
+                let (inline_start, inline_end) = parse::span_lines(
+                    &mod_item.mod_token.span,
+                    &mod_item.attrs,
+                    &source,
+                    mod_item,
+                );
 
                 let mut sub_module = Module {
                     path: mod_path,
                     file_path: relative_path,
                     file_hash: parent_module.file_hash.clone(), // shares parent file
+                    // Both kinds of docs are in the parent's file, so they are never split
                     doc_comment,
+                    inner_doc: None,
+                    outer_doc: None,
                     visibility,
                     items: inline_items,
                     submodules: Vec::new(),
                     use_statements: Vec::new(), // inline modules inherit parent's scope
                     is_inline: true,
+                    is_test,
+                    loc: inline_end - inline_start + 1,
+                    path_attr: false,
+                    error: None,
+                    missing_mod_files: Vec::new(),
                 };
 
                 // Recursively resolve nested inline modules
-                resolve_submodules(inner_items, &mut sub_module, parent_file, project_root, cache)?;
+                resolve_submodules(
+                    ctx,
+                    inner_items,
+                    &mut sub_module,
+                    parent_file,
+                    &module_dir.join(&mod_name),
+                )?;
 
                 parent_module.submodules.push(sub_module);
             } else {
                 // External module: mod foo; -> look for foo.rs or foo/mod.rs
                 let custom_path = get_path_attribute(&mod_item.attrs);
-                let mod_file = resolve_mod_file(parent_dir, &mod_name, custom_path.as_deref())?;
+                let base_dir = if custom_path.is_some() {
+                    path_base
+                } else {
+                    module_dir
+                };
+                let mod_file =
+                    resolve_mod_file(ctx.source, base_dir, &mod_name, custom_path.as_deref())?;
 
                 if let Some(mod_file) = mod_file {
-                    let source = std::fs::read_to_string(&mod_file).with_context(|| {
-                        format!("Cannot read module file: {}", mod_file.display())
-                    })?;
-                    let file_hash = parse::hash_file_contents(&source);
-
-                    let syntax = syn::parse_file(&source).with_context(|| {
-                        format!("Failed to parse {}", mod_file.display())
-                    })?;
-
-                    let mod_doc = parse::extract_inner_doc_comment(&syntax.attrs)
-                        .or(doc_comment);
-                    let items = parse::parse_file(&mod_file, &source)?;
-
-                    let mod_path = format!("{}::{}", parent_module.path, mod_name);
-                    let relative_path = mod_file
-                        .strip_prefix(project_root)
-                        .unwrap_or(&mod_file)
-                        .to_path_buf();
-
-                    let use_statements = parse::parse_use_statements(&source);
-
-                    let mut sub_module = Module {
-                        path: mod_path,
-                        file_path: relative_path,
-                        file_hash,
-                        doc_comment: mod_doc,
-                        visibility,
-                        items,
-                        submodules: Vec::new(),
-                        use_statements,
-                        is_inline: false,
-                    };
-
-                    // Recursively resolve
-                    resolve_submodules(
-                        &syntax.items,
-                        &mut sub_module,
+                    if ctx.skip_excluded(&mod_path, &mod_file) {
+                        continue;
+                    }
+                    let sub_module = resolve_file_module(
+                        ctx,
                         &mod_file,
-                        project_root,
-                        cache,
+                        mod_path,
+                        visibility,
+                        doc_comment,
+                        is_test,
+                        custom_path.is_some(),
                     )?;
-
                     parent_module.submodules.push(sub_module);
                 } else {
                     eprintln!(
@@ -162,6 +991,11 @@ fn resolve_submodules(
                         mod_name,
                         parent_file.display()
                     );
+                    let candidates =
+                        mod_file_candidates(base_dir, &mod_name, custom_path.as_deref());
+                    parent_module
+                        .missing_mod_files
+                        .extend(candidates.iter().map(|file| ctx.display_path(file)));
                 }
             }
         }
@@ -170,67 +1004,68 @@ fn resolve_submodules(
     Ok(())
 }
 
-/// Extract items from an inline module's content
-fn extract_inline_module_items(
-    inner_items: &[syn::Item],
-    file_path: &Path,
-    _source: &str,
-) -> Result<Vec<crate::model::Item>> {
-    // We need to parse items directly from the syn items
-    let mut items = Vec::new();
-    // Re-use the same extraction logic by converting items back to source
-    // This is a simplification - for inline modules we extract from the parent file's AST
-    for item in inner_items {
-        let item_source = item.to_token_stream().to_string();
-        if let Ok(mut parsed) = parse::parse_file(file_path, &item_source) {
-            items.append(&mut parsed);
-        }
-    }
-    Ok(items)
-}
-
 /// Resolve the file path for `mod foo;` declaration
 fn resolve_mod_file(
+    source: &dyn SourceProvider,
     parent_dir: &Path,
     mod_name: &str,
     custom_path: Option<&str>,
 ) -> Result<Option<PathBuf>> {
-    if let Some(custom) = custom_path {
-        let path = parent_dir.join(custom);
-        if path.exists() {
-            return Ok(Some(path));
-        }
-        return Ok(None);
-    }
-
-    // Try mod_name.rs first
-    let file_path = parent_dir.join(format!("{}.rs", mod_name));
-    if file_path.exists() {
-        return Ok(Some(file_path));
-    }
+    Ok(mod_file_candidates(parent_dir, mod_name, custom_path)
+        .into_iter()
+        .find(|path| source.exists(path)))
+}
 
-    // Try mod_name/mod.rs
-    let dir_path = parent_dir.join(mod_name).join("mod.rs");
-    if dir_path.exists() {
-        return Ok(Some(dir_path));
+/// The files a `mod` declaration may load, in the order they're tried: the
+/// `#[path]` if given, else `mod_name.rs` then `mod_name/mod.rs`
+fn mod_file_candidates(
+    parent_dir: &Path,
+    mod_name: &str,
+    custom_path: Option<&str>,
+) -> Vec<PathBuf> {
+    match custom_path {
+        Some(custom) => vec![parent_dir.join(custom)],
+        None => vec![
+            parent_dir.join(format!("{}.rs", mod_name)),
+            parent_dir.join(mod_name).join("mod.rs"),
+        ],
     }
-
-    Ok(None)
 }
 
-/// Check if a module has #[cfg(test)]
-fn is_cfg_test(attrs: &[syn::Attribute]) -> bool {
+/// Check if a module is only compiled for tests, e.g. `#[cfg(test)]` or
+/// `#[cfg(all(test, feature = "x"))]`. `#[cfg(not(test))]` and feature gates
+/// such as `#[cfg(feature = "test")]` do not count.
+pub fn is_cfg_test(attrs: &[syn::Attribute]) -> bool {
     attrs.iter().any(|attr| {
-        if attr.path().is_ident("cfg") {
-            if let Ok(meta) = attr.parse_args::<syn::Ident>() {
-                return meta == "test";
+        attr.path().is_ident("cfg")
+            && attr
+                .parse_args::<syn::Meta>()
+                .map(|pred| cfg_requires_test(&pred))
+                .unwrap_or(false)
+    })
+}
+
+/// Whether a cfg predicate can only be true when `test` is set
+fn cfg_requires_test(pred: &syn::Meta) -> bool {
+    match pred {
+        syn::Meta::Path(p) => p.is_ident("test"),
+        syn::Meta::List(list) => {
+            let Ok(args) = list.parse_args_with(
+                syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated,
+            ) else {
+                return false;
+            };
+            if list.path.is_ident("all") {
+                args.iter().any(cfg_requires_test)
+            } else if list.path.is_ident("any") {
+                !args.is_empty() && args.iter().all(cfg_requires_test)
+            } else {
+                // not(...) and unknown predicates never imply test
+                false
             }
-            // Also check for cfg(test) in meta list form
-            let tokens = attr.meta.to_token_stream().to_string();
-            return tokens.contains("test");
         }
-        false
-    })
+        syn::Meta::NameValue(_) => false,
+    }
 }
 
 /// Get #[path = "..."] attribute value
@@ -257,9 +1092,133 @@ mod tests {
 
     #[test]
     fn test_resolve_mod_file() {
-        // This test requires actual filesystem, so we just test the logic
-        let result = resolve_mod_file(Path::new("/nonexistent"), "foo", None).unwrap();
+        let result = resolve_mod_file(&DiskSource, Path::new("/nonexistent"), "foo", None).unwrap();
         assert!(result.is_none());
+
+        let source: MemorySource = [("src/a.rs", ""), ("src/b/mod.rs", ""), ("src/x/y.rs", "")]
+            .into_iter()
+            .collect();
+        let find = |name, custom| {
+            resolve_mod_file(&source, Path::new("src"), name, custom).unwrap()
+        };
+        assert_eq!(find("a", None), Some(PathBuf::from("src/a.rs")));
+        assert_eq!(find("b", None), Some(PathBuf::from("src/b/mod.rs")));
+        assert_eq!(find("c", Some("x/y.rs")), Some(PathBuf::from("src/x/y.rs")));
+        assert_eq!(find("c", None), None);
+    }
+
+    #[test]
+    fn test_resolve_from_memory() {
+        let source: MemorySource = [
+            (
+                "/mem/src/lib.rs",
+                "//! Root\npub mod engine;\n#[path = \"sys/unix.rs\"]\nmod platform;\n\
+                 mod missing;\npub mod api {\n    pub mod v1;\n}\ninclude!(\"generated.rs\");\n",
+            ),
+            ("/mem/src/engine/mod.rs", "pub mod eval;\npub struct Engine;\n"),
+            ("/mem/src/engine/eval.rs", "pub fn evaluate() {}\n"),
+            ("/mem/src/sys/unix.rs", "pub fn detect() {}\n"),
+            ("/mem/src/api/v1.rs", "pub fn handler() {}\n"),
+            ("/mem/src/generated.rs", "pub const BUILD: u32 = 1;\n"),
+        ]
+        .into_iter()
+        .collect();
+        let crate_meta = CrateMetadata {
+            name: "test".to_string(),
+            kind: crate::model::CrateKind::Lib,
+            edition: "2021".to_string(),
+            version: "0.1.0".to_string(),
+            external_deps: vec![],
+            dev_deps: vec![],
+            build_deps: vec![],
+            root_file: PathBuf::from("/mem/src/lib.rs"),
+            manifest_dir: PathBuf::from("/mem"),
+            workspace_root: PathBuf::from("/mem"),
+        };
+
+        let mut stats = ResolveStats::default();
+        let root = resolve_module_tree_from(
+            &source,
+            &crate_meta,
+            Path::new("/mem"),
+            None,
+            None,
+            &ResolveOptions::default(),
+            &mut stats,
+        )
+        .unwrap();
+
+        let modules: Vec<(&str, &Path)> = root
+            .all_modules()
+            .into_iter()
+            .map(|m| (m.path.as_str(), m.file_path.as_path()))
+            .collect();
+        assert_eq!(
+            modules,
+            vec![
+                ("crate", Path::new("src/lib.rs")),
+                ("crate::engine", Path::new("src/engine/mod.rs")),
+                ("crate::engine::eval", Path::new("src/engine/eval.rs")),
+                ("crate::platform", Path::new("src/sys/unix.rs")),
+                ("crate::api", Path::new("src/lib.rs")),
+                ("crate::api::v1", Path::new("src/api/v1.rs")),
+            ]
+        );
+        assert_eq!(root.doc_comment.as_deref(), Some("Root"));
+        assert!(root.items.iter().any(|i| i.name == "BUILD"));
+        assert_eq!(root.submodules[0].submodules[0].items[0].name, "evaluate");
+        assert_eq!(stats.parsed_files, 6);
+
+        // The root file itself has to be there
+        let err = resolve_module_tree_from(
+            &MemorySource::new(),
+            &crate_meta,
+            Path::new("/mem"),
+            None,
+            None,
+            &ResolveOptions::default(),
+            &mut stats,
+        )
+        .unwrap_err();
+        assert!(matches!(err, RsmapError::Io { .. }));
+    }
+
+    #[test]
+    fn test_include_targets() {
+        let syntax = syn::parse_file(
+            "include!(\"gen.rs\");\n\
+             include!(concat!(\"out/\", \"types.rs\"));\n\
+             include!(concat!(env!(\"RSMAP_TEST_UNSET_DIR\"), \"/x.rs\"));\n\
+             include_str!(\"notes.md\");\n\
+             macro_rules! include { () => {} }\n",
+        )
+        .unwrap();
+        assert_eq!(
+            include_targets(&syntax.items, Path::new("src/lib.rs")),
+            vec![
+                Some(PathBuf::from("src/gen.rs")),
+                Some(PathBuf::from("src/out/types.rs")),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_module_dir() {
+        assert_eq!(module_dir(Path::new("src/lib.rs"), true), Path::new("src"));
+        assert_eq!(
+            module_dir(Path::new("src/engine/mod.rs"), false),
+            Path::new("src/engine")
+        );
+        assert_eq!(
+            module_dir(Path::new("src/utils.rs"), false),
+            Path::new("src/utils")
+        );
+        // #[path] files behave like mod.rs
+        assert_eq!(
+            module_dir(Path::new("src/platform/native.rs"), true),
+            Path::new("src/platform")
+        );
     }
 
     #[test]
@@ -275,4 +1234,278 @@ mod tests {
             assert!(is_cfg_test(&m.attrs));
         }
     }
+
+    #[test]
+    fn test_expand_glob_reexports() {
+        let eval = Module::for_test("crate::engine::eval", "src/lib.rs")
+            .with_source("pub struct Ctx; pub fn evaluate() {} fn private() {} impl Ctx {}");
+        let engine = Module::for_test("crate::engine", "src/lib.rs")
+            .with_source("pub use self::eval::*;")
+            .with_submodules(vec![eval]);
+        let mut root = Module::for_test("crate", "src/lib.rs")
+            .with_source("pub use engine::eval::*; pub use serde::*;")
+            .with_submodules(vec![engine]);
+
+        expand_glob_reexports(&mut root);
+
+        assert!(root.items[0]
+            .signature
+            .ends_with("// (2 items: Ctx, evaluate)"));
+        // External crates fall back to the literal glob
+        assert_eq!(root.items[1].signature, "pub use serde::*;");
+        assert!(root.submodules[0].items[0]
+            .signature
+            .ends_with("// (2 items: Ctx, evaluate)"));
+    }
+
+    #[test]
+    fn test_link_reexports() {
+        let eval = Module::for_test("crate::engine::eval", "src/lib.rs")
+            .with_source("pub struct Expr;\npub fn run() {}");
+        let engine = Module::for_test("crate::engine", "src/lib.rs")
+            .with_source("pub use self::eval::Expr;\npub use eval::run as execute;")
+            .with_submodules(vec![eval]);
+        let mut root = Module::for_test("crate", "src/lib.rs")
+            .with_source(
+                "pub use engine::Expr;\npub use crate::engine::execute as go;\n\
+                 pub use serde::Serialize;\npub use engine::{Expr as Other};\n\
+                 pub use engine::eval::*;",
+            )
+            .with_submodules(vec![engine]);
+
+        link_reexports(&mut root);
+
+        // Chains are followed to the definition
+        assert_eq!(root.items[0].reexport_of.as_deref(), Some("crate::engine::eval::Expr"));
+        assert_eq!(
+            root.items[0].signature,
+            "pub use engine::Expr; // -> crate::engine::eval::Expr (src/lib.rs:1)"
+        );
+        assert_eq!(root.items[0].full_path("crate"), "crate::Expr");
+        // Renames resolve through the original name, at every step
+        assert_eq!(root.items[1].reexport_of.as_deref(), Some("crate::engine::eval::run"));
+        assert_eq!(root.items[1].full_path("crate"), "crate::go");
+        let engine = &root.submodules[0];
+        assert_eq!(engine.items[1].reexport_of.as_deref(), Some("crate::engine::eval::run"));
+        // Grouped re-exports are split per name, so they link too
+        assert_eq!(root.items[3].reexport_of.as_deref(), Some("crate::engine::eval::Expr"));
+        assert_eq!(root.items[3].full_path("crate"), "crate::Other");
+        // External targets and globs stay unlinked
+        assert_eq!(root.items[2].reexport_of, None);
+        assert_eq!(root.items[2].signature, "pub use serde::Serialize;");
+        assert_eq!(root.items[4].reexport_of, None);
+    }
+
+    #[test]
+    fn test_link_reexports_cycle() {
+        let a = Module::for_test("crate::a", "src/lib.rs").with_source("pub use super::b::Thing;");
+        let b = Module::for_test("crate::b", "src/lib.rs").with_source("pub use super::a::Thing;");
+        let mut root = Module::for_test("crate", "src/lib.rs").with_submodules(vec![a, b]);
+        link_reexports(&mut root);
+        assert_eq!(root.submodules[0].items[0].reexport_of, None);
+    }
+
+    fn mod_attrs(source: &str) -> Vec<syn::Attribute> {
+        let syntax = syn::parse_file(source).unwrap();
+        match &syntax.items[0] {
+            syn::Item::Mod(m) => m.attrs.clone(),
+            _ => panic!("expected a module"),
+        }
+    }
+
+    #[test]
+    fn test_is_cfg_test_not_test() {
+        assert!(!is_cfg_test(&mod_attrs("#[cfg(not(test))] mod real {}")));
+    }
+
+    #[test]
+    fn test_is_cfg_test_all_test() {
+        assert!(is_cfg_test(&mod_attrs(
+            "#[cfg(all(test, feature = \"x\"))] mod tests {}"
+        )));
+    }
+
+    #[test]
+    fn test_is_cfg_test_feature_named_test() {
+        assert!(!is_cfg_test(&mod_attrs("#[cfg(feature = \"test\")] mod utils {}")));
+        assert!(!is_cfg_test(&mod_attrs(
+            "#[cfg(feature = \"test-utils\")] mod utils {}"
+        )));
+    }
+
+    #[test]
+    fn test_file_filter_exclude_globs() {
+        let root = Path::new("/project");
+        let filter = FileFilter::new(
+            root,
+            &["src/generated/**".to_string(), "**/*_pb.rs".to_string()],
+            false,
+        )
+        .unwrap();
+
+        assert!(filter.is_excluded(&root.join("src/generated/schema.rs")));
+        assert!(filter.is_excluded(&root.join("src/proto/messages_pb.rs")));
+        assert!(!filter.is_excluded(&root.join("src/lib.rs")));
+        assert!(!FileFilter::default().is_excluded(&root.join("src/lib.rs")));
+    }
+
+    #[test]
+    fn test_file_filter_respect_gitignore() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/vendor")).unwrap();
+        std::fs::write(root.join(".gitignore"), "src/vendor/\n").unwrap();
+        std::fs::write(root.join("src/lib.rs"), "mod vendor;").unwrap();
+        std::fs::write(root.join("src/vendor/mod.rs"), "").unwrap();
+
+        let filter = FileFilter::new(root, &[], true).unwrap();
+        assert!(filter.is_excluded(&root.join("src/vendor/mod.rs")));
+        assert!(!filter.is_excluded(&root.join("src/lib.rs")));
+
+        // The cache fingerprint changes with the filter settings
+        let plain = FileFilter::new(root, &[], false).unwrap();
+        assert_ne!(filter.fingerprint(), plain.fingerprint());
+    }
+
+    #[test]
+    fn test_module_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        let root_file = dir.path().join("lib.rs");
+        std::fs::write(
+            &root_file,
+            "use std::fmt;\npub fn top() {}\n\
+             pub mod engine {\n    pub fn start() {}\n\
+             pub mod eval {\n        pub fn run() {}\n    }\n}\n\
+             pub mod models {\n    pub struct Model;\n}\n",
+        )
+        .unwrap();
+        let crate_meta = CrateMetadata {
+            name: "test".to_string(),
+            kind: crate::model::CrateKind::Lib,
+            edition: "2021".to_string(),
+            version: "0.1.0".to_string(),
+            external_deps: vec![],
+            dev_deps: vec![],
+            build_deps: vec![],
+            root_file,
+            manifest_dir: dir.path().to_path_buf(),
+            workspace_root: dir.path().to_path_buf(),
+        };
+        let root = resolve_module_tree(
+            &crate_meta,
+            dir.path(),
+            None,
+            None,
+            &ResolveOptions::default(),
+            &mut ResolveStats::default(),
+        )
+        .unwrap();
+        let crates = vec![CrateInfo::for_test("test", root)];
+
+        let filtered = ModuleFilter::new(&["engine::*".to_string()]).unwrap().apply(&crates);
+        let root = &filtered[0].root_module;
+        // Ancestors stay only as the path to the match, without their own items
+        assert!(root.items.is_empty() && root.use_statements.is_empty());
+        assert_eq!(root.submodules.len(), 1);
+        let engine = &root.submodules[0];
+        assert_eq!(engine.path, "crate::engine");
+        assert!(engine.items.is_empty());
+        assert_eq!(engine.submodules[0].path, "crate::engine::eval");
+        assert_eq!(engine.submodules[0].items[0].name, "run");
+
+        // A match keeps its whole subtree; the `crate::` prefix is optional
+        let filtered = ModuleFilter::new(&["crate::engine".to_string()]).unwrap().apply(&crates);
+        let engine = &filtered[0].root_module.submodules[0];
+        assert_eq!(engine.items[0].name, "start");
+        assert_eq!(engine.submodules.len(), 1);
+
+        assert!(ModuleFilter::new(&["nothing".to_string()]).unwrap().apply(&crates).is_empty());
+        let err = ModuleFilter::new(&["[".to_string()]).unwrap_err();
+        assert!(matches!(err, RsmapError::Pattern { flag: "--filter-module", .. }));
+    }
+
+    #[test]
+    fn test_module_loc() {
+        let dir = tempfile::tempdir().unwrap();
+        let root_file = dir.path().join("lib.rs");
+        std::fs::write(
+            &root_file,
+            "pub fn a() {}\n\n/// Inline\nmod inner {\n    fn b() {}\n}\n",
+        )
+        .unwrap();
+        let crate_meta = CrateMetadata {
+            name: "test".to_string(),
+            kind: crate::model::CrateKind::Lib,
+            edition: "2021".to_string(),
+            version: "0.1.0".to_string(),
+            external_deps: vec![],
+            dev_deps: vec![],
+            build_deps: vec![],
+            root_file,
+            manifest_dir: dir.path().to_path_buf(),
+            workspace_root: dir.path().to_path_buf(),
+        };
+
+        let mut stats = ResolveStats::default();
+        let root = resolve_module_tree(
+            &crate_meta,
+            dir.path(),
+            None,
+            None,
+            &ResolveOptions::default(),
+            &mut stats,
+        )
+        .unwrap();
+
+        assert_eq!(root.loc, 6);
+        // Inline modules count only their own block, doc comment included
+        assert_eq!(root.submodules[0].loc, 4);
+    }
+
+    #[test]
+    fn test_parse_error_becomes_empty_module() {
+        let dir = tempfile::tempdir().unwrap();
+        let root_file = dir.path().join("lib.rs");
+        std::fs::write(&root_file, "mod broken;\nmod fine;\n").unwrap();
+        std::fs::write(dir.path().join("broken.rs"), "pub fn oops( {}\n").unwrap();
+        std::fs::write(dir.path().join("fine.rs"), "pub fn ok() {}\n").unwrap();
+        let crate_meta = CrateMetadata {
+            name: "test".to_string(),
+            kind: crate::model::CrateKind::Lib,
+            edition: "2021".to_string(),
+            version: "0.1.0".to_string(),
+            external_deps: vec![],
+            dev_deps: vec![],
+            build_deps: vec![],
+            root_file,
+            manifest_dir: dir.path().to_path_buf(),
+            workspace_root: dir.path().to_path_buf(),
+        };
+
+        let mut stats = ResolveStats::default();
+        let root = resolve_module_tree(
+            &crate_meta,
+            dir.path(),
+            None,
+            None,
+            &ResolveOptions::default(),
+            &mut stats,
+        )
+        .unwrap();
+        let broken = &root.submodules[0];
+        assert_eq!(broken.path, "crate::broken");
+        assert!(broken.items.is_empty());
+        assert!(broken.error.as_deref().unwrap().contains("broken.rs:1:"));
+        assert_eq!(root.submodules[1].items[0].name, "ok");
+        assert!(root.error.is_none());
+
+        let strict = ResolveOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let err = resolve_module_tree(&crate_meta, dir.path(), None, None, &strict, &mut stats)
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("broken.rs"));
+        assert!(matches!(err, RsmapError::Parse { line: 1, column: 12, .. }));
+    }
 }
@@ -0,0 +1,186 @@
+//! Query an existing index.json without re-parsing the project.
+
+use regex::{Regex, RegexBuilder};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::error::{Result, RsmapError};
+use crate::layer3::{self, IndexEntry};
+
+/// What to look for in the index
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    /// Case-insensitive substring (or regex) matched against fully-qualified paths
+    pub pattern: String,
+    /// Treat `pattern` as a regular expression
+    pub regex: bool,
+    /// Only entries of this kind, e.g. `function`; `impl` matches every impl block
+    pub kind: Option<String>,
+    /// Only entries with this visibility, e.g. `pub` or `private`
+    pub visibility: Option<String>,
+}
+
+/// Load index.json from the output directory
+pub fn load_index(output_dir: &Path) -> Result<BTreeMap<String, IndexEntry>> {
+    layer3::read_index(&output_dir.join("index.json"))
+}
+
+/// Return the index entries matching the query, in path order
+pub fn search<'a>(
+    index: &'a BTreeMap<String, IndexEntry>,
+    query: &SearchQuery,
+) -> Result<Vec<(&'a String, &'a IndexEntry)>> {
+    let regex = if query.regex {
+        Some(
+            RegexBuilder::new(&query.pattern)
+                .case_insensitive(true)
+                .build()
+                .map_err(|source| RsmapError::Regex {
+                    pattern: query.pattern.clone(),
+                    source,
+                })?,
+        )
+    } else {
+        None
+    };
+    let needle = query.pattern.to_lowercase();
+
+    Ok(index
+        .iter()
+        .filter(|(path, _)| path_matches(path, &needle, regex.as_ref()))
+        .filter(|(_, entry)| query.kind.as_deref().is_none_or(|k| kind_matches(&entry.kind, k)))
+        .filter(|(_, entry)| {
+            query
+                .visibility
+                .as_deref()
+                .is_none_or(|v| entry.visibility == v)
+        })
+        .collect())
+}
+
+fn path_matches(path: &str, needle: &str, regex: Option<&Regex>) -> bool {
+    match regex {
+        Some(re) => re.is_match(path),
+        None => path.to_lowercase().contains(needle),
+    }
+}
+
+/// Impl entries have kinds like `impl Display for Config`, so `impl` matches all of them
+fn kind_matches(entry_kind: &str, wanted: &str) -> bool {
+    entry_kind == wanted || (wanted == "impl" && entry_kind.starts_with("impl "))
+}
+
+/// One line per match: path, kind, visibility and source location
+pub fn format_results(results: &[(&String, &IndexEntry)]) -> String {
+    let mut out = String::new();
+    for (path, entry) in results {
+        out.push_str(&format!(
+            "{}  [{}] {}  {}:{}-{}\n",
+            path, entry.kind, entry.visibility, entry.file, entry.line_start, entry.line_end
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(kind: &str, visibility: &str) -> IndexEntry {
+        IndexEntry {
+            file: "src/lib.rs".to_string(),
+            line_start: 1,
+            line_end: 3,
+            byte_start: None,
+            byte_end: None,
+            kind: kind.to_string(),
+            visibility: visibility.to_string(),
+            struct_kind: None,
+            field_count: None,
+            generics: vec![],
+            where_clause: None,
+            abi: None,
+            deprecated: false,
+            params: vec![],
+            return_type: None,
+            return_trait: None,
+            is_async: false,
+            is_unsafe: false,
+            is_const: false,
+            has_default: false,
+            is_method: false,
+            cfg: None,
+            doc_hidden: false,
+            reexport_of: None,
+            implementors: vec![],
+            implements: None,
+            doc_summary: None,
+        }
+    }
+
+    fn sample_index() -> BTreeMap<String, IndexEntry> {
+        let mut index = BTreeMap::new();
+        index.insert("crate::Config".to_string(), entry("struct", "pub"));
+        index.insert("crate::Config::port".to_string(), entry("field", "private"));
+        index.insert("crate::init_config".to_string(), entry("function", "pub"));
+        index.insert(
+            "crate::impl Default for Config".to_string(),
+            entry("impl Default for Config", "private"),
+        );
+        index
+    }
+
+    fn paths(results: &[(&String, &IndexEntry)]) -> Vec<String> {
+        results.iter().map(|(p, _)| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_search_substring_case_insensitive() {
+        let index = sample_index();
+        let query = SearchQuery {
+            pattern: "CONFIG".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(search(&index, &query).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_search_filters() {
+        let index = sample_index();
+        let query = SearchQuery {
+            pattern: "config".to_string(),
+            kind: Some("function".to_string()),
+            visibility: Some("pub".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(paths(&search(&index, &query).unwrap()), ["crate::init_config"]);
+
+        let query = SearchQuery {
+            pattern: "config".to_string(),
+            kind: Some("impl".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            paths(&search(&index, &query).unwrap()),
+            ["crate::impl Default for Config"]
+        );
+    }
+
+    #[test]
+    fn test_search_regex() {
+        let index = sample_index();
+        let query = SearchQuery {
+            pattern: r"::config$".to_string(),
+            regex: true,
+            ..Default::default()
+        };
+        assert_eq!(paths(&search(&index, &query).unwrap()), ["crate::Config"]);
+
+        let invalid = SearchQuery {
+            pattern: "(".to_string(),
+            regex: true,
+            ..Default::default()
+        };
+        assert!(matches!(search(&index, &invalid), Err(RsmapError::Regex { .. })));
+    }
+}
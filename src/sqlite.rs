@@ -0,0 +1,113 @@
+//! SQLite output (`--sqlite`), so tooling can query a large index with SQL
+//! instead of loading all of index.json.
+
+use rusqlite::{params, Connection};
+use std::path::Path;
+
+use crate::error::{Result, RsmapError};
+use crate::layer3::indexed_items;
+use crate::model::CrateInfo;
+
+const SCHEMA: &str = "
+CREATE TABLE items (
+    path TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    visibility TEXT NOT NULL,
+    file TEXT NOT NULL,
+    line_start INTEGER NOT NULL,
+    line_end INTEGER NOT NULL,
+    signature TEXT NOT NULL,
+    doc TEXT
+);
+CREATE INDEX items_kind ON items (kind);
+";
+
+/// Write every index entry to a fresh SQLite database at `path` (replacing any
+/// existing file). Returns the number of rows written.
+///
+/// Rows mirror index.json; `path` is the primary key, so it's indexed too.
+pub fn write_sqlite(crates: &[CrateInfo], path: &Path) -> Result<usize> {
+    if path.exists() {
+        std::fs::remove_file(path).map_err(|e| RsmapError::io(path, e))?;
+    }
+    let sqlite_error = |source| RsmapError::Sqlite {
+        path: path.to_path_buf(),
+        source,
+    };
+    let mut conn = Connection::open(path).map_err(sqlite_error)?;
+    conn.execute_batch(SCHEMA).map_err(sqlite_error)?;
+
+    let tx = conn.transaction().map_err(sqlite_error)?;
+    let mut rows = 0;
+    {
+        let mut insert = tx
+            .prepare(
+                "INSERT OR REPLACE INTO items \
+                 (path, kind, visibility, file, line_start, line_end, signature, doc) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )
+            .map_err(sqlite_error)?;
+        for item in indexed_items(crates) {
+            let entry = &item.entry;
+            insert
+                .execute(params![
+                    item.path,
+                    entry.kind,
+                    entry.visibility,
+                    entry.file,
+                    entry.line_start as i64,
+                    entry.line_end as i64,
+                    item.signature,
+                    item.doc_comment,
+                ])
+                .map_err(sqlite_error)?;
+            rows += 1;
+        }
+    }
+    tx.commit().map_err(sqlite_error)?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_write_sqlite() {
+        let items = crate::parse::parse_file(
+            &PathBuf::from("src/lib.rs"),
+            "/// Entry point\npub fn init() {}\npub struct Config {\n    pub port: u16,\n}\n",
+        )
+        .unwrap();
+        let crates = vec![CrateInfo::for_test(
+            "test",
+            Module::for_test("crate", "src/lib.rs").with_items(items),
+        )];
+
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("index.db");
+        assert_eq!(write_sqlite(&crates, &db).unwrap(), 3);
+        // A second run replaces the database rather than failing on the schema
+        assert_eq!(write_sqlite(&crates, &db).unwrap(), 3);
+
+        let conn = Connection::open(&db).unwrap();
+        let (kind, line, doc): (String, i64, Option<String>) = conn
+            .query_row(
+                "SELECT kind, line_start, doc FROM items WHERE path = 'crate::init'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!((kind.as_str(), line), ("function", 1));
+        assert_eq!(doc.as_deref(), Some("Entry point"));
+
+        let fields: i64 = conn
+            .query_row("SELECT COUNT(*) FROM items WHERE kind = 'field'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(fields, 1);
+    }
+}
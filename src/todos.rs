@@ -0,0 +1,152 @@
+//! `// TODO`, `// FIXME`, `// HACK` and `// XXX` comments (todos.md).
+//!
+//! Comments aren't part of the parsed model, so this re-reads each module's
+//! source files, the way the call graph does.
+
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use crate::model::{CrateInfo, Module};
+
+/// One tech-debt comment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TodoComment {
+    /// Module whose source holds the comment
+    pub module: String,
+    pub file: String,
+    /// 1-based line number
+    pub line: usize,
+    /// `TODO`, `FIXME`, `HACK` or `XXX`
+    pub tag: String,
+    /// The rest of the comment, after the tag and any `(owner)` or `:`
+    pub text: String,
+}
+
+/// Every tech-debt comment in the crates' module files (and files they
+/// `include!`), sorted by file then line. Files that can't be read are skipped.
+pub fn collect_todos(crates: &[CrateInfo], project_root: &Path) -> Vec<TodoComment> {
+    let pattern = Regex::new(r"//[/!]?\s*\b(TODO|FIXME|HACK|XXX)\b(?:\([^)]*\))?:?\s*(.*)$")
+        .expect("valid regex");
+    let mut seen = BTreeSet::new();
+    let mut todos = Vec::new();
+
+    for crate_info in crates {
+        for module in crate_info.root_module.all_modules() {
+            for file in module_files(module) {
+                // Inline modules share their parent's file, which owns its comments
+                if !seen.insert(file.clone()) {
+                    continue;
+                }
+                let Ok(source) = std::fs::read_to_string(project_root.join(&file)) else {
+                    continue;
+                };
+                for (i, line) in source.lines().enumerate() {
+                    if let Some(caps) = pattern.captures(line) {
+                        todos.push(TodoComment {
+                            module: module.path.clone(),
+                            file: file.clone(),
+                            line: i + 1,
+                            tag: caps[1].to_string(),
+                            text: caps[2].trim().to_string(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    todos.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+    todos
+}
+
+/// The module's own file, then any other file its items come from
+fn module_files(module: &Module) -> Vec<String> {
+    let mut files = vec![module.file_path.display().to_string()];
+    for item in &module.items {
+        let file = item.file_path.display().to_string();
+        if !files.contains(&file) {
+            files.push(file);
+        }
+    }
+    files
+}
+
+/// Generate todos.md: tech-debt comments grouped by module, modules in the
+/// order of their first file
+pub fn generate_todos(crates: &[CrateInfo], project_root: &Path) -> String {
+    let todos = collect_todos(crates, project_root);
+    let mut out = format!("# Tech Debt ({} comments)\n\n", todos.len());
+    if todos.is_empty() {
+        out.push_str("(no TODO, FIXME, HACK or XXX comments)\n");
+        return out;
+    }
+
+    let mut modules: Vec<&str> = Vec::new();
+    for todo in &todos {
+        if !modules.contains(&todo.module.as_str()) {
+            modules.push(&todo.module);
+        }
+    }
+    for module in modules {
+        out.push_str(&format!("## {}\n\n", module));
+        for todo in todos.iter().filter(|t| t.module == module) {
+            out.push_str(&format!("- {}:{} {}", todo.file, todo.line, todo.tag));
+            if !todo.text.is_empty() {
+                out.push_str(&format!(": {}", todo.text));
+            }
+            out.push('\n');
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::*;
+
+    #[test]
+    fn test_generate_todos() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir(root.path().join("src")).unwrap();
+        std::fs::write(
+            root.path().join("src/lib.rs"),
+            "// TODO(ann): split this up\nmod inner {\n    // todos are tracked elsewhere\n}\n\
+             fn run() {} // XXX\n",
+        )
+        .unwrap();
+        std::fs::write(root.path().join("src/a.rs"), "/// FIXME: document\npub fn a() {}\n")
+            .unwrap();
+
+        // The inline module shares lib.rs, whose comments belong to the crate root
+        let inner = Module::for_test("crate::inner", "src/lib.rs");
+        let a = Module::for_test("crate::a", "src/a.rs");
+        let root_module = Module::for_test("crate", "src/lib.rs").with_submodules(vec![inner, a]);
+        let crates = vec![CrateInfo::for_test("test", root_module)];
+
+        let todos = collect_todos(&crates, root.path());
+        let found: Vec<_> = todos
+            .iter()
+            .map(|t| (t.module.as_str(), t.file.as_str(), t.line, t.tag.as_str(), t.text.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("crate::a", "src/a.rs", 1, "FIXME", "document"),
+                ("crate", "src/lib.rs", 1, "TODO", "split this up"),
+                ("crate", "src/lib.rs", 5, "XXX", ""),
+            ]
+        );
+
+        let md = generate_todos(&crates, root.path());
+        assert!(md.starts_with("# Tech Debt (3 comments)\n\n## crate::a\n\n"));
+        assert!(md.contains(
+            "## crate\n\n- src/lib.rs:1 TODO: split this up\n- src/lib.rs:5 XXX\n"
+        ));
+
+        let empty = generate_todos(&[], root.path());
+        assert!(empty.contains("(no TODO, FIXME, HACK or XXX comments)"));
+    }
+}
@@ -0,0 +1,6 @@
+//! Minimal end-to-end usage of the sample crate.
+
+fn main() {
+    let config = sample_crate::init();
+    println!("{}", config.name);
+}
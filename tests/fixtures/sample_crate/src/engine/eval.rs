@@ -117,3 +117,6 @@ fn apply_operator(op: &BinOp, left: &Value, right: &Value) -> Result<Value, Engi
         _ => Ok(Value::Null),
     }
 }
+
+// FIXME(eval): report division by zero as an error instead of Null
+// HACK keep in sync with Value::type_name
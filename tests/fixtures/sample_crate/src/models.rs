@@ -72,3 +72,13 @@ impl From<i64> for Value {
         Value::Int(n)
     }
 }
+
+/// Conversions between values and plain strings
+pub mod convert {
+    use super::Value;
+
+    /// Render a value as plain text
+    pub fn to_text(value: &Value) -> String {
+        format!("{:?}", value)
+    }
+}
@@ -0,0 +1,3 @@
+//! Left over from an old refactor; no `mod` declaration points here.
+
+pub fn unused() {}
@@ -0,0 +1,8 @@
+//! Runtime platform detection.
+
+pub(crate) mod cpu;
+
+/// Name of the current operating system
+pub(crate) fn os_name() -> &'static str {
+    std::env::consts::OS
+}
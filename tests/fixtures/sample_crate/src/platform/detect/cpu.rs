@@ -0,0 +1,6 @@
+//! CPU feature detection.
+
+/// Number of logical CPUs, falling back to 1
+pub(crate) fn logical_cores() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
@@ -0,0 +1,3 @@
+//! Native platform support.
+
+pub(crate) mod detect;
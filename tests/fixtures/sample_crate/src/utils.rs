@@ -30,3 +30,9 @@ macro_rules! log_debug {
         }
     };
 }
+
+/// Platform detection, kept outside the usual module layout
+#[path = "platform/native.rs"]
+pub(crate) mod platform;
+
+// TODO: detect the platform at runtime instead of at compile time
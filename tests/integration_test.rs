@@ -9,6 +9,13 @@ fn fixture_path() -> PathBuf {
     project_root().join("tests/fixtures/sample_crate")
 }
 
+/// The entries of a generated index.json
+fn read_index_entries(dir: &std::path::Path) -> serde_json::Value {
+    let index = std::fs::read_to_string(dir.join("index.json")).unwrap();
+    let mut index: serde_json::Value = serde_json::from_str(&index).expect("Invalid JSON");
+    index["entries"].take()
+}
+
 fn binary_path() -> PathBuf {
     // Build the binary first via cargo
     let status = Command::new("cargo")
@@ -71,7 +78,13 @@ fn test_generate_on_fixture() {
     let overview = std::fs::read_to_string(output_dir.path().join("overview.md")).unwrap();
     assert!(overview.contains("# Crate: sample_crate (lib)"));
     assert!(overview.contains("Edition: 2021"));
-    assert!(overview.contains("serde"));
+    assert!(overview.contains("External deps: serde\n"));
+    assert!(overview.contains("Dev deps: serde_json\n"));
+    // Public API: 4 structs, 5 enums, 1 trait, 6 free fns, plus a type alias and a const
+    assert!(overview.contains(
+        "## Public API\n| Structs | Enums | Traits | Functions | Total items |\n\
+         |---------|-------|--------|-----------|-------------|\n| 4 | 5 | 1 | 6 | 18 |\n"
+    ));
     assert!(overview.contains("engine"));
     assert!(overview.contains("models"));
     assert!(overview.contains("utils"));
@@ -83,6 +96,7 @@ fn test_generate_on_fixture() {
     assert!(api_surface.contains("pub fn init()"));
     assert!(api_surface.contains("pub fn run("));
     assert!(api_surface.contains("pub trait Evaluable"));
+    assert!(api_surface.contains("// object-safe: yes\npub trait Evaluable {"));
     assert!(api_surface.contains("pub struct EvalContext"));
     assert!(api_surface.contains("fn resolve_name(")); // private function included
     assert!(api_surface.contains("fn apply_operator(")); // private function included
@@ -99,8 +113,7 @@ fn test_generate_on_fixture() {
     assert!(relationships.contains("## Key Types"));
 
     // Verify JSON index is valid JSON
-    let index_json = std::fs::read_to_string(output_dir.path().join("index.json")).unwrap();
-    let index: serde_json::Value = serde_json::from_str(&index_json).expect("Invalid JSON");
+    let index = read_index_entries(output_dir.path());
 
     // Verify specific entries exist
     assert!(index.get("crate::Config").is_some(), "Config not in index");
@@ -132,6 +145,53 @@ fn test_generate_on_fixture() {
     let resolve_name = &index["crate::engine::eval::resolve_name"];
     assert_eq!(resolve_name["kind"], "function");
     assert_eq!(resolve_name["visibility"], "private");
+    assert_eq!(resolve_name["params"][0]["name"], "name");
+    assert_eq!(resolve_name["params"][0]["ty"], "& str");
+    assert_eq!(resolve_name["params"][0]["is_self"], false);
+    assert_eq!(resolve_name["return_type"], "Result < Value , EngineError >");
+    // The byte range slices the item's source back out of its file
+    let source = std::fs::read_to_string(fixture.join("src/engine/eval.rs")).unwrap();
+    let (start, end) = (&resolve_name["byte_start"], &resolve_name["byte_end"]);
+    let text = &source[start.as_u64().unwrap() as usize..end.as_u64().unwrap() as usize];
+    assert!(text.starts_with("fn resolve_name(name: &str, scope: &HashMap<String, Value>)"));
+    assert!(text.ends_with(".ok_or_else(|| EngineError::UnknownVariable(name.to_string()))\n}"));
+
+    // Traits and their impls point at each other
+    let evaluable = &index["crate::engine::eval::Evaluable"];
+    assert_eq!(evaluable["implementors"], serde_json::json!(["crate::engine::eval::Expr"]));
+    let impl_entry = &index["crate::engine::eval::impl Evaluable for Expr"];
+    assert_eq!(impl_entry["implements"], "crate::engine::eval::Evaluable");
+
+    // Struct fields and enum variants have their own entries
+    let port = &index["crate::Config::port"];
+    assert_eq!(port["kind"], "field");
+    assert_eq!(port["visibility"], "private");
+    assert_eq!(port["line_start"], 16);
+    let variant = &index["crate::AppError::Config"];
+    assert_eq!(variant["kind"], "variant");
+    assert_eq!(variant["visibility"], "pub");
+    // Children of a #[path] module resolve relative to the redirected file
+    assert!(
+        index
+            .get("crate::utils::platform::detect::cpu::logical_cores")
+            .is_some(),
+        "nested module under #[path] not in index"
+    );
+
+    let method = &index["crate::engine::eval::Evaluable::eval"];
+    assert_eq!(method["kind"], "method");
+    assert_eq!(method["line_start"], 17);
+
+    // Impl methods are indexed under their self type
+    let impl_method = &index["crate::engine::eval::Expr::eval"];
+    assert_eq!(impl_method["kind"], "method");
+    assert!(index.get("crate::engine::eval::EvalContext::new").is_some());
+
+    // Items inside inline modules keep their real position in the parent file
+    let to_text = &index["crate::models::convert::to_text"];
+    assert_eq!(to_text["file"].as_str().unwrap(), "src/models.rs");
+    assert_eq!(to_text["line_start"], 80);
+    assert_eq!(to_text["line_end"], 83);
 
     // Verify annotations.toml is valid TOML
     let annotations_toml =
@@ -164,9 +224,12 @@ fn test_incremental_rebuild() {
         .status()
         .expect("Failed to run generate");
     assert!(status.success());
+    let first_api_surface =
+        std::fs::read_to_string(output_dir.path().join("api-surface.md")).unwrap();
+    let first_index = read_index_entries(output_dir.path());
 
     // Second run - incremental (uses cache)
-    let status = Command::new(&binary)
+    let output = Command::new(&binary)
         .args([
             "generate",
             "--path",
@@ -174,13 +237,105 @@ fn test_incremental_rebuild() {
             "--output",
             output_dir.path().to_str().unwrap(),
         ])
-        .status()
+        .output()
         .expect("Failed to run generate");
-    assert!(status.success());
+    assert!(output.status.success());
+
+    // No file changed, so nothing should be re-parsed
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Parsed 0 file(s), reused 8 unchanged file(s) from cache"),
+        "unexpected stderr: {}",
+        stderr
+    );
 
     // Both runs should produce the same output
     let overview = std::fs::read_to_string(output_dir.path().join("overview.md")).unwrap();
     assert!(overview.contains("# Crate: sample_crate"));
+    let api_surface = std::fs::read_to_string(output_dir.path().join("api-surface.md")).unwrap();
+    assert_eq!(api_surface, first_api_surface);
+    assert_eq!(read_index_entries(output_dir.path()), first_index);
+}
+
+#[test]
+fn test_incremental_rebuild_module_docs() {
+    let binary = binary_path();
+    let project = tempfile::tempdir().unwrap();
+    let root = project.path();
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"docs\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )
+    .unwrap();
+    std::fs::write(root.join("src/lib.rs"), "/// First\npub mod a;\n/// Outer doc\npub mod b;\n")
+        .unwrap();
+    std::fs::write(root.join("src/a.rs"), "pub fn a() {}\n").unwrap();
+    std::fs::write(root.join("src/b.rs"), "//! Old doc\npub fn b() {}\n").unwrap();
+
+    // The cached run must match a fresh one
+    let overviews = || {
+        let generate = |output: &str, extra: &[&str]| {
+            let status = Command::new(&binary)
+                .args(["generate", "--path", root.to_str().unwrap(), "--output", output])
+                .args(["--quiet"])
+                .args(extra)
+                .status()
+                .expect("Failed to run generate");
+            assert!(status.success());
+            std::fs::read_to_string(root.join(output).join("overview.md")).unwrap()
+        };
+        (generate("cached", &[]), generate("fresh", &["--no-cache"]))
+    };
+    overviews();
+
+    // Only lib.rs changes, so a.rs comes from the cache with a new outer doc
+    std::fs::write(root.join("src/lib.rs"), "/// Second\npub mod a;\n/// Outer doc\npub mod b;\n")
+        .unwrap();
+    let (cached, fresh) = overviews();
+    assert!(fresh.contains("Second"), "{}", fresh);
+    assert_eq!(cached, fresh);
+
+    // Only b.rs changes, under a cached lib.rs: its outer doc takes over
+    std::fs::write(root.join("src/b.rs"), "pub fn b() {}\n").unwrap();
+    let (cached, fresh) = overviews();
+    assert!(fresh.contains("Outer doc") && !fresh.contains("Old doc"), "{}", fresh);
+    assert_eq!(cached, fresh);
+}
+
+#[test]
+fn test_incremental_rebuild_new_module_file() {
+    let binary = binary_path();
+    let project = tempfile::tempdir().unwrap();
+    let root = project.path();
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"late\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )
+    .unwrap();
+    // `mod a;` has no file yet, inside an inline module too
+    std::fs::write(root.join("src/lib.rs"), "pub mod a;\npub mod outer {\n    pub mod b;\n}\n")
+        .unwrap();
+
+    let index = || {
+        let status = Command::new(&binary)
+            .args(["generate", "--path", root.to_str().unwrap(), "--output", "out", "--quiet"])
+            .status()
+            .expect("Failed to run generate");
+        assert!(status.success());
+        read_index_entries(&root.join("out"))
+    };
+    let first = index();
+    assert!(first.get("crate::a::late_fn").is_none());
+
+    // lib.rs is unchanged, so it comes from the cache, but the new files are found
+    std::fs::write(root.join("src/a.rs"), "pub fn late_fn() {}\n").unwrap();
+    std::fs::create_dir_all(root.join("src/outer/b")).unwrap();
+    std::fs::write(root.join("src/outer/b/mod.rs"), "pub fn nested_fn() {}\n").unwrap();
+    let second = index();
+    assert!(second.get("crate::a::late_fn").is_some());
+    assert!(second.get("crate::outer::b::nested_fn").is_some());
 }
 
 #[test]
@@ -218,6 +373,21 @@ fn test_annotate_export() {
     assert!(output.status.success());
     let stdout = String::from_utf8_lossy(&output.stdout);
     assert!(stdout.contains("items need descriptions"));
+
+    // The JSON form carries signatures from the parsed model
+    let output = Command::new(&binary)
+        .args(["annotate", "export", "--json", "--path", fixture.to_str().unwrap(), "--output"])
+        .arg(output_dir.path())
+        .output()
+        .expect("Failed to run annotate export --json");
+    assert!(output.status.success());
+    let entries: Vec<serde_json::Value> =
+        serde_json::from_slice(&output.stdout).expect("Invalid JSON");
+    assert!(entries.iter().any(|e| e["reason"] == "new"));
+    let config = entries.iter().find(|e| e["path"] == "crate::Config").unwrap();
+    assert_eq!(config["kind"], "struct");
+    assert!(config["signature"].as_str().unwrap().contains("pub struct Config"));
+    assert_eq!(config["existing_doc"], "Application configuration");
 }
 
 #[test]
@@ -270,3 +440,1162 @@ note = "Initializes the application with default settings"
         std::fs::read_to_string(output_dir.path().join("annotations.toml")).unwrap();
     assert!(annotations.contains("Initializes the application with default settings"));
 }
+
+#[test]
+fn test_annotate_import_json() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let status = Command::new(&binary)
+        .args(["generate", "--path", fixture.to_str().unwrap(), "--output"])
+        .arg(output_dir.path())
+        .args(["--no-cache", "--quiet"])
+        .status()
+        .expect("Failed to run generate");
+    assert!(status.success());
+
+    let import_file = output_dir.path().join("import.json");
+    std::fs::write(
+        &import_file,
+        r#"{
+  "modules": { "crate::engine": { "note": "Expression engine" } },
+  "items": { "crate::init": { "note": "Builds the default configuration" } }
+}"#,
+    )
+    .unwrap();
+
+    let status = Command::new(&binary)
+        .args(["annotate", "import"])
+        .arg(&import_file)
+        .arg("--output")
+        .arg(output_dir.path())
+        .status()
+        .expect("Failed to run annotate import");
+    assert!(status.success());
+
+    let store = rsmap::annotations::AnnotationStore::load(output_dir.path()).unwrap();
+    assert_eq!(store.modules["crate::engine"].note, "Expression engine");
+    assert_eq!(store.items["crate::init"].note, "Builds the default configuration");
+}
+
+#[test]
+fn test_generate_dry_run() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+    let generate = |extra: &[&str]| {
+        Command::new(&binary)
+            .args(["generate", "--path", fixture.to_str().unwrap(), "--output"])
+            .arg(output_dir.path())
+            .arg("--quiet")
+            .args(extra)
+            .output()
+            .expect("Failed to run generate")
+    };
+    let snapshot = || {
+        let mut files: Vec<_> = walkdir::WalkDir::new(output_dir.path())
+            .into_iter()
+            .map(|entry| entry.unwrap())
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| {
+                let modified = entry.metadata().unwrap().modified().unwrap();
+                (entry.path().to_path_buf(), std::fs::read(entry.path()).unwrap(), modified)
+            })
+            .collect();
+        files.sort();
+        files
+    };
+
+    assert!(generate(&[]).status.success());
+    let before = snapshot();
+
+    // Up to date: nothing would change, and nothing is touched
+    let output = generate(&["--dry-run", "--check"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Dry run: 0 of 4 file(s) would change"));
+    assert!(stdout.contains("  unchanged overview.md\n"));
+    assert_eq!(snapshot(), before);
+
+    // A stale file and a new one are reported, and --check fails
+    let overview = output_dir.path().join("overview.md");
+    std::fs::write(&overview, "stale\n").unwrap();
+    let before = snapshot();
+    let output = generate(&["--dry-run", "--check", "--scan-todos"]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Dry run: 2 of 5 file(s) would change"));
+    assert!(stdout.contains("  changed   overview.md\n"));
+    assert!(stdout.contains("  new       todos.md\n"));
+    assert_eq!(snapshot(), before);
+    assert!(!output_dir.path().join("todos.md").exists());
+}
+
+#[test]
+fn test_generate_include_tests() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let status = Command::new(&binary)
+        .args([
+            "generate",
+            "--path",
+            fixture.to_str().unwrap(),
+            "--output",
+            output_dir.path().to_str().unwrap(),
+            "--no-cache",
+            "--include-tests",
+        ])
+        .status()
+        .expect("Failed to run generate");
+    assert!(status.success());
+
+    let api_surface = std::fs::read_to_string(output_dir.path().join("api-surface.md")).unwrap();
+    assert!(api_surface.contains("# Test Modules"));
+    assert!(api_surface.contains("# crate::tests"));
+    assert!(api_surface.contains("fn test_init()"));
+}
+
+#[test]
+fn test_library_generate_index() {
+    let output = rsmap::generate_index(&fixture_path(), &rsmap::GenerateOptions::default())
+        .expect("generate_index failed");
+
+    assert_eq!(output.crates.len(), 1);
+    assert!(output.overview.contains("# Crate: sample_crate (lib)"));
+    assert!(output.api_surface.contains("pub struct Config"));
+    assert!(output.relationships.contains("## Trait Implementations"));
+
+    let index: serde_json::Value = serde_json::from_str(&output.index).expect("Invalid JSON");
+    assert!(index["entries"].get("crate::Config").is_some());
+}
+
+#[test]
+fn test_library_index_queries() {
+    use rsmap::model::ItemKind;
+
+    let output = rsmap::generate_index(&fixture_path(), &rsmap::GenerateOptions::default())
+        .expect("generate_index failed");
+    let index = rsmap::index::Index::new(output.crates);
+
+    let expr = index.get("crate::engine::eval::Expr").expect("Expr is indexed");
+    assert_eq!(expr.kind, ItemKind::Enum);
+    assert!(index.get("crate::engine::eval::impl Evaluable for Expr").is_some());
+    assert!(index.get("crate::engine::eval::Missing").is_none());
+
+    assert_eq!(
+        index.implementors_of("crate::engine::eval::Evaluable"),
+        ["crate::engine::eval::Expr"]
+    );
+    assert_eq!(
+        index.implementors_of("std::fmt::Display"),
+        ["crate::AppError", "crate::engine::EngineError", "crate::models::Value"]
+    );
+    assert!(index.implementors_of("crate::Config").is_empty());
+
+    let engine = index.items_in_module("crate::engine").expect("crate::engine is indexed");
+    assert!(engine.iter().any(|item| item.name == "process"));
+    // Submodule items aren't included
+    assert!(!engine.iter().any(|item| item.name == "evaluate"));
+    assert!(index.items_in_module("crate::missing").is_none());
+
+    let evaluators: Vec<&str> = index
+        .search(|path, item| item.kind == ItemKind::Function && path.contains("evaluate"))
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect();
+    assert_eq!(
+        evaluators,
+        ["crate::engine::eval::evaluate", "crate::engine::eval::evaluate_batch"]
+    );
+}
+
+#[test]
+fn test_library_parse_crates_from_memory() {
+    use rsmap::metadata::CrateMetadata;
+    use rsmap::model::CrateKind;
+    use rsmap::resolve::{MemorySource, ResolveOptions, ResolveStats};
+
+    // Nothing under /mem exists on disk
+    let source: MemorySource = [
+        ("/mem/src/lib.rs", "#![forbid(unsafe_code)]\npub mod engine;\n"),
+        ("/mem/src/engine.rs", "include!(\"../gen/types.rs\");\npub fn run() {}\n"),
+        ("/mem/gen/types.rs", "pub struct Generated;\n"),
+    ]
+    .into_iter()
+    .collect();
+    let crate_meta = CrateMetadata {
+        name: "mem".to_string(),
+        kind: CrateKind::Lib,
+        edition: "2021".to_string(),
+        version: "0.1.0".to_string(),
+        external_deps: vec![],
+        dev_deps: vec![],
+        build_deps: vec![],
+        root_file: PathBuf::from("/mem/src/lib.rs"),
+        manifest_dir: PathBuf::from("/mem"),
+        workspace_root: PathBuf::from("/mem"),
+    };
+
+    let crates = rsmap::parse_crates(
+        &source,
+        std::path::Path::new("/mem"),
+        &[crate_meta],
+        None,
+        None,
+        &ResolveOptions::default(),
+        &mut ResolveStats::default(),
+    )
+    .expect("parse_crates failed");
+
+    assert_eq!(crates[0].crate_attributes, ["#![forbid(unsafe_code)]"]);
+    let engine = &crates[0].root_module.submodules[0];
+    let files: Vec<(&str, PathBuf)> =
+        engine.items.iter().map(|i| (i.name.as_str(), i.file_path.clone())).collect();
+    assert_eq!(
+        files,
+        [
+            ("run", PathBuf::from("src/engine.rs")),
+            ("Generated", PathBuf::from("gen/types.rs")),
+        ]
+    );
+}
+
+#[test]
+fn test_library_errors() {
+    use rsmap::error::RsmapError;
+
+    let missing = fixture_path().join("no-such-dir");
+    let err = rsmap::generate_index(&missing, &rsmap::GenerateOptions::default()).unwrap_err();
+    assert!(matches!(err, RsmapError::Io { ref path, .. } if *path == missing));
+
+    let not_a_project = tempfile::tempdir().unwrap();
+    let err = rsmap::generate_index(not_a_project.path(), &rsmap::GenerateOptions::default())
+        .unwrap_err();
+    assert!(matches!(err, RsmapError::Metadata(_)));
+}
+
+#[test]
+fn test_generate_exclude() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let output = Command::new(&binary)
+        .args([
+            "generate",
+            "--path",
+            fixture.to_str().unwrap(),
+            "--output",
+            output_dir.path().to_str().unwrap(),
+            "--no-cache",
+            "--exclude",
+            "src/utils.rs",
+        ])
+        .output()
+        .expect("Failed to run generate");
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Skipping excluded module crate::utils (src/utils.rs)"));
+
+    let index = read_index_entries(output_dir.path());
+    assert!(index.get("crate::utils::truncate").is_none());
+    assert!(index.get("crate::Config").is_some());
+}
+
+#[test]
+fn test_search() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let status = Command::new(&binary)
+        .args([
+            "generate",
+            "--path",
+            fixture.to_str().unwrap(),
+            "--output",
+            output_dir.path().to_str().unwrap(),
+            "--no-cache",
+        ])
+        .status()
+        .expect("Failed to run generate");
+    assert!(status.success());
+
+    let output = Command::new(&binary)
+        .args([
+            "search",
+            "EVAL",
+            "--output",
+            output_dir.path().to_str().unwrap(),
+            "--kind",
+            "function",
+            "--visibility",
+            "pub",
+        ])
+        .output()
+        .expect("Failed to run search");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("crate::engine::eval::evaluate  [function] pub  src/engine/eval.rs:"));
+    assert!(!stdout.contains("resolve_name")); // private
+    assert!(!stdout.contains("EvalContext")); // struct
+}
+
+#[test]
+fn test_generate_targets() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let status = Command::new(&binary)
+        .args([
+            "generate",
+            "--path",
+            fixture.to_str().unwrap(),
+            "--output",
+            output_dir.path().to_str().unwrap(),
+            "--no-cache",
+            "--targets",
+            "lib,example",
+        ])
+        .status()
+        .expect("Failed to run generate");
+    assert!(status.success());
+
+    let overview = std::fs::read_to_string(output_dir.path().join("overview.md")).unwrap();
+    assert!(overview.contains("# Crate: sample_crate (lib)"));
+    assert!(overview.contains("# Crate: quickstart (example)"));
+
+    // Examples are opt-in
+    let output = rsmap::generate_index(&fixture, &rsmap::GenerateOptions::default())
+        .expect("generate_index failed");
+    assert!(!output.overview.contains("quickstart"));
+}
+
+#[test]
+fn test_library_no_dev_deps() {
+    let options = rsmap::GenerateOptions {
+        no_dev_deps: true,
+        ..Default::default()
+    };
+    let output = rsmap::generate_index(&fixture_path(), &options).expect("generate_index failed");
+    assert!(output.overview.contains("External deps: serde\n"));
+    assert!(!output.overview.contains("Dev deps:"));
+}
+
+#[test]
+fn test_generate_description_overrides() {
+    let dir = tempfile::tempdir().unwrap();
+    let write = |path: &str, contents: &str| {
+        let path = dir.path().join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    };
+    write(
+        "Cargo.toml",
+        "[package]\nname = \"described\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    );
+    write("src/lib.rs", "//! Root docs\npub mod engine;\npub mod utils;\n");
+    write("src/engine.rs", "pub fn run() {}\n");
+    write("src/utils.rs", "//! Utility helpers\npub fn help() {}\n");
+    write(
+        "descriptions.toml",
+        "\"crate::engine\" = \"Expression evaluation\"\n\
+         \"crate::utils\" = \"Outranked by the doc\"\n",
+    );
+
+    let output_dir = dir.path().join("out");
+    let status = Command::new(binary_path())
+        .args(["generate", "--path"])
+        .arg(dir.path())
+        .arg("--output")
+        .arg(&output_dir)
+        .args(["--no-cache", "--quiet"])
+        .status()
+        .expect("Failed to run generate");
+    assert!(status.success());
+
+    let overview = std::fs::read_to_string(output_dir.join("overview.md")).unwrap();
+    assert!(overview.contains("  - engine — Expression evaluation ("));
+    assert!(overview.contains("  - utils — Utility helpers ("));
+    assert!(!overview.contains("Outranked"));
+}
+
+#[test]
+fn test_library_workspace_dependencies() {
+    let dir = tempfile::tempdir().unwrap();
+    let write = |path: &str, contents: &str| {
+        let path = dir.path().join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    };
+    let manifest = |name: &str, deps: &str| {
+        format!(
+            "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n{}",
+            name, deps
+        )
+    };
+    // Path dependencies outside the workspace, so they aren't members themselves
+    for dep in ["shared", "solo"] {
+        write(&format!("deps/{}/Cargo.toml", dep), &manifest(dep, ""));
+        write(&format!("deps/{}/src/lib.rs", dep), "");
+    }
+    write("ws/Cargo.toml", "[workspace]\nmembers = [\"alpha\", \"beta\"]\n");
+    write(
+        "ws/alpha/Cargo.toml",
+        &manifest(
+            "alpha",
+            "[dependencies]\nsolo = { path = \"../../deps/solo\" }\n\
+             shared = { path = \"../../deps/shared\" }\n\n\
+             [target.'cfg(unix)'.dependencies]\nshared = { path = \"../../deps/shared\" }\n",
+        ),
+    );
+    write("ws/alpha/src/lib.rs", "pub fn a() {}\n");
+    write(
+        "ws/beta/Cargo.toml",
+        &manifest("beta", "[dependencies]\nshared = { path = \"../../deps/shared\" }\n"),
+    );
+    write("ws/beta/src/lib.rs", "pub fn b() {}\n");
+
+    let output = rsmap::generate_index(&dir.path().join("ws"), &Default::default())
+        .expect("generate_index failed");
+    // A dependency listed under several targets is listed once, and lists are sorted
+    assert!(output.overview.contains("External deps: shared, solo\n"));
+    assert!(output.overview.starts_with(
+        "# Workspace Dependencies\n\
+         - shared — used by 2 crate(s)\n\
+         - solo — used by 1 crate(s)\n\n"
+    ));
+}
+
+#[test]
+fn test_generate_call_graph() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let status = Command::new(&binary)
+        .args([
+            "generate",
+            "--path",
+            fixture.to_str().unwrap(),
+            "--output",
+            output_dir.path().to_str().unwrap(),
+            "--no-cache",
+            "--call-graph",
+        ])
+        .status()
+        .expect("Failed to run generate");
+    assert!(status.success());
+
+    let call_graph = std::fs::read_to_string(output_dir.path().join("call-graph.md")).unwrap();
+    assert!(call_graph.contains("## Called By"));
+    assert!(call_graph.contains("<- crate::engine::eval::Expr::eval"));
+
+    let json = std::fs::read_to_string(output_dir.path().join("call-graph.json")).unwrap();
+    let graph: serde_json::Value = serde_json::from_str(&json).expect("Invalid JSON");
+    let calls = graph["calls"]["crate::engine::eval::Expr::eval"]
+        .as_array()
+        .unwrap();
+    assert!(calls.contains(&serde_json::json!("crate::engine::eval::resolve_name")));
+}
+
+#[test]
+fn test_parse_single_file() {
+    let binary = binary_path();
+    let file = fixture_path().join("src/utils.rs");
+
+    let output = Command::new(&binary)
+        .args(["parse", file.to_str().unwrap()])
+        .output()
+        .expect("Failed to run parse");
+    assert!(output.status.success());
+
+    let items: serde_json::Value = serde_json::from_slice(&output.stdout).expect("Invalid JSON");
+    let names: Vec<&str> = items
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|i| i["name"].as_str().unwrap())
+        .collect();
+    assert!(names.contains(&"truncate"));
+
+    // Syntax errors are reported with their location
+    let broken = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(broken.path(), "pub fn ok() {}\n\npub struct Broken {\n    x: ,\n}\n").unwrap();
+    let output = Command::new(&binary)
+        .args(["parse", broken.path().to_str().unwrap()])
+        .output()
+        .expect("Failed to run parse");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(":4:8:"), "unexpected stderr: {}", stderr);
+}
+
+#[test]
+fn test_generate_html() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let status = Command::new(&binary)
+        .args([
+            "generate",
+            "--path",
+            fixture.to_str().unwrap(),
+            "--output",
+            output_dir.path().to_str().unwrap(),
+            "--no-cache",
+            "--html",
+        ])
+        .status()
+        .expect("Failed to run generate");
+    assert!(status.success());
+
+    let html = std::fs::read_to_string(output_dir.path().join("index.html")).unwrap();
+    assert!(html.contains("<a href=\"#sample_crate/crate::engine::eval\">eval</a>"));
+    assert!(html.contains("<details id=\"sample_crate/crate::engine::eval\">"));
+    assert!(html.contains("id=\"sample_crate/crate::models::convert::to_text\""));
+    assert!(!html.contains("<script src") && !html.contains("<link"));
+}
+
+#[test]
+fn test_diff() {
+    let binary = binary_path();
+    let dir = tempfile::tempdir().unwrap();
+    let old = dir.path().join("old.json");
+    let new = dir.path().join("new.json");
+    std::fs::write(
+        &old,
+        r#"{
+  "crate::init": {"file": "src/lib.rs", "line_start": 1, "line_end": 3, "kind": "function", "visibility": "pub"},
+  "crate::Config": {"file": "src/lib.rs", "line_start": 5, "line_end": 9, "kind": "struct", "visibility": "pub"}
+}"#,
+    )
+    .unwrap();
+    std::fs::write(
+        &new,
+        r#"{
+  "crate::Config": {"file": "src/lib.rs", "line_start": 1, "line_end": 5, "kind": "struct", "visibility": "pub"},
+  "crate::start": {"file": "src/lib.rs", "line_start": 7, "line_end": 9, "kind": "function", "visibility": "pub"}
+}"#,
+    )
+    .unwrap();
+
+    let output = Command::new(&binary)
+        .args(["diff", old.to_str().unwrap(), new.to_str().unwrap()])
+        .output()
+        .expect("Failed to run diff");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("+ crate::start  [function] pub\n"));
+    assert!(stdout.contains("- crate::init  [function] pub  BREAKING\n"));
+    assert!(stdout.contains("~ crate::Config  src/lib.rs:5-9 -> src/lib.rs:1-5\n"));
+
+    let output = Command::new(&binary)
+        .args([
+            "diff",
+            old.to_str().unwrap(),
+            new.to_str().unwrap(),
+            "--json",
+            "--fail-on-breaking",
+        ])
+        .output()
+        .expect("Failed to run diff");
+    assert!(!output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("Invalid JSON");
+    assert_eq!(json["removed"][0]["path"], "crate::init");
+    assert_eq!(json["removed"][0]["breaking"], true);
+}
+
+#[test]
+fn test_generate_split_by_crate() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let status = Command::new(&binary)
+        .args([
+            "generate",
+            "--path",
+            fixture.to_str().unwrap(),
+            "--output",
+            output_dir.path().to_str().unwrap(),
+            "--no-cache",
+            "--targets",
+            "lib,example",
+            "--split-by-crate",
+        ])
+        .status()
+        .expect("Failed to run generate");
+    assert!(status.success());
+
+    let overview = std::fs::read_to_string(output_dir.path().join("overview.md")).unwrap();
+    assert!(overview.contains("- [sample_crate](sample_crate/overview.md) (lib)"));
+    assert!(overview.contains("- [quickstart](quickstart/overview.md) (example)"));
+
+    let lib_dir = output_dir.path().join("sample_crate");
+    let lib_overview = std::fs::read_to_string(lib_dir.join("overview.md")).unwrap();
+    assert!(lib_overview.contains("# Crate: sample_crate (lib)"));
+    assert!(!lib_overview.contains("quickstart"));
+    assert!(lib_dir.join("api-surface.md").exists());
+    assert!(lib_dir.join("index.json").exists());
+    assert!(output_dir.path().join("quickstart/api-surface.md").exists());
+
+    // Cache and annotations stay shared at the top level
+    assert!(output_dir.path().join("cache.json").exists());
+    assert!(output_dir.path().join("annotations.toml").exists());
+    assert!(!lib_dir.join("cache.json").exists());
+}
+
+#[test]
+fn test_generate_overview_max_depth() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let status = Command::new(&binary)
+        .args([
+            "generate",
+            "--path",
+            fixture.to_str().unwrap(),
+            "--output",
+            output_dir.path().to_str().unwrap(),
+            "--no-cache",
+            "--overview-max-depth",
+            "1",
+        ])
+        .status()
+        .expect("Failed to run generate");
+    assert!(status.success());
+
+    let overview = std::fs::read_to_string(output_dir.path().join("overview.md")).unwrap();
+    assert!(overview.contains("(… 1 submodules collapsed)"));
+    assert!(overview.contains("(… 3 submodules collapsed)"));
+    assert!(!overview.contains("- eval"));
+    assert!(!overview.contains("- cpu"));
+
+    // The other layers still cover the collapsed modules
+    let index = read_index_entries(output_dir.path());
+    assert!(index.as_object().unwrap().keys().any(|k| k.starts_with("crate::engine::eval::")));
+    let api = std::fs::read_to_string(output_dir.path().join("api-surface.md")).unwrap();
+    assert!(api.contains("# crate::utils::platform::detect::cpu"));
+}
+
+#[test]
+fn test_generate_workspace_relative_paths() {
+    let binary = binary_path();
+    let workspace = tempfile::tempdir().unwrap();
+    let root = workspace.path();
+    let helper = root.join("crates/helper");
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    std::fs::create_dir_all(helper.join("src")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+         [workspace]\nmembers = [\"crates/helper\"]\n",
+    )
+    .unwrap();
+    std::fs::write(root.join("src/lib.rs"), "pub fn run() {}\n").unwrap();
+    std::fs::write(
+        helper.join("Cargo.toml"),
+        "[package]\nname = \"helper\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )
+    .unwrap();
+    std::fs::write(helper.join("src/lib.rs"), "pub fn help() {}\n").unwrap();
+
+    let generate = |output: &str, extra: &[&str]| -> serde_json::Value {
+        // --path points at the member crate; paths are still relative to the workspace root
+        let status = Command::new(&binary)
+            .args(["generate", "--path", helper.to_str().unwrap(), "--output", output])
+            .args(["--no-cache"])
+            .args(extra)
+            .status()
+            .expect("Failed to run generate");
+        assert!(status.success());
+        read_index_entries(&helper.join(output))
+    };
+
+    let index = generate("out", &[]);
+    assert_eq!(index["crate::help"]["file"], "crates/helper/src/lib.rs");
+    assert_eq!(index["crate::run"]["file"], "src/lib.rs");
+
+    let index = generate("out-abs", &["--absolute-paths"]);
+    let file = PathBuf::from(index["crate::help"]["file"].as_str().unwrap());
+    assert!(file.is_absolute());
+    assert!(file.ends_with("crates/helper/src/lib.rs"));
+}
+
+#[test]
+fn test_generate_combined_with_prefix() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+    let generate = |extra: &[&str]| {
+        let status = Command::new(&binary)
+            .args([
+                "generate",
+                "--path",
+                fixture.to_str().unwrap(),
+                "--output",
+                output_dir.path().to_str().unwrap(),
+                "--no-cache",
+                "--prefix",
+                "sample-",
+            ])
+            .args(extra)
+            .status()
+            .expect("Failed to run generate");
+        assert!(status.success());
+    };
+
+    generate(&[]);
+    for file in ["overview.md", "api-surface.md", "relationships.md", "index.json"] {
+        assert!(output_dir.path().join(format!("sample-{}", file)).exists());
+        assert!(!output_dir.path().join(file).exists());
+    }
+
+    generate(&["--combined"]);
+    let combined = std::fs::read_to_string(output_dir.path().join("sample-CODEBASE.md")).unwrap();
+    assert!(combined.starts_with("# Codebase: sample_crate\n\n## Contents\n\n"));
+    for (section, anchor) in [
+        ("Overview", "overview"),
+        ("API Surface", "api-surface"),
+        ("Relationships", "relationships"),
+        ("Index", "index"),
+    ] {
+        assert!(combined.contains(&format!("- [{}](#{})\n", section, anchor)));
+        assert!(combined.contains(&format!("\n# {}\n\n", section)));
+    }
+    // Layer headings sit one level below the section headers
+    assert!(combined.contains("## Crate: sample_crate (lib)"));
+    assert!(combined.contains("### Trait Implementations"));
+    assert!(combined.contains("```json\n{\n  \"schema_version\": 1,"));
+}
+
+#[test]
+fn test_generate_quiet_and_verbose() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+    let generate = |flag: &str| {
+        let output = Command::new(&binary)
+            .args([
+                "generate",
+                "--path",
+                fixture.to_str().unwrap(),
+                "--output",
+                output_dir.path().to_str().unwrap(),
+                "--no-cache",
+                flag,
+            ])
+            .output()
+            .expect("Failed to run generate");
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+
+    assert_eq!(generate("--quiet"), "");
+    assert!(output_dir.path().join("index.json").exists());
+
+    let stderr = generate("--verbose");
+    assert!(stderr.contains("  Parsing src/lib.rs\n"));
+    assert!(stderr.contains("  Parsing src/engine/eval.rs\n"));
+    // The summary is still printed
+    assert!(stderr.contains("Done! Output written to"));
+}
+
+#[test]
+fn test_generate_report_orphans() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let output = Command::new(&binary)
+        .args([
+            "generate",
+            "--path",
+            fixture.to_str().unwrap(),
+            "--output",
+            output_dir.path().to_str().unwrap(),
+            "--no-cache",
+            "--quiet",
+            "--report-orphans",
+        ])
+        .output()
+        .expect("Failed to run generate");
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // src/orphan.rs exists in the fixture but no `mod` declaration points at it;
+    // the #[path] module and the crate root are not orphans
+    assert_eq!(
+        stderr,
+        "Orphaned module files (not reached by any `mod` declaration):\n  - src/orphan.rs\n"
+    );
+
+    let index = std::fs::read_to_string(output_dir.path().join("index.json")).unwrap();
+    assert!(!index.contains("orphan"));
+}
+
+#[test]
+fn test_generate_config_file() {
+    let binary = binary_path();
+    let project = tempfile::tempdir().unwrap();
+    let root = project.path();
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )
+    .unwrap();
+    std::fs::write(root.join("src/lib.rs"), "pub fn run() {}\nfn helper() {}\n").unwrap();
+    std::fs::write(
+        root.join("rsmap.toml"),
+        "output = \"from-config\"\nformat = \"both\"\nvisibility = \"pub\"\n",
+    )
+    .unwrap();
+
+    let generate = |extra: &[&str]| {
+        let status = Command::new(&binary)
+            .args(["generate", "--path", root.to_str().unwrap(), "--no-cache", "--quiet"])
+            .args(extra)
+            .status()
+            .expect("Failed to run generate");
+        assert!(status.success());
+    };
+
+    // rsmap.toml in the project root supplies the settings
+    generate(&[]);
+    let out = root.join("from-config");
+    assert!(out.join("relationships.json").exists());
+    assert!(out.join("index.yaml").exists());
+    let api = std::fs::read_to_string(out.join("api-surface.md")).unwrap();
+    assert!(api.contains("fn run"));
+    assert!(!api.contains("fn helper"));
+
+    // Flags on the command line win over the file; unset keys still come from it
+    generate(&["--output", "from-cli", "--visibility", "all"]);
+    let out = root.join("from-cli");
+    assert!(out.join("relationships.json").exists());
+    let api = std::fs::read_to_string(out.join("api-surface.md")).unwrap();
+    assert!(api.contains("fn helper"));
+
+    // --config replaces the project's rsmap.toml entirely
+    let other = root.join("other.toml");
+    std::fs::write(&other, "output = \"from-other\"\n").unwrap();
+    generate(&["--config", other.to_str().unwrap()]);
+    let out = root.join("from-other");
+    assert!(out.join("relationships.md").exists());
+    assert!(!out.join("relationships.json").exists());
+
+    // Unknown keys are an error rather than silently ignored
+    std::fs::write(&other, "outptu = \"typo\"\n").unwrap();
+    let output = Command::new(&binary)
+        .args(["generate", "--path", root.to_str().unwrap(), "--config"])
+        .arg(&other)
+        .output()
+        .expect("Failed to run generate");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid config file"));
+}
+
+#[test]
+fn test_generate_legacy_index() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+    let generate = |extra: &[&str]| -> serde_json::Value {
+        let status = Command::new(&binary)
+            .args(["generate", "--path", fixture.to_str().unwrap(), "--output"])
+            .arg(output_dir.path())
+            .args(["--no-cache", "--quiet"])
+            .args(extra)
+            .status()
+            .expect("Failed to run generate");
+        assert!(status.success());
+        let index = std::fs::read_to_string(output_dir.path().join("index.json")).unwrap();
+        serde_json::from_str(&index).expect("Invalid JSON")
+    };
+
+    let index = generate(&[]);
+    assert_eq!(index["schema_version"], rsmap::layer3::INDEX_SCHEMA_VERSION);
+    assert!(index["generated_at"].is_string());
+    assert!(index["entries"].get("crate::Config").is_some());
+
+    let legacy = generate(&["--legacy-index"]);
+    assert!(legacy.get("schema_version").is_none());
+    assert_eq!(legacy["crate::Config"], index["entries"]["crate::Config"]);
+
+    // search still reads the legacy layout
+    let output = Command::new(&binary)
+        .args(["search", "Config", "--output"])
+        .arg(output_dir.path())
+        .output()
+        .expect("Failed to run search");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("crate::Config"));
+}
+
+#[test]
+fn test_generate_index_docs() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+    let generate = |extra: &[&str]| {
+        let status = Command::new(&binary)
+            .args(["generate", "--path", fixture.to_str().unwrap(), "--output"])
+            .arg(output_dir.path())
+            .args(["--no-cache", "--quiet"])
+            .args(extra)
+            .status()
+            .expect("Failed to run generate");
+        assert!(status.success());
+        read_index_entries(output_dir.path())
+    };
+
+    let index = generate(&[]);
+    assert!(index["crate::Config"].get("doc_summary").is_none());
+
+    let index = generate(&["--index-docs"]);
+    assert_eq!(index["crate::Config"]["doc_summary"], "Application configuration");
+}
+
+#[test]
+fn test_generate_index_ndjson() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+    let status = Command::new(&binary)
+        .args(["generate", "--path", fixture.to_str().unwrap(), "--output"])
+        .arg(output_dir.path())
+        .args(["--no-cache", "--quiet", "--format", "ndjson"])
+        .status()
+        .expect("Failed to run generate");
+    assert!(status.success());
+
+    // index.json is still written, with the same entries
+    let index = read_index_entries(output_dir.path());
+    let ndjson = std::fs::read_to_string(output_dir.path().join("index.ndjson")).unwrap();
+    let mut count = 0;
+    for line in ndjson.lines() {
+        let entry: serde_json::Value = serde_json::from_str(line).expect("Invalid JSON line");
+        let path = entry["path"].as_str().expect("line without a path");
+        assert_eq!(entry["kind"], index[path]["kind"]);
+        count += 1;
+    }
+    assert_eq!(count, index.as_object().unwrap().len());
+}
+
+#[test]
+fn test_generate_include_body() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+    let status = Command::new(&binary)
+        .args(["generate", "--path", fixture.to_str().unwrap(), "--output"])
+        .arg(output_dir.path())
+        .args(["--no-cache", "--quiet", "--include-body", "crate::init"])
+        .status()
+        .expect("Failed to run generate");
+    assert!(status.success());
+
+    let api = std::fs::read_to_string(output_dir.path().join("api-surface.md")).unwrap();
+    // The doc comment comes with the source instead of being written twice
+    assert!(api.contains(
+        "```rust\n/// Initialize the application with default config\n\
+         pub fn init() -> Config {\n    Config {\n        name: \"sample\".to_string(),"
+    ));
+    assert_eq!(api.matches("/// Initialize the application").count(), 1);
+    assert!(!api.contains("pub fn init() -> Config;"));
+    // Other items keep their signatures
+    assert!(api.contains("pub fn run(config: &Config)"));
+}
+
+#[test]
+fn test_generate_mermaid() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+    let generate = |extra: &[&str]| {
+        let status = Command::new(&binary)
+            .args(["generate", "--path", fixture.to_str().unwrap(), "--output"])
+            .arg(output_dir.path())
+            .args(["--no-cache", "--quiet"])
+            .args(extra)
+            .status()
+            .expect("Failed to run generate");
+        assert!(status.success());
+        std::fs::read_to_string(output_dir.path().join("relationships.md")).unwrap()
+    };
+
+    assert!(!generate(&[]).contains("```mermaid"));
+
+    let relationships = generate(&["--mermaid"]);
+    assert!(relationships.contains("## Module Dependency Graph\n\n```mermaid\ngraph LR\n"));
+    assert!(relationships.contains("    engine_eval[\"engine::eval\"]\n"));
+    assert!(relationships.contains("    engine_eval --> models\n"));
+    assert!(relationships.contains("## Trait Implementation Graph\n\n```mermaid\ngraph LR\n"));
+    assert!(relationships.contains("    Expr --> Evaluable\n"));
+}
+
+#[test]
+fn test_generate_scan_todos() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+    let generate = |extra: &[&str]| {
+        let status = Command::new(&binary)
+            .args(["generate", "--path", fixture.to_str().unwrap(), "--output"])
+            .arg(output_dir.path())
+            .args(["--no-cache", "--quiet"])
+            .args(extra)
+            .status()
+            .expect("Failed to run generate");
+        assert!(status.success());
+    };
+
+    generate(&[]);
+    assert!(!output_dir.path().join("todos.md").exists());
+
+    generate(&["--scan-todos"]);
+    let todos = std::fs::read_to_string(output_dir.path().join("todos.md")).unwrap();
+    assert!(todos.starts_with("# Tech Debt (3 comments)\n"));
+    // Grouped by module, in file order
+    let eval = todos.find("## crate::engine::eval\n").unwrap();
+    let utils = todos.find("## crate::utils\n").unwrap();
+    assert!(eval < utils);
+    assert!(todos.contains(
+        "- src/engine/eval.rs:121 FIXME: report division by zero as an error instead of Null\n"
+    ));
+    assert!(todos.contains("- src/engine/eval.rs:122 HACK: keep in sync with Value::type_name\n"));
+    assert!(todos.contains(
+        "- src/utils.rs:38 TODO: detect the platform at runtime instead of at compile time\n"
+    ));
+}
+
+#[test]
+fn test_generate_filter_module() {
+    let binary = binary_path();
+    let fixture = fixture_path();
+    let output_dir = tempfile::tempdir().unwrap();
+    let generate = |extra: &[&str]| {
+        let output = Command::new(&binary)
+            .args(["generate", "--path", fixture.to_str().unwrap(), "--output"])
+            .arg(output_dir.path())
+            .args(extra)
+            .output()
+            .expect("Failed to run generate");
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stderr).to_string()
+    };
+
+    generate(&["--no-cache", "--filter-module", "engine::*"]);
+    let api = std::fs::read_to_string(output_dir.path().join("api-surface.md")).unwrap();
+    assert!(api.contains("# crate::engine::eval"));
+    assert!(!api.contains("models"));
+    let index = read_index_entries(output_dir.path());
+    let paths: Vec<&String> = index.as_object().unwrap().keys().collect();
+    assert!(paths.iter().all(|p| p.starts_with("crate::engine::")), "{:?}", paths);
+
+    // The cache still covers the whole crate, so an unfiltered run reuses every file
+    let stderr = generate(&[]);
+    assert!(stderr.contains("Parsed 0 file(s), reused 8 unchanged file(s) from cache"));
+    let api = std::fs::read_to_string(output_dir.path().join("api-surface.md")).unwrap();
+    assert!(api.contains("# crate::models"));
+}
+
+#[test]
+fn test_generate_reexport_targets() {
+    let binary = binary_path();
+    let project = tempfile::tempdir().unwrap();
+    let root = project.path();
+    std::fs::create_dir_all(root.join("src/engine")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("src/lib.rs"),
+        "mod engine;\npub use engine::Expr;\npub use engine::eval::evaluate as eval;\n\
+         pub use std::fmt::Display;\n",
+    )
+    .unwrap();
+    std::fs::write(root.join("src/engine/mod.rs"), "pub mod eval;\npub use eval::Expr;\n").unwrap();
+    std::fs::write(
+        root.join("src/engine/eval.rs"),
+        "/// An expression\npub struct Expr;\n\npub fn evaluate() {}\n",
+    )
+    .unwrap();
+
+    let status = Command::new(&binary)
+        .args(["generate", "--path", root.to_str().unwrap(), "--no-cache", "--quiet"])
+        .status()
+        .expect("Failed to run generate");
+    assert!(status.success());
+
+    let index = read_index_entries(&root.join(".codebase-index"));
+    assert_eq!(index["crate::Expr"]["kind"], "use");
+    assert_eq!(index["crate::Expr"]["reexport_of"], "crate::engine::eval::Expr");
+    assert_eq!(index["crate::eval"]["reexport_of"], "crate::engine::eval::evaluate");
+    assert!(index["crate::Display"].get("reexport_of").is_none());
+    assert_eq!(index["crate::engine::eval::Expr"]["kind"], "struct");
+
+    let api = std::fs::read_to_string(root.join(".codebase-index/api-surface.md")).unwrap();
+    assert!(api.contains(
+        "pub use engine::Expr; // -> crate::engine::eval::Expr (src/engine/eval.rs:1)"
+    ));
+}
+
+#[test]
+fn test_generate_include_macro() {
+    let binary = binary_path();
+    let project = tempfile::tempdir().unwrap();
+    let root = project.path();
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"proto\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("src/lib.rs"),
+        "pub fn decode() {}\n\
+         include!(\"generated.rs\");\n\
+         include!(concat!(env!(\"OUT_DIR\"), \"/bindings.rs\"));\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("src/generated.rs"),
+        "// @generated\n\n/// A decoded message\npub struct Message {\n    pub id: u32,\n}\n",
+    )
+    .unwrap();
+
+    // Run twice: a module with `include!` must not be served stale from the cache
+    for _ in 0..2 {
+        let output = Command::new(&binary)
+            .args(["generate", "--path", root.to_str().unwrap(), "--report-orphans"])
+            .env_remove("OUT_DIR")
+            .output()
+            .expect("Failed to run generate");
+        assert!(output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // The OUT_DIR include can't be resolved here and is skipped quietly
+        assert!(!stderr.contains("bindings.rs"), "unexpected stderr: {}", stderr);
+        // An included file is reached, so it isn't an orphan
+        assert!(!stderr.contains("Orphaned"), "unexpected stderr: {}", stderr);
+
+        // Items from src/generated.rs land in the including module, located in their own file
+        let index = read_index_entries(&root.join(".codebase-index"));
+        assert_eq!(index["crate::Message"]["kind"], "struct");
+        assert_eq!(index["crate::Message"]["file"], "src/generated.rs");
+        assert_eq!(index["crate::Message"]["line_start"], 3);
+        assert_eq!(index["crate::Message::id"]["file"], "src/generated.rs");
+        assert_eq!(index["crate::decode"]["file"], "src/lib.rs");
+    }
+}